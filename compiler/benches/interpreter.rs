@@ -0,0 +1,79 @@
+// Compares the tree-walking interpreter with the bytecode VM
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use cylc::interpreter::vm::{self, Vm};
+use cylc::interpreter::Interpreter;
+use cylc::lexer::Lexer;
+use cylc::parser::helpers::Parser;
+use cylc::typeck::{TypeChecker, TypedProgram};
+
+const FIB: &str = "fn fib(n: int) -> int {
+    if n < 2 { return n; }
+    return fib(n - 1) + fib(n - 2);
+}
+fn main() -> void {
+    print_int(fib(20));
+}";
+
+const LOOPS: &str = "struct Point { x: int, y: int }
+fn main() -> void {
+    let mut total = 0;
+    let mut p = Point { x: 0, y: 0 };
+    for i in 0..20000 {
+        if i % 3 == 0 && i % 5 != 0 {
+            total = total + i;
+        }
+        p.x = p.x + 1;
+    }
+    let xs = [1, 2, 3, 4, 5, 6, 7, 8];
+    for round in 0..1000 {
+        for x in xs {
+            total = total + x * round;
+        }
+    }
+    print_int(total + p.x);
+}";
+
+fn check(src: &str) -> TypedProgram {
+    let tokens = Lexer::new(src).tokenize().expect("lexing failed");
+    let program = Parser::new(tokens).parse().expect("parsing failed");
+    TypeChecker::new()
+        .check_program(program)
+        .expect("type checking failed")
+}
+
+fn bench_program(c: &mut Criterion, name: &str, src: &str) {
+    let program = check(src);
+    let module = vm::compile(&program).expect("the VM supports the benchmark");
+    let mut group = c.benchmark_group(name);
+    group.bench_function("tree_walker", |b| {
+        b.iter(|| {
+            let mut interpreter = Interpreter::new();
+            interpreter.run(black_box(&program)).unwrap();
+            interpreter.output_buffer
+        })
+    });
+    group.bench_function("vm", |b| {
+        b.iter(|| {
+            let mut vm = Vm::new();
+            vm.execute(black_box(&module)).unwrap();
+            vm.output_buffer
+        })
+    });
+    group.bench_function("vm_with_compile", |b| {
+        b.iter(|| {
+            let mut vm = Vm::new();
+            vm.run(black_box(&program)).unwrap();
+            vm.output_buffer
+        })
+    });
+    group.finish();
+}
+
+fn interpreter_benchmarks(c: &mut Criterion) {
+    bench_program(c, "fib", FIB);
+    bench_program(c, "loops", LOOPS);
+}
+
+criterion_group!(benches, interpreter_benchmarks);
+criterion_main!(benches);
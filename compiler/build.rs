@@ -3,4 +3,38 @@ fn main() {
         println!("cargo:rustc-link-arg=-undefined");
         println!("cargo:rustc-link-arg=dynamic_lookup");
     }
+
+    if std::env::var_os("CARGO_FEATURE_CRANELIFT").is_some()
+        || std::env::var_os("CARGO_FEATURE_LLVM").is_some()
+    {
+        build_runtime();
+    }
+}
+
+/// Build the C runtime of the native backends twice: linked into the
+/// compiler for the Cranelift JIT, and with an entry point as an archive that
+/// `cylc build` links into executables.
+fn build_runtime() {
+    for file in ["build.rs", "runtime/cyl_runtime.c", "runtime/cyl_main.c"] {
+        println!("cargo:rerun-if-changed={file}");
+    }
+    cc::Build::new()
+        .file("runtime/cyl_runtime.c")
+        .warnings(true)
+        .compile("cyl_jit_runtime");
+    cc::Build::new()
+        .file("runtime/cyl_runtime.c")
+        .file("runtime/cyl_main.c")
+        .warnings(true)
+        .cargo_metadata(false)
+        .compile("cyl_runtime");
+
+    let out_dir = std::env::var("OUT_DIR").expect("cargo sets OUT_DIR");
+    let archive = if std::env::var("CARGO_CFG_TARGET_ENV").as_deref() == Ok("msvc") {
+        "cyl_runtime.lib"
+    } else {
+        "libcyl_runtime.a"
+    };
+    let path = std::path::Path::new(&out_dir).join(archive);
+    println!("cargo:rustc-env=CYL_RUNTIME_ARCHIVE={}", path.display());
 }
@@ -0,0 +1,17 @@
+/*
+ * Entry point of executables built by the Cranelift backend.
+ *
+ * The compiled program exports `cyl_entry`, which runs the program's `main`;
+ * Cyl functions are exported with a `cyl_fn_` prefix so they cannot clash
+ * with the C library or the runtime.
+ */
+
+#include <stdio.h>
+#include <stdlib.h>
+
+extern void cyl_entry(void);
+
+int main(void) {
+    cyl_entry();
+    return fflush(stdout) == 0 ? EXIT_SUCCESS : EXIT_FAILURE;
+}
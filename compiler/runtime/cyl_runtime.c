@@ -0,0 +1,230 @@
+/*
+ * Runtime support for code compiled by the native backends, linked into
+ * executables and into the compiler itself for the Cranelift JIT.
+ *
+ * Values print exactly as the interpreter prints them, one line per call.
+ * Strings, arrays and structs use the layouts of `src/layout.rs`. Strings are
+ * a 64-bit length, the bytes, and a NUL; arrays a 64-bit length and one
+ * 8-byte slot per element. All are immutable once built. Compiled code only
+ * frees the strings it makes for a single operation; see `cyl_string_free`.
+ */
+
+#include <math.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+struct cyl_string {
+    int64_t length;
+    char bytes[];
+};
+
+struct cyl_array {
+    int64_t length;
+    uint64_t slots[];
+};
+
+/* Longest `cyl_format_float` result: a sign, 17 digits, and up to 308 zeros
+ * and a point around them. */
+#define FLOAT_BUFFER_SIZE 352
+
+/*
+ * Format `value` like Rust's `f64` `Display`, which the interpreter uses: the
+ * fewest digits that read back as the same value, without an exponent.
+ */
+static void cyl_format_float(double value, char *out) {
+    if (isnan(value)) {
+        strcpy(out, "NaN");
+        return;
+    }
+    if (isinf(value)) {
+        strcpy(out, value < 0 ? "-inf" : "inf");
+        return;
+    }
+    if (signbit(value)) {
+        *out++ = '-';
+        value = -value;
+    }
+    if (value == 0.0) {
+        strcpy(out, "0");
+        return;
+    }
+
+    /* Scientific notation with the fewest significant digits that round-trip */
+    char scientific[32];
+    for (int precision = 0; precision < 17; precision++) {
+        snprintf(scientific, sizeof scientific, "%.*e", precision, value);
+        if (strtod(scientific, NULL) == value) {
+            break;
+        }
+    }
+
+    char digits[24];
+    size_t count = 0;
+    const char *p = scientific;
+    for (; *p != 'e'; p++) {
+        if (*p != '.') {
+            digits[count++] = *p;
+        }
+    }
+    while (count > 1 && digits[count - 1] == '0') {
+        count--;
+    }
+    int exponent = atoi(p + 1);
+
+    if (exponent < 0) {
+        *out++ = '0';
+        *out++ = '.';
+        for (int i = -1; i > exponent; i--) {
+            *out++ = '0';
+        }
+        memcpy(out, digits, count);
+        out += count;
+    } else if ((size_t)exponent + 1 >= count) {
+        memcpy(out, digits, count);
+        out += count;
+        for (size_t i = count; i < (size_t)exponent + 1; i++) {
+            *out++ = '0';
+        }
+    } else {
+        memcpy(out, digits, (size_t)exponent + 1);
+        out += exponent + 1;
+        *out++ = '.';
+        memcpy(out, digits + exponent + 1, count - (size_t)exponent - 1);
+        out += count - (size_t)exponent - 1;
+    }
+    *out = '\0';
+}
+
+/* `size` bytes of heap memory, for structs and the objects below */
+void *cyl_alloc(int64_t size) {
+    /* `malloc(0)` may return NULL, and empty structs need an address too */
+    void *memory = malloc(size > 0 ? (size_t)size : 1);
+    if (memory == NULL) {
+        fputs("Out of memory\n", stderr);
+        abort();
+    }
+    return memory;
+}
+
+/* A string of `length` bytes, for the caller to fill in */
+static struct cyl_string *cyl_string_alloc(size_t length) {
+    struct cyl_string *string = cyl_alloc((int64_t)(sizeof(struct cyl_string) + length + 1));
+    string->length = (int64_t)length;
+    string->bytes[length] = '\0';
+    return string;
+}
+
+static const struct cyl_string *cyl_string_new(const char *bytes, size_t length) {
+    struct cyl_string *string = cyl_string_alloc(length);
+    memcpy(string->bytes, bytes, length);
+    return string;
+}
+
+/* `+` with a string operand */
+const struct cyl_string *cyl_string_concat(const struct cyl_string *left,
+                                           const struct cyl_string *right) {
+    struct cyl_string *string = cyl_string_alloc((size_t)(left->length + right->length));
+    memcpy(string->bytes, left->bytes, (size_t)left->length);
+    memcpy(string->bytes + left->length, right->bytes, (size_t)right->length);
+    return string;
+}
+
+/*
+ * Release a string made at runtime that nothing refers to any more, such as
+ * the result of `a + b` in `a + b + c`. Strings a program stores in a
+ * variable, field or element are never freed.
+ */
+void cyl_string_free(const struct cyl_string *string) {
+    free((void *)string);
+}
+
+/* The other operand of a string `+`, as the interpreter formats it */
+const struct cyl_string *cyl_string_from_int(int64_t value) {
+    char buffer[24];
+    int length = snprintf(buffer, sizeof buffer, "%lld", (long long)value);
+    return cyl_string_new(buffer, (size_t)length);
+}
+
+const struct cyl_string *cyl_string_from_float(double value) {
+    char buffer[FLOAT_BUFFER_SIZE];
+    cyl_format_float(value, buffer);
+    return cyl_string_new(buffer, strlen(buffer));
+}
+
+const struct cyl_string *cyl_string_from_bool(int8_t value) {
+    return value ? cyl_string_new("true", 4) : cyl_string_new("false", 5);
+}
+
+/* Byte-wise comparison: negative, zero or positive */
+int64_t cyl_string_compare(const struct cyl_string *left, const struct cyl_string *right) {
+    size_t shorter = left->length < right->length ? (size_t)left->length : (size_t)right->length;
+    int order = memcmp(left->bytes, right->bytes, shorter);
+    if (order != 0) {
+        return order < 0 ? -1 : 1;
+    }
+    return (left->length > right->length) - (left->length < right->length);
+}
+
+/* `len()`, which counts characters rather than bytes */
+int64_t cyl_string_length(const struct cyl_string *string) {
+    int64_t count = 0;
+    for (int64_t i = 0; i < string->length; i++) {
+        /* Every character has exactly one byte that is not a continuation */
+        if (((unsigned char)string->bytes[i] & 0xC0) != 0x80) {
+            count++;
+        }
+    }
+    return count;
+}
+
+/* An array of `length` elements, for compiled code to fill in */
+struct cyl_array *cyl_array_new(int64_t length) {
+    struct cyl_array *array =
+        cyl_alloc((int64_t)(sizeof(struct cyl_array) + (size_t)length * sizeof(uint64_t)));
+    array->length = length;
+    return array;
+}
+
+/* Report an index outside an array, as the interpreter does, and exit */
+void cyl_index_out_of_bounds(int64_t index, int64_t length, int64_t line, int64_t column) {
+    fflush(stdout);
+    fprintf(stderr,
+            "error[E0006]: Index %lld is out of bounds for an array of length %lld\n"
+            "  at line %lld, column %lld\n",
+            (long long)index, (long long)length, (long long)line, (long long)column);
+    exit(1);
+}
+
+/* Report a runtime error the interpreter reports with `message`, and exit */
+void cyl_runtime_error(const struct cyl_string *message, int64_t line, int64_t column) {
+    fflush(stdout);
+    fprintf(stderr, "error[E0006]: %s\n  at line %lld, column %lld\n", message->bytes,
+            (long long)line, (long long)column);
+    exit(1);
+}
+
+void cyl_print_int(int64_t value) {
+    printf("%lld\n", (long long)value);
+}
+
+void cyl_print_float(double value) {
+    char buffer[FLOAT_BUFFER_SIZE];
+    cyl_format_float(value, buffer);
+    puts(buffer);
+}
+
+void cyl_print_bool(int8_t value) {
+    puts(value ? "true" : "false");
+}
+
+void cyl_print_str(const struct cyl_string *value) {
+    fwrite(value->bytes, 1, (size_t)value->length, stdout);
+    putchar('\n');
+}
+
+/* `println()` without arguments */
+void cyl_print_newline(void) {
+    putchar('\n');
+}
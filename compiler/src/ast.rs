@@ -1,5 +1,44 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
+
+/// Location of a node in the source: its byte range plus the 1-based line and
+/// column where it starts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize, column: usize) -> Self {
+        Span {
+            start,
+            end,
+            line,
+            column,
+        }
+    }
+
+    /// The smallest span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Span {
+        let first = if other.start < self.start { other } else { self };
+        Span {
+            start: first.start,
+            end: self.end.max(other.end),
+            line: first.line,
+            column: first.column,
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Program {
@@ -7,7 +46,19 @@ pub struct Program {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub enum Statement {
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
+}
+
+impl Statement {
+    pub fn new(kind: StatementKind, span: Span) -> Self {
+        Statement { kind, span }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum StatementKind {
     Import(ImportStatement),
     Function(FunctionDeclaration),
     Struct(StructDeclaration),
@@ -20,8 +71,11 @@ pub enum Statement {
     For(ForStatement),
     Match(MatchStatement),
     Try(TryStatement),
-    Break,
-    Continue,
+    Throw(ThrowStatement),
+    /// `break;`, or `break 'label;` to leave an enclosing labelled loop
+    Break(Option<String>),
+    /// `continue;`, or `continue 'label;`
+    Continue(Option<String>),
     Block(BlockStatement), // <-- Added to support block statements
 }
 
@@ -36,6 +90,8 @@ pub struct FunctionDeclaration {
     pub name: String,
     pub parameters: Vec<Parameter>,
     pub return_type: Option<Type>,
+    /// Where `return_type` is written.
+    pub return_type_span: Option<Span>,
     pub body: BlockStatement,
     pub is_async: bool,
     pub type_parameters: Vec<String>, // NEW: generics
@@ -45,8 +101,11 @@ pub struct FunctionDeclaration {
 pub struct Parameter {
     pub name: String,
     pub param_type: Type,
+    /// Where `param_type` is written; `None` when it is inferred.
+    pub type_span: Option<Span>,
     pub is_mutable: bool,
     pub default_value: Option<Expression>, // NEW: default args
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -60,7 +119,10 @@ pub struct StructDeclaration {
 pub struct StructField {
     pub name: String,
     pub field_type: Type,
+    /// Where `field_type` is written.
+    pub type_span: Span,
     pub is_public: bool,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -74,6 +136,9 @@ pub struct EnumDeclaration {
 pub struct EnumVariant {
     pub name: String,
     pub fields: Option<Vec<Type>>, // None for unit variant
+    /// Where each of `fields` is written.
+    pub field_spans: Vec<Span>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -81,6 +146,8 @@ pub struct DeclareStatement {
     pub name: String,
     pub value: Expression,
     pub var_type: Option<Type>,
+    /// Where `var_type` is written.
+    pub type_span: Option<Span>,
     pub is_mutable: bool,
 }
 
@@ -98,13 +165,16 @@ pub struct IfStatement {
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct WhileStatement {
+    pub label: Option<String>,
     pub condition: Expression,
     pub body: BlockStatement,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ForStatement {
-    pub variable: String,
+    pub label: Option<String>,
+    /// A name, `_`, or a tuple of them, bound to each element in turn
+    pub pattern: Pattern,
     pub iterable: Expression,
     pub body: BlockStatement,
 }
@@ -113,6 +183,8 @@ pub struct ForStatement {
 pub struct MatchStatement {
     pub expression: Expression,
     pub arms: Vec<MatchArm>,
+    /// Type of `expression`, filled in by the type checker.
+    pub scrutinee_type: Option<Type>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -120,6 +192,7 @@ pub struct MatchArm {
     pub pattern: Pattern,
     pub guard: Option<Expression>,
     pub body: BlockStatement,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -128,20 +201,43 @@ pub struct TryStatement {
     pub catch_clauses: Vec<CatchClause>,
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThrowStatement {
+    pub value: Expression,
+    /// Type of `value`, filled in by the type checker.
+    pub value_type: Option<Type>,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CatchClause {
     pub exception_type: Option<Type>,
+    /// Where `exception_type` is written.
+    pub type_span: Option<Span>,
     pub variable: Option<String>,
     pub body: BlockStatement,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct BlockStatement {
     pub statements: Vec<Statement>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub span: Span,
+}
+
+impl Expression {
+    pub fn new(kind: ExpressionKind, span: Span) -> Self {
+        Expression { kind, span }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub enum Expression {
+pub enum ExpressionKind {
     Identifier(String),
     IntLiteral(i64),
     FloatLiteral(f64),
@@ -179,6 +275,15 @@ pub enum Expression {
         index: Box<Expression>,
     },
     TupleLiteral(Vec<Expression>), // NEW: tuple literal
+    /// `start..end`, or `start..=end` when `inclusive`
+    Range {
+        start: Box<Expression>,
+        end: Box<Expression>,
+        inclusive: bool,
+    },
+    /// `expr?`: unwrap an `Ok`/`Some`, or return the `Err`/`None` from the
+    /// enclosing function.
+    Propagate(Box<Expression>),
     // Async
     Await(Box<Expression>),
 
@@ -189,7 +294,7 @@ pub enum Expression {
     },
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum BinaryOperator {
     // Arithmetic
     Add,
@@ -218,7 +323,7 @@ pub enum BinaryOperator {
     RightShift,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum UnaryOperator {
     Not,
     Minus,
@@ -244,7 +349,7 @@ pub enum Type {
     // Custom types
     Custom(String),
     // Generic types
-    Generic(String, Vec<String>), // e.g., Result<T, E>
+    Generic(String, Vec<Type>), // e.g., Result<T, E>
     // Function types
     Function {
         parameters: Vec<Type>,
@@ -256,7 +361,19 @@ pub enum Type {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub enum Pattern {
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub span: Span,
+}
+
+impl Pattern {
+    pub fn new(kind: PatternKind, span: Span) -> Self {
+        Pattern { kind, span }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PatternKind {
     Identifier(String),
     Literal(Expression),
     Wildcard,
@@ -1,7 +1,13 @@
 use crate::ast::{
-    BinaryOperator, Expression, FunctionDeclaration, Program, Statement, StructDeclaration, Type,
+    BinaryOperator, EnumDeclaration, Expression, ExpressionKind, FunctionDeclaration,
+    MatchStatement, Parameter, Pattern, PatternKind, Span, Statement, StatementKind, StructDeclaration,
+    ThrowStatement, TryStatement, Type,
 };
 use crate::error::CylError;
+use crate::exceptions;
+use crate::layout::{self, EnumLayout};
+use crate::runtime::{self, LinkOptions};
+use crate::typeck::{Constructors, ExpressionTypes, TypedProgram};
 use inkwell::builder::Builder;
 use inkwell::context::Context;
 use inkwell::module::Module;
@@ -9,9 +15,11 @@ use inkwell::passes::PassManager;
 use inkwell::targets::{
     CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetMachine,
 };
-use inkwell::types::{BasicMetadataTypeEnum, BasicType, BasicTypeEnum, StructType};
+use inkwell::module::Linkage;
+use inkwell::types::{BasicMetadataTypeEnum, BasicType, BasicTypeEnum, PointerType, StructType};
+use inkwell::basic_block::BasicBlock;
 use inkwell::values::{
-    BasicMetadataValueEnum, BasicValueEnum, FunctionValue, IntValue, PointerValue,
+    BasicMetadataValueEnum, BasicValueEnum, CallSiteValue, FunctionValue, IntValue, PointerValue,
 };
 use inkwell::{AddressSpace, IntPredicate, OptimizationLevel};
 use std::collections::HashMap;
@@ -26,7 +34,26 @@ pub struct LLVMCodegen<'ctx> {
     variables: HashMap<String, (PointerValue<'ctx>, Type)>, // Store type info with variables
     functions: HashMap<String, FunctionValue<'ctx>>,
     function_signatures: HashMap<String, (Vec<Type>, Option<Type>)>, // (params, return_type)
+    defaults: HashMap<String, (Vec<Parameter>, ExpressionTypes)>, // Functions with defaults, and the types they were checked with
     struct_types: HashMap<String, (StructType<'ctx>, Vec<(String, Type)>)>, // (LLVM type, field info)
+    enum_types: HashMap<String, (StructType<'ctx>, EnumLayout)>, // { i64 tag, [N x i64] payload }
+    handlers: Vec<Vec<Handler<'ctx>>>, // Catch clauses of the enclosing `try` statements, innermost last
+    types: ExpressionTypes, // Types of the expressions of the function being compiled
+    constructors: Constructors, // The enum variants constructor expressions build
+
+    // Exceptions leaving functions, see `crate::exceptions`
+    escaping: HashMap<String, Vec<Type>>,
+    exception_types: Vec<Type>, // Numbered from 1 in the record
+    exception: Option<PointerValue<'ctx>>, // The record, if anything is thrown out of a function
+    uncaught: Option<BasicBlock<'ctx>>, // Block reporting the pending exception, in `main`
+}
+
+/// A catch clause a `throw` or a call can branch to.
+struct Handler<'ctx> {
+    exception_type: Option<Type>,
+    /// Blocks the clause starts in and the slots holding the thrown value,
+    /// one for each type of value that reaches it.
+    entries: Vec<(BasicBlock<'ctx>, PointerValue<'ctx>, Type)>,
 }
 
 impl<'ctx> LLVMCodegen<'ctx> {
@@ -40,85 +67,265 @@ impl<'ctx> LLVMCodegen<'ctx> {
             variables: HashMap::new(),
             functions: HashMap::new(),
             function_signatures: HashMap::new(),
+            defaults: HashMap::new(),
             struct_types: HashMap::new(),
+            enum_types: HashMap::new(),
+            handlers: Vec::new(),
+            types: ExpressionTypes::default(),
+            constructors: Constructors::default(),
+            escaping: HashMap::new(),
+            exception_types: Vec::new(),
+            exception: None,
+            uncaught: None,
         })
     }
 
     /// Declare builtin/standard library functions  
     fn declare_builtin_functions(&mut self) -> Result<(), CylError> {
-        // Declare printf for print functionality
-        let printf_type = self.context.i32_type().fn_type(
-            &[self
-                .context
-                .i8_type()
-                .ptr_type(AddressSpace::default())
-                .into()],
-            true, // variadic
-        );
-        let printf_fn = self.module.add_function("printf", printf_type, None);
+        let i8_type = self.context.i8_type();
+        let i64_type = self.context.i64_type();
+        let f64_type = self.context.f64_type();
+        let void_type = self.context.void_type();
+        let string_type = self.string_type();
+
+        // The runtime's printing and string functions, see `runtime/cyl_runtime.c`
+        let runtime_functions = [
+            ("cyl_print_int", void_type.fn_type(&[i64_type.into()], false)),
+            ("cyl_print_float", void_type.fn_type(&[f64_type.into()], false)),
+            ("cyl_print_bool", void_type.fn_type(&[i8_type.into()], false)),
+            ("cyl_print_str", void_type.fn_type(&[string_type.into()], false)),
+            ("cyl_print_newline", void_type.fn_type(&[], false)),
+            (
+                "cyl_string_concat",
+                string_type.fn_type(&[string_type.into(), string_type.into()], false),
+            ),
+            ("cyl_string_free", void_type.fn_type(&[string_type.into()], false)),
+            ("cyl_string_from_int", string_type.fn_type(&[i64_type.into()], false)),
+            ("cyl_string_from_float", string_type.fn_type(&[f64_type.into()], false)),
+            ("cyl_string_from_bool", string_type.fn_type(&[i8_type.into()], false)),
+            (
+                "cyl_string_compare",
+                i64_type.fn_type(&[string_type.into(), string_type.into()], false),
+            ),
+            ("cyl_string_length", i64_type.fn_type(&[string_type.into()], false)),
+            (
+                "cyl_runtime_error",
+                void_type.fn_type(&[string_type.into(), i64_type.into(), i64_type.into()], false),
+            ),
+        ];
+        for (name, fn_type) in runtime_functions {
+            self.module.add_function(name, fn_type, None);
+        }
 
-        // Declare puts for simple string printing
-        let puts_type = self.context.i32_type().fn_type(
-            &[self
-                .context
-                .i8_type()
-                .ptr_type(AddressSpace::default())
-                .into()],
-            false,
-        );
-        let puts_fn = self.module.add_function("puts", puts_type, None);
+        Ok(())
+    }
 
-        // Register Cyl builtin functions that will map to these
-        self.functions.insert("print".to_string(), puts_fn);
-        self.functions.insert("printf".to_string(), printf_fn);
+    /// Strings are `i8*` pointers to string objects laid out as described in
+    /// [`crate::layout`].
+    fn string_type(&self) -> PointerType<'ctx> {
+        self.context.i8_type().ptr_type(AddressSpace::default())
+    }
 
-        // For print_int, we'll use printf with a format string
-        self.functions.insert("print_int".to_string(), printf_fn);
+    /// Call the runtime function `name`, declared by
+    /// `declare_builtin_functions`.
+    fn call_runtime(
+        &self,
+        name: &str,
+        arguments: &[BasicMetadataValueEnum<'ctx>],
+    ) -> Option<BasicValueEnum<'ctx>> {
+        let function = self.module.get_function(name).unwrap();
+        self.builder
+            .build_call(function, arguments, name)
+            .unwrap()
+            .try_as_basic_value()
+            .left()
+    }
 
-        // Add function signatures for type checking
-        self.function_signatures.insert(
-            "print".to_string(),
-            (vec![Type::String], Some(Type::Int)), // puts returns int
-        );
-        self.function_signatures
-            .insert("printf".to_string(), (vec![Type::String], Some(Type::Int)));
-        self.function_signatures
-            .insert("print_int".to_string(), (vec![Type::Int], Some(Type::Int)));
+    /// A read-only string object holding `value`.
+    fn compile_string_literal(&self, value: &str) -> PointerValue<'ctx> {
+        let object = self.context.const_string(&layout::string_object(value), false);
+        let global = self.module.add_global(object.get_type(), None, "str");
+        global.set_initializer(&object);
+        global.set_constant(true);
+        global.set_linkage(Linkage::Private);
+        global.set_alignment(layout::STRING_ALIGN);
+        global.as_pointer_value().const_cast(self.string_type())
+    }
 
-        Ok(())
+    /// `value`, of type `ty`, as the string the interpreter makes of it when
+    /// it is an operand of a string `+`.
+    fn compile_to_string(
+        &self,
+        value: BasicValueEnum<'ctx>,
+        ty: &Type,
+        span: Span,
+    ) -> Result<PointerValue<'ctx>, CylError> {
+        let converted = match ty {
+            Type::String => return Ok(value.into_pointer_value()),
+            Type::Float => self.call_runtime("cyl_string_from_float", &[value.into()]),
+            Type::Bool => {
+                let byte = self
+                    .builder
+                    .build_int_z_extend(value.into_int_value(), self.context.i8_type(), "bool")
+                    .unwrap();
+                self.call_runtime("cyl_string_from_bool", &[byte.into()])
+            }
+            Type::Int => {
+                let wide = self
+                    .builder
+                    .build_int_s_extend(value.into_int_value(), self.context.i64_type(), "int")
+                    .unwrap();
+                self.call_runtime("cyl_string_from_int", &[wide.into()])
+            }
+            _ => None,
+        };
+        converted
+            .map(|string| string.into_pointer_value())
+            .ok_or_else(|| CylError::CodeGenError {
+                message: "Only numbers and bools can be concatenated to strings yet".to_string(),
+                span: Some(span),
+            })
+    }
+
+    /// String concatenation and comparison of operands of the given types.
+    /// An operand marked temporary is a string made for this operation
+    /// alone, which is freed after it.
+    fn compile_string_op(
+        &self,
+        operator: &BinaryOperator,
+        (left, left_type, left_temporary): (BasicValueEnum<'ctx>, &Type, bool),
+        (right, right_type, right_temporary): (BasicValueEnum<'ctx>, &Type, bool),
+        span: Span,
+    ) -> Result<BasicValueEnum<'ctx>, CylError> {
+        let left_temporary = left_temporary && *left_type == Type::String;
+        let right_temporary = right_temporary && *right_type == Type::String;
+        let predicate = match operator {
+            BinaryOperator::Add => {
+                // Numbers and bools are formatted into new strings
+                let left_temporary = left_temporary || *left_type != Type::String;
+                let right_temporary = right_temporary || *right_type != Type::String;
+                let left = self.compile_to_string(left, left_type, span)?;
+                let right = self.compile_to_string(right, right_type, span)?;
+                let text = self
+                    .call_runtime("cyl_string_concat", &[left.into(), right.into()])
+                    .unwrap();
+                self.free_temporaries([(left, left_temporary), (right, right_temporary)]);
+                return Ok(text);
+            }
+            _ if (left_type, right_type) != (&Type::String, &Type::String) => {
+                return Err(CylError::CodeGenError {
+                    message: format!("Cannot apply `{operator:?}` to a string and a non-string"),
+                    span: Some(span),
+                })
+            }
+            BinaryOperator::Equal => IntPredicate::EQ,
+            BinaryOperator::NotEqual => IntPredicate::NE,
+            BinaryOperator::Less => IntPredicate::SLT,
+            BinaryOperator::LessEqual => IntPredicate::SLE,
+            BinaryOperator::Greater => IntPredicate::SGT,
+            BinaryOperator::GreaterEqual => IntPredicate::SGE,
+            _ => {
+                return Err(CylError::CodeGenError {
+                    message: format!("Binary operator not implemented for strings: {operator:?}"),
+                    span: Some(span),
+                })
+            }
+        };
+        let order = self
+            .call_runtime("cyl_string_compare", &[left.into(), right.into()])
+            .unwrap()
+            .into_int_value();
+        self.free_temporaries([
+            (left.into_pointer_value(), left_temporary),
+            (right.into_pointer_value(), right_temporary),
+        ]);
+        let zero = self.context.i64_type().const_zero();
+        Ok(self
+            .builder
+            .build_int_compare(predicate, order, zero, "strcmp")
+            .unwrap()
+            .into())
     }
 
-    pub fn compile_program(&mut self, program: &Program) -> Result<(), CylError> {
+    /// Free the strings marked temporary.
+    fn free_temporaries(&self, strings: [(PointerValue<'ctx>, bool); 2]) {
+        for (string, temporary) in strings {
+            if temporary {
+                self.call_runtime("cyl_string_free", &[string.into()]);
+            }
+        }
+    }
+
+    pub fn compile_program(&mut self, typed: &TypedProgram) -> Result<(), CylError> {
+        let program = &typed.program;
+
         // Declare builtin functions first
         self.declare_builtin_functions()?;
+        self.constructors = typed.constructors.clone();
 
-        // First pass: declare all structs and functions
+        // First pass: declare all structs, enums and functions
         for statement in &program.statements {
-            match statement {
-                Statement::Struct(struct_decl) => {
+            match &statement.kind {
+                StatementKind::Struct(struct_decl) => {
                     self.declare_struct(struct_decl)?;
                 }
-                Statement::Function(function) => {
-                    self.declare_function(function)?;
+                StatementKind::Enum(enum_decl) => {
+                    self.declare_enum(enum_decl);
                 }
                 _ => {}
             }
         }
+        for (function, types) in typed.functions() {
+            self.declare_function(function)?;
+            if function.parameters.iter().any(|param| param.default_value.is_some()) {
+                self.defaults
+                    .insert(function.name.clone(), (function.parameters.clone(), types.clone()));
+            }
+        }
+        self.declare_exceptions(typed);
 
-        // Second pass: compile function bodies and other statements
-        for statement in &program.statements {
-            match statement {
-                Statement::Function(function) => {
-                    self.compile_function(function)?;
-                }
-                _ => {
-                    // For now, skip non-function statements at the top level
-                    // TODO: Handle global statements
+        // Second pass: compile function bodies
+        // TODO: Handle global statements
+        for (function, types) in typed.functions() {
+            self.types = types.clone();
+            self.compile_function(function)?;
+        }
+
+        Ok(())
+    }
+
+    /// The type the type checker resolved for `expr`.
+    fn type_of(&self, expr: &Expression) -> Result<&Type, CylError> {
+        self.types.get(expr).ok_or_else(|| CylError::CodeGenError {
+            message: "Expression was not type checked".to_string(),
+            span: Some(expr.span),
+        })
+    }
+
+    /// Number the types of the exceptions that can leave a function and
+    /// declare the record holding the pending one.
+    fn declare_exceptions(&mut self, typed: &TypedProgram) {
+        self.escaping = exceptions::escaping(typed);
+        let mut names: Vec<_> = self.escaping.keys().cloned().collect();
+        names.sort();
+        for name in names {
+            for ty in &self.escaping[&name] {
+                if !self.exception_types.contains(ty) {
+                    self.exception_types.push(ty.clone());
                 }
             }
         }
+        if self.exception_types.is_empty() {
+            return;
+        }
 
-        Ok(())
+        let i64_type = self.context.i64_type();
+        let slots = (exceptions::RECORD_SIZE / 8) as usize;
+        let record_type = self.context.struct_type(&vec![i64_type.into(); slots], false);
+        let record = self.module.add_global(record_type, None, "cyl_exception");
+        record.set_initializer(&record_type.const_zero());
+        record.set_linkage(Linkage::Private);
+        self.exception = Some(record.as_pointer_value());
     }
 
     fn declare_function(&mut self, function: &FunctionDeclaration) -> Result<(), CylError> {
@@ -209,11 +416,28 @@ impl<'ctx> LLVMCodegen<'ctx> {
         Ok(())
     }
 
+    /// Declare the tagged union described in [`crate::layout`] for an enum.
+    fn declare_enum(&mut self, enum_decl: &EnumDeclaration) {
+        let layout = EnumLayout::new(enum_decl);
+        let i64_type = self.context.i64_type();
+        let enum_type = self.context.opaque_struct_type(&enum_decl.name);
+        enum_type.set_body(
+            &[
+                i64_type.into(),
+                i64_type.array_type(layout.payload_slots()).into(),
+            ],
+            false,
+        );
+        self.enum_types
+            .insert(enum_decl.name.clone(), (enum_type, layout));
+    }
+
     fn compile_function(&mut self, function: &FunctionDeclaration) -> Result<(), CylError> {
         // Check for unsupported features
         if function.is_async {
             return Err(CylError::CodeGenError {
                 message: "Async functions are not yet implemented".to_string(),
+                span: None,
             });
         }
 
@@ -223,6 +447,12 @@ impl<'ctx> LLVMCodegen<'ctx> {
 
         // Clear local variables for new function
         self.variables.clear();
+        let uncaught = match self.escaping.get(&function.name) {
+            Some(types) if function.name == "main" && !types.is_empty() => types.clone(),
+            _ => Vec::new(),
+        };
+        self.uncaught = (!uncaught.is_empty())
+            .then(|| self.context.append_basic_block(fn_value, "uncaught"));
 
         // Add parameters to symbol table
         for (i, param) in function.parameters.iter().enumerate() {
@@ -238,48 +468,48 @@ impl<'ctx> LLVMCodegen<'ctx> {
             self.compile_statement(statement)?;
         }
 
-        // If no explicit return, add a default return
-        if function.name == "main" {
-            // Main function should return 0 (success)
-            let zero = self.context.i32_type().const_int(0, false);
-            self.builder.build_return(Some(&zero)).unwrap();
-        } else if function.return_type.is_none() {
-            self.builder.build_return(None).unwrap();
+        // If no explicit return, add a default return. A non-void function
+        // only gets here past a `match` or `try` whose branches all return,
+        // so the block left open has no predecessors.
+        if self.builder.get_insert_block().unwrap().get_terminator().is_none() {
+            if function.name == "main" {
+                // Main function should return 0 (success)
+                let zero = self.context.i32_type().const_int(0, false);
+                self.builder.build_return(Some(&zero)).unwrap();
+            } else if function.return_type.is_none() {
+                self.builder.build_return(None).unwrap();
+            } else {
+                self.builder.build_unreachable().unwrap();
+            }
+        }
+        if let Some(report) = self.uncaught.take() {
+            self.report_uncaught(report, &uncaught)?;
         }
 
         Ok(())
     }
 
     fn compile_statement(&mut self, statement: &Statement) -> Result<(), CylError> {
-        match statement {
-            Statement::Expression(expr) => {
+        match &statement.kind {
+            StatementKind::Expression(expr) => {
                 // For expression statements, we compile the expression but don't use the result
                 // This allows function calls that return void
-                match expr {
-                    Expression::Call { callee, arguments } => {
-                        if let Expression::Identifier(function_name) = callee.as_ref() {
+                match &expr.kind {
+                    ExpressionKind::Call { callee, arguments } if self.constructors.get(expr).is_none() => {
+                        if let ExpressionKind::Identifier(function_name) = &callee.kind {
                             // Handle builtin functions specially
-                            if function_name == "print_int" {
-                                self.compile_print_int_call(arguments)?;
+                            if matches!(function_name.as_str(), "print" | "println" | "print_int") {
+                                self.compile_print_call(function_name, arguments, expr.span)?;
                                 return Ok(());
                             }
 
-                            if let Some(fn_value) = self.functions.get(function_name).copied() {
-                                let args: Vec<BasicMetadataValueEnum> = arguments
-                                    .iter()
-                                    .map(|arg| self.compile_expression(arg).map(|v| v.into()))
-                                    .collect::<Result<Vec<_>, _>>()?;
-
-                                self.builder.build_call(fn_value, &args, "calltmp").unwrap();
-                                // Don't care about the return value for statement-level calls
-                            } else {
-                                return Err(CylError::CodeGenError {
-                                    message: format!("Unknown function: {function_name}"),
-                                });
-                            }
+                            let function_name = self.types.callee(expr).unwrap_or(function_name).to_string();
+                            // Don't care about the return value for statement-level calls
+                            self.compile_call(&function_name, arguments, callee.span)?;
                         } else {
                             return Err(CylError::CodeGenError {
                                 message: "Complex function calls not yet supported".to_string(),
+                                span: Some(callee.span),
                             });
                         }
                     }
@@ -288,76 +518,18 @@ impl<'ctx> LLVMCodegen<'ctx> {
                     }
                 }
             }
-            Statement::Declare(declare_stmt) => {
-                // Use the explicitly declared type, or infer from the expression
-                let var_type = if let Some(ref explicit_type) = declare_stmt.var_type {
-                    explicit_type.clone()
-                } else {
-                    // Try to infer type from the expression
-                    match &declare_stmt.value {
-                        Expression::Call { callee, .. } => {
-                            if let Expression::Identifier(function_name) = callee.as_ref() {
-                                if let Some((_, return_type)) =
-                                    self.function_signatures.get(function_name)
-                                {
-                                    return_type.clone().unwrap_or(Type::Int) // Default to int if function is void
-                                } else {
-                                    Type::Int // Unknown function, default to int
-                                }
-                            } else {
-                                Type::Int // Complex call, default to int
-                            }
-                        }
-                        Expression::IntLiteral(_) => Type::Int,
-                        Expression::FloatLiteral(_) => Type::Float,
-                        Expression::BoolLiteral(_) => Type::Bool,
-                        Expression::StringLiteral(_) => Type::String,
-                        Expression::ObjectLiteral(fields) => {
-                            // Infer struct type from object literal
-                            if let Some(Expression::StringLiteral(struct_name)) =
-                                fields.get("__struct_name__")
-                            {
-                                Type::Custom(struct_name.clone())
-                            } else {
-                                Type::Int // Fallback if no struct name
-                            }
-                        }
-                        Expression::MemberAccess { object, property } => {
-                            // For member access, try to infer the field type
-                            if let Expression::Identifier(obj_name) = object.as_ref() {
-                                // Try to find the object in variables to get its type
-                                if let Some((_, Type::Custom(struct_name))) =
-                                    self.variables.get(obj_name)
-                                {
-                                    // Look up the field type in the struct definition
-                                    if let Some((_, field_info)) =
-                                        self.struct_types.get(struct_name)
-                                    {
-                                        if let Some((_, field_type)) =
-                                            field_info.iter().find(|(name, _)| name == property)
-                                        {
-                                            field_type.clone()
-                                        } else {
-                                            Type::Int // Field not found
-                                        }
-                                    } else {
-                                        Type::Int // Struct not found
-                                    }
-                                } else {
-                                    Type::Int // Variable not found or not a struct
-                                }
-                            } else {
-                                Type::Int // Complex object expression
-                            }
-                        }
-                        _ => Type::Int, // Default fallback
-                    }
-                };
+            StatementKind::Declare(declare_stmt) => {
+                // The type checker resolved every declaration's type
+                let var_type = declare_stmt.var_type.clone().ok_or_else(|| CylError::CodeGenError {
+                    message: format!("Unresolved type for variable '{}'", declare_stmt.name),
+                    span: Some(statement.span),
+                })?;
 
                 // Skip void variables (they don't make sense)
                 if matches!(var_type, Type::Void) {
                     return Err(CylError::CodeGenError {
                         message: "Cannot declare void variable".to_string(),
+                        span: Some(statement.span),
                     });
                 }
 
@@ -365,31 +537,22 @@ impl<'ctx> LLVMCodegen<'ctx> {
                 let init_value = self.compile_expression(&declare_stmt.value)?;
 
                 // Handle array literals specially
-                if matches!(&declare_stmt.value, Expression::ArrayLiteral(_)) {
+                if matches!(&declare_stmt.value.kind, ExpressionKind::ArrayLiteral(_)) {
                     // For array literals, the init_value is already a pointer to the array
                     // We can directly use it as the variable
-                    if let Expression::ArrayLiteral(elements) = &declare_stmt.value {
-                        // Determine array type from first element
-                        let first_val = self.compile_expression(&elements[0])?;
-                        let element_type = first_val.get_type();
-                        let array_size = elements.len() as u32;
-                        let _array_type = element_type.array_type(array_size);
-                        let inferred_type = Type::Array(Box::new(Type::Int)); // For now, assume int arrays
-
-                        self.variables.insert(
-                            declare_stmt.name.clone(),
-                            (init_value.into_pointer_value(), inferred_type),
-                        );
-                    }
-                } else if matches!(&declare_stmt.value, Expression::ObjectLiteral(_)) {
+                    self.variables.insert(
+                        declare_stmt.name.clone(),
+                        (init_value.into_pointer_value(), var_type),
+                    );
+                } else if matches!(&declare_stmt.value.kind, ExpressionKind::ObjectLiteral(_)) {
                     // For struct literals, the init_value is already a pointer to the struct
                     // We can directly use it as the variable
                     self.variables.insert(
                         declare_stmt.name.clone(),
                         (init_value.into_pointer_value(), var_type),
                     );
-                } else if matches!(&declare_stmt.value, Expression::MemberAccess { .. })
-                    && matches!(var_type, Type::Custom(_))
+                } else if matches!(&declare_stmt.value.kind, ExpressionKind::MemberAccess { .. })
+                    && matches!(&var_type, Type::Custom(name) if self.struct_types.contains_key(name))
                 {
                     // For struct field access that returns a struct pointer, use it directly
                     self.variables.insert(
@@ -404,7 +567,7 @@ impl<'ctx> LLVMCodegen<'ctx> {
                         .insert(declare_stmt.name.clone(), (alloca, var_type));
                 }
             }
-            Statement::Return(return_stmt) => {
+            StatementKind::Return(return_stmt) => {
                 if let Some(ref return_expr) = return_stmt.value {
                     let return_value = self.compile_expression(return_expr)?;
                     self.builder.build_return(Some(&return_value)).unwrap();
@@ -412,7 +575,7 @@ impl<'ctx> LLVMCodegen<'ctx> {
                     self.builder.build_return(None).unwrap();
                 }
             }
-            Statement::If(if_stmt) => {
+            StatementKind::If(if_stmt) => {
                 let condition_value = self.compile_expression(&if_stmt.condition)?;
                 let condition_bool = self.value_to_bool(condition_value)?;
 
@@ -447,7 +610,7 @@ impl<'ctx> LLVMCodegen<'ctx> {
                 // Merge block
                 self.builder.position_at_end(merge_bb);
             }
-            Statement::While(while_stmt) => {
+            StatementKind::While(while_stmt) => {
                 let function = self
                     .builder
                     .get_insert_block()
@@ -479,14 +642,44 @@ impl<'ctx> LLVMCodegen<'ctx> {
                 // After loop
                 self.builder.position_at_end(after_bb);
             }
-            Statement::Block(block) => {
+            StatementKind::Block(block) => {
                 for stmt in &block.statements {
                     self.compile_statement(stmt)?;
                 }
             }
-            Statement::For(for_stmt) => {
+            StatementKind::Match(match_stmt) => {
+                self.compile_match(match_stmt)?;
+            }
+            StatementKind::Try(try_stmt) => {
+                self.compile_try(try_stmt)?;
+            }
+            StatementKind::Throw(throw) => {
+                self.compile_throw(throw, statement.span)?;
+            }
+            StatementKind::For(for_stmt) => {
                 // Compile for loop: for variable in iterable { body }
-                // Currently supports: for i in N (iterate from 0 to N-1)
+                // Currently supports: for i in N (iterate from 0 to N-1) and
+                // for i in a..b / a..=b
+                let PatternKind::Identifier(variable) = &for_stmt.pattern.kind else {
+                    return Err(CylError::CodeGenError {
+                        message: "Destructuring for loops are not supported by the LLVM backend yet"
+                            .to_string(),
+                        span: Some(for_stmt.pattern.span),
+                    });
+                };
+                let (first, limit, inclusive) = match &for_stmt.iterable.kind {
+                    ExpressionKind::Range {
+                        start,
+                        end,
+                        inclusive,
+                    } => (Some(start.as_ref()), end.as_ref(), *inclusive),
+                    _ => (None, &for_stmt.iterable, false),
+                };
+                let predicate = match (first, inclusive) {
+                    (Some(_), true) => IntPredicate::SLE,
+                    (Some(_), false) => IntPredicate::SLT,
+                    (None, _) => IntPredicate::ULT,
+                };
 
                 // Get the current function
                 let current_fn = self
@@ -501,18 +694,21 @@ impl<'ctx> LLVMCodegen<'ctx> {
                 let body_bb = self.context.append_basic_block(current_fn, "loopbody");
                 let after_bb = self.context.append_basic_block(current_fn, "afterloop");
 
-                // Initialize loop variable (i = 0)
+                // Initialize loop variable (i = 0, or the start of the range)
                 let loop_var_type = self.context.i32_type(); // Changed from i64 to i32
                 let loop_var_ptr = self
                     .builder
-                    .build_alloca(loop_var_type, &for_stmt.variable)
+                    .build_alloca(loop_var_type, variable)
                     .unwrap();
-                let zero = loop_var_type.const_int(0, false);
-                self.builder.build_store(loop_var_ptr, zero).unwrap();
+                let initial = match first {
+                    Some(start) => self.compile_expression(start)?,
+                    None => loop_var_type.const_int(0, false).into(),
+                };
+                self.builder.build_store(loop_var_ptr, initial).unwrap();
 
                 // Store the loop variable in our variables map
                 self.variables
-                    .insert(for_stmt.variable.clone(), (loop_var_ptr, Type::Int));
+                    .insert(variable.clone(), (loop_var_ptr, Type::Int));
 
                 // Jump to loop condition check
                 self.builder.build_unconditional_branch(loop_bb).unwrap();
@@ -524,15 +720,15 @@ impl<'ctx> LLVMCodegen<'ctx> {
                     .build_load(loop_var_ptr, "loopvar")
                     .unwrap();
 
-                // Compile the iterable expression (e.g., the number 5 in "for i in 5")
-                let limit_val = self.compile_expression(&for_stmt.iterable)?;
+                // Compile the limit (e.g., the number 5 in "for i in 5")
+                let limit_val = self.compile_expression(limit)?;
                 let limit_int = limit_val.into_int_value();
 
-                // Compare: current_val < limit_val
+                // Compare: current_val < limit_val (<= for inclusive ranges)
                 let condition = self
                     .builder
                     .build_int_compare(
-                        IntPredicate::ULT,
+                        predicate,
                         current_val.into_int_value(),
                         limit_int,
                         "loopcond",
@@ -549,11 +745,28 @@ impl<'ctx> LLVMCodegen<'ctx> {
                     self.compile_statement(stmt)?;
                 }
 
-                // Increment loop variable: i = i + 1
+                // Increment loop variable: i = i + 1. Inclusive ranges leave
+                // after visiting the limit instead, as it may be the largest int
                 let current_val = self
                     .builder
                     .build_load(loop_var_ptr, "loopvar")
                     .unwrap();
+                if inclusive {
+                    let last = self
+                        .builder
+                        .build_int_compare(
+                            IntPredicate::EQ,
+                            current_val.into_int_value(),
+                            limit_int,
+                            "lastiter",
+                        )
+                        .unwrap();
+                    let step_bb = self.context.append_basic_block(current_fn, "loopstep");
+                    self.builder
+                        .build_conditional_branch(last, after_bb, step_bb)
+                        .unwrap();
+                    self.builder.position_at_end(step_bb);
+                }
                 let one = loop_var_type.const_int(1, false);
                 let next_val = self
                     .builder
@@ -568,11 +781,12 @@ impl<'ctx> LLVMCodegen<'ctx> {
                 self.builder.position_at_end(after_bb);
 
                 // Remove the loop variable from scope (optional, but clean)
-                self.variables.remove(&for_stmt.variable);
+                self.variables.remove(variable);
             }
             _ => {
                 return Err(CylError::CodeGenError {
-                    message: format!("Statement type not yet implemented: {statement:?}"),
+                    message: format!("Statement type not yet implemented: {:?}", statement.kind),
+                    span: Some(statement.span),
                 });
             }
         }
@@ -583,35 +797,36 @@ impl<'ctx> LLVMCodegen<'ctx> {
         &mut self,
         expression: &Expression,
     ) -> Result<BasicValueEnum<'ctx>, CylError> {
-        match expression {
-            Expression::IntLiteral(value) => Ok(self
+        if let Some((enum_name, variant)) = self.constructors.get(expression) {
+            let (enum_name, variant) = (enum_name.to_string(), variant.to_string());
+            let arguments = match &expression.kind {
+                ExpressionKind::Call { arguments, .. } => arguments.as_slice(),
+                _ => &[],
+            };
+            return self.compile_variant(&enum_name, &variant, arguments, expression);
+        }
+        match &expression.kind {
+            ExpressionKind::IntLiteral(value) => Ok(self
                 .context
                 .i32_type() // Changed from i64 to i32
                 .const_int(*value as u64, false)
                 .into()),
-            Expression::FloatLiteral(value) => {
+            ExpressionKind::FloatLiteral(value) => {
                 Ok(self.context.f64_type().const_float(*value).into())
             }
-            Expression::StringLiteral(value) => {
-                let string_type = self.context.i8_type().array_type(value.len() as u32 + 1);
-                let string_global =
-                    self.module
-                        .add_global(string_type, Some(AddressSpace::default()), "str");
-                let string_value = self.context.const_string(value.as_bytes(), true);
-                string_global.set_initializer(&string_value);
-                Ok(string_global.as_pointer_value().into())
-            }
-            Expression::BoolLiteral(value) => Ok(self
+            ExpressionKind::StringLiteral(value) => Ok(self.compile_string_literal(value).into()),
+            ExpressionKind::BoolLiteral(value) => Ok(self
                 .context
                 .bool_type()
                 .const_int(*value as u64, false)
                 .into()),
-            Expression::Identifier(name) => {
+            ExpressionKind::Identifier(name) => {
                 if let Some((variable, var_type)) = self.variables.get(name) {
                     // Don't try to load void types
                     if matches!(var_type, Type::Void) {
                         return Err(CylError::CodeGenError {
                             message: "Cannot use void variable".to_string(),
+                            span: Some(expression.span),
                         });
                     }
 
@@ -627,16 +842,26 @@ impl<'ctx> LLVMCodegen<'ctx> {
                 } else {
                     Err(CylError::CodeGenError {
                         message: format!("Undefined variable: {name}"),
+                        span: Some(expression.span),
                     })
                 }
             }
-            Expression::BinaryOp {
+            ExpressionKind::BinaryOp {
                 left,
                 operator,
                 right,
             } => {
                 let left_val = self.compile_expression(left)?;
                 let right_val = self.compile_expression(right)?;
+                let (left_type, right_type) = (self.type_of(left)?, self.type_of(right)?);
+                if *left_type == Type::String || *right_type == Type::String {
+                    return self.compile_string_op(
+                        operator,
+                        (left_val, left_type, runtime::is_concatenation(left)),
+                        (right_val, right_type, runtime::is_concatenation(right)),
+                        expression.span,
+                    );
+                }
 
                 match operator {
                     BinaryOperator::Add => {
@@ -681,6 +906,7 @@ impl<'ctx> LLVMCodegen<'ctx> {
                         } else {
                             Err(CylError::CodeGenError {
                                 message: "Invalid types for addition".to_string(),
+                                span: Some(expression.span),
                             })
                         }
                     }
@@ -699,6 +925,7 @@ impl<'ctx> LLVMCodegen<'ctx> {
                             Err(CylError::CodeGenError {
                                 message: "Subtraction not implemented for non-integers yet"
                                     .to_string(),
+                                span: Some(expression.span),
                             })
                         }
                     }
@@ -717,6 +944,7 @@ impl<'ctx> LLVMCodegen<'ctx> {
                             Err(CylError::CodeGenError {
                                 message: "Multiplication not implemented for non-integers yet"
                                     .to_string(),
+                                span: Some(expression.span),
                             })
                         }
                     }
@@ -735,6 +963,7 @@ impl<'ctx> LLVMCodegen<'ctx> {
                             Err(CylError::CodeGenError {
                                 message: "Division not implemented for non-integers yet"
                                     .to_string(),
+                                span: Some(expression.span),
                             })
                         }
                     }
@@ -754,6 +983,7 @@ impl<'ctx> LLVMCodegen<'ctx> {
                             Err(CylError::CodeGenError {
                                 message: "Equality comparison not implemented for non-integers yet"
                                     .to_string(),
+                                span: Some(expression.span),
                             })
                         }
                     }
@@ -774,6 +1004,7 @@ impl<'ctx> LLVMCodegen<'ctx> {
                                 message:
                                     "Not-equal comparison not implemented for non-integers yet"
                                         .to_string(),
+                                span: Some(expression.span),
                             })
                         }
                     }
@@ -794,6 +1025,7 @@ impl<'ctx> LLVMCodegen<'ctx> {
                                 message:
                                     "Less-than comparison not implemented for non-integers yet"
                                         .to_string(),
+                                span: Some(expression.span),
                             })
                         }
                     }
@@ -814,6 +1046,7 @@ impl<'ctx> LLVMCodegen<'ctx> {
                                 message:
                                     "Less-equal comparison not implemented for non-integers yet"
                                         .to_string(),
+                                span: Some(expression.span),
                             })
                         }
                     }
@@ -834,6 +1067,7 @@ impl<'ctx> LLVMCodegen<'ctx> {
                                 message:
                                     "Greater-than comparison not implemented for non-integers yet"
                                         .to_string(),
+                                span: Some(expression.span),
                             })
                         }
                     }
@@ -854,59 +1088,72 @@ impl<'ctx> LLVMCodegen<'ctx> {
                                 message:
                                     "Greater-equal comparison not implemented for non-integers yet"
                                         .to_string(),
+                                span: Some(expression.span),
                             })
                         }
                     }
                     _ => Err(CylError::CodeGenError {
                         message: format!("Binary operator not implemented: {operator:?}"),
+                        span: Some(expression.span),
                     }),
                 }
             }
-            Expression::Call { callee, arguments } => {
+            ExpressionKind::Call { callee, arguments } => {
+                if let ExpressionKind::MemberAccess { object, property } = &callee.kind {
+                    let receiver = self.compile_expression(object)?;
+                    if property == "len" && arguments.is_empty() && *self.type_of(object)? == Type::String {
+                        let length = self
+                            .call_runtime("cyl_string_length", &[receiver.into()])
+                            .unwrap()
+                            .into_int_value();
+                        return Ok(self
+                            .builder
+                            .build_int_truncate(length, self.context.i32_type(), "len")
+                            .unwrap()
+                            .into());
+                    }
+                    return Err(CylError::CodeGenError {
+                        message: format!("Method not implemented: {property}"),
+                        span: Some(expression.span),
+                    });
+                }
+
                 // For now, assume callee is an identifier representing a function name
-                if let Expression::Identifier(function_name) = callee.as_ref() {
+                if let ExpressionKind::Identifier(function_name) = &callee.kind {
                     // Handle builtin functions specially
-                    if function_name == "print_int" {
-                        return self.compile_print_int_call(arguments);
+                    if matches!(function_name.as_str(), "print" | "println" | "print_int") {
+                        return self.compile_print_call(function_name, arguments, expression.span);
                     }
 
-                    if let Some(fn_value) = self.functions.get(function_name).copied() {
-                        let args: Vec<BasicMetadataValueEnum> = arguments
-                            .iter()
-                            .map(|arg| self.compile_expression(arg).map(|v| v.into()))
-                            .collect::<Result<Vec<_>, _>>()?;
-
-                        let call_result =
-                            self.builder.build_call(fn_value, &args, "calltmp").unwrap();
-                        if let Some(result) = call_result.try_as_basic_value().left() {
-                            Ok(result)
-                        } else {
-                            // Void function - this should not be used in expressions that need a value
-                            Err(CylError::CodeGenError {
-                                message: format!(
-                                    "Function '{function_name}' returns void and cannot be used in expressions"
-                                ),
-                            })
-                        }
+                    let function_name = self.types.callee(expression).unwrap_or(function_name).to_string();
+                    let call_result = self.compile_call(&function_name, arguments, expression.span)?;
+                    if let Some(result) = call_result.try_as_basic_value().left() {
+                        Ok(result)
                     } else {
+                        // Void function - this should not be used in expressions that need a value
                         Err(CylError::CodeGenError {
-                            message: format!("Unknown function: {function_name}"),
+                            message: format!(
+                                "Function '{function_name}' returns void and cannot be used in expressions"
+                            ),
+                            span: Some(expression.span),
                         })
                     }
                 } else {
                     Err(CylError::CodeGenError {
                         message: "Complex function calls not yet supported".to_string(),
+                        span: Some(expression.span),
                     })
                 }
             }
-            Expression::Assignment { target, value } => {
+            ExpressionKind::Assignment { target, value } => {
                 // For now, only support identifier assignments
-                if let Expression::Identifier(var_name) = target.as_ref() {
+                if let ExpressionKind::Identifier(var_name) = &target.kind {
                     let var_ptr = if let Some((var_ptr, _)) = self.variables.get(var_name) {
                         *var_ptr
                     } else {
                         return Err(CylError::CodeGenError {
                             message: format!("Undefined variable in assignment: {var_name}"),
+                            span: Some(expression.span),
                         });
                     };
 
@@ -916,13 +1163,15 @@ impl<'ctx> LLVMCodegen<'ctx> {
                 } else {
                     Err(CylError::CodeGenError {
                         message: "Only simple variable assignment supported currently".to_string(),
+                        span: Some(expression.span),
                     })
                 }
             }
-            Expression::ArrayLiteral(elements) => {
+            ExpressionKind::ArrayLiteral(elements) => {
                 if elements.is_empty() {
                     return Err(CylError::CodeGenError {
                         message: "Empty arrays not supported yet".to_string(),
+                        span: Some(expression.span),
                     });
                 }
 
@@ -955,13 +1204,14 @@ impl<'ctx> LLVMCodegen<'ctx> {
 
                 Ok(array_ptr.into())
             }
-            Expression::IndexAccess { object, index } => {
+            ExpressionKind::IndexAccess { object, index } => {
                 let array_val = self.compile_expression(object)?;
                 let index_val = self.compile_expression(index)?;
 
                 if !array_val.is_pointer_value() {
                     return Err(CylError::CodeGenError {
                         message: "Can only index into arrays".to_string(),
+                        span: Some(expression.span),
                     });
                 }
 
@@ -971,6 +1221,7 @@ impl<'ctx> LLVMCodegen<'ctx> {
                 } else {
                     return Err(CylError::CodeGenError {
                         message: "Array index must be an integer".to_string(),
+                        span: Some(expression.span),
                     });
                 };
 
@@ -1005,10 +1256,10 @@ impl<'ctx> LLVMCodegen<'ctx> {
 
                 Ok(loaded_val)
             }
-            Expression::ObjectLiteral(fields) => {
+            ExpressionKind::ObjectLiteral(fields) => {
                 // Struct literal compilation
                 // Look for __struct_name__ to identify the struct type
-                if let Some(Expression::StringLiteral(struct_name)) = fields.get("__struct_name__")
+                if let Some(ExpressionKind::StringLiteral(struct_name)) = fields.get("__struct_name__").map(|e| &e.kind)
                 {
                     if let Some((struct_type, field_info)) =
                         self.struct_types.get(struct_name).cloned()
@@ -1045,17 +1296,19 @@ impl<'ctx> LLVMCodegen<'ctx> {
                     } else {
                         Err(CylError::CodeGenError {
                             message: format!("Unknown struct type: {struct_name}"),
+                            span: Some(expression.span),
                         })
                     }
                 } else {
                     Err(CylError::CodeGenError {
                         message: "Object literal without struct type information".to_string(),
+                        span: Some(expression.span),
                     })
                 }
             }
-            Expression::MemberAccess { object, property } => {
+            ExpressionKind::MemberAccess { object, property } => {
                 // Struct field access compilation
-                let struct_ptr = if let Expression::Identifier(var_name) = object.as_ref() {
+                let struct_ptr = if let ExpressionKind::Identifier(var_name) = &object.kind {
                     // For identifiers, get the pointer directly from variables without loading
                     if let Some((variable, var_type)) = self.variables.get(var_name) {
                         if matches!(var_type, Type::Custom(_)) {
@@ -1063,11 +1316,13 @@ impl<'ctx> LLVMCodegen<'ctx> {
                         } else {
                             return Err(CylError::CodeGenError {
                                 message: format!("Variable '{var_name}' is not a struct"),
+                                span: Some(expression.span),
                             });
                         }
                     } else {
                         return Err(CylError::CodeGenError {
                             message: format!("Unknown variable '{var_name}'"),
+                            span: Some(expression.span),
                         });
                     }
                 } else {
@@ -1076,52 +1331,56 @@ impl<'ctx> LLVMCodegen<'ctx> {
                     if !struct_val.is_pointer_value() {
                         return Err(CylError::CodeGenError {
                             message: "Can only access fields on struct pointers".to_string(),
+                            span: Some(expression.span),
                         });
                     }
                     struct_val.into_pointer_value()
                 };
 
-                // Find the field index - iterate through all struct types to find matching field
-                let struct_types_clone = self.struct_types.clone();
-                for (_struct_type, field_info) in struct_types_clone.values() {
-                    if let Some(field_index) =
-                        field_info.iter().position(|(name, _)| name == property)
-                    {
-                        // Get pointer to the field
-                        let field_ptr = self
+                // Find the field in the struct the object is of
+                let struct_name = match self.type_of(object)? {
+                    Type::Custom(name) | Type::Generic(name, _) => name.clone(),
+                    _ => String::new(),
+                };
+                let field = self.struct_types.get(&struct_name).and_then(|(_, field_info)| {
+                    field_info
+                        .iter()
+                        .position(|(name, _)| name == property)
+                        .map(|field_index| (field_index, field_info[field_index].1.clone()))
+                });
+                if let Some((field_index, field_type)) = field {
+                    // Get pointer to the field
+                    let field_ptr = self
+                        .builder
+                        .build_struct_gep(
+                            struct_ptr,
+                            field_index as u32,
+                            &format!("field_{property}"),
+                        )
+                        .unwrap();
+
+                    // For struct fields, return the pointer instead of loading the value
+                    if matches!(field_type, Type::Custom(_)) {
+                        return Ok(field_ptr.into());
+                    } else {
+                        // For primitive fields, load the value
+                        let _llvm_type = self.cyl_type_to_llvm(&field_type)?;
+                        let loaded_val = self
                             .builder
-                            .build_struct_gep(
-                                struct_ptr,
-                                field_index as u32,
-                                &format!("field_{property}"),
-                            )
+                            .build_load(field_ptr, &format!("load_{property}"))
                             .unwrap();
-
-                        // Load the value from the field
-                        let field_type = &field_info[field_index].1;
-
-                        // For struct fields, return the pointer instead of loading the value
-                        if matches!(field_type, Type::Custom(_)) {
-                            return Ok(field_ptr.into());
-                        } else {
-                            // For primitive fields, load the value
-                            let _llvm_type = self.cyl_type_to_llvm(field_type)?;
-                            let loaded_val = self
-                                .builder
-                                .build_load(field_ptr, &format!("load_{property}"))
-                                .unwrap();
-                            return Ok(loaded_val);
-                        }
+                        return Ok(loaded_val);
                     }
                 }
 
                 Err(CylError::CodeGenError {
-                    message: format!("Field '{property}' not found in any struct type"),
+                    message: format!("Field '{property}' not found in struct type '{struct_name}'"),
+                    span: Some(expression.span),
                 })
             }
-            Expression::IndexAccess { object, index } => {
+            ExpressionKind::IndexAccess { object, index } => {
                 // Array indexing compilation: array[index]
-                let array_ptr = if let Expression::Identifier(var_name) = object.as_ref() {
+                let array_ptr = if let ExpressionKind::Identifier(var_name) = &object.kind {
                     // Get the array variable directly
                     if let Some((variable, var_type)) = self.variables.get(var_name) {
                         if matches!(var_type, Type::Array(_)) {
@@ -1129,11 +1388,13 @@ impl<'ctx> LLVMCodegen<'ctx> {
                         } else {
                             return Err(CylError::CodeGenError {
                                 message: format!("Variable '{var_name}' is not an array"),
+                                span: Some(expression.span),
                             });
                         }
                     } else {
                         return Err(CylError::CodeGenError {
                             message: format!("Unknown variable '{var_name}'"),
+                            span: Some(expression.span),
                         });
                     }
                 } else {
@@ -1142,6 +1403,7 @@ impl<'ctx> LLVMCodegen<'ctx> {
                     if !array_val.is_pointer_value() {
                         return Err(CylError::CodeGenError {
                             message: "Can only index arrays".to_string(),
+                            span: Some(expression.span),
                         });
                     }
                     array_val.into_pointer_value()
@@ -1154,6 +1416,7 @@ impl<'ctx> LLVMCodegen<'ctx> {
                 } else {
                     return Err(CylError::CodeGenError {
                         message: "Array index must be an integer".to_string(),
+                        span: Some(expression.span),
                     });
                 };
 
@@ -1177,11 +1440,647 @@ impl<'ctx> LLVMCodegen<'ctx> {
                 Ok(loaded_val)
             }
             _ => Err(CylError::CodeGenError {
-                message: format!("Expression type not implemented: {expression:?}"),
+                message: format!("Expression type not implemented: {:?}", expression.kind),
+                span: Some(expression.span),
             }),
         }
     }
 
+    /// Pointer to payload field `index` of the enum value at `enum_ptr`,
+    /// typed as `llvm_type`.
+    fn variant_field_ptr(
+        &self,
+        enum_ptr: PointerValue<'ctx>,
+        index: usize,
+        llvm_type: BasicTypeEnum<'ctx>,
+        span: Span,
+    ) -> Result<PointerValue<'ctx>, CylError> {
+        if llvm_type.is_struct_type() {
+            // Every payload field has to fit one 64-bit slot
+            return Err(CylError::CodeGenError {
+                message: format!("Enum payloads of type {llvm_type:?} are not supported yet"),
+                span: Some(span),
+            });
+        }
+        let payload_ptr = self
+            .builder
+            .build_struct_gep(enum_ptr, 1, "payload")
+            .unwrap();
+        let slot_ptr = unsafe {
+            self.builder
+                .build_gep(
+                    payload_ptr,
+                    &[
+                        self.context.i32_type().const_zero(),
+                        self.context.i32_type().const_int(index as u64, false),
+                    ],
+                    &format!("slot_{index}"),
+                )
+                .unwrap()
+        };
+        Ok(self
+            .builder
+            .build_pointer_cast(
+                slot_ptr,
+                llvm_type.ptr_type(AddressSpace::default()),
+                &format!("field_{index}"),
+            )
+            .unwrap())
+    }
+
+    /// Build a variant of the enum `enum_name` and return it by value.
+    fn compile_variant(
+        &mut self,
+        enum_name: &str,
+        variant: &str,
+        arguments: &[Expression],
+        expression: &Expression,
+    ) -> Result<BasicValueEnum<'ctx>, CylError> {
+        let (enum_type, layout) = self.enum_types[enum_name].clone();
+        let Some(variant) = layout.variant(variant).cloned() else {
+            return Err(CylError::CodeGenError {
+                message: format!("Unknown variant: {enum_name}.{variant}"),
+                span: Some(expression.span),
+            });
+        };
+
+        let enum_ptr = self.builder.build_alloca(enum_type, "enum_alloc").unwrap();
+        let tag_ptr = self.builder.build_struct_gep(enum_ptr, 0, "tag").unwrap();
+        let tag = self.context.i64_type().const_int(variant.tag as u64, false);
+        self.builder.build_store(tag_ptr, tag).unwrap();
+
+        // Fields are stored with the type of their value, which also settles
+        // generic ones such as the `T` of `Some(T)`
+        for (i, argument) in arguments.iter().enumerate().take(variant.fields.len()) {
+            let value = self.compile_expression(argument)?;
+            let field_ptr = self.variant_field_ptr(enum_ptr, i, value.get_type(), argument.span)?;
+            self.builder.build_store(field_ptr, value).unwrap();
+        }
+
+        Ok(self.builder.build_load(enum_ptr, "enum_val").unwrap())
+    }
+
+    /// Compile a match as a chain of tests: an arm whose pattern or guard
+    /// fails falls through to the next one.
+    fn compile_match(&mut self, match_stmt: &MatchStatement) -> Result<(), CylError> {
+        let scrutinee_type = match_stmt
+            .scrutinee_type
+            .clone()
+            .unwrap_or(Type::Dynamic);
+        let value = self.compile_expression(&match_stmt.expression)?;
+
+        let function = self
+            .builder
+            .get_insert_block()
+            .unwrap()
+            .get_parent()
+            .unwrap();
+        let merge_bb = self.context.append_basic_block(function, "match_end");
+
+        for arm in &match_stmt.arms {
+            let next_bb = self.context.append_basic_block(function, "match_next");
+            let outer_variables = self.variables.clone();
+
+            self.compile_pattern(&arm.pattern, value, &scrutinee_type, next_bb)?;
+            if let Some(ref guard) = arm.guard {
+                let guard_value = self.compile_expression(guard)?;
+                let guard_bool = self.value_to_bool(guard_value)?;
+                self.branch_unless(guard_bool, next_bb);
+            }
+            for stmt in &arm.body.statements {
+                self.compile_statement(stmt)?;
+            }
+            if self.builder.get_insert_block().unwrap().get_terminator().is_none() {
+                self.builder.build_unconditional_branch(merge_bb).unwrap();
+            }
+
+            self.variables = outer_variables;
+            self.builder.position_at_end(next_bb);
+        }
+
+        // The type checker proved the arms exhaustive
+        self.builder.build_unreachable().unwrap();
+        self.builder.position_at_end(merge_bb);
+        Ok(())
+    }
+
+    /// Compile the body with its catch clauses as handlers, then the clauses
+    /// that some `throw` in the body reaches.
+    fn compile_try(&mut self, try_stmt: &TryStatement) -> Result<(), CylError> {
+        let function = self
+            .builder
+            .get_insert_block()
+            .unwrap()
+            .get_parent()
+            .unwrap();
+        let merge_bb = self.context.append_basic_block(function, "try_end");
+
+        self.handlers.push(
+            try_stmt
+                .catch_clauses
+                .iter()
+                .map(|clause| Handler {
+                    exception_type: clause.exception_type.clone(),
+                    entries: Vec::new(),
+                })
+                .collect(),
+        );
+        let outer_variables = self.variables.clone();
+        let body = try_stmt
+            .body
+            .statements
+            .iter()
+            .try_for_each(|stmt| self.compile_statement(stmt));
+        self.variables = outer_variables;
+        let handlers = self.handlers.pop().unwrap_or_default();
+        body?;
+        if self.builder.get_insert_block().unwrap().get_terminator().is_none() {
+            self.builder.build_unconditional_branch(merge_bb).unwrap();
+        }
+
+        // A clause is compiled once for each type of value it receives
+        for (clause, handler) in try_stmt.catch_clauses.iter().zip(handlers) {
+            for (catch_bb, exception_ptr, exception_type) in handler.entries {
+                self.builder.position_at_end(catch_bb);
+                let outer_variables = self.variables.clone();
+                if let Some(variable) = &clause.variable {
+                    self.variables
+                        .insert(variable.clone(), (exception_ptr, exception_type));
+                }
+                for stmt in &clause.body.statements {
+                    self.compile_statement(stmt)?;
+                }
+                if self.builder.get_insert_block().unwrap().get_terminator().is_none() {
+                    self.builder.build_unconditional_branch(merge_bb).unwrap();
+                }
+                self.variables = outer_variables;
+            }
+        }
+
+        self.builder.position_at_end(merge_bb);
+        Ok(())
+    }
+
+    /// Branch to the first enclosing catch clause that accepts the thrown
+    /// value's type, or leave the function with the exception when no
+    /// clause here catches it.
+    fn compile_throw(&mut self, throw: &ThrowStatement, span: Span) -> Result<(), CylError> {
+        let value_type = throw.value_type.clone().unwrap_or(Type::Dynamic);
+        let value = self.compile_expression(&throw.value)?;
+        let Some((catch_bb, exception_ptr)) = self.handler_entry(&value_type)? else {
+            return self.raise(value, &value_type, span);
+        };
+        self.builder.build_store(exception_ptr, value).unwrap();
+        self.builder.build_unconditional_branch(catch_bb).unwrap();
+        Ok(())
+    }
+
+    /// The block starting the first enclosing catch clause that accepts
+    /// values of type `ty` and the slot it reads the value from, if any.
+    fn handler_entry(
+        &mut self,
+        ty: &Type,
+    ) -> Result<Option<(BasicBlock<'ctx>, PointerValue<'ctx>)>, CylError> {
+        let target = self.handlers.iter().enumerate().rev().find_map(|(level, handlers)| {
+            handlers
+                .iter()
+                .position(|handler| exceptions::catches(handler.exception_type.as_ref(), ty))
+                .map(|index| (level, index))
+        });
+        let Some((level, index)) = target else {
+            return Ok(None);
+        };
+        let handler = &self.handlers[level][index];
+        if let Some((catch_bb, exception_ptr, _)) =
+            handler.entries.iter().find(|(_, _, entry_type)| entry_type == ty)
+        {
+            return Ok(Some((*catch_bb, *exception_ptr)));
+        }
+        let function = self
+            .builder
+            .get_insert_block()
+            .unwrap()
+            .get_parent()
+            .unwrap();
+        let catch_bb = self.context.append_basic_block(function, "catch");
+        let exception_ptr = self.create_entry_block_alloca("exception", ty)?;
+        self.handlers[level][index]
+            .entries
+            .push((catch_bb, exception_ptr, ty.clone()));
+        Ok(Some((catch_bb, exception_ptr)))
+    }
+
+    /// Record `value`, of type `ty`, thrown at `span`, as the pending
+    /// exception and leave the function with it.
+    fn raise(
+        &mut self,
+        value: BasicValueEnum<'ctx>,
+        ty: &Type,
+        span: Span,
+    ) -> Result<(), CylError> {
+        if !(value.is_int_value() || value.is_float_value() || value.is_pointer_value()) {
+            // Only what fits the record's value slot can be recorded
+            return Err(CylError::CodeGenError {
+                message: format!(
+                    "Throwing `{ty}` values out of a function is not supported by the LLVM backend yet"
+                ),
+                span: Some(span),
+            });
+        }
+        let i64_type = self.context.i64_type();
+        let number = i64_type.const_int(self.exception_number(ty), false);
+        let slot = self.exception_slot(exceptions::VALUE_OFFSET, "exception_value");
+        let slot = self
+            .builder
+            .build_pointer_cast(
+                slot,
+                value.get_type().ptr_type(AddressSpace::default()),
+                "exception_slot",
+            )
+            .unwrap();
+        self.builder.build_store(slot, value).unwrap();
+        let line = i64_type.const_int(span.line as u64, false);
+        let column = i64_type.const_int(span.column as u64, false);
+        for (offset, field) in [
+            (exceptions::TYPE_OFFSET, number),
+            (exceptions::LINE_OFFSET, line),
+            (exceptions::COLUMN_OFFSET, column),
+        ] {
+            let field_ptr = self.exception_slot(offset, "exception_field");
+            self.builder.build_store(field_ptr, field).unwrap();
+        }
+        self.unwind();
+        Ok(())
+    }
+
+    /// Leave the function with the pending exception: return a zero, which
+    /// the caller never reads, or report the exception in `main`.
+    fn unwind(&mut self) {
+        if let Some(report) = self.uncaught {
+            self.builder.build_unconditional_branch(report).unwrap();
+            return;
+        }
+        let function = self
+            .builder
+            .get_insert_block()
+            .unwrap()
+            .get_parent()
+            .unwrap();
+        let return_type = function.get_type().get_return_type();
+        let zero: Option<BasicValueEnum> = return_type.map(|ty| match ty {
+            BasicTypeEnum::IntType(int_type) => int_type.const_zero().into(),
+            BasicTypeEnum::FloatType(float_type) => float_type.const_zero().into(),
+            BasicTypeEnum::PointerType(ptr_type) => ptr_type.const_zero().into(),
+            BasicTypeEnum::ArrayType(array_type) => array_type.const_zero().into(),
+            BasicTypeEnum::StructType(struct_type) => struct_type.const_zero().into(),
+            BasicTypeEnum::VectorType(vector_type) => vector_type.const_zero().into(),
+        });
+        match zero {
+            Some(zero) => self.builder.build_return(Some(&zero)).unwrap(),
+            None => self.builder.build_return(None).unwrap(),
+        };
+    }
+
+    /// Call the user function, or instance, `name`. Missing arguments take
+    /// their parameters' defaults, evaluated with only the earlier
+    /// parameters in scope, as the interpreter does.
+    fn compile_call(
+        &mut self,
+        name: &str,
+        arguments: &[Expression],
+        span: Span,
+    ) -> Result<CallSiteValue<'ctx>, CylError> {
+        let Some(fn_value) = self.functions.get(name).copied() else {
+            return Err(CylError::CodeGenError {
+                message: format!("Unknown function: {name}"),
+                span: Some(span),
+            });
+        };
+        let mut values = arguments
+            .iter()
+            .map(|arg| self.compile_expression(arg))
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some((parameters, types)) = self.defaults.get(name).cloned() {
+            if values.len() < parameters.len() {
+                // The defaults were checked with the callee
+                let caller = std::mem::take(&mut self.variables);
+                let caller_types = std::mem::replace(&mut self.types, types);
+                let result = self.default_arguments(&parameters, &mut values);
+                self.variables = caller;
+                self.types = caller_types;
+                result?;
+            }
+        }
+
+        let args: Vec<BasicMetadataValueEnum> = values.into_iter().map(Into::into).collect();
+        let call_result = self.builder.build_call(fn_value, &args, "calltmp").unwrap();
+        self.check_exception(name)?;
+        Ok(call_result)
+    }
+
+    /// Append the defaults of the parameters `values` does not cover.
+    fn default_arguments(
+        &mut self,
+        parameters: &[Parameter],
+        values: &mut Vec<BasicValueEnum<'ctx>>,
+    ) -> Result<(), CylError> {
+        for (parameter, &value) in parameters.iter().zip(values.iter()) {
+            let alloca = self.create_entry_block_alloca(&parameter.name, &parameter.param_type)?;
+            self.builder.build_store(alloca, value).unwrap();
+            self.variables
+                .insert(parameter.name.clone(), (alloca, parameter.param_type.clone()));
+        }
+        for parameter in &parameters[values.len()..] {
+            let Some(default) = &parameter.default_value else {
+                return Err(CylError::CodeGenError {
+                    message: format!("Missing argument for parameter `{}`", parameter.name),
+                    span: Some(parameter.span),
+                });
+            };
+            let value = self.compile_expression(default)?;
+            let alloca = self.create_entry_block_alloca(&parameter.name, &parameter.param_type)?;
+            self.builder.build_store(alloca, value).unwrap();
+            self.variables
+                .insert(parameter.name.clone(), (alloca, parameter.param_type.clone()));
+            values.push(value);
+        }
+        Ok(())
+    }
+
+    /// After a call to the function `name`, hand an exception it let escape
+    /// to a catch clause around the call, or leave with it in turn.
+    fn check_exception(&mut self, name: &str) -> Result<(), CylError> {
+        let types = self.escaping.get(name).cloned().unwrap_or_default();
+        if types.is_empty() {
+            return Ok(());
+        }
+        let function = self
+            .builder
+            .get_insert_block()
+            .unwrap()
+            .get_parent()
+            .unwrap();
+        let type_ptr = self.exception_slot(exceptions::TYPE_OFFSET, "exception_type");
+        let number = self
+            .builder
+            .build_load(type_ptr, "exception_type")
+            .unwrap()
+            .into_int_value();
+        let none = self.context.i64_type().const_zero();
+        let is_thrown = self
+            .builder
+            .build_int_compare(IntPredicate::NE, number, none, "thrown")
+            .unwrap();
+        let thrown_bb = self.context.append_basic_block(function, "thrown");
+        let next_bb = self.context.append_basic_block(function, "call_end");
+        self.builder
+            .build_conditional_branch(is_thrown, thrown_bb, next_bb)
+            .unwrap();
+
+        self.builder.position_at_end(thrown_bb);
+        for ty in &types {
+            let Some((catch_bb, exception_ptr)) = self.handler_entry(ty)? else {
+                continue;
+            };
+            let caught_bb = self.context.append_basic_block(function, "caught");
+            let rest_bb = self.context.append_basic_block(function, "rethrow");
+            let expected = self.context.i64_type().const_int(self.exception_number(ty), false);
+            let matched = self
+                .builder
+                .build_int_compare(IntPredicate::EQ, number, expected, "matched")
+                .unwrap();
+            self.builder
+                .build_conditional_branch(matched, caught_bb, rest_bb)
+                .unwrap();
+
+            self.builder.position_at_end(caught_bb);
+            let value = self.exception_value(ty)?;
+            self.builder.build_store(type_ptr, none).unwrap();
+            self.builder.build_store(exception_ptr, value).unwrap();
+            self.builder.build_unconditional_branch(catch_bb).unwrap();
+            self.builder.position_at_end(rest_bb);
+        }
+        self.unwind();
+
+        self.builder.position_at_end(next_bb);
+        Ok(())
+    }
+
+    /// Report the pending exception, which is of one of the `uncaught`
+    /// types, starting at the `report` block, as the interpreter reports an
+    /// uncaught exception.
+    fn report_uncaught(
+        &mut self,
+        report: BasicBlock<'ctx>,
+        uncaught: &[Type],
+    ) -> Result<(), CylError> {
+        let function = report.get_parent().unwrap();
+        self.builder.position_at_end(report);
+        let type_ptr = self.exception_slot(exceptions::TYPE_OFFSET, "exception_type");
+        let number = self
+            .builder
+            .build_load(type_ptr, "exception_type")
+            .unwrap()
+            .into_int_value();
+        let line_ptr = self.exception_slot(exceptions::LINE_OFFSET, "exception_line");
+        let line = self.builder.build_load(line_ptr, "line").unwrap();
+        let column_ptr = self.exception_slot(exceptions::COLUMN_OFFSET, "exception_column");
+        let column = self.builder.build_load(column_ptr, "column").unwrap();
+        let prefix = self.compile_string_literal("Uncaught exception: ");
+        for ty in uncaught {
+            let matched_bb = self.context.append_basic_block(function, "report");
+            let rest_bb = self.context.append_basic_block(function, "report_next");
+            let expected = self.context.i64_type().const_int(self.exception_number(ty), false);
+            let matched = self
+                .builder
+                .build_int_compare(IntPredicate::EQ, number, expected, "matched")
+                .unwrap();
+            self.builder
+                .build_conditional_branch(matched, matched_bb, rest_bb)
+                .unwrap();
+
+            self.builder.position_at_end(matched_bb);
+            let value = self.exception_value(ty)?;
+            let text = self.compile_to_string(value, ty, Span::default())?;
+            let message = self
+                .call_runtime("cyl_string_concat", &[prefix.into(), text.into()])
+                .unwrap();
+            self.call_runtime(
+                "cyl_runtime_error",
+                &[message.into(), line.into(), column.into()],
+            );
+            self.builder.build_unreachable().unwrap();
+            self.builder.position_at_end(rest_bb);
+        }
+        // The pending exception is always of one of the types
+        self.builder.build_unreachable().unwrap();
+        Ok(())
+    }
+
+    /// The number identifying exceptions of type `ty` in the record.
+    fn exception_number(&self, ty: &Type) -> u64 {
+        let index = self
+            .exception_types
+            .iter()
+            .position(|known| known == ty)
+            .expect("exception type numbered by declare_exceptions");
+        index as u64 + 1
+    }
+
+    /// Pointer to the `i64` at `offset` in the record.
+    fn exception_slot(&self, offset: u32, name: &str) -> PointerValue<'ctx> {
+        let record = self
+            .exception
+            .expect("exception record declared by declare_exceptions");
+        self.builder
+            .build_struct_gep(record, offset / 8, name)
+            .unwrap()
+    }
+
+    /// The pending exception's value, of type `ty`.
+    fn exception_value(&self, ty: &Type) -> Result<BasicValueEnum<'ctx>, CylError> {
+        let value_type = self.cyl_type_to_llvm(ty)?;
+        let slot = self.exception_slot(exceptions::VALUE_OFFSET, "exception_value");
+        let slot = self
+            .builder
+            .build_pointer_cast(
+                slot,
+                value_type.ptr_type(AddressSpace::default()),
+                "exception_slot",
+            )
+            .unwrap();
+        Ok(self.builder.build_load(slot, "exception").unwrap())
+    }
+
+    /// Test `value` against `pattern` and bind its variables, branching to
+    /// `fail_bb` as soon as a test fails.
+    fn compile_pattern(
+        &mut self,
+        pattern: &Pattern,
+        value: BasicValueEnum<'ctx>,
+        value_type: &Type,
+        fail_bb: BasicBlock<'ctx>,
+    ) -> Result<(), CylError> {
+        let layout = match value_type {
+            Type::Custom(name) | Type::Generic(name, _) => {
+                self.enum_types.get(name).map(|(_, layout)| layout.clone())
+            }
+            _ => None,
+        };
+
+        match &pattern.kind {
+            PatternKind::Wildcard => Ok(()),
+            PatternKind::Identifier(name) => {
+                if let Some(variant) = layout.as_ref().and_then(|l| l.variant(name)) {
+                    let enum_ptr = self.spill(value, "scrutinee");
+                    self.test_tag(enum_ptr, variant.tag, fail_bb);
+                } else {
+                    let alloca = self.create_entry_block_alloca(name, value_type)?;
+                    self.builder.build_store(alloca, value).unwrap();
+                    self.variables
+                        .insert(name.clone(), (alloca, value_type.clone()));
+                }
+                Ok(())
+            }
+            PatternKind::Literal(literal) => {
+                let expected = self.compile_expression(literal)?;
+                let matched = if value.is_float_value() {
+                    self.builder
+                        .build_float_compare(
+                            inkwell::FloatPredicate::OEQ,
+                            value.into_float_value(),
+                            expected.into_float_value(),
+                            "pat_eq",
+                        )
+                        .unwrap()
+                } else if value.is_int_value() {
+                    self.builder
+                        .build_int_compare(
+                            IntPredicate::EQ,
+                            value.into_int_value(),
+                            expected.into_int_value(),
+                            "pat_eq",
+                        )
+                        .unwrap()
+                } else {
+                    return Err(CylError::CodeGenError {
+                        message: format!("Literal patterns on {value_type:?} values are not supported yet"),
+                        span: Some(pattern.span),
+                    });
+                };
+                self.branch_unless(matched, fail_bb);
+                Ok(())
+            }
+            PatternKind::TupleOrEnum(path, fields)
+            | PatternKind::Enum {
+                variant: path,
+                fields,
+            } => {
+                let name = path.rsplit('.').next().unwrap_or(path);
+                let Some(variant) = layout.as_ref().and_then(|l| l.variant(name)).cloned() else {
+                    return Err(CylError::CodeGenError {
+                        message: format!("Pattern `{path}` cannot match a value of type {value_type:?}"),
+                        span: Some(pattern.span),
+                    });
+                };
+                let enum_ptr = self.spill(value, "scrutinee");
+                self.test_tag(enum_ptr, variant.tag, fail_bb);
+                let field_types = layout.as_ref().unwrap().field_types(&variant, value_type);
+                for (i, (field, field_type)) in fields.iter().zip(&field_types).enumerate() {
+                    let llvm_type = self.cyl_type_to_llvm(field_type)?;
+                    let field_ptr = self.variant_field_ptr(enum_ptr, i, llvm_type, field.span)?;
+                    let field_value = self
+                        .builder
+                        .build_load(field_ptr, &format!("field_{i}"))
+                        .unwrap();
+                    self.compile_pattern(field, field_value, field_type, fail_bb)?;
+                }
+                Ok(())
+            }
+            _ => Err(CylError::CodeGenError {
+                message: "Struct and tuple patterns are not supported yet".to_string(),
+                span: Some(pattern.span),
+            }),
+        }
+    }
+
+    /// Store an aggregate value in a stack slot so its fields can be addressed.
+    fn spill(&self, value: BasicValueEnum<'ctx>, name: &str) -> PointerValue<'ctx> {
+        let ptr = self.builder.build_alloca(value.get_type(), name).unwrap();
+        self.builder.build_store(ptr, value).unwrap();
+        ptr
+    }
+
+    fn test_tag(&self, enum_ptr: PointerValue<'ctx>, tag: i64, fail_bb: BasicBlock<'ctx>) {
+        let tag_ptr = self.builder.build_struct_gep(enum_ptr, 0, "tag").unwrap();
+        let actual = self.builder.build_load(tag_ptr, "tag_val").unwrap();
+        let matched = self
+            .builder
+            .build_int_compare(
+                IntPredicate::EQ,
+                actual.into_int_value(),
+                self.context.i64_type().const_int(tag as u64, false),
+                "is_variant",
+            )
+            .unwrap();
+        self.branch_unless(matched, fail_bb);
+    }
+
+    /// Continue in a fresh block if `condition` holds, else go to `fail_bb`.
+    fn branch_unless(&self, condition: IntValue<'ctx>, fail_bb: BasicBlock<'ctx>) {
+        let function = self
+            .builder
+            .get_insert_block()
+            .unwrap()
+            .get_parent()
+            .unwrap();
+        let next_bb = self.context.append_basic_block(function, "match_test");
+        self.builder
+            .build_conditional_branch(condition, next_bb, fail_bb)
+            .unwrap();
+        self.builder.position_at_end(next_bb);
+    }
+
     fn value_to_bool(&self, value: BasicValueEnum<'ctx>) -> Result<IntValue<'ctx>, CylError> {
         if value.is_int_value() {
             let int_val = value.into_int_value();
@@ -1226,6 +2125,7 @@ impl<'ctx> LLVMCodegen<'ctx> {
             Type::Char => Ok(self.context.i8_type().into()),
             Type::Void => Err(CylError::CodeGenError {
                 message: "Void type cannot be used as a basic type".to_string(),
+                span: None,
             }),
             Type::Custom(name) => {
                 // Map common type names
@@ -1241,12 +2141,15 @@ impl<'ctx> LLVMCodegen<'ctx> {
                     "f32" | "float32" => Ok(self.context.f32_type().into()),
                     "f64" | "float64" => Ok(self.context.f64_type().into()),
                     _ => {
-                        // Check if it's a struct type
+                        // Check if it's a struct or enum type
                         if let Some((struct_type, _)) = self.struct_types.get(name) {
                             Ok((*struct_type).into())
+                        } else if let Some((enum_type, _)) = self.enum_types.get(name) {
+                            Ok((*enum_type).into())
                         } else {
                             Err(CylError::CodeGenError {
                                 message: format!("Unknown type: {name}"),
+                                span: None,
                             })
                         }
                     }
@@ -1254,6 +2157,7 @@ impl<'ctx> LLVMCodegen<'ctx> {
             }
             _ => Err(CylError::CodeGenError {
                 message: format!("Type not implemented: {cyl_type:?}"),
+                span: None,
             }),
         }
     }
@@ -1317,6 +2221,7 @@ impl<'ctx> LLVMCodegen<'ctx> {
             _ => {
                 return Err(CylError::CodeGenError {
                     message: format!("Invalid optimization level: {opt_level}"),
+                    span: None,
                 });
             }
         }
@@ -1340,6 +2245,7 @@ impl<'ctx> LLVMCodegen<'ctx> {
         let target_triple = TargetMachine::get_default_triple();
         let target = Target::from_triple(&target_triple).map_err(|e| CylError::CodeGenError {
             message: format!("Failed to create target: {e}"),
+            span: None,
         })?;
 
         // Create target machine
@@ -1362,6 +2268,7 @@ impl<'ctx> LLVMCodegen<'ctx> {
             )
             .ok_or_else(|| CylError::CodeGenError {
                 message: "Failed to create target machine".to_string(),
+                span: None,
             })?;
 
         // Generate object file
@@ -1369,13 +2276,19 @@ impl<'ctx> LLVMCodegen<'ctx> {
             .write_to_file(&self.module, FileType::Object, output_path)
             .map_err(|e| CylError::CodeGenError {
                 message: format!("Failed to write object file: {e}"),
+                span: None,
             })?;
 
         Ok(())
     }
 
-    /// Generate executable from LLVM IR
-    pub fn compile_to_executable(&self, output_path: &Path, opt_level: u8) -> Result<(), CylError> {
+    /// Generate executable from LLVM IR, linked with the runtime
+    pub fn compile_to_executable(
+        &self,
+        output_path: &Path,
+        opt_level: u8,
+        options: &LinkOptions,
+    ) -> Result<(), CylError> {
         // Apply optimizations
         self.optimize(opt_level)?;
 
@@ -1383,111 +2296,68 @@ impl<'ctx> LLVMCodegen<'ctx> {
         let obj_path = output_path.with_extension("o");
         self.compile_to_object(&obj_path, opt_level)?;
 
-        // Link object file to create executable
-        self.link_executable(&obj_path, output_path)?;
+        // The module defines `main` itself, so only the runtime's functions
+        // are taken from its archive
+        let result = runtime::link_executable(&obj_path, output_path, options);
 
         // Clean up temporary object file
         if obj_path.exists() {
             std::fs::remove_file(&obj_path).map_err(|e| CylError::CodeGenError {
                 message: format!("Failed to remove temporary object file: {e}"),
+                span: None,
             })?;
         }
 
-        Ok(())
+        result
     }
 
-    /// Link object file to create executable
-    fn link_executable(&self, obj_path: &Path, output_path: &Path) -> Result<(), CylError> {
-        use std::process::Command;
-
-        // Determine the appropriate linker and system libraries based on the target platform
-        #[cfg(target_os = "macos")]
-        let mut cmd = {
-            // Use cc for easier linking on macOS
-            let mut c = Command::new("cc");
-            c.arg("-o").arg(output_path).arg(obj_path);
-            c
-        };
-
-        #[cfg(target_os = "linux")]
-        let mut cmd = {
-            // Use cc with PIE flags for Linux to ensure compatibility with modern security settings
-            let mut c = Command::new("cc");
-            c.arg("-pie")  // Create Position Independent Executable
-                .arg("-o")
-                .arg(output_path)
-                .arg(obj_path);
-            c
-        };
-
-        #[cfg(target_os = "windows")]
-        let mut cmd = {
-            let mut c = Command::new("link");
-            c.arg("/OUT:").arg(output_path).arg(obj_path);
-            c
-        };
-
-        // For other platforms, try using cc as a fallback linker
-        #[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
-        let mut cmd = {
-            let mut c = Command::new("cc");
-            c.arg("-o").arg(output_path).arg(obj_path);
-            c
-        };
-
-        let output = cmd.output().map_err(|e| CylError::CodeGenError {
-            message: format!("Failed to run linker: {e}"),
-        })?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(CylError::CodeGenError {
-                message: format!("Linker failed: {stderr}"),
-            });
-        }
-
-        Ok(())
-    }
-
-    /// Compile print_int function call which uses printf with a format string
-    fn compile_print_int_call(
+    /// Call the runtime function that prints the argument of the print
+    /// builtin `name` like the interpreter does.
+    fn compile_print_call(
         &mut self,
+        name: &str,
         arguments: &[Expression],
+        span: Span,
     ) -> Result<BasicValueEnum<'ctx>, CylError> {
-        if arguments.len() != 1 {
-            return Err(CylError::CodeGenError {
-                message: "print_int expects exactly one argument".to_string(),
-            });
-        }
-
-        // Compile the integer argument
-        let int_val = self.compile_expression(&arguments[0])?;
-
-        // Create the format string "%d\n" for printf
-        let format_str = "%d\n\0";
-        let format_global = self
-            .builder
-            .build_global_string_ptr(format_str, "format_int")
-            .unwrap();
-
-        // Get the printf function
-        let printf_fn = self.functions.get("printf").copied().unwrap();
-
-        // Call printf with format string and integer
-        let call_result = self
-            .builder
-            .build_call(
-                printf_fn,
-                &[format_global.as_pointer_value().into(), int_val.into()],
-                "print_int_call",
-            )
-            .unwrap();
+        let Some(argument) = arguments.first() else {
+            if name == "println" {
+                self.call_runtime("cyl_print_newline", &[]);
+            }
+            return Ok(self.context.i32_type().const_zero().into());
+        };
 
-        if let Some(result) = call_result.try_as_basic_value().left() {
-            Ok(result)
-        } else {
-            // This shouldn't happen with printf but handle gracefully
-            Ok(self.context.i32_type().const_zero().into())
+        let value = self.compile_expression(argument)?;
+        match self.type_of(argument)? {
+            Type::String => {
+                self.call_runtime("cyl_print_str", &[value.into()]);
+                if runtime::is_concatenation(argument) {
+                    self.call_runtime("cyl_string_free", &[value.into()]);
+                }
+            }
+            Type::Float => {
+                self.call_runtime("cyl_print_float", &[value.into()]);
+            }
+            Type::Bool => {
+                let byte = self
+                    .builder
+                    .build_int_z_extend(value.into_int_value(), self.context.i8_type(), "bool")
+                    .unwrap();
+                self.call_runtime("cyl_print_bool", &[byte.into()]);
+            }
+            Type::Int => {
+                let wide = self
+                    .builder
+                    .build_int_s_extend(value.into_int_value(), self.context.i64_type(), "int")
+                    .unwrap();
+                self.call_runtime("cyl_print_int", &[wide.into()]);
+            }
+            ty => {
+                return Err(CylError::CodeGenError {
+                    message: format!("Printing `{ty}` values is not implemented yet"),
+                    span: Some(span),
+                });
+            }
         }
+        Ok(self.context.i32_type().const_zero().into())
     }
 }
@@ -1,87 +1,325 @@
 use crate::ast::{
-    BinaryOperator, Expression, FunctionDeclaration, Program, Statement, Type,
+    BinaryOperator, DeclareStatement, Expression, ExpressionKind, ForStatement,
+    FunctionDeclaration, IfStatement, MatchStatement, Parameter, Pattern, PatternKind, Span,
+    Statement, StatementKind, ThrowStatement, TryStatement, Type, UnaryOperator, WhileStatement,
 };
 use crate::error::CylError;
+use crate::exceptions;
+use crate::layout::{self, EnumLayout, FieldLayout, StructLayout, VariantLayout};
+use crate::runtime::{self, LinkOptions};
+use crate::typeck::{ExpressionTypes, TypedProgram};
 use cranelift_codegen::{
-    ir::{AbiParam, Value, types, InstBuilder, condcodes::IntCC},
-    Context, settings::Flags
+    ir::{
+        condcodes::{FloatCC, IntCC},
+        types, AbiParam, Block, FuncRef, InstBuilder, MemFlags,
+        TrapCode, Value,
+    },
+    Context, settings::{Configurable, Flags}
 };
 use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
-use cranelift_module::{Linkage, Module, FuncId};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{DataDescription, DataId, FuncId, Linkage, Module};
 use cranelift_object::{ObjectBuilder, ObjectModule};
 use std::collections::HashMap;
+use std::path::Path;
 
-pub struct CraneliftCodegen {
-    module: ObjectModule,
+/// Trap code of code the type checker proved unreachable.
+pub const UNREACHABLE_TRAP: TrapCode = TrapCode::unwrap_user(2);
+
+/// Symbol of the function the runtime's C `main` calls. It runs the
+/// program's `main`, if it has one.
+pub const ENTRY_SYMBOL: &str = "cyl_entry";
+
+/// Compiles programs into a Cranelift module: an object file for `cylc
+/// build`, or executable memory for the JIT behind `cylc run`.
+pub struct CraneliftCodegen<M: Module = ObjectModule> {
+    module: M,
     ctx: Context,
     
     // Symbol tables
-    variables: HashMap<String, Variable>,
-    var_types: HashMap<String, Type>, // Use string keys instead of Variable
     functions: HashMap<String, FuncId>,
-    function_signatures: HashMap<String, (Vec<Type>, Option<Type>)>,
-    
-    // Variable counter for unique variables
-    var_counter: usize,
+    entry: Option<FuncId>,
+    function_signatures: HashMap<String, (Vec<Parameter>, Option<Type>)>,
+    enums: HashMap<String, EnumLayout>,
+    structs: HashMap<String, StructLayout>,
+    /// Data objects of the top-level `let`s, with their Cyl types.
+    globals: HashMap<String, (DataId, Type)>,
+    /// Read-only string objects of the string literals compiled so far.
+    strings: HashMap<String, DataId>,
+    /// Types of the exceptions that can escape each function.
+    escaping: HashMap<String, Vec<Type>>,
+    /// Types of the exceptions that can escape any function, numbered from
+    /// one in this order in the exception record.
+    exception_types: Vec<Type>,
+    /// The exception record, if any exception can escape a function; see
+    /// [`crate::exceptions`].
+    exception: Option<DataId>,
 }
 
 impl CraneliftCodegen {
     pub fn new() -> Result<Self, CylError> {
-        let isa_builder = cranelift_native::builder()
-            .map_err(|e| CylError::CodeGenError { 
-                message: format!("Failed to create ISA builder: {}", e) 
-            })?;
-        let isa = isa_builder
-            .finish(Flags::new(cranelift_codegen::settings::builder()))
-            .map_err(|e| CylError::CodeGenError { 
-                message: format!("Failed to create ISA: {}", e) 
-            })?;
-
         let builder = ObjectBuilder::new(
-            isa,
+            native_isa(true)?,
             "cyl_module".to_string(),
             cranelift_module::default_libcall_names(),
         )
-        .map_err(|e| CylError::CodeGenError { 
-            message: format!("Failed to create object builder: {}", e) 
+        .map_err(|e| CylError::CodeGenError {
+            message: format!("Failed to create object builder: {}", e),
+            span: None,
         })?;
 
-        let module = ObjectModule::new(builder);
+        Ok(Self::with_module(ObjectModule::new(builder)))
+    }
+}
+
+impl CraneliftCodegen<JITModule> {
+    /// A code generator that compiles into executable memory.
+    pub fn jit() -> Result<Self, CylError> {
+        let mut builder =
+            JITBuilder::with_isa(native_isa(false)?, cranelift_module::default_libcall_names());
+        builder.symbols(runtime::jit_symbols());
+        Ok(Self::with_module(JITModule::new(builder)))
+    }
 
-        Ok(Self {
+    /// Finalize the compiled functions in memory and call `main`, if the
+    /// program has one.
+    pub fn run_main(mut self) -> Result<(), CylError> {
+        let entry = self.entry.ok_or_else(|| CylError::CodeGenError {
+            message: "No program has been compiled".to_string(),
+            span: None,
+        })?;
+        self.module
+            .finalize_definitions()
+            .map_err(|e| CylError::CodeGenError {
+                message: format!("Failed to finalize functions: {}", e),
+                span: None,
+            })?;
+        let code = self.module.get_finalized_function(entry);
+        // SAFETY: the entry function was finalized above with the platform's
+        // C calling convention, no parameters and no result
+        let entry: extern "C" fn() = unsafe { std::mem::transmute(code) };
+        entry();
+        runtime::flush();
+        // SAFETY: nothing refers to the compiled code once the entry returned
+        unsafe { self.module.free_memory() };
+        Ok(())
+    }
+}
+
+impl<M: Module> CraneliftCodegen<M> {
+    fn with_module(module: M) -> Self {
+        Self {
             module,
             ctx: Context::new(),
-            variables: HashMap::new(),
-            var_types: HashMap::new(),
             functions: HashMap::new(),
+            entry: None,
             function_signatures: HashMap::new(),
-            var_counter: 0,
-        })
+            enums: HashMap::new(),
+            structs: HashMap::new(),
+            globals: HashMap::new(),
+            strings: HashMap::new(),
+            escaping: HashMap::new(),
+            exception_types: Vec::new(),
+            exception: None,
+        }
     }
 
-    pub fn compile_program(&mut self, program: &Program) -> Result<(), CylError> {
-        // First pass: declare all functions
+    pub fn compile_program(&mut self, typed: &TypedProgram) -> Result<(), CylError> {
+        let program = &typed.program;
+
+        // First pass: lay out enums and structs and declare all functions
+        // and globals
         for stmt in &program.statements {
-            if let Statement::Function(func) = stmt {
-                self.declare_function(func)?;
+            match &stmt.kind {
+                StatementKind::Enum(decl) => {
+                    self.enums.insert(decl.name.clone(), EnumLayout::new(decl));
+                }
+                StatementKind::Struct(decl) => {
+                    self.structs.insert(decl.name.clone(), StructLayout::new(decl));
+                }
+                _ => {}
             }
         }
+        for (func, _) in typed.functions() {
+            self.declare_function(func)?;
+        }
+        for stmt in &program.statements {
+            if let StatementKind::Declare(decl) = &stmt.kind {
+                self.declare_global(decl, stmt.span)?;
+            }
+        }
+        let uncaught = self.declare_exceptions(typed)?;
 
         // Second pass: compile function bodies
-        for stmt in &program.statements {
-            match stmt {
-                Statement::Function(func) => {
-                    self.compile_function(func)?;
-                }
-                _ => {
-                    return Err(CylError::CodeGenError {
-                        message: "Top-level statements other than function declarations not supported yet"
-                            .to_string(),
-                    });
+        for (func, types) in typed.functions() {
+            self.compile_function(func, typed, types)?;
+        }
+        let top_level: Vec<_> = program
+            .statements
+            .iter()
+            .filter(|stmt| {
+                !matches!(
+                    stmt.kind,
+                    StatementKind::Function(_)
+                        | StatementKind::Struct(_)
+                        | StatementKind::Enum(_)
+                        | StatementKind::Import(_)
+                )
+            })
+            .collect();
+
+        self.define_entry(&top_level, &uncaught, typed)
+    }
+
+    /// Work out the exceptions that can escape each function and declare
+    /// the exception record if there are any. Returns the types of the
+    /// exceptions that can escape the top-level statements or `main`.
+    fn declare_exceptions(&mut self, typed: &TypedProgram) -> Result<Vec<Type>, CylError> {
+        self.escaping = exceptions::escaping(typed);
+        let mut uncaught =
+            exceptions::escaping_from(&typed.program.statements, &typed.types, &self.escaping);
+        for ty in self.escaping.get("main").into_iter().flatten() {
+            if !uncaught.contains(ty) {
+                uncaught.push(ty.clone());
+            }
+        }
+        for ty in self.escaping.values().flatten().chain(&uncaught) {
+            if !self.exception_types.contains(ty) {
+                self.exception_types.push(ty.clone());
+            }
+        }
+        if self.exception_types.is_empty() {
+            return Ok(uncaught);
+        }
+
+        let data_id = self
+            .module
+            .declare_data("cyl_exception", Linkage::Local, true, false)
+            .map_err(|e| CylError::CodeGenError {
+                message: format!("Failed to declare the exception record: {}", e),
+                span: None,
+            })?;
+        let mut description = DataDescription::new();
+        description.define_zeroinit(exceptions::RECORD_SIZE as usize);
+        description.set_align(u64::from(layout::SLOT_SIZE));
+        self.module
+            .define_data(data_id, &description)
+            .map_err(|e| CylError::CodeGenError {
+                message: format!("Failed to define the exception record: {}", e),
+                span: None,
+            })?;
+        self.exception = Some(data_id);
+        Ok(uncaught)
+    }
+
+    /// Declare the zeroed data object a top-level `let` initializes.
+    fn declare_global(&mut self, decl: &DeclareStatement, span: Span) -> Result<(), CylError> {
+        let ty = decl.var_type.clone().unwrap_or(Type::Dynamic);
+        cl_type(&ty, &self.enums, &self.structs).map_err(|e| at(e, span))?;
+        if let Some((_, declared)) = self.globals.get(&decl.name) {
+            if *declared == ty {
+                return Ok(());
+            }
+            return Err(CylError::CodeGenError {
+                message: format!(
+                    "Redeclaring global `{}` with another type is not supported by the Cranelift backend yet",
+                    decl.name
+                ),
+                span: Some(span),
+            });
+        }
+
+        let data_id = self
+            .module
+            .declare_data(&format!("cyl_global_{}", decl.name), Linkage::Local, true, false)
+            .map_err(|e| CylError::CodeGenError {
+                message: format!("Failed to declare global: {}", e),
+                span: None,
+            })?;
+        // Every value fits in one slot
+        let mut description = DataDescription::new();
+        description.define_zeroinit(layout::SLOT_SIZE as usize);
+        description.set_align(u64::from(layout::SLOT_SIZE));
+        self.module
+            .define_data(data_id, &description)
+            .map_err(|e| CylError::CodeGenError {
+                message: format!("Failed to define global: {}", e),
+                span: None,
+            })?;
+        self.globals.insert(decl.name.clone(), (data_id, ty));
+        Ok(())
+    }
+
+    /// Define [`ENTRY_SYMBOL`], which runs the top-level `statements` in
+    /// order, as the interpreter does, and then calls the program's `main`,
+    /// if any, reporting the exceptions of the `uncaught` types that escape
+    /// them.
+    fn define_entry(
+        &mut self,
+        statements: &[&Statement],
+        uncaught: &[Type],
+        typed: &TypedProgram,
+    ) -> Result<(), CylError> {
+        let sig = self.module.make_signature();
+        let entry = self
+            .module
+            .declare_function(ENTRY_SYMBOL, Linkage::Export, &sig)
+            .map_err(|e| CylError::CodeGenError {
+                message: format!("Failed to declare function: {}", e),
+                span: None,
+            })?;
+        let main = match self.functions.get("main") {
+            Some(_) if !self.function_signatures["main"].0.is_empty() => {
+                return Err(CylError::CodeGenError {
+                    message: "`main` cannot take parameters".to_string(),
+                    span: None,
+                });
+            }
+            main => main.copied(),
+        };
+
+        self.ctx.clear();
+        self.ctx.func.signature = sig;
+        let mut fn_builder_ctx = FunctionBuilderContext::new();
+        let mut lowering = self.lowering(&mut fn_builder_ctx, typed, &typed.types);
+        let block = lowering.builder.create_block();
+        lowering.switch_to(block);
+        let report = lowering.builder.create_block();
+        lowering.builder.set_cold_block(report);
+        lowering.uncaught = Some(report);
+        for stmt in statements {
+            if lowering.filled {
+                break;
+            }
+            match &stmt.kind {
+                StatementKind::Declare(decl) => {
+                    let value = lowering.owned(&decl.value)?;
+                    let address = lowering.global_address(&decl.name);
+                    lowering.builder.ins().store(MemFlags::trusted(), value, address, 0);
                 }
+                _ => lowering.statement(stmt)?,
+            }
+        }
+        if !lowering.filled {
+            if let Some(main) = main {
+                // A result, if `main` has one, is ignored
+                let callee = lowering.module.declare_func_in_func(main, lowering.builder.func);
+                lowering.builder.ins().call(callee, &[]);
+                lowering.check_exception("main")?;
             }
+            lowering.builder.ins().return_(&[]);
         }
+        lowering.report_uncaught(report, uncaught)?;
+        lowering.builder.seal_all_blocks();
+        lowering.builder.finalize();
 
+        self.module
+            .define_function(entry, &mut self.ctx)
+            .map_err(|e| CylError::CodeGenError {
+                message: format!("Failed to define function: {}", e),
+                span: None,
+            })?;
+        self.entry = Some(entry);
         Ok(())
     }
 
@@ -91,307 +329,2033 @@ impl CraneliftCodegen {
         // Set return type (only if not void)
         if let Some(ref return_type) = func.return_type {
             if return_type != &Type::Void {
-                sig.returns.push(AbiParam::new(self.type_to_cranelift(return_type)?));
+                sig.returns.push(AbiParam::new(cl_type(return_type, &self.enums, &self.structs)?));
             }
         }
 
         // Set parameter types
-        let mut param_types = Vec::new();
         for param in &func.parameters {
-            let cl_type = self.type_to_cranelift(&param.param_type)?;
+            let cl_type = cl_type(&param.param_type, &self.enums, &self.structs).map_err(|e| at(e, param.span))?;
             sig.params.push(AbiParam::new(cl_type));
-            param_types.push(param.param_type.clone());
         }
 
         let func_id = self
             .module
-            .declare_function(&func.name, Linkage::Export, &sig)
-            .map_err(|e| CylError::CodeGenError { 
-                message: format!("Failed to declare function: {}", e) 
+            .declare_function(&symbol(&func.name), Linkage::Export, &sig)
+            .map_err(|e| CylError::CodeGenError {
+                message: format!("Failed to declare function: {}", e),
+                span: None,
             })?;
 
         self.functions.insert(func.name.clone(), func_id);
         self.function_signatures
-            .insert(func.name.clone(), (param_types, func.return_type.clone()));
+            .insert(func.name.clone(), (func.parameters.clone(), func.return_type.clone()));
 
         Ok(())
     }
 
-    fn compile_function(&mut self, func: &FunctionDeclaration) -> Result<(), CylError> {
+    fn compile_function(
+        &mut self,
+        func: &FunctionDeclaration,
+        typed: &TypedProgram,
+        types: &ExpressionTypes,
+    ) -> Result<(), CylError> {
         let func_id = self.functions[&func.name];
         
         // Clear context for new function
         self.ctx.clear();
-        self.variables.clear();
-        self.var_types.clear();
-        self.var_counter = 0;
+        self.ctx.func.signature = self
+            .module
+            .declarations()
+            .get_function_decl(func_id)
+            .signature
+            .clone();
 
         // Create function builder context
         let mut fn_builder_ctx = FunctionBuilderContext::new();
-        let mut builder = FunctionBuilder::new(&mut self.ctx.func, &mut fn_builder_ctx);
+        let mut lowering = self.lowering(&mut fn_builder_ctx, typed, types);
 
         // Create entry block
-        let entry_block = builder.create_block();
-        builder.append_block_params_for_function_params(entry_block);
-        builder.switch_to_block(entry_block);
-        builder.seal_block(entry_block);
+        let entry_block = lowering.builder.create_block();
+        lowering.builder.append_block_params_for_function_params(entry_block);
+        lowering.switch_to(entry_block);
 
         // Declare function parameters as variables
         for (i, param) in func.parameters.iter().enumerate() {
-            let var = Variable::from_u32(self.var_counter as u32);
-            self.var_counter += 1;
-            
-            let param_value = builder.block_params(entry_block)[i];
-            
-            // Convert type separately to avoid borrow issues
-            let cranelift_type = match param.param_type {
-                Type::Int => types::I64,
-                Type::Float => types::F64,
-                Type::Bool => types::I8,
-                Type::String => types::I64,
-                _ => return Err(CylError::CodeGenError { 
-                    message: format!("Parameter type not supported: {:?}", param.param_type) 
-                }),
-            };
-            
-            builder.declare_var(var, cranelift_type);
-            builder.def_var(var, param_value);
-            
-            self.variables.insert(param.name.clone(), var);
-            self.var_types.insert(param.name.clone(), param.param_type.clone());
-        }
-
-        // Compile function body statements one by one, handling each statement type directly
-        for stmt in &func.body.statements {
-            match stmt {
-                Statement::Declare(decl) => {
-                    let var = Variable::from_u32(self.var_counter as u32);
-                    self.var_counter += 1;
-
-                    // Compile the initial value
-                    let val = Self::compile_expr_static(&decl.value, &mut builder, &self.variables)?;
-                    let var_type = Self::infer_expression_type_static(&decl.value)?;
-                    
-                    // Convert type inline to avoid borrow issues
-                    let cranelift_type = match var_type {
-                        Type::Int => types::I64,
-                        Type::Float => types::F64,
-                        Type::Bool => types::I8,
-                        Type::String => types::I64,
-                        _ => return Err(CylError::CodeGenError { 
-                            message: format!("Variable type not supported: {:?}", var_type) 
-                        }),
-                    };
-                    
-                    builder.declare_var(var, cranelift_type);
-                    builder.def_var(var, val);
-                    
-                    self.variables.insert(decl.name.clone(), var);
-                    self.var_types.insert(decl.name.clone(), var_type);
-                }
-                Statement::Return(ret_stmt) => {
-                    if let Some(ref expr) = ret_stmt.value {
-                        let val = Self::compile_expr_static(expr, &mut builder, &self.variables)?;
-                        builder.ins().return_(&[val]);
-                    } else {
-                        builder.ins().return_(&[]);
-                    }
-                }
-                Statement::Expression(expr) => {
-                    Self::compile_expr_static(expr, &mut builder, &self.variables)?;
-                }
-                _ => {
-                    return Err(CylError::CodeGenError { 
-                        message: format!("Statement type not implemented: {:?}", stmt) 
-                    });
-                }
-            }
+            let param_value = lowering.builder.block_params(entry_block)[i];
+            lowering
+                .define(&param.name, &param.param_type, param_value)
+                .map_err(|e| at(e, param.span))?;
         }
 
-        // Ensure we have a proper terminator
-        // Check if we need to add a return statement
-        let mut has_return = false;
-        for stmt in &func.body.statements {
-            if matches!(stmt, Statement::Return(_)) {
-                has_return = true;
-                break;
-            }
-        }
+        lowering.block(&func.body.statements)?;
 
-        if !has_return {
-            builder.ins().return_(&[]);
+        // Falling off the end returns from a void function; the type checker
+        // rejects any other function whose end is reachable
+        if !lowering.filled {
+            match func.return_type {
+                None | Some(Type::Void) => lowering.builder.ins().return_(&[]),
+                Some(_) => lowering.builder.ins().trap(UNREACHABLE_TRAP),
+            };
         }
 
         // Finalize function
-        builder.finalize();
+        lowering.builder.seal_all_blocks();
+        lowering.builder.finalize();
 
         // Define the function in the module
         self.module
             .define_function(func_id, &mut self.ctx)
-            .map_err(|e| CylError::CodeGenError { 
-                message: format!("Failed to define function: {}", e) 
+            .map_err(|e| CylError::CodeGenError {
+                message: format!("Failed to define function: {}", e),
+                span: None,
             })?;
 
         Ok(())
     }
 
-    fn compile_expr_static(
-        expr: &Expression,
-        builder: &mut FunctionBuilder,
-        variables: &HashMap<String, Variable>,
-    ) -> Result<Value, CylError> {
-        match expr {
-            Expression::IntLiteral(val) => {
-                Ok(builder.ins().iconst(types::I64, *val))
+    /// Lower statements of `typed`, whose expressions have the `types`,
+    /// into the function in `self.ctx`.
+    fn lowering<'s>(
+        &'s mut self,
+        fn_builder_ctx: &'s mut FunctionBuilderContext,
+        typed: &'s TypedProgram,
+        types: &'s ExpressionTypes,
+    ) -> FunctionLowering<'s, M> {
+        FunctionLowering {
+            builder: FunctionBuilder::new(&mut self.ctx.func, fn_builder_ctx),
+            module: &mut self.module,
+            typed,
+            types,
+            functions: &self.functions,
+            signatures: &self.function_signatures,
+            enums: &self.enums,
+            structs: &self.structs,
+            globals: &self.globals,
+            strings: &mut self.strings,
+            escaping: &self.escaping,
+            exception_types: &self.exception_types,
+            exception: self.exception,
+            uncaught: None,
+            imports: HashMap::new(),
+            variables: HashMap::new(),
+            var_counter: 0,
+            filled: false,
+            handlers: Vec::new(),
+            loops: Vec::new(),
+        }
+    }
+
+    pub fn print_ir(&self) {
+        println!("Cranelift IR generation complete!");
+        // TODO: Add IR printing functionality if needed
+    }
+}
+
+impl CraneliftCodegen {
+    pub fn write_object_file(&mut self, path: &str) -> Result<(), CylError> {
+        let object = self.emit_object()?;
+        std::fs::write(path, object).map_err(|e| CylError::CodeGenError {
+            message: format!("Failed to write object file: {}", e),
+            span: None,
+        })
+    }
+
+    /// Link the compiled program with the Cyl runtime and the C library
+    /// into the executable `output_path`.
+    pub fn write_executable(
+        &mut self,
+        output_path: &Path,
+        options: &LinkOptions,
+    ) -> Result<(), CylError> {
+        let object = self.emit_object()?;
+        let obj_path = runtime::scratch_file("program.o", &object)?;
+        let result = runtime::link_executable(&obj_path, output_path, options);
+        std::fs::remove_file(&obj_path).ok();
+        result
+    }
+
+    /// Finish the module and serialize it as an object file.
+    fn emit_object(&mut self) -> Result<Vec<u8>, CylError> {
+        // We need to consume the module to finish it
+        let module = std::mem::replace(&mut self.module, {
+            // Create a dummy module for replacement
+            let builder = ObjectBuilder::new(
+                native_isa(true)?,
+                "dummy".to_string(),
+                cranelift_module::default_libcall_names(),
+            )
+            .map_err(|e| CylError::CodeGenError {
+                message: format!("Failed to create object builder: {}", e),
+                span: None,
+            })?;
+            ObjectModule::new(builder)
+        });
+
+        let product = module.finish();
+        product.emit().map_err(|e| CylError::CodeGenError {
+            message: format!("Failed to emit object file: {}", e),
+            span: None,
+        })
+    }
+}
+
+/// Symbol a Cyl function is exported under.
+fn symbol(name: &str) -> String {
+    format!("cyl_fn_{name}")
+}
+
+/// The instruction set of the machine the compiler runs on, generating
+/// position-independent code if `pic` is set.
+fn native_isa(pic: bool) -> Result<cranelift_codegen::isa::OwnedTargetIsa, CylError> {
+    let mut flags = cranelift_codegen::settings::builder();
+    if pic {
+        flags.enable("is_pic").map_err(|e| CylError::CodeGenError {
+            message: format!("Failed to enable position-independent code: {}", e),
+            span: None,
+        })?;
+    }
+    let isa_builder = cranelift_native::builder()
+        .map_err(|e| CylError::CodeGenError {
+            message: format!("Failed to create ISA builder: {}", e),
+            span: None,
+        })?;
+    isa_builder
+        .finish(Flags::new(flags))
+        .map_err(|e| CylError::CodeGenError {
+            message: format!("Failed to create ISA: {}", e),
+            span: None,
+        })
+}
+
+/// Lowers the body of a single function.
+///
+/// Enum values, structs, strings and arrays are addresses of heap or
+/// read-only objects, laid out as described in [`crate::layout`].
+struct FunctionLowering<'a, M: Module> {
+    builder: FunctionBuilder<'a>,
+    module: &'a mut M,
+    typed: &'a TypedProgram,
+    /// Types of the expressions being lowered.
+    types: &'a ExpressionTypes,
+    functions: &'a HashMap<String, FuncId>,
+    signatures: &'a HashMap<String, (Vec<Parameter>, Option<Type>)>,
+    enums: &'a HashMap<String, EnumLayout>,
+    structs: &'a HashMap<String, StructLayout>,
+    globals: &'a HashMap<String, (DataId, Type)>,
+    strings: &'a mut HashMap<String, DataId>,
+    escaping: &'a HashMap<String, Vec<Type>>,
+    exception_types: &'a [Type],
+    exception: Option<DataId>,
+    /// Block an exception escaping the function jumps to, in the entry
+    /// function; other functions return.
+    uncaught: Option<Block>,
+    /// Functions of the C library and the runtime this function calls.
+    imports: HashMap<&'static str, FuncRef>,
+    /// Variables in scope with their Cyl types.
+    variables: HashMap<String, (Variable, Type)>,
+    var_counter: u32,
+    /// Whether the current block already ends in a terminator.
+    filled: bool,
+    /// Catch clauses of the enclosing `try` statements, innermost last.
+    handlers: Vec<Vec<Handler>>,
+    /// Enclosing loops, innermost last.
+    loops: Vec<Loop>,
+}
+
+/// A catch clause a `throw` can jump to.
+struct Handler {
+    exception_type: Option<Type>,
+    /// Blocks the clause starts in, each taking a thrown value as its
+    /// parameter, with that value's type; one for each type of exception
+    /// that reaches the clause.
+    entries: Vec<(Block, Type)>,
+}
+
+/// A loop that `break` and `continue` can leave.
+struct Loop {
+    label: Option<String>,
+    /// Block `continue` jumps to.
+    next: Block,
+    /// Block after the loop, where `break` jumps.
+    exit: Block,
+}
+
+impl<'a, M: Module> FunctionLowering<'a, M> {
+    fn switch_to(&mut self, block: Block) {
+        self.builder.switch_to_block(block);
+        self.filled = false;
+    }
+
+    /// Jump to `block` unless the current block already left.
+    fn jump_if_open(&mut self, block: Block) {
+        if !self.filled {
+            self.builder.ins().jump(block, &[]);
+            self.filled = true;
+        }
+    }
+
+    /// Bind `name` to `value` in a fresh variable.
+    fn define(&mut self, name: &str, ty: &Type, value: Value) -> Result<(), CylError> {
+        let var = Variable::from_u32(self.var_counter);
+        self.var_counter += 1;
+        self.builder.declare_var(var, cl_type(ty, self.enums, self.structs)?);
+        self.builder.def_var(var, value);
+        self.variables.insert(name.to_string(), (var, ty.clone()));
+        Ok(())
+    }
+
+    /// Compile `statements` in a scope of their own.
+    fn scoped_block(&mut self, statements: &[Statement]) -> Result<(), CylError> {
+        let outer = self.variables.clone();
+        let result = self.block(statements);
+        self.variables = outer;
+        result
+    }
+
+    /// Compile `statements` up to the first one that leaves the block.
+    fn block(&mut self, statements: &[Statement]) -> Result<(), CylError> {
+        for stmt in statements {
+            if self.filled {
+                break;
             }
-            Expression::FloatLiteral(val) => {
-                Ok(builder.ins().f64const(*val))
+            self.statement(stmt)?;
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, stmt: &Statement) -> Result<(), CylError> {
+        match &stmt.kind {
+            StatementKind::Declare(decl) => {
+                // Compile the initial value
+                let val = self.owned(&decl.value)?;
+                // The type checker resolved every declaration's type
+                let var_type = decl.var_type.clone().unwrap_or(Type::Dynamic);
+                self.define(&decl.name, &var_type, val)
+                    .map_err(|e| at(e, stmt.span))?;
             }
-            Expression::StringLiteral(_) => {
-                // TODO: Implement string literals
-                Err(CylError::CodeGenError { 
-                    message: "String literals not implemented yet".to_string() 
-                })
+            StatementKind::Return(ret_stmt) => {
+                if let Some(ref expr) = ret_stmt.value {
+                    let val = match &expr.kind {
+                        // Nothing can reach a local's struct once the function returns
+                        ExpressionKind::Identifier(name) if self.variables.contains_key(name) => {
+                            self.expression(expr)?
+                        }
+                        _ => self.owned(expr)?,
+                    };
+                    self.builder.ins().return_(&[val]);
+                } else {
+                    self.builder.ins().return_(&[]);
+                }
+                self.filled = true;
             }
-            Expression::BoolLiteral(val) => {
-                Ok(builder.ins().iconst(types::I8, if *val { 1 } else { 0 }))
+            StatementKind::Expression(expr) => {
+                self.expression(expr)?;
             }
-            Expression::Identifier(name) => {
-                if let Some(&var) = variables.get(name) {
-                    Ok(builder.use_var(var))
-                } else {
-                    Err(CylError::CodeGenError { 
-                        message: format!("Undefined variable: {}", name) 
+            StatementKind::Block(block) => self.scoped_block(&block.statements)?,
+            StatementKind::If(if_stmt) => self.if_statement(if_stmt)?,
+            StatementKind::While(while_stmt) => self.while_statement(while_stmt)?,
+            StatementKind::For(for_stmt) => self.for_statement(for_stmt)?,
+            StatementKind::Break(label) => self.leave_loop(label.as_deref(), true, stmt.span)?,
+            StatementKind::Continue(label) => {
+                self.leave_loop(label.as_deref(), false, stmt.span)?
+            }
+            StatementKind::Match(match_stmt) => self.match_statement(match_stmt)?,
+            StatementKind::Try(try_stmt) => self.try_statement(try_stmt)?,
+            StatementKind::Throw(throw) => self.throw_statement(throw, stmt.span)?,
+            _ => {
+                return Err(CylError::CodeGenError {
+                    message: format!("Statement type not implemented: {:?}", stmt.kind),
+                    span: Some(stmt.span),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn if_statement(&mut self, if_stmt: &IfStatement) -> Result<(), CylError> {
+        let condition = self.expression(&if_stmt.condition)?;
+        let then_block = self.builder.create_block();
+        let else_block = self.builder.create_block();
+        let merge = self.builder.create_block();
+        self.builder.ins().brif(condition, then_block, &[], else_block, &[]);
+
+        self.switch_to(then_block);
+        self.scoped_block(&if_stmt.then_block.statements)?;
+        self.jump_if_open(merge);
+
+        self.switch_to(else_block);
+        if let Some(else_stmt) = &if_stmt.else_block {
+            let outer = self.variables.clone();
+            let result = self.statement(else_stmt);
+            self.variables = outer;
+            result?;
+        }
+        self.jump_if_open(merge);
+        self.switch_to(merge);
+        Ok(())
+    }
+
+    fn while_statement(&mut self, while_stmt: &WhileStatement) -> Result<(), CylError> {
+        let header = self.builder.create_block();
+        let body = self.builder.create_block();
+        let exit = self.builder.create_block();
+        self.builder.ins().jump(header, &[]);
+
+        self.switch_to(header);
+        let condition = self.expression(&while_stmt.condition)?;
+        self.builder.ins().brif(condition, body, &[], exit, &[]);
+
+        self.switch_to(body);
+        self.loop_body(&while_stmt.label, header, exit, &while_stmt.body.statements)?;
+        self.jump_if_open(header);
+        self.switch_to(exit);
+        Ok(())
+    }
+
+    /// Count through a range, or from zero up to an int, or walk the
+    /// elements of an array, like the interpreter. Other iterables need
+    /// runtime support.
+    fn for_statement(&mut self, for_stmt: &ForStatement) -> Result<(), CylError> {
+        // Inclusive ranges stop after visiting `end` rather than at `end + 1`,
+        // which would overflow at `i64::MAX`
+        let (start, end, inclusive, array) = match &for_stmt.iterable.kind {
+            ExpressionKind::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let start = self.expression(start)?;
+                let end = self.expression(end)?;
+                (start, end, *inclusive, None)
+            }
+            _ => match self.typed_expression(&for_stmt.iterable)? {
+                (end, Type::Int) => (self.builder.ins().iconst(types::I64, 0), end, false, None),
+                (array, Type::Array(element_type)) => {
+                    let start = self.builder.ins().iconst(types::I64, 0);
+                    let end = self.array_length(array);
+                    (start, end, false, Some((array, *element_type)))
+                }
+                (_, ty) => {
+                    return Err(CylError::CodeGenError {
+                        message: format!(
+                            "Iterating over `{}` values is not supported by the Cranelift backend yet",
+                            ty
+                        ),
+                        span: Some(for_stmt.iterable.span),
                     })
                 }
+            },
+        };
+
+        let header = self.builder.create_block();
+        let index = self.builder.append_block_param(header, types::I64);
+        let body = self.builder.create_block();
+        let next = self.builder.create_block();
+        let exit = self.builder.create_block();
+        self.builder.ins().jump(header, &[start]);
+
+        self.switch_to(header);
+        let cc = if inclusive {
+            IntCC::SignedLessThanOrEqual
+        } else {
+            IntCC::SignedLessThan
+        };
+        let more = self.builder.ins().icmp(cc, index, end);
+        self.builder.ins().brif(more, body, &[], exit, &[]);
+
+        self.switch_to(body);
+        let (element, element_type) = match array {
+            Some((array, element_type)) => {
+                (self.element(array, index, &element_type)?, element_type)
             }
-            Expression::BinaryOp { left, operator, right } => {
-                let left_val = Self::compile_expr_static(left, builder, variables)?;
-                let right_val = Self::compile_expr_static(right, builder, variables)?;
-
-                match operator {
-                    BinaryOperator::Add => Ok(builder.ins().iadd(left_val, right_val)),
-                    BinaryOperator::Subtract => Ok(builder.ins().isub(left_val, right_val)),
-                    BinaryOperator::Multiply => Ok(builder.ins().imul(left_val, right_val)),
-                    BinaryOperator::Divide => Ok(builder.ins().sdiv(left_val, right_val)),
-                    BinaryOperator::Equal => Ok(builder.ins().icmp(IntCC::Equal, left_val, right_val)),
-                    BinaryOperator::NotEqual => Ok(builder.ins().icmp(IntCC::NotEqual, left_val, right_val)),
-                    _ => Err(CylError::CodeGenError { 
-                        message: format!("Binary operator not implemented: {:?}", operator) 
-                    }),
+            None => (index, Type::Int),
+        };
+        let outer = self.variables.clone();
+        let result = match &for_stmt.pattern.kind {
+            PatternKind::Identifier(name) => self
+                .copy_struct(element, &element_type)
+                .and_then(|element| self.define(name, &element_type, element)),
+            PatternKind::Wildcard => Ok(()),
+            _ => Err(CylError::CodeGenError {
+                message: format!(
+                    "Destructuring `{}` loop elements is not supported by the Cranelift backend yet",
+                    element_type
+                ),
+                span: Some(for_stmt.pattern.span),
+            }),
+        }
+        .map_err(|e| at(e, for_stmt.pattern.span))
+        .and_then(|()| self.loop_body(&for_stmt.label, next, exit, &for_stmt.body.statements));
+        self.variables = outer;
+        result?;
+        self.jump_if_open(next);
+
+        self.switch_to(next);
+        let following = self.builder.ins().iadd_imm(index, 1);
+        if inclusive {
+            let last = self.builder.ins().icmp(IntCC::Equal, index, end);
+            self.builder.ins().brif(last, exit, &[], header, &[following]);
+        } else {
+            self.builder.ins().jump(header, &[following]);
+        }
+        self.switch_to(exit);
+        Ok(())
+    }
+
+    /// Compile the body of a loop that continues at `next` and breaks to
+    /// `exit`.
+    fn loop_body(
+        &mut self,
+        label: &Option<String>,
+        next: Block,
+        exit: Block,
+        statements: &[Statement],
+    ) -> Result<(), CylError> {
+        self.loops.push(Loop {
+            label: label.clone(),
+            next,
+            exit,
+        });
+        let result = self.scoped_block(statements);
+        self.loops.pop();
+        result
+    }
+
+    /// Jump out of the innermost loop, or the one labelled `label`: to its
+    /// exit for a `break`, or its next iteration for a `continue`.
+    fn leave_loop(&mut self, label: Option<&str>, exit: bool, span: Span) -> Result<(), CylError> {
+        let target = self
+            .loops
+            .iter()
+            .rev()
+            .find(|l| label.map_or(true, |label| l.label.as_deref() == Some(label)));
+        let Some(target) = target else {
+            return Err(CylError::CodeGenError {
+                message: format!("`{}` outside of a loop", if exit { "break" } else { "continue" }),
+                span: Some(span),
+            });
+        };
+        let block = if exit { target.exit } else { target.next };
+        self.builder.ins().jump(block, &[]);
+        self.filled = true;
+        Ok(())
+    }
+
+    /// Try the arms in order; an arm whose pattern or guard fails falls
+    /// through to the next one.
+    fn match_statement(&mut self, match_stmt: &MatchStatement) -> Result<(), CylError> {
+        let ty = match_stmt.scrutinee_type.clone().unwrap_or(Type::Dynamic);
+        let value = self.expression(&match_stmt.expression)?;
+        let merge = self.builder.create_block();
+        for arm in &match_stmt.arms {
+            let next = self.builder.create_block();
+            let outer = self.variables.clone();
+            self.pattern(&arm.pattern, value, &ty, next)?;
+            if let Some(guard) = &arm.guard {
+                let condition = self.expression(guard)?;
+                self.branch_unless(condition, next);
+            }
+            self.block(&arm.body.statements)?;
+            if !self.filled {
+                self.builder.ins().jump(merge, &[]);
+            }
+            self.variables = outer;
+            self.switch_to(next);
+        }
+        // The type checker proved the arms exhaustive
+        self.builder.ins().trap(UNREACHABLE_TRAP);
+        self.switch_to(merge);
+        Ok(())
+    }
+
+    /// Lower the body with its catch clauses as handlers, then the clauses
+    /// that some `throw` in the body reaches.
+    fn try_statement(&mut self, try_stmt: &TryStatement) -> Result<(), CylError> {
+        let merge = self.builder.create_block();
+        let handlers = try_stmt
+            .catch_clauses
+            .iter()
+            .map(|clause| Handler {
+                exception_type: clause.exception_type.clone(),
+                entries: Vec::new(),
+            })
+            .collect();
+        self.handlers.push(handlers);
+        let outer = self.variables.clone();
+        let body = self.block(&try_stmt.body.statements);
+        self.variables = outer;
+        let handlers = self.handlers.pop().unwrap_or_default();
+        body?;
+        if !self.filled {
+            self.builder.ins().jump(merge, &[]);
+        }
+
+        // A clause is lowered once for each type of value it receives
+        let reached = try_stmt.catch_clauses.iter().zip(handlers).flat_map(|(clause, handler)| {
+            handler.entries.into_iter().map(move |entry| (clause, entry))
+        });
+        for (clause, (entry, ty)) in reached {
+            self.switch_to(entry);
+            let outer = self.variables.clone();
+            if let Some(variable) = &clause.variable {
+                let exception = self.builder.block_params(entry)[0];
+                self.define(variable, &ty, exception)
+                    .map_err(|e| at(e, clause.span))?;
+            }
+            self.block(&clause.body.statements)?;
+            if !self.filled {
+                self.builder.ins().jump(merge, &[]);
+            }
+            self.variables = outer;
+        }
+        self.switch_to(merge);
+        Ok(())
+    }
+
+    /// Jump to the first enclosing catch clause that accepts the thrown
+    /// value's type, or leave the function with the exception if there is
+    /// none.
+    fn throw_statement(&mut self, throw: &ThrowStatement, span: Span) -> Result<(), CylError> {
+        let ty = throw.value_type.clone().unwrap_or(Type::Dynamic);
+        let value = self.owned(&throw.value)?;
+        match self.handler_entry(&ty, span)? {
+            Some(entry) => {
+                self.builder.ins().jump(entry, &[value]);
+                self.filled = true;
+            }
+            None => self.raise(value, &ty, span)?,
+        }
+        Ok(())
+    }
+
+    /// The block starting the first enclosing catch clause that accepts
+    /// values of type `ty`, taking the value as its parameter, if any.
+    fn handler_entry(&mut self, ty: &Type, span: Span) -> Result<Option<Block>, CylError> {
+        let target = self.handlers.iter().enumerate().rev().find_map(|(level, handlers)| {
+            handlers
+                .iter()
+                .position(|handler| exceptions::catches(handler.exception_type.as_ref(), ty))
+                .map(|index| (level, index))
+        });
+        let Some((level, index)) = target else {
+            return Ok(None);
+        };
+        let handler = &self.handlers[level][index];
+        if let Some((entry, _)) = handler.entries.iter().find(|(_, entry_type)| entry_type == ty) {
+            return Ok(Some(*entry));
+        }
+        let entry = self.builder.create_block();
+        let param_type = cl_type(ty, self.enums, self.structs).map_err(|e| at(e, span))?;
+        self.builder.append_block_param(entry, param_type);
+        self.handlers[level][index].entries.push((entry, ty.clone()));
+        Ok(Some(entry))
+    }
+
+    /// Record `value`, of type `ty`, thrown at `span`, as the pending
+    /// exception and leave the function with it.
+    fn raise(&mut self, value: Value, ty: &Type, span: Span) -> Result<(), CylError> {
+        let number = self.exception_number(ty);
+        let record = self.exception_address();
+        let number = self.builder.ins().iconst(types::I64, number);
+        let slot = match self.builder.func.dfg.value_type(value) {
+            types::F64 => self.builder.ins().bitcast(types::I64, MemFlags::new(), value),
+            types::I64 => value,
+            _ => self.builder.ins().uextend(types::I64, value),
+        };
+        let line = self.builder.ins().iconst(types::I64, span.line as i64);
+        let column = self.builder.ins().iconst(types::I64, span.column as i64);
+        for (field, offset) in [
+            (number, exceptions::TYPE_OFFSET),
+            (slot, exceptions::VALUE_OFFSET),
+            (line, exceptions::LINE_OFFSET),
+            (column, exceptions::COLUMN_OFFSET),
+        ] {
+            self.builder.ins().store(MemFlags::trusted(), field, record, offset as i32);
+        }
+        self.unwind();
+        Ok(())
+    }
+
+    /// Leave the function with the pending exception: return zeros, which
+    /// the caller never reads, or report the exception in the entry
+    /// function.
+    fn unwind(&mut self) {
+        match self.uncaught {
+            Some(report) => {
+                self.builder.ins().jump(report, &[]);
+            }
+            None => {
+                let returns: Vec<_> = self
+                    .builder
+                    .func
+                    .signature
+                    .returns
+                    .iter()
+                    .map(|param| param.value_type)
+                    .collect();
+                let zeros: Vec<_> = returns
+                    .into_iter()
+                    .map(|ty| match ty {
+                        types::F64 => self.builder.ins().f64const(0.0),
+                        ty => self.builder.ins().iconst(ty, 0),
+                    })
+                    .collect();
+                self.builder.ins().return_(&zeros);
+            }
+        }
+        self.filled = true;
+    }
+
+    /// After a call to the function `name`, hand an exception it let escape
+    /// to the first enclosing catch clause that accepts it, or leave this
+    /// function with it.
+    fn check_exception(&mut self, name: &str) -> Result<(), CylError> {
+        let escaping = self.escaping;
+        let Some(types) = escaping.get(name).filter(|types| !types.is_empty()) else {
+            return Ok(());
+        };
+        let record = self.exception_address();
+        let number = self.builder.ins().load(
+            types::I64,
+            MemFlags::trusted(),
+            record,
+            exceptions::TYPE_OFFSET as i32,
+        );
+        let thrown = self.builder.create_block();
+        let next = self.builder.create_block();
+        self.builder.set_cold_block(thrown);
+        self.builder.ins().brif(number, thrown, &[], next, &[]);
+
+        self.switch_to(thrown);
+        for ty in types {
+            // `span` only locates errors about the clause's parameter
+            let Some(entry) = self.handler_entry(ty, Span::default())? else {
+                continue;
+            };
+            let caught = self.builder.create_block();
+            let rest = self.builder.create_block();
+            let expected = self.exception_number(ty);
+            let matched = self.builder.ins().icmp_imm(IntCC::Equal, number, expected);
+            self.builder.ins().brif(matched, caught, &[], rest, &[]);
+
+            self.switch_to(caught);
+            let value = self.exception_value(record, ty)?;
+            let none = self.builder.ins().iconst(types::I64, 0);
+            self.builder.ins().store(
+                MemFlags::trusted(),
+                none,
+                record,
+                exceptions::TYPE_OFFSET as i32,
+            );
+            self.builder.ins().jump(entry, &[value]);
+            self.switch_to(rest);
+        }
+        self.unwind();
+
+        self.switch_to(next);
+        Ok(())
+    }
+
+    /// Report the pending exception, which is of one of the `uncaught`
+    /// types, starting at the `report` block, as the interpreter reports an
+    /// uncaught exception.
+    fn report_uncaught(&mut self, report: Block, uncaught: &[Type]) -> Result<(), CylError> {
+        self.switch_to(report);
+        if uncaught.is_empty() {
+            // Nothing ever jumps here
+            self.builder.ins().trap(UNREACHABLE_TRAP);
+            return Ok(());
+        }
+        let record = self.exception_address();
+        let number = self.builder.ins().load(
+            types::I64,
+            MemFlags::trusted(),
+            record,
+            exceptions::TYPE_OFFSET as i32,
+        );
+        let line = self.builder.ins().load(
+            types::I64,
+            MemFlags::trusted(),
+            record,
+            exceptions::LINE_OFFSET as i32,
+        );
+        let column = self.builder.ins().load(
+            types::I64,
+            MemFlags::trusted(),
+            record,
+            exceptions::COLUMN_OFFSET as i32,
+        );
+        for ty in uncaught {
+            let this = self.builder.create_block();
+            let rest = self.builder.create_block();
+            let expected = self.exception_number(ty);
+            let matched = self.builder.ins().icmp_imm(IntCC::Equal, number, expected);
+            self.builder.ins().brif(matched, this, &[], rest, &[]);
+
+            self.switch_to(this);
+            let value = self.exception_value(record, ty)?;
+            let text = self.stringify(value, ty).ok_or_else(|| CylError::CodeGenError {
+                message: format!(
+                    "Reporting uncaught `{}` exceptions is not supported by the Cranelift backend yet",
+                    ty
+                ),
+                span: None,
+            })?;
+            let prefix = self.string_literal("Uncaught exception: ")?;
+            let message = self.call_runtime("cyl_string_concat", &[prefix, text], Some(types::I64))?;
+            self.call_runtime("cyl_runtime_error", &[message, line, column], None)?;
+            self.builder.ins().trap(UNREACHABLE_TRAP);
+            self.switch_to(rest);
+        }
+        self.builder.ins().trap(UNREACHABLE_TRAP);
+        Ok(())
+    }
+
+    /// The number identifying exceptions of type `ty` in the exception
+    /// record.
+    fn exception_number(&self, ty: &Type) -> i64 {
+        let index = self.exception_types.iter().position(|known| known == ty);
+        index.expect("every escaping exception type is numbered") as i64 + 1
+    }
+
+    fn exception_address(&mut self) -> Value {
+        let data_id = self.exception.expect("the exception record is declared");
+        let global = self.module.declare_data_in_func(data_id, self.builder.func);
+        self.builder.ins().symbol_value(types::I64, global)
+    }
+
+    /// The pending exception's value, of type `ty`, from the exception
+    /// record at `record`.
+    fn exception_value(&mut self, record: Value, ty: &Type) -> Result<Value, CylError> {
+        let slot = self.builder.ins().load(
+            types::I64,
+            MemFlags::trusted(),
+            record,
+            exceptions::VALUE_OFFSET as i32,
+        );
+        Ok(match cl_type(ty, self.enums, self.structs)? {
+            types::F64 => self.builder.ins().bitcast(types::F64, MemFlags::new(), slot),
+            types::I64 => slot,
+            narrow => self.builder.ins().ireduce(narrow, slot),
+        })
+    }
+
+    /// Test `value`, of type `ty`, against `pattern` and bind its variables,
+    /// jumping to `fail` as soon as a test fails.
+    fn pattern(
+        &mut self,
+        pattern: &Pattern,
+        value: Value,
+        ty: &Type,
+        fail: Block,
+    ) -> Result<(), CylError> {
+        let layout = enum_name(ty, self.enums).map(|name| &self.enums[name]);
+        match &pattern.kind {
+            PatternKind::Wildcard => Ok(()),
+            PatternKind::Identifier(name) => match layout.and_then(|l| l.variant(name)) {
+                Some(variant) => {
+                    self.test_tag(value, variant.tag, fail);
+                    Ok(())
+                }
+                None => {
+                    let value = self.copy_struct(value, ty)?;
+                    self.define(name, ty, value).map_err(|e| at(e, pattern.span))
                 }
+            },
+            PatternKind::Literal(literal) if matches!(ty, Type::Int | Type::Bool | Type::Float) => {
+                let expected = self.expression(literal)?;
+                let matched = if *ty == Type::Float {
+                    self.builder.ins().fcmp(FloatCC::Equal, value, expected)
+                } else {
+                    self.builder.ins().icmp(IntCC::Equal, value, expected)
+                };
+                self.branch_unless(matched, fail);
+                Ok(())
             }
-            Expression::Call { callee, arguments: _ } => {
-                // Extract function name from callee
-                let function_name = match callee.as_ref() {
-                    Expression::Identifier(name) => name,
-                    _ => return Err(CylError::CodeGenError { 
-                        message: "Complex function calls not supported yet".to_string() 
-                    }),
+            PatternKind::Literal(literal) if *ty == Type::String => {
+                let expected = self.expression(literal)?;
+                let order =
+                    self.call_runtime("cyl_string_compare", &[value, expected], Some(types::I64))?;
+                let matched = self.builder.ins().icmp_imm(IntCC::Equal, order, 0);
+                self.branch_unless(matched, fail);
+                Ok(())
+            }
+            PatternKind::TupleOrEnum(path, fields)
+            | PatternKind::Enum {
+                variant: path,
+                fields,
+            } => {
+                let name = path.rsplit('.').next().unwrap_or(path);
+                let Some(variant) = layout.and_then(|l| l.variant(name)).cloned() else {
+                    return Err(CylError::CodeGenError {
+                        message: format!("Pattern `{}` cannot match a value of type `{}`", path, ty),
+                        span: Some(pattern.span),
+                    });
                 };
+                self.test_tag(value, variant.tag, fail);
+                let field_types = layout.unwrap().field_types(&variant, ty);
+                for (i, (field, field_type)) in fields.iter().zip(&field_types).enumerate() {
+                    let field_cl_type =
+                        cl_type(field_type, self.enums, self.structs).map_err(|e| at(e, field.span))?;
+                    let field_value = self.builder.ins().load(
+                        field_cl_type,
+                        MemFlags::trusted(),
+                        value,
+                        EnumLayout::field_offset(i) as i32,
+                    );
+                    self.pattern(field, field_value, field_type, fail)?;
+                }
+                Ok(())
+            }
+            _ => Err(CylError::CodeGenError {
+                message: format!(
+                    "Pattern not supported by the Cranelift backend yet on a value of type `{}`",
+                    ty
+                ),
+                span: Some(pattern.span),
+            }),
+        }
+    }
+
+    fn test_tag(&mut self, value: Value, tag: i64, fail: Block) {
+        let actual = self.builder.ins().load(types::I64, MemFlags::trusted(), value, 0);
+        let matched = self.builder.ins().icmp_imm(IntCC::Equal, actual, tag);
+        self.branch_unless(matched, fail);
+    }
+
+    /// Continue in a fresh block if `condition` holds, else jump to `fail`.
+    fn branch_unless(&mut self, condition: Value, fail: Block) {
+        let next = self.builder.create_block();
+        self.builder.ins().brif(condition, next, &[], fail, &[]);
+        self.switch_to(next);
+    }
+
+    /// Build `enum_name.variant(arguments...)` on the heap, like a struct,
+    /// and return its address, typed as the enum.
+    fn construct_variant(
+        &mut self,
+        enum_name: &str,
+        variant: &str,
+        arguments: &[Expression],
+        expr: &Expression,
+    ) -> Result<Value, CylError> {
+        let layout = &self.enums[enum_name];
+        let Some(variant) = layout.variant(variant) else {
+            return Err(CylError::CodeGenError {
+                message: format!("Enum `{}` has no variant `{}`", layout.name, variant),
+                span: Some(expr.span),
+            });
+        };
+        let size = self.builder.ins().iconst(types::I64, i64::from(layout.size()));
+        let tag = self.builder.ins().iconst(types::I64, variant.tag);
+        let object = self.call_runtime("cyl_alloc", &[size], Some(types::I64))?;
+        self.builder.ins().store(MemFlags::trusted(), tag, object, 0);
+        for (i, argument) in arguments.iter().enumerate() {
+            let value = self.owned(argument)?;
+            self.builder
+                .ins()
+                .store(MemFlags::trusted(), value, object, EnumLayout::field_offset(i) as i32);
+        }
+        Ok(object)
+    }
+
+    /// The address of the data object of the global `name`.
+    fn global_address(&mut self, name: &str) -> Value {
+        let (data_id, _) = self.globals[name];
+        let global = self.module.declare_data_in_func(data_id, self.builder.func);
+        self.builder.ins().symbol_value(types::I64, global)
+    }
+
+    /// Lower `expr`, returning its value with the type the type checker
+    /// resolved for it.
+    fn typed_expression(&mut self, expr: &Expression) -> Result<(Value, Type), CylError> {
+        let value = self.expression(expr)?;
+        let ty = match (self.type_of(expr)?, &expr.kind) {
+            // A clause catching anything is lowered once for each type of
+            // value it receives, binding its variable with that type
+            (Type::Dynamic, ExpressionKind::Identifier(name)) if self.variables.contains_key(name) => {
+                self.variables[name].1.clone()
+            }
+            (ty, _) => ty,
+        };
+        Ok((value, ty))
+    }
+
+    /// The type the type checker resolved for `expr`.
+    fn type_of(&self, expr: &Expression) -> Result<Type, CylError> {
+        self.types.get(expr).cloned().ok_or_else(|| CylError::CodeGenError {
+            message: "Expression was not type checked".to_string(),
+            span: Some(expr.span),
+        })
+    }
 
-                // Handle builtin functions
-                if function_name == "print" || function_name == "println" {
-                    // For now, just return a dummy value since we can't easily call printf
-                    // TODO: Implement proper print function calling
-                    Ok(builder.ins().iconst(types::I32, 0))
+    fn expression(&mut self, expr: &Expression) -> Result<Value, CylError> {
+        if let Some((enum_name, variant)) = self.typed.constructors.get(expr) {
+            let arguments = match &expr.kind {
+                ExpressionKind::Call { arguments, .. } => arguments.as_slice(),
+                _ => &[],
+            };
+            return self.construct_variant(enum_name, variant, arguments, expr);
+        }
+        match &expr.kind {
+            ExpressionKind::IntLiteral(val) => Ok(self.builder.ins().iconst(types::I64, *val)),
+            ExpressionKind::FloatLiteral(val) => Ok(self.builder.ins().f64const(*val)),
+            ExpressionKind::StringLiteral(value) => {
+                self.string_literal(value).map_err(|e| at(e, expr.span))
+            }
+            ExpressionKind::BoolLiteral(val) => {
+                Ok(self.builder.ins().iconst(types::I8, if *val { 1 } else { 0 }))
+            }
+            ExpressionKind::Identifier(name) => {
+                if let Some((var, _)) = self.variables.get(name) {
+                    Ok(self.builder.use_var(*var))
+                } else if let Some((_, ty)) = self.globals.get(name) {
+                    let address = self.global_address(name);
+                    Ok(self.builder.ins().load(
+                        cl_type(ty, self.enums, self.structs)?,
+                        MemFlags::trusted(),
+                        address,
+                        0,
+                    ))
                 } else {
-                    // User-defined function call (simple case - return dummy for now)
-                    Ok(builder.ins().iconst(types::I32, 0))
+                    Err(CylError::CodeGenError {
+                        message: format!("Undefined variable: {}", name),
+                        span: Some(expr.span),
+                    })
                 }
             }
-            Expression::ArrayLiteral(_elements) => {
-                // For now, return a dummy value since array support is complex in Cranelift
-                // TODO: Implement proper array allocation and initialization
-                Ok(builder.ins().iconst(types::I64, 0))
+            ExpressionKind::BinaryOp { left, operator, right } => {
+                self.binary_op(left, *operator, right, expr.span)
+            }
+            ExpressionKind::UnaryOp { operator, operand } => {
+                self.unary_op(*operator, operand, expr.span)
+            }
+            ExpressionKind::Assignment { target, value } => {
+                let value = self.owned(value)?;
+                self.assign(target, value)?;
+                Ok(value)
+            }
+            ExpressionKind::MemberAccess { object, property } => {
+                self.member_access(object, property, expr)
             }
-            Expression::IndexAccess { object: _object, index: _index } => {
-                // For now, return a dummy value since array indexing requires proper array support
-                // TODO: Implement proper array indexing with bounds checking
-                Ok(builder.ins().iconst(types::I32, 0))
+            ExpressionKind::ObjectLiteral(fields) => self.struct_literal(fields, expr.span),
+            ExpressionKind::Call { callee, arguments } => {
+                if let ExpressionKind::MemberAccess { object, property } = &callee.kind {
+                    return self.method_call(object, property, arguments, expr.span);
+                }
+
+                let ExpressionKind::Identifier(function_name) = &callee.kind else {
+                    return Err(CylError::CodeGenError {
+                        message: "Complex function calls not supported yet".to_string(),
+                        span: Some(callee.span),
+                    });
+                };
+                match function_name.as_str() {
+                    "print" | "println" | "print_int" => {
+                        self.print(function_name, arguments.first(), expr.span)
+                    }
+                    _ => {
+                        let name = self.types.callee(expr).unwrap_or(function_name);
+                        self.call(name, arguments, expr.span)
+                    }
+                }
             }
-            _ => Err(CylError::CodeGenError { 
-                message: format!("Expression type not implemented: {:?}", expr) 
+            ExpressionKind::ArrayLiteral(elements) => self.array_literal(elements),
+            ExpressionKind::IndexAccess { object, index } => self.index(object, index, expr),
+            _ => Err(CylError::CodeGenError {
+                message: format!("Expression type not implemented: {:?}", expr.kind),
+                span: Some(expr.span),
             }),
         }
     }
 
-    fn infer_expression_type_static(expr: &Expression) -> Result<Type, CylError> {
-        match expr {
-            Expression::IntLiteral(_) => Ok(Type::Int),
-            Expression::FloatLiteral(_) => Ok(Type::Float),
-            Expression::BoolLiteral(_) => Ok(Type::Bool),
-            Expression::StringLiteral(_) => Ok(Type::String),
-            Expression::Identifier(_) => {
-                // For simplicity, assume int for now
-                Ok(Type::Int)
+    /// Call the user function, or instance, `name`. Missing arguments take
+    /// their parameters' defaults, evaluated with only the earlier
+    /// parameters in scope, as the interpreter does.
+    fn call(&mut self, name: &str, arguments: &[Expression], span: Span) -> Result<Value, CylError> {
+        let (Some(&func_id), Some((parameters, _))) =
+            (self.functions.get(name), self.signatures.get(name))
+        else {
+            return Err(CylError::CodeGenError {
+                message: format!("Calling `{}` is not supported by the Cranelift backend yet", name),
+                span: Some(span),
+            });
+        };
+        let mut values = arguments
+            .iter()
+            .map(|argument| self.owned(argument))
+            .collect::<Result<Vec<_>, _>>()?;
+        if values.len() < parameters.len() {
+            // The defaults were checked with the callee
+            let typed = self.typed;
+            let caller = std::mem::take(&mut self.variables);
+            let caller_types = std::mem::replace(&mut self.types, typed.types_of(name));
+            let result = self.default_arguments(parameters, &mut values);
+            self.variables = caller;
+            self.types = caller_types;
+            result?;
+        }
+
+        let callee = self.module.declare_func_in_func(func_id, self.builder.func);
+        let call = self.builder.ins().call(callee, &values);
+        let result = match self.builder.inst_results(call).first() {
+            Some(&value) => value,
+            // The type checker rejects any use of a void result, so this
+            // value is never read
+            None => self.builder.ins().iconst(types::I8, 0),
+        };
+        self.check_exception(name)?;
+        Ok(result)
+    }
+
+    /// Append the defaults of the parameters `values` does not cover.
+    fn default_arguments(
+        &mut self,
+        parameters: &[Parameter],
+        values: &mut Vec<Value>,
+    ) -> Result<(), CylError> {
+        for (parameter, &value) in parameters.iter().zip(values.iter()) {
+            self.define(&parameter.name, &parameter.param_type, value)?;
+        }
+        for parameter in &parameters[values.len()..] {
+            let Some(default) = &parameter.default_value else {
+                return Err(CylError::CodeGenError {
+                    message: format!("Missing argument for parameter `{}`", parameter.name),
+                    span: Some(parameter.span),
+                });
+            };
+            let value = self.owned(default)?;
+            self.define(&parameter.name, &parameter.param_type, value)?;
+            values.push(value);
+        }
+        Ok(())
+    }
+
+    /// Store `value` in the variable or struct field `target`. Fields are
+    /// stored to in place: see [`Self::owned`] for why no other value sees
+    /// the change.
+    fn assign(&mut self, target: &Expression, value: Value) -> Result<(), CylError> {
+        match &target.kind {
+            ExpressionKind::Identifier(name) if self.variables.contains_key(name) => {
+                let (var, _) = self.variables[name];
+                self.builder.def_var(var, value);
+                Ok(())
             }
-            Expression::BinaryOp { left, operator, .. } => {
-                match operator {
-                    BinaryOperator::Equal | BinaryOperator::NotEqual => Ok(Type::Bool),
-                    _ => Self::infer_expression_type_static(left), // Assume same type as left operand
-                }
+            ExpressionKind::Identifier(name) if self.globals.contains_key(name) => {
+                let address = self.global_address(name);
+                self.builder.ins().store(MemFlags::trusted(), value, address, 0);
+                Ok(())
             }
-            Expression::ArrayLiteral(_) => Ok(Type::Array(Box::new(Type::Int))), // Assume int arrays for now
-            Expression::IndexAccess { .. } => Ok(Type::Int), // Array elements are ints for now
-            _ => Err(CylError::CodeGenError { 
-                message: format!("Type inference not implemented for: {:?}", expr) 
+            ExpressionKind::MemberAccess { object, property } => {
+                let (object, ty) = self.typed_expression(object)?;
+                let (_, field) = self.struct_field(&ty, property, target.span)?;
+                self.builder
+                    .ins()
+                    .store(MemFlags::trusted(), value, object, field.offset as i32);
+                Ok(())
+            }
+            _ => Err(CylError::CodeGenError {
+                message: "Assigning to anything but a variable or struct field is not supported by the Cranelift backend yet"
+                    .to_string(),
+                span: Some(target.span),
             }),
         }
     }
 
-    fn type_to_cranelift(&self, ty: &Type) -> Result<cranelift_codegen::ir::Type, CylError> {
-        match ty {
-            Type::Int => Ok(types::I64),
-            Type::Float => Ok(types::F64),
-            Type::Bool => Ok(types::I8),
-            Type::String => Ok(types::I64), // Pointer to string data
-            Type::Void => Err(CylError::CodeGenError { 
-                message: "Cannot convert void type".to_string() 
-            }),
-            _ => Err(CylError::CodeGenError { 
-                message: format!("Type conversion not implemented: {:?}", ty) 
+    /// The value of `expr`, to be bound or stored somewhere. A struct read
+    /// from a variable, a field or an array element is copied, so every
+    /// struct object has a single owner and storing to its fields in place
+    /// changes no other value, as with the interpreter's struct values.
+    fn owned(&mut self, expr: &Expression) -> Result<Value, CylError> {
+        let (value, ty) = self.typed_expression(expr)?;
+        match &expr.kind {
+            ExpressionKind::Identifier(_)
+            | ExpressionKind::MemberAccess { .. }
+            | ExpressionKind::IndexAccess { .. } => self.copy_struct(value, &ty),
+            _ => Ok(value),
+        }
+    }
+
+    /// A copy of the struct `value`, of type `ty`, and of the structs its
+    /// fields hold. Values of other types are never stored to, so they are
+    /// returned as they are.
+    fn copy_struct(&mut self, value: Value, ty: &Type) -> Result<Value, CylError> {
+        let Some(layout) = struct_layout(ty, self.structs) else {
+            return Ok(value);
+        };
+        let size = self.builder.ins().iconst(types::I64, i64::from(layout.size));
+        let copy = self.call_runtime("cyl_alloc", &[size], Some(types::I64))?;
+        let align = layout.align as u8;
+        let config = self.module.target_config();
+        self.builder.emit_small_memory_copy(
+            config,
+            copy,
+            value,
+            u64::from(layout.size),
+            align,
+            align,
+            true,
+            MemFlags::trusted(),
+        );
+        for field in &layout.fields {
+            let field_type = layout.field_type(field, ty);
+            if struct_layout(&field_type, self.structs).is_some() {
+                let offset = field.offset as i32;
+                let inner = self.builder.ins().load(types::I64, MemFlags::trusted(), copy, offset);
+                let inner = self.copy_struct(inner, &field_type)?;
+                self.builder.ins().store(MemFlags::trusted(), inner, copy, offset);
+            }
+        }
+        Ok(copy)
+    }
+
+    /// A new struct holding the values of the fields of a struct literal,
+    /// evaluated in declaration order.
+    fn struct_literal(
+        &mut self,
+        fields: &HashMap<String, Expression>,
+        span: Span,
+    ) -> Result<Value, CylError> {
+        let layout = match fields.get("__struct_name__").map(|e| &e.kind) {
+            Some(ExpressionKind::StringLiteral(name)) => self.structs.get(name),
+            _ => None,
+        };
+        let Some(layout) = layout else {
+            return Err(CylError::CodeGenError {
+                message: "Object literals are not supported by the Cranelift backend yet".to_string(),
+                span: Some(span),
+            });
+        };
+        let size = self.builder.ins().iconst(types::I64, i64::from(layout.size));
+        let object = self.call_runtime("cyl_alloc", &[size], Some(types::I64))?;
+        for field in &layout.fields {
+            let Some(value) = fields.get(&field.name) else {
+                return Err(CylError::CodeGenError {
+                    message: format!(
+                        "Missing field `{}` in literal of struct `{}`",
+                        field.name, layout.name
+                    ),
+                    span: Some(span),
+                });
+            };
+            let value = self.owned(value)?;
+            self.builder
+                .ins()
+                .store(MemFlags::trusted(), value, object, field.offset as i32);
+        }
+        Ok(object)
+    }
+
+    /// Load the field `property` of the struct `object`, as `expr` does.
+    fn member_access(
+        &mut self,
+        object: &Expression,
+        property: &str,
+        expr: &Expression,
+    ) -> Result<Value, CylError> {
+        let (value, ty) = self.typed_expression(object)?;
+        let (_, field) = self.struct_field(&ty, property, expr.span)?;
+        let field_type = self.type_of(expr)?;
+        Ok(self.builder.ins().load(
+            cl_type(&field_type, self.enums, self.structs).map_err(|e| at(e, expr.span))?,
+            MemFlags::trusted(),
+            value,
+            field.offset as i32,
+        ))
+    }
+
+    /// The layout of the struct type `ty` and of its field `name`.
+    fn struct_field(
+        &self,
+        ty: &Type,
+        name: &str,
+        span: Span,
+    ) -> Result<(&'a StructLayout, &'a FieldLayout), CylError> {
+        let Some(layout) = struct_layout(ty, self.structs) else {
+            return Err(CylError::CodeGenError {
+                message: format!(
+                    "Member access on `{}` values is not supported by the Cranelift backend yet",
+                    ty
+                ),
+                span: Some(span),
+            });
+        };
+        let field = layout.field(name).ok_or_else(|| CylError::CodeGenError {
+            message: format!("Struct `{}` has no field `{}`", layout.name, name),
+            span: Some(span),
+        })?;
+        Ok((layout, field))
+    }
+
+    /// Call the method `name` of `object`.
+    fn method_call(
+        &mut self,
+        object: &Expression,
+        name: &str,
+        arguments: &[Expression],
+        span: Span,
+    ) -> Result<Value, CylError> {
+        let (receiver, ty) = self.typed_expression(object)?;
+        match (&ty, name) {
+            (Type::String, "len") if arguments.is_empty() => {
+                self.call_runtime("cyl_string_length", &[receiver], Some(types::I64))
+            }
+            (Type::Array(_), "len") if arguments.is_empty() => Ok(self.array_length(receiver)),
+            _ => Err(CylError::CodeGenError {
+                message: format!(
+                    "Method `{}` on `{}` values is not supported by the Cranelift backend yet",
+                    name, ty
+                ),
+                span: Some(span),
             }),
         }
     }
 
-    pub fn write_object_file(&mut self, path: &str) -> Result<(), CylError> {
-        // We need to consume the module to finish it
-        let module = std::mem::replace(&mut self.module, {
-            // Create a dummy module for replacement
-            let isa_builder = cranelift_native::builder()
-                .map_err(|e| CylError::CodeGenError { 
-                    message: format!("Failed to create ISA builder: {}", e) 
-                })?;
-            let isa = isa_builder
-                .finish(Flags::new(cranelift_codegen::settings::builder()))
-                .map_err(|e| CylError::CodeGenError { 
-                    message: format!("Failed to create ISA: {}", e) 
+    /// The address of a read-only string object holding `value`, shared by
+    /// every literal with the same contents.
+    fn string_literal(&mut self, value: &str) -> Result<Value, CylError> {
+        let data_id = match self.strings.get(value) {
+            Some(&data_id) => data_id,
+            None => {
+                let data_id = self.module.declare_anonymous_data(false, false).map_err(|e| {
+                    CylError::CodeGenError {
+                        message: format!("Failed to declare string literal: {}", e),
+                        span: None,
+                    }
                 })?;
-            let builder = ObjectBuilder::new(
-                isa,
-                "dummy".to_string(),
-                cranelift_module::default_libcall_names(),
-            )
-            .map_err(|e| CylError::CodeGenError { 
-                message: format!("Failed to create object builder: {}", e) 
-            })?;
-            ObjectModule::new(builder)
-        });
-        
-        let product = module.finish();
-        
-        std::fs::write(path, product.emit().unwrap())
-            .map_err(|e| CylError::CodeGenError { 
-                message: format!("Failed to write object file: {}", e) 
-            })?;
+                let mut description = DataDescription::new();
+                description.define(layout::string_object(value).into_boxed_slice());
+                description.set_align(u64::from(layout::STRING_ALIGN));
+                self.module
+                    .define_data(data_id, &description)
+                    .map_err(|e| CylError::CodeGenError {
+                        message: format!("Failed to define string literal: {}", e),
+                        span: None,
+                    })?;
+                self.strings.insert(value.to_string(), data_id);
+                data_id
+            }
+        };
+        let global = self.module.declare_data_in_func(data_id, self.builder.func);
+        Ok(self.builder.ins().symbol_value(types::I64, global))
+    }
+
+    /// `value`, of type `ty`, as the string the interpreter makes of it
+    /// when it prints it or adds it to a string.
+    fn stringify(&mut self, value: Value, ty: &Type) -> Option<Value> {
+        let function = match ty {
+            Type::String => return Some(value),
+            Type::Int => "cyl_string_from_int",
+            Type::Float => "cyl_string_from_float",
+            Type::Bool => "cyl_string_from_bool",
+            Type::Array(element_type) => return self.stringify_array(value, element_type),
+            _ if enum_name(ty, self.enums).is_some() => return self.stringify_enum(value, ty),
+            _ if struct_layout(ty, self.structs).is_some() => {
+                return self.stringify_struct(value, ty)
+            }
+            // Formatting anything else needs more runtime support
+            _ => return None,
+        };
+        self.call_runtime(function, &[value], Some(types::I64)).ok()
+    }
+
+    /// `[1, 2, 3]`, as the interpreter formats an array.
+    fn stringify_array(&mut self, array: Value, element_type: &Type) -> Option<Value> {
+        let element_cl_type = cl_type(element_type, self.enums, self.structs).ok()?;
+        let length = self.array_length(array);
+        let open = self.string_literal("[").ok()?;
+        let header = self.builder.create_block();
+        let index = self.builder.append_block_param(header, types::I64);
+        let text = self.builder.append_block_param(header, types::I64);
+        let body = self.builder.create_block();
+        let separator = self.builder.create_block();
+        let element = self.builder.create_block();
+        let prefix = self.builder.append_block_param(element, types::I64);
+        let exit = self.builder.create_block();
+        let zero = self.builder.ins().iconst(types::I64, 0);
+        self.builder.ins().jump(header, &[zero, open]);
+
+        self.switch_to(header);
+        let more = self.builder.ins().icmp(IntCC::SignedLessThan, index, length);
+        self.builder.ins().brif(more, body, &[], exit, &[]);
+
+        self.switch_to(body);
+        let first = self.builder.ins().icmp_imm(IntCC::Equal, index, 0);
+        self.builder.ins().brif(first, element, &[text], separator, &[]);
+
+        self.switch_to(separator);
+        let comma = self.string_literal(", ").ok()?;
+        let separated = self.concat(text, comma)?;
+        self.builder.ins().jump(element, &[separated]);
+
+        self.switch_to(element);
+        let offset = self.builder.ins().imul_imm(index, i64::from(layout::SLOT_SIZE));
+        let address = self.builder.ins().iadd(array, offset);
+        let value = self.builder.ins().load(
+            element_cl_type,
+            MemFlags::trusted(),
+            address,
+            layout::ARRAY_ELEMENTS_OFFSET as i32,
+        );
+        let formatted = self.stringify(value, element_type)?;
+        let extended = self.concat(prefix, formatted)?;
+        let following = self.builder.ins().iadd_imm(index, 1);
+        self.builder.ins().jump(header, &[following, extended]);
+
+        self.switch_to(exit);
+        let close = self.string_literal("]").ok()?;
+        self.concat(text, close)
+    }
+
+    /// `Point { x: 1, y: 2 }`, as the interpreter formats a struct.
+    fn stringify_struct(&mut self, value: Value, ty: &Type) -> Option<Value> {
+        let layout = struct_layout(ty, self.structs)?;
+        let mut text = self.string_literal(&format!("{} {{ ", layout.name)).ok()?;
+        for (i, field) in layout.fields.iter().enumerate() {
+            let separator = if i == 0 { "" } else { ", " };
+            let label = self.string_literal(&format!("{separator}{}: ", field.name)).ok()?;
+            text = self.concat(text, label)?;
+            let field_type = layout.field_type(field, ty);
+            let field_value = self.builder.ins().load(
+                cl_type(&field_type, self.enums, self.structs).ok()?,
+                MemFlags::trusted(),
+                value,
+                field.offset as i32,
+            );
+            let formatted = self.stringify(field_value, &field_type)?;
+            text = self.concat(text, formatted)?;
+        }
+        let close = self.string_literal(" }").ok()?;
+        self.concat(text, close)
+    }
+
+    /// `Shape.Rect(2, 3)`, or `Some(1)` for the prelude's variants, as the
+    /// interpreter formats an enum value.
+    fn stringify_enum(&mut self, value: Value, ty: &Type) -> Option<Value> {
+        let layout = self.enums.get(enum_name(ty, self.enums)?)?;
+        let merge = self.builder.create_block();
+        let result = self.builder.append_block_param(merge, types::I64);
+        let tag = self.builder.ins().load(types::I64, MemFlags::trusted(), value, 0);
+        for (i, variant) in layout.variants.iter().enumerate() {
+            // The last variant is the only one left once the others failed
+            if i + 1 < layout.variants.len() {
+                let matched = self.builder.ins().icmp_imm(IntCC::Equal, tag, variant.tag);
+                let next = self.builder.create_block();
+                let this = self.builder.create_block();
+                self.builder.ins().brif(matched, this, &[], next, &[]);
+                self.switch_to(this);
+                let text = self.stringify_variant(value, ty, layout, variant)?;
+                self.builder.ins().jump(merge, &[text]);
+                self.switch_to(next);
+            } else {
+                let text = self.stringify_variant(value, ty, layout, variant)?;
+                self.builder.ins().jump(merge, &[text]);
+            }
+        }
+        if layout.variants.is_empty() {
+            self.builder.ins().trap(UNREACHABLE_TRAP);
+        }
+        self.switch_to(merge);
+        Some(result)
+    }
+
+    fn stringify_variant(
+        &mut self,
+        value: Value,
+        ty: &Type,
+        layout: &EnumLayout,
+        variant: &VariantLayout,
+    ) -> Option<Value> {
+        let name = if crate::prelude::constructor_enum(&variant.name) == Some(layout.name.as_str()) {
+            variant.name.clone()
+        } else {
+            format!("{}.{}", layout.name, variant.name)
+        };
+        if variant.fields.is_empty() {
+            return self.string_literal(&name).ok();
+        }
+        let mut text = self.string_literal(&format!("{name}(")).ok()?;
+        for (i, field_type) in layout.field_types(variant, ty).iter().enumerate() {
+            if i > 0 {
+                let separator = self.string_literal(", ").ok()?;
+                text = self.concat(text, separator)?;
+            }
+            let field_value = self.builder.ins().load(
+                cl_type(field_type, self.enums, self.structs).ok()?,
+                MemFlags::trusted(),
+                value,
+                EnumLayout::field_offset(i) as i32,
+            );
+            let formatted = self.stringify(field_value, field_type)?;
+            text = self.concat(text, formatted)?;
+        }
+        let close = self.string_literal(")").ok()?;
+        self.concat(text, close)
+    }
+
+    fn concat(&mut self, left: Value, right: Value) -> Option<Value> {
+        self.call_runtime("cyl_string_concat", &[left, right], Some(types::I64)).ok()
+    }
+
+    /// String concatenation and comparison. An operand marked temporary is
+    /// a string made for this operation alone, which is freed after it.
+    fn string_op(
+        &mut self,
+        operator: BinaryOperator,
+        (left, left_type, left_temporary): (Value, &Type, bool),
+        (right, right_type, right_temporary): (Value, &Type, bool),
+    ) -> Option<Value> {
+        use BinaryOperator::*;
+        let (result, operands) = if operator == Add {
+            let left_text = self.stringify(left, left_type)?;
+            let right_text = self.stringify(right, right_type)?;
+            let text = self.concat(left_text, right_text)?;
+            // Numbers and bools are formatted into new strings
+            let formatted = |ty: &Type| matches!(ty, Type::Int | Type::Float | Type::Bool);
+            let operands = [
+                (left_text, left_temporary || formatted(left_type)),
+                (right_text, right_temporary || formatted(right_type)),
+            ];
+            (text, operands)
+        } else {
+            if !matches!(operator, Equal | NotEqual | Less | LessEqual | Greater | GreaterEqual)
+                || (left_type, right_type) != (&Type::String, &Type::String)
+            {
+                return None;
+            }
+            let order = self
+                .call_runtime("cyl_string_compare", &[left, right], Some(types::I64))
+                .ok()?;
+            let result = self.builder.ins().icmp_imm(int_cc(operator), order, 0);
+            (result, [(left, left_temporary), (right, right_temporary)])
+        };
+        for (operand, temporary) in operands {
+            if temporary {
+                self.call_runtime("cyl_string_free", &[operand], None).ok()?;
+            }
+        }
+        Some(result)
+    }
+
+    /// Call the runtime function `name` and return its result, if it has
+    /// one.
+    fn call_runtime(
+        &mut self,
+        name: &'static str,
+        arguments: &[Value],
+        result: Option<cranelift_codegen::ir::Type>,
+    ) -> Result<Value, CylError> {
+        let params: Vec<_> = arguments
+            .iter()
+            .map(|&value| self.builder.func.dfg.value_type(value))
+            .collect();
+        let callee = self.import(name, &params, result)?;
+        let call = self.builder.ins().call(callee, arguments);
+        Ok(match result {
+            Some(_) => self.builder.inst_results(call)[0],
+            None => self.builder.ins().iconst(types::I8, 0),
+        })
+    }
 
-        println!("Object file written to: {}", path);
+    /// Call the runtime function that prints `argument` like the print
+    /// builtin `name`.
+    fn print(
+        &mut self,
+        name: &str,
+        argument: Option<&Expression>,
+        span: Span,
+    ) -> Result<Value, CylError> {
+        match argument {
+            None if name == "println" => {
+                self.call_runtime("cyl_print_newline", &[], None)?;
+            }
+            None => {}
+            Some(argument) => {
+                let (value, ty) = self.typed_expression(argument)?;
+                match &ty {
+                    Type::Array(element_type) => self.print_elements(value, element_type, span)?,
+                    _ => self.print_value(value, &ty, span)?,
+                }
+                if ty == Type::String && runtime::is_concatenation(argument) {
+                    self.call_runtime("cyl_string_free", &[value], None)?;
+                }
+            }
+        }
+        Ok(self.builder.ins().iconst(types::I8, 0))
+    }
+
+    /// Print `value`, of type `ty`, on a line of its own.
+    fn print_value(&mut self, value: Value, ty: &Type, span: Span) -> Result<(), CylError> {
+        if let Some(function) = print_function(ty) {
+            self.call_runtime(function, &[value], None)?;
+            return Ok(());
+        }
+        let text = self.stringify(value, ty).ok_or_else(|| CylError::CodeGenError {
+            message: format!("Printing `{ty}` values is not supported by the Cranelift backend yet"),
+            span: Some(span),
+        })?;
+        self.call_runtime("cyl_print_str", &[text], None)?;
         Ok(())
     }
 
-    pub fn print_ir(&self) {
-        println!("Cranelift IR generation complete!");
-        // TODO: Add IR printing functionality if needed
+    /// Print the elements of `array` one per line with blank lines between
+    /// them, as the interpreter prints arrays.
+    fn print_elements(&mut self, array: Value, element_type: &Type, span: Span) -> Result<(), CylError> {
+        let length = self.array_length(array);
+        let header = self.builder.create_block();
+        let index = self.builder.append_block_param(header, types::I64);
+        let body = self.builder.create_block();
+        let separator = self.builder.create_block();
+        let print = self.builder.create_block();
+        let exit = self.builder.create_block();
+        let zero = self.builder.ins().iconst(types::I64, 0);
+        self.builder.ins().jump(header, &[zero]);
+
+        self.switch_to(header);
+        let more = self.builder.ins().icmp(IntCC::SignedLessThan, index, length);
+        self.builder.ins().brif(more, body, &[], exit, &[]);
+
+        self.switch_to(body);
+        let first = self.builder.ins().icmp_imm(IntCC::Equal, index, 0);
+        self.builder.ins().brif(first, print, &[], separator, &[]);
+
+        self.switch_to(separator);
+        self.call_runtime("cyl_print_newline", &[], None)?;
+        self.builder.ins().jump(print, &[]);
+
+        self.switch_to(print);
+        let element = self.element(array, index, element_type).map_err(|e| at(e, span))?;
+        self.print_value(element, element_type, span)?;
+        let following = self.builder.ins().iadd_imm(index, 1);
+        self.builder.ins().jump(header, &[following]);
+
+        self.switch_to(exit);
+        Ok(())
+    }
+
+    /// A new array holding the values of `elements`.
+    fn array_literal(&mut self, elements: &[Expression]) -> Result<Value, CylError> {
+        let length = self.builder.ins().iconst(types::I64, elements.len() as i64);
+        let array = self.call_runtime("cyl_array_new", &[length], Some(types::I64))?;
+        for (i, element) in elements.iter().enumerate() {
+            let value = self.owned(element)?;
+            self.builder.ins().store(
+                MemFlags::trusted(),
+                value,
+                array,
+                layout::array_element_offset(i) as i32,
+            );
+        }
+        Ok(array)
+    }
+
+    /// `object[index]`, as `expr` reads it, exiting with the interpreter's
+    /// error message when `index` is out of bounds.
+    fn index(
+        &mut self,
+        object: &Expression,
+        index: &Expression,
+        expr: &Expression,
+    ) -> Result<Value, CylError> {
+        let (array, ty) = self.typed_expression(object)?;
+        if !matches!(ty, Type::Array(_)) {
+            return Err(CylError::CodeGenError {
+                message: format!("Indexing `{}` values is not supported by the Cranelift backend yet", ty),
+                span: Some(object.span),
+            });
+        }
+        let position = self.expression(index)?;
+        let length = self.array_length(array);
+        // Negative indices wrap around to huge unsigned ones
+        let in_bounds = self.builder.ins().icmp(IntCC::UnsignedLessThan, position, length);
+        let out_of_bounds = self.builder.create_block();
+        let next = self.builder.create_block();
+        self.builder.set_cold_block(out_of_bounds);
+        self.builder.ins().brif(in_bounds, next, &[], out_of_bounds, &[]);
+
+        self.switch_to(out_of_bounds);
+        let line = self.builder.ins().iconst(types::I64, index.span.line as i64);
+        let column = self.builder.ins().iconst(types::I64, index.span.column as i64);
+        self.call_runtime("cyl_index_out_of_bounds", &[position, length, line, column], None)?;
+        self.builder.ins().trap(UNREACHABLE_TRAP);
+
+        self.switch_to(next);
+        let element_type = self.type_of(expr)?;
+        self.element(array, position, &element_type)
+    }
+
+    fn array_length(&mut self, array: Value) -> Value {
+        self.builder.ins().load(
+            types::I64,
+            MemFlags::trusted(),
+            array,
+            layout::ARRAY_LENGTH_OFFSET as i32,
+        )
+    }
+
+    /// Load element `position` of `array` without checking its bounds.
+    fn element(&mut self, array: Value, position: Value, ty: &Type) -> Result<Value, CylError> {
+        let offset = self.builder.ins().imul_imm(position, i64::from(layout::SLOT_SIZE));
+        let address = self.builder.ins().iadd(array, offset);
+        Ok(self.builder.ins().load(
+            cl_type(ty, self.enums, self.structs)?,
+            MemFlags::trusted(),
+            address,
+            layout::ARRAY_ELEMENTS_OFFSET as i32,
+        ))
+    }
+
+    fn binary_op(
+        &mut self,
+        left: &Expression,
+        operator: BinaryOperator,
+        right: &Expression,
+        span: Span,
+    ) -> Result<Value, CylError> {
+        use BinaryOperator::*;
+        if matches!(operator, And | Or) {
+            return self.short_circuit(left, operator == And, right);
+        }
+        let temporaries = [left, right].map(runtime::is_concatenation);
+        let (left, left_type) = self.typed_expression(left)?;
+        let (right, right_type) = self.typed_expression(right)?;
+        match (&left_type, &right_type) {
+            (Type::Int, Type::Int) => self.int_op(operator, left, right, span),
+            (Type::Float, Type::Float) => self.float_op(operator, left, right, span),
+            (Type::Bool, Type::Bool) if matches!(operator, Equal | NotEqual) => {
+                Some(self.builder.ins().icmp(int_cc(operator), left, right))
+            }
+            (Type::String, _) | (_, Type::String) => self.string_op(
+                operator,
+                (left, &left_type, temporaries[0] && left_type == Type::String),
+                (right, &right_type, temporaries[1] && right_type == Type::String),
+            ),
+            _ => None,
+        }
+        .ok_or_else(|| CylError::CodeGenError {
+            message: format!(
+                "`{:?}` on `{}` and `{}` values is not supported by the Cranelift backend yet",
+                operator, left_type, right_type
+            ),
+            span: Some(span),
+        })
+    }
+
+    /// `left && right` or `left || right`, evaluating `right` only when
+    /// `left` does not decide the result.
+    fn short_circuit(
+        &mut self,
+        left: &Expression,
+        and: bool,
+        right: &Expression,
+    ) -> Result<Value, CylError> {
+        let left = self.expression(left)?;
+        let rest = self.builder.create_block();
+        let merge = self.builder.create_block();
+        let result = self.builder.append_block_param(merge, types::I8);
+        if and {
+            self.builder.ins().brif(left, rest, &[], merge, &[left]);
+        } else {
+            self.builder.ins().brif(left, merge, &[left], rest, &[]);
+        }
+        self.switch_to(rest);
+        let right = self.expression(right)?;
+        self.builder.ins().jump(merge, &[right]);
+        self.switch_to(merge);
+        Ok(result)
+    }
+
+    /// Exit with the runtime error `message` at `span` when `failed` is
+    /// true, as the interpreter would stop there.
+    fn fail_if(&mut self, failed: Value, message: &str, span: Span) -> Result<(), CylError> {
+        self.fail_with(failed, span, |lowering| lowering.string_literal(message))
+    }
+
+    /// [`Self::fail_if`] with a message computed by `message` on failure.
+    fn fail_with(
+        &mut self,
+        failed: Value,
+        span: Span,
+        message: impl FnOnce(&mut Self) -> Result<Value, CylError>,
+    ) -> Result<(), CylError> {
+        let failure = self.builder.create_block();
+        let next = self.builder.create_block();
+        self.builder.set_cold_block(failure);
+        self.builder.ins().brif(failed, failure, &[], next, &[]);
+
+        self.switch_to(failure);
+        let message = message(self)?;
+        let line = self.builder.ins().iconst(types::I64, span.line as i64);
+        let column = self.builder.ins().iconst(types::I64, span.column as i64);
+        self.call_runtime("cyl_runtime_error", &[message, line, column], None)?;
+        self.builder.ins().trap(UNREACHABLE_TRAP);
+
+        self.switch_to(next);
+        Ok(())
+    }
+
+    /// Integer arithmetic, failing with the interpreter's error where it
+    /// reports overflow or division by zero.
+    fn int_op(
+        &mut self,
+        operator: BinaryOperator,
+        left: Value,
+        right: Value,
+        span: Span,
+    ) -> Option<Value> {
+        use BinaryOperator::*;
+        let overflow_message = format!("Integer overflow in `{operator:?}`");
+        let ins = self.builder.ins();
+        let (value, overflow) = match operator {
+            Add => ins.sadd_overflow(left, right),
+            Subtract => ins.ssub_overflow(left, right),
+            Multiply => ins.smul_overflow(left, right),
+            Divide | Modulo => {
+                let by_zero = self.builder.ins().icmp_imm(IntCC::Equal, right, 0);
+                let by_zero_message = if operator == Divide {
+                    "Division by zero"
+                } else {
+                    "Modulo by zero"
+                };
+                self.fail_if(by_zero, by_zero_message, span).ok()?;
+                // `i64::MIN / -1` overflows, and so does its remainder in
+                // the interpreter
+                let min = self.builder.ins().icmp_imm(IntCC::Equal, left, i64::MIN);
+                let minus_one = self.builder.ins().icmp_imm(IntCC::Equal, right, -1);
+                let overflow = self.builder.ins().band(min, minus_one);
+                self.fail_if(overflow, &overflow_message, span).ok()?;
+                let value = if operator == Divide {
+                    self.builder.ins().sdiv(left, right)
+                } else {
+                    self.builder.ins().srem(left, right)
+                };
+                return Some(value);
+            }
+            Equal | NotEqual | Less | LessEqual | Greater | GreaterEqual => {
+                return Some(ins.icmp(int_cc(operator), left, right));
+            }
+            BitwiseAnd => return Some(ins.band(left, right)),
+            BitwiseOr => return Some(ins.bor(left, right)),
+            BitwiseXor => return Some(ins.bxor(left, right)),
+            LeftShift | RightShift => {
+                let out_of_range =
+                    ins.icmp_imm(IntCC::UnsignedGreaterThanOrEqual, right, i64::from(i64::BITS));
+                self.fail_with(out_of_range, span, |lowering| {
+                    let prefix = lowering.string_literal("Shift amount ")?;
+                    let amount =
+                        lowering.call_runtime("cyl_string_from_int", &[right], Some(types::I64))?;
+                    let suffix = lowering.string_literal(" is out of range")?;
+                    let message = lowering.call_runtime(
+                        "cyl_string_concat",
+                        &[prefix, amount],
+                        Some(types::I64),
+                    )?;
+                    lowering.call_runtime("cyl_string_concat", &[message, suffix], Some(types::I64))
+                })
+                .ok()?;
+                let value = if operator == LeftShift {
+                    self.builder.ins().ishl(left, right)
+                } else {
+                    self.builder.ins().sshr(left, right)
+                };
+                return Some(value);
+            }
+            And | Or => return None,
+        };
+        self.fail_if(overflow, &overflow_message, span).ok()?;
+        Some(value)
+    }
+
+    fn float_op(
+        &mut self,
+        operator: BinaryOperator,
+        left: Value,
+        right: Value,
+        span: Span,
+    ) -> Option<Value> {
+        use BinaryOperator::*;
+        let value = match operator {
+            Add => self.builder.ins().fadd(left, right),
+            Subtract => self.builder.ins().fsub(left, right),
+            Multiply => self.builder.ins().fmul(left, right),
+            Divide | Modulo => {
+                let zero = self.builder.ins().f64const(0.0);
+                let by_zero = self.builder.ins().fcmp(FloatCC::Equal, right, zero);
+                let by_zero_message = if operator == Divide {
+                    "Division by zero"
+                } else {
+                    "Modulo by zero"
+                };
+                self.fail_if(by_zero, by_zero_message, span).ok()?;
+                if operator == Divide {
+                    self.builder.ins().fdiv(left, right)
+                } else {
+                    // Cranelift has no float remainder instruction
+                    let fmod = self.import("fmod", &[types::F64, types::F64], Some(types::F64)).ok()?;
+                    let call = self.builder.ins().call(fmod, &[left, right]);
+                    self.builder.inst_results(call)[0]
+                }
+            }
+            Equal | NotEqual => {
+                // The interpreter treats floats within an epsilon as equal
+                let difference = self.builder.ins().fsub(left, right);
+                let distance = self.builder.ins().fabs(difference);
+                let epsilon = self.builder.ins().f64const(f64::EPSILON);
+                let cc = if operator == Equal {
+                    FloatCC::LessThan
+                } else {
+                    FloatCC::UnorderedOrGreaterThanOrEqual
+                };
+                return Some(self.builder.ins().fcmp(cc, distance, epsilon));
+            }
+            Less | LessEqual | Greater | GreaterEqual => {
+                let cc = match operator {
+                    Less => FloatCC::LessThan,
+                    LessEqual => FloatCC::LessThanOrEqual,
+                    Greater => FloatCC::GreaterThan,
+                    _ => FloatCC::GreaterThanOrEqual,
+                };
+                return Some(self.builder.ins().fcmp(cc, left, right));
+            }
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    fn unary_op(
+        &mut self,
+        operator: UnaryOperator,
+        operand: &Expression,
+        span: Span,
+    ) -> Result<Value, CylError> {
+        let (value, ty) = self.typed_expression(operand)?;
+        Ok(match (operator, &ty) {
+            (UnaryOperator::Minus, Type::Int) => {
+                let min = self.builder.ins().icmp_imm(IntCC::Equal, value, i64::MIN);
+                self.fail_if(min, "Integer overflow in `Minus`", span)?;
+                self.builder.ins().ineg(value)
+            }
+            (UnaryOperator::Minus, Type::Float) => self.builder.ins().fneg(value),
+            (UnaryOperator::Plus, Type::Int | Type::Float) => value,
+            (UnaryOperator::Not, Type::Bool) => self.builder.ins().icmp_imm(IntCC::Equal, value, 0),
+            (UnaryOperator::BitwiseNot, Type::Int) => self.builder.ins().bnot(value),
+            _ => {
+                return Err(CylError::CodeGenError {
+                    message: format!(
+                        "`{:?}` on `{}` values is not supported by the Cranelift backend yet",
+                        operator, ty
+                    ),
+                    span: Some(span),
+                })
+            }
+        })
+    }
+
+    /// A reference to the C library or runtime function `name`, declared
+    /// on first use.
+    fn import(
+        &mut self,
+        name: &'static str,
+        params: &[cranelift_codegen::ir::Type],
+        result: Option<cranelift_codegen::ir::Type>,
+    ) -> Result<FuncRef, CylError> {
+        if let Some(&func_ref) = self.imports.get(name) {
+            return Ok(func_ref);
+        }
+        let mut sig = self.module.make_signature();
+        // C expects callers to extend narrow integers such as `bool`s
+        sig.params.extend(params.iter().map(|&ty| match ty {
+            types::I8 => AbiParam::new(ty).uext(),
+            _ => AbiParam::new(ty),
+        }));
+        sig.returns.extend(result.map(AbiParam::new));
+        let func_id = self
+            .module
+            .declare_function(name, Linkage::Import, &sig)
+            .map_err(|e| CylError::CodeGenError {
+                message: format!("Failed to declare function: {}", e),
+                span: None,
+            })?;
+        let func_ref = self.module.declare_func_in_func(func_id, self.builder.func);
+        self.imports.insert(name, func_ref);
+        Ok(func_ref)
+    }
+}
+
+/// Signed integer condition of a comparison operator.
+fn int_cc(operator: BinaryOperator) -> IntCC {
+    match operator {
+        BinaryOperator::NotEqual => IntCC::NotEqual,
+        BinaryOperator::Less => IntCC::SignedLessThan,
+        BinaryOperator::LessEqual => IntCC::SignedLessThanOrEqual,
+        BinaryOperator::Greater => IntCC::SignedGreaterThan,
+        BinaryOperator::GreaterEqual => IntCC::SignedGreaterThanOrEqual,
+        _ => IntCC::Equal,
+    }
+}
+
+/// Layout of the struct `ty` refers to, if any.
+fn struct_layout<'a>(ty: &Type, structs: &'a HashMap<String, StructLayout>) -> Option<&'a StructLayout> {
+    match ty {
+        Type::Custom(name) | Type::Generic(name, _) => structs.get(name),
+        _ => None,
+    }
+}
+
+/// The runtime function that prints a value of type `ty`, if there is one.
+fn print_function(ty: &Type) -> Option<&'static str> {
+    match ty {
+        Type::Int => Some("cyl_print_int"),
+        Type::Float => Some("cyl_print_float"),
+        Type::Bool => Some("cyl_print_bool"),
+        Type::String => Some("cyl_print_str"),
+        _ => None,
+    }
+}
+
+/// Name of the enum `ty` refers to, if any.
+fn enum_name<'a>(ty: &'a Type, enums: &HashMap<String, EnumLayout>) -> Option<&'a str> {
+    match ty {
+        Type::Custom(name) | Type::Generic(name, _) if enums.contains_key(name) => Some(name),
+        _ => None,
+    }
+}
+
+fn cl_type(
+    ty: &Type,
+    enums: &HashMap<String, EnumLayout>,
+    structs: &HashMap<String, StructLayout>,
+) -> Result<cranelift_codegen::ir::Type, CylError> {
+    match ty {
+        Type::Int => Ok(types::I64),
+        Type::Float => Ok(types::F64),
+        Type::Bool => Ok(types::I8),
+        Type::String => Ok(types::I64), // Pointer to the string object
+        Type::Array(_) => Ok(types::I64), // Pointer to the array object
+        _ if enum_name(ty, enums).is_some() => Ok(types::I64), // Pointer to the tagged union
+        _ if struct_layout(ty, structs).is_some() => Ok(types::I64), // Pointer to the fields
+        Type::Void => Err(CylError::CodeGenError {
+            message: "Cannot convert void type".to_string(),
+            span: None,
+        }),
+        _ => Err(CylError::CodeGenError {
+            message: format!("Type conversion not implemented: {:?}", ty),
+            span: None,
+        }),
+    }
+}
+
+/// Point an error raised without a location at `span`.
+fn at(error: CylError, span: Span) -> CylError {
+    match error {
+        CylError::CodeGenError {
+            message,
+            span: None,
+        } => CylError::CodeGenError {
+            message,
+            span: Some(span),
+        },
+        other => other,
     }
 }
@@ -0,0 +1,480 @@
+//! Compiler diagnostics: errors rendered as labelled source snippets.
+//!
+//! A [`Diagnostic`] is built from a [`CylError`] (or by hand), may carry any
+//! number of primary and secondary labels, notes and help messages, and is
+//! rendered in the familiar rustc/codespan style:
+//!
+//! ```text
+//! error[E0003]: Mismatched types in declaration of `y`: expected `string`, found `int`
+//!   ┌─ main.cyl:3:5
+//!   │
+//! 3 │     let y: string = x + 2;
+//!   │     ^^^^^^^^^^^^^^^^^^^^^^
+//! ```
+//!
+//! With `--message-format=json` each diagnostic is instead printed as one
+//! [`JsonDiagnostic`] object per line.
+
+use crate::ast::Span;
+use crate::error::{CylError, Warning};
+use codespan_reporting::diagnostic as cs;
+use codespan_reporting::files::SimpleFile;
+use codespan_reporting::term::{
+    self,
+    termcolor::{ColorChoice, NoColor, StandardStream},
+};
+use serde::{Serialize, Serializer};
+use std::io::IsTerminal;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    /// Primary labels mark the cause (`^^^`), secondary ones add context (`---`).
+    pub primary: bool,
+}
+
+impl Label {
+    pub fn primary(span: Span, message: impl Into<String>) -> Self {
+        Label {
+            span,
+            message: message.into(),
+            primary: true,
+        }
+    }
+
+    pub fn secondary(span: Span, message: impl Into<String>) -> Self {
+        Label {
+            span,
+            message: message.into(),
+            primary: false,
+        }
+    }
+}
+
+/// A machine-applicable fix: replace the text at `span` with `replacement`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Suggestion {
+    pub span: Span,
+    pub replacement: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Vec<String>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+/// One diagnostic as emitted by `--message-format=json`. `span`, `line` and
+/// `column` locate the primary label and are `null` when there is none.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JsonDiagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    pub file: Option<String>,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub span: Option<Span>,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Vec<String>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, code: &'static str, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity,
+            code,
+            message: message.into(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: Vec::new(),
+            suggestions: Vec::new(),
+        }
+    }
+
+    pub fn error(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, code, message)
+    }
+
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.labels.push(label);
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help.push(help.into());
+        self
+    }
+
+    pub fn with_suggestion(mut self, suggestion: Suggestion) -> Self {
+        self.suggestions.push(suggestion);
+        self
+    }
+
+    /// The primary label's span, if the diagnostic has one.
+    pub fn primary_span(&self) -> Option<Span> {
+        self.labels
+            .iter()
+            .find(|label| label.primary)
+            .map(|label| label.span)
+    }
+
+    pub fn to_json(&self, file: Option<&str>) -> JsonDiagnostic {
+        let span = self.primary_span();
+        JsonDiagnostic {
+            severity: self.severity,
+            code: self.code,
+            message: self.message.clone(),
+            file: file.map(str::to_string),
+            line: span.map(|s| s.line),
+            column: span.map(|s| s.column),
+            span,
+            labels: self.labels.clone(),
+            notes: self.notes.clone(),
+            help: self.help.clone(),
+            suggestions: self.suggestions.clone(),
+        }
+    }
+
+    /// Build the diagnostic for `error`, resolving its location in `source`.
+    pub fn from_error(error: &CylError, source: &str) -> Self {
+        let span = match error {
+            CylError::LexError { line, column, .. } | CylError::ParseError { line, column, .. } => {
+                Some(point_span(source, *line, *column))
+            }
+            _ => error.span(),
+        };
+        let mut diagnostic = Diagnostic::for_error(error, span);
+        // Fixes may only know their byte range, as when inserting after a node
+        for suggestion in diagnostic.suggestions.iter_mut() {
+            suggestion.span = locate(source, suggestion.span.start, suggestion.span.end);
+        }
+        diagnostic
+    }
+
+    /// The diagnostic for `error`, labelled at `span`.
+    fn for_error(error: &CylError, span: Option<Span>) -> Self {
+        let mut diagnostic = Diagnostic::error(error.code(), error.message());
+        let details = error.details().cloned().unwrap_or_default();
+        if let Some(span) = span {
+            diagnostic = diagnostic.with_label(Label::primary(span, details.label));
+        }
+        diagnostic.labels.extend(details.secondary_labels);
+        if let Some(note) = stack_note(error) {
+            diagnostic = diagnostic.with_note(note);
+        }
+        diagnostic.help.extend(details.help);
+        diagnostic.suggestions.extend(details.suggestions);
+        diagnostic
+    }
+
+    pub fn from_warning(warning: &Warning) -> Self {
+        Diagnostic::new(Severity::Warning, warning.code, &warning.message)
+            .with_label(Label::primary(warning.span, ""))
+    }
+
+    /// Render to a string without colour.
+    pub fn render(&self, file_name: &str, source: &str) -> String {
+        let mut out = NoColor::new(Vec::new());
+        self.emit_to(&mut out, file_name, source);
+        String::from_utf8_lossy(&out.into_inner()).into_owned()
+    }
+
+    /// Print to stderr, in colour when stderr is a terminal and `NO_COLOR`
+    /// is not set.
+    pub fn emit(&self, file_name: &str, source: &str) {
+        let choice = if std::io::stderr().is_terminal() {
+            ColorChoice::Auto
+        } else {
+            ColorChoice::Never
+        };
+        let mut stderr = StandardStream::stderr(choice);
+        self.emit_to(&mut stderr, file_name, source);
+    }
+
+    fn emit_to(&self, out: &mut dyn term::termcolor::WriteColor, file_name: &str, source: &str) {
+        // Spans index the lexer's normalized source
+        let file = SimpleFile::new(file_name, source.replace("\r\n", "\n"));
+        let config = term::Config::default();
+        if term::emit(out, &config, &file, &self.to_codespan(file.source().len())).is_err() {
+            // The span does not fit the file; fall back to the bare message
+            let _ = writeln!(out, "error[{}]: {}", self.code, self.message);
+        }
+    }
+
+    fn to_codespan(&self, source_len: usize) -> cs::Diagnostic<()> {
+        let severity = match self.severity {
+            Severity::Error => cs::Severity::Error,
+            Severity::Warning => cs::Severity::Warning,
+            Severity::Note => cs::Severity::Note,
+        };
+        let labels = self
+            .labels
+            .iter()
+            .map(|label| {
+                let start = label.span.start.min(source_len);
+                let end = label.span.end.clamp(start, source_len);
+                let rendered = if label.primary {
+                    cs::Label::primary((), start..end)
+                } else {
+                    cs::Label::secondary((), start..end)
+                };
+                rendered.with_message(&label.message)
+            })
+            .collect();
+        let notes = self
+            .notes
+            .iter()
+            .map(|note| format!("note: {note}"))
+            .chain(self.help.iter().map(|help| format!("help: {help}")))
+            .chain(self.suggestions.iter().map(render_suggestion))
+            .collect();
+        cs::Diagnostic::new(severity)
+            .with_code(self.code)
+            .with_message(&self.message)
+            .with_labels(labels)
+            .with_notes(notes)
+    }
+}
+
+/// Errors serialize to the `--message-format=json` schema. Lexer and parser
+/// errors only know their line and column, so without the source at hand
+/// their `span` is `null`; `Diagnostic::from_error` resolves it.
+impl Serialize for CylError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut json = Diagnostic::for_error(self, self.span()).to_json(None);
+        if let CylError::LexError { line, column, .. } | CylError::ParseError { line, column, .. } =
+            self
+        {
+            json.line = Some(*line);
+            json.column = Some(*column);
+        }
+        json.serialize(serializer)
+    }
+}
+
+/// A fix as a help line: the replacement inline, or on lines of its own when
+/// it spans several, as new match arms do.
+fn render_suggestion(fix: &Suggestion) -> String {
+    if !fix.replacement.contains('\n') {
+        return format!("help: {}: `{}`", fix.message, fix.replacement);
+    }
+    let lines: Vec<String> = fix
+        .replacement
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| format!("    {line}"))
+        .collect();
+    format!("help: {}:\n{}", fix.message, lines.join("\n"))
+}
+
+/// Frames listed in a runtime error's call stack note; deep recursion is
+/// elided after this many.
+const MAX_STACK_NOTE_FRAMES: usize = 10;
+
+/// The call stack of a runtime error, as a note.
+fn stack_note(error: &CylError) -> Option<String> {
+    let stack = match error {
+        CylError::RuntimeError { stack, .. } if !stack.is_empty() => stack,
+        _ => return None,
+    };
+    let mut note = "call stack, innermost first:".to_string();
+    for frame in stack.iter().take(MAX_STACK_NOTE_FRAMES) {
+        note.push_str(&format!("\n  in `{}` at {}", frame.function, frame.span));
+    }
+    if stack.len() > MAX_STACK_NOTE_FRAMES {
+        let hidden = stack.len() - MAX_STACK_NOTE_FRAMES;
+        note.push_str(&format!("\n  ... and {hidden} more"));
+    }
+    Some(note)
+}
+
+/// A one-character span at 1-based `line` and `column` of `source`.
+fn point_span(source: &str, line: usize, column: usize) -> Span {
+    let source = source.replace("\r\n", "\n");
+    let line_start: usize = source
+        .split_inclusive('\n')
+        .take(line.saturating_sub(1))
+        .map(str::len)
+        .sum();
+    let start = source[line_start..]
+        .char_indices()
+        .nth(column.saturating_sub(1))
+        .map(|(offset, _)| line_start + offset)
+        .unwrap_or(source.len());
+    let end = source[start..]
+        .chars()
+        .next()
+        .filter(|c| *c != '\n')
+        .map(|c| start + c.len_utf8())
+        .unwrap_or(start);
+    Span::new(start, end, line, column)
+}
+
+/// The span of bytes `start..end` of `source`, with the line and column
+/// where it starts.
+fn locate(source: &str, start: usize, end: usize) -> Span {
+    let source = source.replace("\r\n", "\n");
+    let start = start.min(source.len());
+    let before = source.get(..start).unwrap_or_default();
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let column = before[line_start..].chars().count() + 1;
+    Span::new(start, end.max(start), line, column)
+}
+
+/// Long-form explanation of a diagnostic code, as printed by `cylc explain`.
+/// Codes are matched case-insensitively.
+pub fn explain(code: &str) -> Option<&'static str> {
+    let code = code.to_ascii_uppercase();
+    EXPLANATIONS
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map(|(_, text)| *text)
+}
+
+const EXPLANATIONS: &[(&str, &str)] = &[
+    (
+        "E0001",
+        "E0001: invalid token
+
+The lexer found characters that do not form any Cyl token, such as a stray
+`@` or an unterminated string literal.
+
+    let price = 5 @ 2;   // error: `@` is not a Cyl operator
+
+Remove or replace the offending characters.",
+    ),
+    (
+        "E0002",
+        "E0002: syntax error
+
+The parser expected a different token at this point, for example a missing
+name in a declaration, an unbalanced brace, or a token that cannot start a
+top-level item.
+
+    fn main() -> void {
+        let = 1;         // error: expected identifier after 'let'
+        print(x);
+    }
+
+The parser recovers at the next statement boundary and keeps going, so a
+single file may report several of these; later ones can be a consequence of
+earlier ones.",
+    ),
+    (
+        "E0003",
+        "E0003: type mismatch
+
+An expression's type does not match what its context requires: a declared
+variable type, a function parameter, a return type, or an operator's operands.
+
+    fn main() -> void {
+        let name: string = 42;   // error: expected `string`, found `int`
+    }
+
+Change the expression or the annotation so the two agree. Use `dynamic` to
+opt a value out of static checking.",
+    ),
+    (
+        "E0004",
+        "E0004: semantic error
+
+The program violates a language rule, such as referring to an undefined
+variable or function, using `break` outside of a loop, or defining the same
+type twice.
+
+    fn main() -> void {
+        print(count);   // error: undefined variable `count`
+    }
+
+Check the spelling and make sure the name is declared before it is used.
+
+A `match` must also handle every possible value of its scrutinee. The error
+lists example patterns that no arm matches:
+
+    enum Shape { Circle(float), Square(float), Empty }
+
+    match shape {           // error: missing `Shape.Square(_)`, `Shape.Empty`
+        Shape.Circle(r) => { print(r); }
+    }
+
+Add arms for the missing patterns, or a `_` arm to handle the rest.",
+    ),
+    (
+        "E0005",
+        "E0005: code generation error
+
+A native backend (Cranelift or LLVM) does not support a construct used by the
+program yet.
+
+Run the program with `--backend interpreter` in the meantime, or rewrite the
+construct using features the backend supports.",
+    ),
+    (
+        "E0006",
+        "E0006: runtime error
+
+The program failed while running, for example by dividing by zero, indexing
+an array out of bounds, or applying an operator to values of the wrong type.
+The error points at the expression being evaluated when the failure happened,
+and a note lists the function calls that were active, innermost first.
+
+    fn divide(a: int, b: int) -> int {
+        return a / b;   // error: Division by zero, when called as divide(1, 0)
+    }",
+    ),
+    (
+        "E0007",
+        "E0007: I/O error
+
+The compiler could not read or write a file, for example because the input
+path does not exist or the output directory is not writable.",
+    ),
+    (
+        "E0008",
+        "E0008: JSON error
+
+The compiler could not serialize or deserialize JSON, for example when
+printing the AST with `cylc ast --format json`.",
+    ),
+    (
+        "W0001",
+        "W0001: unreachable pattern
+
+A match arm can never run because the arms above it already match every
+value its pattern matches. Arms are tried in order, so the later arm is dead
+code; often the arms are in the wrong order.
+
+    match n {
+        _ => { print(\"anything\"); }
+        0 => { print(\"zero\"); }   // warning: unreachable pattern
+    }
+
+Arms with a guard (`x if x > 0 => ...`) never hide later arms, since the
+guard may fail.",
+    ),
+];
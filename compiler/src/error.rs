@@ -1,5 +1,37 @@
+use crate::ast::Span;
+use crate::diagnostics::{Label, Suggestion};
+use serde::Serialize;
 use thiserror::Error;
 
+/// A function that was running when a runtime error was raised, and the
+/// location it was executing: the failing expression for the innermost
+/// frame, the call into the next frame for the others.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StackFrame {
+    pub function: String,
+    pub span: Span,
+}
+
+/// A problem worth reporting that does not stop compilation, such as an
+/// unreachable match arm.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    /// Stable diagnostic code; see `cylc explain`.
+    pub code: &'static str,
+    pub message: String,
+    pub span: Span,
+}
+
+/// What a compile error adds to its diagnostic beside the message: text for
+/// the label at its span, related locations, help, and fixes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ErrorDetails {
+    pub label: String,
+    pub secondary_labels: Vec<Label>,
+    pub help: Vec<String>,
+    pub suggestions: Vec<Suggestion>,
+}
+
 #[derive(Error, Debug)]
 #[allow(clippy::enum_variant_names)] // Error suffix is conventional for error types
 #[allow(dead_code)] // Error variants will be used as the compiler develops
@@ -18,17 +50,33 @@ pub enum CylError {
         column: usize,
     },
 
-    #[error("Type error: {message}")]
-    TypeError { message: String },
+    #[error("Type error: {message}{}", at(.span))]
+    TypeError {
+        message: String,
+        span: Option<Span>,
+        details: Box<ErrorDetails>,
+    },
 
-    #[error("Semantic error: {message}")]
-    SemanticError { message: String },
+    #[error("Semantic error: {message}{}", at(.span))]
+    SemanticError {
+        message: String,
+        span: Option<Span>,
+        details: Box<ErrorDetails>,
+    },
 
-    #[error("Code generation error: {message}")]
-    CodeGenError { message: String },
+    #[error("Code generation error: {message}{}", at(.span))]
+    CodeGenError {
+        message: String,
+        span: Option<Span>,
+    },
 
-    #[error("Runtime error: {message}")]
-    RuntimeError { message: String },
+    #[error("Runtime error: {message}{}", at(.span))]
+    RuntimeError {
+        message: String,
+        span: Option<Span>,
+        /// Active Cyl calls, innermost first.
+        stack: Vec<StackFrame>,
+    },
 
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
@@ -36,3 +84,61 @@ pub enum CylError {
     #[error("JSON error: {0}")]
     JsonError(#[from] serde_json::Error),
 }
+
+impl CylError {
+    /// Source location the error points at, if known.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            CylError::TypeError { span, .. }
+            | CylError::SemanticError { span, .. }
+            | CylError::CodeGenError { span, .. }
+            | CylError::RuntimeError { span, .. } => *span,
+            _ => None,
+        }
+    }
+
+    /// Label text, help and fixes for the error's diagnostic, if it has any.
+    pub fn details(&self) -> Option<&ErrorDetails> {
+        match self {
+            CylError::TypeError { details, .. } | CylError::SemanticError { details, .. } => {
+                Some(details)
+            }
+            _ => None,
+        }
+    }
+
+    /// Stable diagnostic code for this kind of error; see `cylc explain`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            CylError::LexError { .. } => "E0001",
+            CylError::ParseError { .. } => "E0002",
+            CylError::TypeError { .. } => "E0003",
+            CylError::SemanticError { .. } => "E0004",
+            CylError::CodeGenError { .. } => "E0005",
+            CylError::RuntimeError { .. } => "E0006",
+            CylError::IoError(_) => "E0007",
+            CylError::JsonError(_) => "E0008",
+        }
+    }
+
+    /// The error message without its kind prefix or location suffix.
+    pub fn message(&self) -> String {
+        match self {
+            CylError::LexError { message, .. }
+            | CylError::ParseError { message, .. }
+            | CylError::TypeError { message, .. }
+            | CylError::SemanticError { message, .. }
+            | CylError::CodeGenError { message, .. }
+            | CylError::RuntimeError { message, .. } => message.clone(),
+            CylError::IoError(e) => e.to_string(),
+            CylError::JsonError(e) => e.to_string(),
+        }
+    }
+}
+
+fn at(span: &Option<Span>) -> String {
+    match span {
+        Some(span) => format!(" at {span}"),
+        None => String::new(),
+    }
+}
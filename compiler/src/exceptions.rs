@@ -0,0 +1,250 @@
+//! Exceptions in code compiled by the native backends.
+//!
+//! Compiled code does not unwind the stack. A `throw` that no enclosing
+//! `try` in its function catches records the exception in a per-program
+//! record and returns from the function, and each call that may let an
+//! exception escape checks the record when it returns: a matching catch
+//! clause around the call takes the exception, and otherwise the caller
+//! returns in turn. An exception escaping `main` is reported as the
+//! interpreter reports it.
+//!
+//! The record holds the number identifying the type of the pending
+//! exception, zero when there is none, the thrown value in a slot like an
+//! array element, and the location of the `throw`:
+//!
+//! ```text
+//! offset   0      8       16     24
+//!        [ type | value | line | column ]   size 32
+//! ```
+//!
+//! A call site only needs to test the types its callee can let escape, which
+//! [`escaping`] works out for every function ahead of code generation.
+
+use crate::ast::{Expression, ExpressionKind, Statement, StatementKind, Type};
+use crate::typeck::{ExpressionTypes, TypedProgram};
+use std::collections::HashMap;
+
+/// Byte offset of the type number of the pending exception.
+pub const TYPE_OFFSET: u32 = 0;
+
+/// Byte offset of the thrown value.
+pub const VALUE_OFFSET: u32 = 8;
+
+/// Byte offset of the line of the `throw`.
+pub const LINE_OFFSET: u32 = 16;
+
+/// Byte offset of the column of the `throw`.
+pub const COLUMN_OFFSET: u32 = 24;
+
+/// Size of the record in bytes.
+pub const RECORD_SIZE: u32 = 32;
+
+/// Whether a catch clause for `expected`, or for anything when `None`,
+/// catches a thrown value of type `thrown`, as the interpreter decides.
+pub fn catches(expected: Option<&Type>, thrown: &Type) -> bool {
+    match (expected, thrown) {
+        (None | Some(Type::Dynamic), _) => true,
+        (
+            Some(Type::Custom(expected) | Type::Generic(expected, _)),
+            Type::Custom(name) | Type::Generic(name, _),
+        ) => expected == name,
+        // The elements of an empty array have no type to check
+        (Some(Type::Array(_)), Type::Array(thrown)) if **thrown == Type::Dynamic => true,
+        (Some(Type::Array(expected)), Type::Array(thrown)) => catches(Some(expected), thrown),
+        (Some(expected), thrown) => expected == thrown,
+    }
+}
+
+/// Types of the exceptions that can escape each function backends compile
+/// of `typed`, in the order they are first found.
+pub fn escaping(typed: &TypedProgram) -> HashMap<String, Vec<Type>> {
+    let functions: Vec<_> = typed.functions().collect();
+    let mut escaping: HashMap<String, Vec<Type>> = functions
+        .iter()
+        .map(|(func, _)| (func.name.clone(), Vec::new()))
+        .collect();
+    // A call lets its callee's exceptions escape in turn, so repeat until
+    // no function gains any, which recursion needs
+    loop {
+        let mut changed = false;
+        for (func, types) in &functions {
+            let found = escaping_from(&func.body.statements, types, &escaping);
+            let known = escaping.get_mut(&func.name).unwrap();
+            for ty in found {
+                if !known.contains(&ty) {
+                    known.push(ty);
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            return escaping;
+        }
+    }
+}
+
+/// Types of the exceptions that can escape `statements`, whose expressions
+/// have the `types`, given those that can escape each function. Function
+/// declarations among them are skipped.
+pub fn escaping_from(
+    statements: &[Statement],
+    types: &ExpressionTypes,
+    escaping: &HashMap<String, Vec<Type>>,
+) -> Vec<Type> {
+    let mut search = Search {
+        types,
+        escaping,
+        handlers: Vec::new(),
+        found: Vec::new(),
+    };
+    search.block(statements);
+    search.found
+}
+
+/// A walk through a function body collecting the exceptions that leave it.
+struct Search<'a> {
+    types: &'a ExpressionTypes,
+    escaping: &'a HashMap<String, Vec<Type>>,
+    /// Types the catch clauses of the enclosing `try` statements catch,
+    /// innermost last.
+    handlers: Vec<Vec<Option<Type>>>,
+    found: Vec<Type>,
+}
+
+impl Search<'_> {
+    fn raise(&mut self, ty: &Type) {
+        let caught = self
+            .handlers
+            .iter()
+            .flatten()
+            .any(|expected| catches(expected.as_ref(), ty));
+        if !caught && !self.found.contains(ty) {
+            self.found.push(ty.clone());
+        }
+    }
+
+    fn block(&mut self, statements: &[Statement]) {
+        for stmt in statements {
+            self.statement(stmt);
+        }
+    }
+
+    fn statement(&mut self, stmt: &Statement) {
+        match &stmt.kind {
+            StatementKind::Declare(decl) => self.expression(&decl.value),
+            StatementKind::Expression(expr) => self.expression(expr),
+            StatementKind::Return(ret) => {
+                if let Some(value) = &ret.value {
+                    self.expression(value);
+                }
+            }
+            StatementKind::If(if_stmt) => {
+                self.expression(&if_stmt.condition);
+                self.block(&if_stmt.then_block.statements);
+                if let Some(else_block) = &if_stmt.else_block {
+                    self.statement(else_block);
+                }
+            }
+            StatementKind::While(while_stmt) => {
+                self.expression(&while_stmt.condition);
+                self.block(&while_stmt.body.statements);
+            }
+            StatementKind::For(for_stmt) => {
+                self.expression(&for_stmt.iterable);
+                self.block(&for_stmt.body.statements);
+            }
+            StatementKind::Match(match_stmt) => {
+                self.expression(&match_stmt.expression);
+                for arm in &match_stmt.arms {
+                    if let Some(guard) = &arm.guard {
+                        self.expression(guard);
+                    }
+                    self.block(&arm.body.statements);
+                }
+            }
+            StatementKind::Try(try_stmt) => {
+                let handlers = try_stmt
+                    .catch_clauses
+                    .iter()
+                    .map(|clause| clause.exception_type.clone())
+                    .collect();
+                self.handlers.push(handlers);
+                self.block(&try_stmt.body.statements);
+                self.handlers.pop();
+                for clause in &try_stmt.catch_clauses {
+                    self.block(&clause.body.statements);
+                }
+            }
+            StatementKind::Throw(throw) => {
+                self.expression(&throw.value);
+                self.raise(throw.value_type.as_ref().unwrap_or(&Type::Dynamic));
+            }
+            StatementKind::Block(block) => self.block(&block.statements),
+            StatementKind::Import(_)
+            | StatementKind::Function(_)
+            | StatementKind::Struct(_)
+            | StatementKind::Enum(_)
+            | StatementKind::Break(_)
+            | StatementKind::Continue(_) => {}
+        }
+    }
+
+    fn expression(&mut self, expr: &Expression) {
+        match &expr.kind {
+            ExpressionKind::Call { callee, arguments } => {
+                self.expression(callee);
+                for argument in arguments {
+                    self.expression(argument);
+                }
+                if let ExpressionKind::Identifier(name) = &callee.kind {
+                    let name = self.types.callee(expr).unwrap_or(name);
+                    let escaping = self.escaping;
+                    for ty in escaping.get(name).into_iter().flatten() {
+                        self.raise(ty);
+                    }
+                }
+            }
+            ExpressionKind::ArrayLiteral(items) | ExpressionKind::TupleLiteral(items) => {
+                for item in items {
+                    self.expression(item);
+                }
+            }
+            ExpressionKind::ObjectLiteral(fields) => {
+                for value in fields.values() {
+                    self.expression(value);
+                }
+            }
+            ExpressionKind::BinaryOp { left, right, .. }
+            | ExpressionKind::Range {
+                start: left,
+                end: right,
+                ..
+            }
+            | ExpressionKind::IndexAccess {
+                object: left,
+                index: right,
+            }
+            | ExpressionKind::Assignment {
+                target: left,
+                value: right,
+            } => {
+                self.expression(left);
+                self.expression(right);
+            }
+            ExpressionKind::UnaryOp { operand, .. }
+            | ExpressionKind::MemberAccess {
+                object: operand, ..
+            }
+            | ExpressionKind::Propagate(operand)
+            | ExpressionKind::Await(operand) => self.expression(operand),
+            ExpressionKind::Identifier(_)
+            | ExpressionKind::IntLiteral(_)
+            | ExpressionKind::FloatLiteral(_)
+            | ExpressionKind::StringLiteral(_)
+            | ExpressionKind::BoolLiteral(_)
+            | ExpressionKind::CharLiteral(_)
+            | ExpressionKind::Null
+            | ExpressionKind::Dynamic => {}
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use super::Value;
+use std::collections::HashMap;
+
+/// A variable's current value and whether it may be assigned to.
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub value: Value,
+    pub mutable: bool,
+}
+
+type Scope = HashMap<String, Binding>;
+
+/// The chain of scopes visible to running code.
+///
+/// Every active call owns a frame of block scopes, innermost last. Names are
+/// resolved through the current frame's scopes and then the module scope, so
+/// a function sees its own locals and the globals but never the locals of its
+/// caller. The bottom frame holds the block scopes of top-level statements.
+#[derive(Debug)]
+pub struct Environment {
+    globals: Scope,
+    frames: Vec<Vec<Scope>>,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Environment {
+            globals: Scope::new(),
+            frames: vec![Vec::new()],
+        }
+    }
+
+    /// Number of active function calls.
+    pub fn call_depth(&self) -> usize {
+        self.frames.len() - 1
+    }
+
+    /// Enter a function call with a fresh scope for its parameters.
+    pub fn push_frame(&mut self) {
+        self.frames.push(vec![Scope::new()]);
+    }
+
+    pub fn pop_frame(&mut self) {
+        if self.frames.len() > 1 {
+            self.frames.pop();
+        }
+    }
+
+    pub fn push_scope(&mut self) {
+        self.current_frame().push(Scope::new());
+    }
+
+    pub fn pop_scope(&mut self) {
+        self.current_frame().pop();
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.binding(name).map(|binding| &binding.value)
+    }
+
+    /// Bind `name` in the innermost scope, shadowing any outer binding.
+    pub fn define(&mut self, name: &str, value: Value, mutable: bool) {
+        let binding = Binding { value, mutable };
+        let scope = match self.frames.last_mut().and_then(|frame| frame.last_mut()) {
+            Some(scope) => scope,
+            None => &mut self.globals,
+        };
+        scope.insert(name.to_string(), binding);
+    }
+
+    /// The value of `name`, for updating part of it in place.
+    pub fn get_mut(&mut self, name: &str) -> Result<&mut Value, String> {
+        match self.binding_mut(name) {
+            Some(binding) if binding.mutable => Ok(&mut binding.value),
+            Some(_) => Err(format!("Cannot mutate immutable variable `{name}`")),
+            None => Err(format!("Cannot assign to undefined variable `{name}`")),
+        }
+    }
+
+    /// Update the innermost visible binding of `name`.
+    pub fn assign(&mut self, name: &str, value: Value) -> Result<(), String> {
+        match self.binding_mut(name) {
+            Some(binding) if binding.mutable => {
+                binding.value = value;
+                Ok(())
+            }
+            Some(_) => Err(format!(
+                "Cannot assign twice to immutable variable `{name}`"
+            )),
+            None => Err(format!("Cannot assign to undefined variable `{name}`")),
+        }
+    }
+
+    fn current_frame(&mut self) -> &mut Vec<Scope> {
+        self.frames
+            .last_mut()
+            .expect("the module frame is never popped")
+    }
+
+    fn binding(&self, name: &str) -> Option<&Binding> {
+        self.frames
+            .last()
+            .and_then(|frame| frame.iter().rev().find_map(|scope| scope.get(name)))
+            .or_else(|| self.globals.get(name))
+    }
+
+    fn binding_mut(&mut self, name: &str) -> Option<&mut Binding> {
+        let Environment { globals, frames } = self;
+        frames
+            .last_mut()
+            .and_then(|frame| frame.iter_mut().rev().find_map(|scope| scope.get_mut(name)))
+            .or_else(|| globals.get_mut(name))
+    }
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,9 +1,14 @@
 mod value;
+mod env;
 mod eval;
+mod ops;
+mod patterns;
 mod stdlib;
 mod utils;
+pub mod vm;
 
 pub use value::*;
-pub use eval::Interpreter;
+pub use eval::{Interpreter, DEFAULT_MAX_CALL_DEPTH};
+pub use patterns::{match_pattern, EnumVariants};
 pub use stdlib::*;
 pub use utils::*;
@@ -0,0 +1,192 @@
+use super::{value_to_string, Value};
+use crate::ast::{BinaryOperator, UnaryOperator};
+use std::ops::RangeInclusive;
+
+/// Apply a binary operator to two evaluated operands.
+///
+/// Errors are plain messages; the evaluator attaches the span of the failing
+/// expression. `And` and `Or` are only reached here when the left operand did
+/// not short-circuit.
+pub fn binary_op(operator: &BinaryOperator, left: Value, right: Value) -> Result<Value, String> {
+    use BinaryOperator::*;
+    let mismatch = |left: &Value, right: &Value| {
+        format!(
+            "Cannot apply `{operator:?}` to values of type `{}` and `{}`",
+            left.type_name(),
+            right.type_name()
+        )
+    };
+    let overflow = || format!("Integer overflow in `{operator:?}`");
+    match (operator, left, right) {
+        (Add, Value::String(a), b) => Ok(Value::String(a + &value_to_string(&b))),
+        (Add, a, Value::String(b)) => Ok(Value::String(value_to_string(&a) + &b)),
+        (Add, Value::Int(a), Value::Int(b)) => {
+            a.checked_add(b).map(Value::Int).ok_or_else(overflow)
+        }
+        (Subtract, Value::Int(a), Value::Int(b)) => {
+            a.checked_sub(b).map(Value::Int).ok_or_else(overflow)
+        }
+        (Multiply, Value::Int(a), Value::Int(b)) => {
+            a.checked_mul(b).map(Value::Int).ok_or_else(overflow)
+        }
+        (Divide, Value::Int(_), Value::Int(0)) => Err("Division by zero".to_string()),
+        (Divide, Value::Int(a), Value::Int(b)) => {
+            a.checked_div(b).map(Value::Int).ok_or_else(overflow)
+        }
+        (Modulo, Value::Int(_), Value::Int(0)) => Err("Modulo by zero".to_string()),
+        (Modulo, Value::Int(a), Value::Int(b)) => {
+            a.checked_rem(b).map(Value::Int).ok_or_else(overflow)
+        }
+        (Add, Value::Float(a), Value::Float(b)) => Ok(Value::Float(a + b)),
+        (Subtract, Value::Float(a), Value::Float(b)) => Ok(Value::Float(a - b)),
+        (Multiply, Value::Float(a), Value::Float(b)) => Ok(Value::Float(a * b)),
+        (Divide, Value::Float(_), Value::Float(0.0)) => Err("Division by zero".to_string()),
+        (Divide, Value::Float(a), Value::Float(b)) => Ok(Value::Float(a / b)),
+        (Modulo, Value::Float(_), Value::Float(0.0)) => Err("Modulo by zero".to_string()),
+        (Modulo, Value::Float(a), Value::Float(b)) => Ok(Value::Float(a % b)),
+
+        (Equal, a, b) => Ok(Value::Bool(values_equal(&a, &b))),
+        (NotEqual, a, b) => Ok(Value::Bool(!values_equal(&a, &b))),
+        (Less | LessEqual | Greater | GreaterEqual, a, b) => {
+            let ordering = match (&a, &b) {
+                (Value::Int(x), Value::Int(y)) => x.partial_cmp(y),
+                (Value::Float(x), Value::Float(y)) => x.partial_cmp(y),
+                (Value::String(x), Value::String(y)) => x.partial_cmp(y),
+                _ => return Err(mismatch(&a, &b)),
+            };
+            Ok(Value::Bool(match (operator, ordering) {
+                (_, None) => false,
+                (Less, Some(o)) => o.is_lt(),
+                (LessEqual, Some(o)) => o.is_le(),
+                (Greater, Some(o)) => o.is_gt(),
+                (_, Some(o)) => o.is_ge(),
+            }))
+        }
+
+        (And, Value::Bool(a), Value::Bool(b)) => Ok(Value::Bool(a && b)),
+        (Or, Value::Bool(a), Value::Bool(b)) => Ok(Value::Bool(a || b)),
+
+        (BitwiseAnd, Value::Int(a), Value::Int(b)) => Ok(Value::Int(a & b)),
+        (BitwiseOr, Value::Int(a), Value::Int(b)) => Ok(Value::Int(a | b)),
+        (BitwiseXor, Value::Int(a), Value::Int(b)) => Ok(Value::Int(a ^ b)),
+        (LeftShift | RightShift, Value::Int(a), Value::Int(b)) => {
+            let shift = u32::try_from(b)
+                .ok()
+                .filter(|shift| *shift < i64::BITS)
+                .ok_or_else(|| format!("Shift amount {b} is out of range"))?;
+            Ok(Value::Int(match operator {
+                LeftShift => a << shift,
+                _ => a >> shift,
+            }))
+        }
+
+        (_, a, b) => Err(mismatch(&a, &b)),
+    }
+}
+
+/// Apply a unary operator to an evaluated operand.
+pub fn unary_op(operator: &UnaryOperator, operand: Value) -> Result<Value, String> {
+    match (operator, operand) {
+        (UnaryOperator::Minus, Value::Int(i)) => i
+            .checked_neg()
+            .map(Value::Int)
+            .ok_or_else(|| "Integer overflow in `Minus`".to_string()),
+        (UnaryOperator::Minus, Value::Float(f)) => Ok(Value::Float(-f)),
+        (UnaryOperator::Plus, value @ (Value::Int(_) | Value::Float(_))) => Ok(value),
+        (UnaryOperator::Not, Value::Bool(b)) => Ok(Value::Bool(!b)),
+        (UnaryOperator::BitwiseNot, Value::Int(i)) => Ok(Value::Int(!i)),
+        (UnaryOperator::Await, Value::Future(inner)) => Ok(*inner),
+        (operator, value) => Err(format!(
+            "Cannot apply `{operator:?}` to a value of type `{}`",
+            value.type_name()
+        )),
+    }
+}
+
+/// Call the built-in method `method` on `receiver`.
+pub fn call_method(receiver: Value, method: &str, args: &[Value]) -> Result<Value, String> {
+    let items = match (&receiver, method, args.len()) {
+        (Value::Array(items), "len", 0) => return Ok(Value::Int(items.len() as i64)),
+        (Value::String(s), "len", 0) => return Ok(Value::Int(s.chars().count() as i64)),
+        (Value::Array(items), "enumerate", 0) => items.clone(),
+        (Value::String(s), "enumerate", 0) => chars(s),
+        _ => {
+            return Err(format!(
+                "Type `{}` has no method `{method}`",
+                receiver.type_name()
+            ))
+        }
+    };
+    // `enumerate`
+    Ok(Value::Array(
+        items
+            .into_iter()
+            .enumerate()
+            .map(|(i, item)| Value::Tuple(vec![Value::Int(i as i64), item]))
+            .collect(),
+    ))
+}
+
+/// The elements a `for` loop over `value` visits: the ints below an int, the
+/// elements of an array, the characters of a string, or the `(key, value)`
+/// pairs of a map in key order.
+pub fn iterate(value: Value) -> Result<Box<dyn Iterator<Item = Value>>, String> {
+    Ok(match value {
+        Value::Int(n) => Box::new((0..n).map(Value::Int)),
+        Value::Array(items) => Box::new(items.into_iter()),
+        Value::String(s) => Box::new(chars(&s).into_iter()),
+        Value::Struct(name, fields) if name == "<anon>" => Box::new(
+            fields
+                .into_iter()
+                .map(|(key, value)| Value::Tuple(vec![Value::String(key), value])),
+        ),
+        other => {
+            return Err(format!(
+                "Cannot iterate over a value of type `{}`",
+                other.type_name()
+            ))
+        }
+    })
+}
+
+/// The ints of `start..end`, or of `start..=end` if `inclusive`, without
+/// overflowing when `end` is at either end of `i64`.
+pub fn int_range(start: i64, end: i64, inclusive: bool) -> RangeInclusive<i64> {
+    match (inclusive, end.checked_sub(1)) {
+        (true, _) => start..=end,
+        (false, Some(last)) => start..=last,
+        // Nothing is below `i64::MIN`
+        (false, None) => RangeInclusive::new(1, 0),
+    }
+}
+
+/// The characters of `s`, each as a one-character string.
+fn chars(s: &str) -> Vec<Value> {
+    s.chars().map(|c| Value::String(c.to_string())).collect()
+}
+
+/// Structural equality. Values of different types are never equal; floats
+/// compare within `f64::EPSILON`.
+pub fn values_equal(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Float(a), Value::Float(b)) => (a - b).abs() < f64::EPSILON,
+        (Value::Array(a), Value::Array(b)) | (Value::Tuple(a), Value::Tuple(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(x, y)| values_equal(x, y))
+        }
+        (Value::Struct(a, a_fields), Value::Struct(b, b_fields)) => {
+            a == b
+                && a_fields.len() == b_fields.len()
+                && a_fields
+                    .iter()
+                    .zip(b_fields)
+                    .all(|((x_name, x), (y_name, y))| x_name == y_name && values_equal(x, y))
+        }
+        (Value::Enum(a, a_variant, a_fields), Value::Enum(b, b_variant, b_fields)) => {
+            a == b
+                && a_variant == b_variant
+                && a_fields.len() == b_fields.len()
+                && a_fields.iter().zip(b_fields).all(|(x, y)| values_equal(x, y))
+        }
+        _ => left == right,
+    }
+}
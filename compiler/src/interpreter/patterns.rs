@@ -0,0 +1,115 @@
+use super::ops::values_equal;
+use super::Value;
+use crate::ast::{Expression, ExpressionKind, Pattern, PatternKind, UnaryOperator};
+use std::collections::HashMap;
+
+/// Variant names of every declared enum. A bare identifier pattern naming a
+/// variant of the scrutinee's enum matches that variant instead of binding.
+pub type EnumVariants = HashMap<String, Vec<String>>;
+
+/// Match `value` against `pattern`, appending the variables it binds to
+/// `bindings` in source order. When the match fails `bindings` may hold a
+/// partial set and should be discarded.
+pub fn match_pattern(
+    pattern: &Pattern,
+    value: &Value,
+    enums: &EnumVariants,
+    bindings: &mut Vec<(String, Value)>,
+) -> bool {
+    match (&pattern.kind, value) {
+        (PatternKind::Wildcard, _) => true,
+        (PatternKind::Identifier(name), Value::Enum(enum_name, variant, _))
+            if is_variant(enums, enum_name, name) =>
+        {
+            variant == name
+        }
+        // `T?` values match `Option` patterns: null is `None` and any other
+        // value is `Some`
+        (PatternKind::Identifier(name), Value::Void)
+            if name == "None" && enums.contains_key("Option") =>
+        {
+            true
+        }
+        (PatternKind::Identifier(name), _) => {
+            bindings.push((name.clone(), value.clone()));
+            true
+        }
+        (PatternKind::Literal(literal), _) => {
+            literal_value(literal).is_some_and(|literal| values_equal(&literal, value))
+        }
+        (PatternKind::Tuple(items), Value::Tuple(values)) => {
+            match_all(items, values, enums, bindings)
+        }
+        (PatternKind::TupleOrEnum(path, fields), Value::Enum(enum_name, variant, values))
+        | (
+            PatternKind::Enum {
+                variant: path,
+                fields,
+            },
+            Value::Enum(enum_name, variant, values),
+        ) => {
+            let (qualifier, name) = match path.rsplit_once('.') {
+                Some((qualifier, name)) => (Some(qualifier), name),
+                None => (None, path.as_str()),
+            };
+            qualifier.map_or(true, |q| q == enum_name)
+                && variant == name
+                && match_all(fields, values, enums, bindings)
+        }
+        (PatternKind::TupleOrEnum(path, fields), _)
+            if path == "Some" && fields.len() == 1 && !matches!(value, Value::Enum(..) | Value::Void) =>
+        {
+            match_pattern(&fields[0], value, enums, bindings)
+        }
+        (PatternKind::Struct { name, fields }, Value::Struct(struct_name, values)) => {
+            name == struct_name
+                && fields.iter().all(|(field, pattern)| {
+                    values
+                        .iter()
+                        .find(|(name, _)| name == field)
+                        .is_some_and(|(_, value)| match_pattern(pattern, value, enums, bindings))
+                })
+        }
+        _ => false,
+    }
+}
+
+fn match_all(
+    patterns: &[Pattern],
+    values: &[Value],
+    enums: &EnumVariants,
+    bindings: &mut Vec<(String, Value)>,
+) -> bool {
+    patterns.len() == values.len()
+        && patterns
+            .iter()
+            .zip(values)
+            .all(|(pattern, value)| match_pattern(pattern, value, enums, bindings))
+}
+
+fn is_variant(enums: &EnumVariants, enum_name: &str, name: &str) -> bool {
+    enums
+        .get(enum_name)
+        .is_some_and(|variants| variants.iter().any(|v| v == name))
+}
+
+/// The value of a literal pattern, as produced by `Parser::parse_pattern`.
+fn literal_value(expr: &Expression) -> Option<Value> {
+    match &expr.kind {
+        ExpressionKind::IntLiteral(i) => Some(Value::Int(*i)),
+        ExpressionKind::FloatLiteral(f) => Some(Value::Float(*f)),
+        ExpressionKind::StringLiteral(s) => Some(Value::String(s.clone())),
+        ExpressionKind::CharLiteral(c) => Some(Value::String(c.to_string())),
+        ExpressionKind::BoolLiteral(b) => Some(Value::Bool(*b)),
+        ExpressionKind::Null => Some(Value::Void),
+        ExpressionKind::UnaryOp {
+            operator: UnaryOperator::Minus,
+            operand,
+        } => match literal_value(operand)? {
+            Value::Int(i) => i.checked_neg().map(Value::Int),
+            Value::Float(f) => Some(Value::Float(-f)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
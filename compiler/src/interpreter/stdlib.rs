@@ -12,3 +12,9 @@ impl StdLibWrapper {
         }
     }
 }
+
+impl Default for StdLibWrapper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
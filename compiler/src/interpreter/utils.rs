@@ -10,24 +10,56 @@ pub fn value_to_string(val: &Value) -> String {
             let inner = elements.iter().map(value_to_string).collect::<Vec<_>>().join(", ");
             format!("[{}]", inner)
         }
+        Value::Tuple(elements) => {
+            let inner = elements.iter().map(value_to_string).collect::<Vec<_>>().join(", ");
+            format!("({})", inner)
+        }
         Value::Struct(name, fields) => {
-            let mut s = format!("{} {{ ", name);
-            for (k, v) in fields {
-                s.push_str(&format!("{}: {}, ", k, value_to_string(v)));
-            }
-            s.push('}');
-            s
+            let inner = fields
+                .iter()
+                .map(|(k, v)| format!("{}: {}", k, value_to_string(v)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("{} {{ {} }}", name, inner)
         }
-        Value::Enum(name, fields) => {
-            let mut s = format!("{}(", name);
-            for v in fields {
-                s.push_str(&format!("{}, ", value_to_string(v)));
+        // The prelude's variants print as they are written, e.g. `Some(1)`
+        Value::Enum(name, variant, fields)
+            if crate::prelude::constructor_enum(variant) == Some(name.as_str()) =>
+        {
+            let inner = fields.iter().map(value_to_string).collect::<Vec<_>>().join(", ");
+            if fields.is_empty() {
+                variant.clone()
+            } else {
+                format!("{}({})", variant, inner)
             }
-            s.push(')');
-            s
         }
-        Value::Result(ok, err) => format!("Ok({}), Err({})", value_to_string(ok), value_to_string(err)),
+        Value::Enum(name, variant, fields) if fields.is_empty() => format!("{}.{}", name, variant),
+        Value::Enum(name, variant, fields) => {
+            let inner = fields.iter().map(value_to_string).collect::<Vec<_>>().join(", ");
+            format!("{}.{}({})", name, variant, inner)
+        }
         Value::Future(inner) => format!("Future({})", value_to_string(inner)),
         Value::Void => "<void>".to_string(),
     }
 }
+
+/// Output lines of the print builtin `name` called with `args`: an array
+/// prints one element per line with blank lines between them, and `println`
+/// without arguments prints an empty line.
+pub fn printed_lines(name: &str, args: &[Value]) -> Vec<String> {
+    match args.first() {
+        Some(Value::Array(elements)) => {
+            let mut lines = Vec::new();
+            for (i, elem) in elements.iter().enumerate() {
+                if i > 0 {
+                    lines.push(String::new());
+                }
+                lines.push(value_to_string(elem));
+            }
+            lines
+        }
+        Some(val) => vec![value_to_string(val)],
+        None if name == "println" => vec![String::new()],
+        None => Vec::new(),
+    }
+}
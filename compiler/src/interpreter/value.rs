@@ -1,6 +1,6 @@
-use std::collections::HashMap;
+use crate::ast::Type;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 #[allow(dead_code)]
 pub enum Value {
     Int(i64),
@@ -8,10 +8,61 @@ pub enum Value {
     String(String),
     Bool(bool),
     Array(Vec<Value>),
-    Struct(String, HashMap<String, Value>),
-    Enum(String, Vec<Value>),       // Enum(variant, fields)
-    Result(Box<Value>, Box<Value>), // Ok(val), Err(val)
+    Tuple(Vec<Value>),
+    Struct(String, Vec<(String, Value)>), // Struct(name, fields in declared order)
+    Enum(String, String, Vec<Value>),     // Enum(enum, variant, fields)
     #[allow(dead_code)]
     Future(Box<Value>), // For async/await, treat as sync for now
     Void,
 }
+
+impl Value {
+    /// Name of the value's type, as used in runtime error messages.
+    pub fn type_name(&self) -> String {
+        match self {
+            Value::Int(_) => "int".to_string(),
+            Value::Float(_) => "float".to_string(),
+            Value::String(_) => "string".to_string(),
+            Value::Bool(_) => "bool".to_string(),
+            Value::Array(_) => "array".to_string(),
+            Value::Tuple(_) => "tuple".to_string(),
+            Value::Struct(name, _) => name.clone(),
+            Value::Enum(name, _, _) => name.clone(),
+            Value::Future(_) => "Future".to_string(),
+            Value::Void => "void".to_string(),
+        }
+    }
+
+    /// Whether the value counts as true in a condition.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Bool(b) => *b,
+            Value::Int(i) => *i != 0,
+            Value::Float(f) => *f != 0.0,
+            Value::String(s) => !s.is_empty(),
+            Value::Void => false,
+            _ => true,
+        }
+    }
+
+    /// Whether the value is an instance of `ty`, as when picking the catch
+    /// clause for a thrown value.
+    pub fn has_type(&self, ty: &Type) -> bool {
+        match (self, ty) {
+            (_, Type::Dynamic) => true,
+            (Value::Int(_), Type::Int)
+            | (Value::Float(_), Type::Float)
+            | (Value::String(_), Type::String)
+            | (Value::Bool(_), Type::Bool)
+            | (Value::Void, Type::Void) => true,
+            (Value::Array(items), Type::Array(element)) => {
+                items.iter().all(|item| item.has_type(element))
+            }
+            (Value::Struct(name, _) | Value::Enum(name, _, _), Type::Custom(expected))
+            | (Value::Struct(name, _) | Value::Enum(name, _, _), Type::Generic(expected, _)) => {
+                name == expected
+            }
+            _ => false,
+        }
+    }
+}
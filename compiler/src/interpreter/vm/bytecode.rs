@@ -0,0 +1,118 @@
+use crate::ast::{BinaryOperator, Pattern, Span, Type, UnaryOperator};
+use crate::interpreter::Value;
+
+/// Index of a function in [`Module::functions`].
+pub type FunctionId = u32;
+
+/// One instruction of the stack machine. Operands index the pools of the
+/// [`Module`] or the local slots of the running function; jump targets are
+/// offsets into the function's code.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    /// Push `constants[index]`.
+    Constant(u32),
+    Void,
+    Pop,
+    Dup,
+    GetLocal(u32),
+    SetLocal(u32),
+    /// Push the global in slot `index`, failing if no `let` has set it yet.
+    GetGlobal(u32),
+    SetGlobal(u32),
+    Binary(BinaryOperator),
+    Unary(UnaryOperator),
+    Jump(u32),
+    /// Pop the condition and jump if it is falsy.
+    JumpIfFalse(u32),
+    /// Jump if the top of the stack is the bool `value`, leaving it as the
+    /// result of a short-circuiting `&&` or `||`.
+    JumpIfBool { value: bool, target: u32 },
+    /// Pop the result of a match guard and jump if it is false.
+    Guard(u32),
+    /// Skip the default value of parameter `parameter` when the caller
+    /// passed an argument for it.
+    JumpIfArgument { parameter: u32, target: u32 },
+    Call { function: FunctionId, arguments: u32 },
+    /// Run the print builtin `names[name]` on the top `arguments` values.
+    Print { name: u32, arguments: u32 },
+    CallMethod { name: u32, arguments: u32 },
+    Return,
+    MakeArray(u32),
+    MakeTuple(u32),
+    /// Build `structs[index]` from one value per field, in declaration order.
+    MakeStruct(u32),
+    MakeVariant { variant: u32, fields: u32 },
+    /// Pop an index and an array or tuple, and push the element.
+    Index,
+    GetField(u32),
+    /// Pop a value and a struct, and push the struct with field
+    /// `names[name]` set to the value.
+    SetField(u32),
+    /// Pop the end and start of a range and push their array.
+    MakeRange { inclusive: bool },
+    /// Pop a value and start iterating over it.
+    IterStart,
+    IterRange { inclusive: bool },
+    /// Push the next element of the innermost iteration, or jump to
+    /// `target` once it is exhausted.
+    IterNext(u32),
+    IterEnd,
+    /// Pop the scrutinee and bind `patterns[index]`, or jump to `fail`.
+    TestPattern { pattern: u32, fail: u32 },
+    /// Pop the element of a `for` loop and bind the loop pattern.
+    Destructure(u32),
+    /// Fail with a non-exhaustive match error about local `scrutinee`.
+    NoMatch(u32),
+    /// Unwrap an `Ok` or `Some`, or return anything else from the function.
+    Propagate,
+    /// Catch values thrown until the matching `PopHandler` at `target`.
+    PushHandler(u32),
+    PopHandler,
+    /// Pop a value and unwind to the innermost handler.
+    Throw,
+    /// Unwind with a value no catch clause accepted, keeping the error of
+    /// its original `throw`.
+    Rethrow,
+    /// Push whether the top of the stack is an instance of `types[index]`.
+    HasType(u32),
+}
+
+/// A compiled function: its code, with the source span of each instruction
+/// for runtime errors.
+#[derive(Debug, Clone, Default)]
+pub struct Function {
+    pub name: String,
+    pub parameters: u32,
+    /// Local slots, parameters first.
+    pub locals: u32,
+    pub code: Vec<Op>,
+    pub spans: Vec<Span>,
+}
+
+/// A pattern of a `match` arm or `for` loop with the local slot of each name
+/// it binds.
+#[derive(Debug, Clone)]
+pub struct PatternSlots {
+    pub pattern: Pattern,
+    pub bindings: Vec<(String, u32)>,
+}
+
+/// A whole compiled program. Function 0 runs its top-level statements and
+/// then calls `main`.
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub functions: Vec<Function>,
+    pub constants: Vec<Value>,
+    /// Field, method and builtin names.
+    pub names: Vec<String>,
+    /// Struct names with their fields in declaration order.
+    pub structs: Vec<(String, Vec<String>)>,
+    /// `(enum, variant)` pairs built by `MakeVariant`.
+    pub variants: Vec<(String, String)>,
+    pub patterns: Vec<PatternSlots>,
+    pub types: Vec<Type>,
+    /// Names of the globals, by slot.
+    pub globals: Vec<String>,
+    /// Variant names of every enum, for matching patterns.
+    pub enums: crate::interpreter::EnumVariants,
+}
@@ -0,0 +1,882 @@
+use super::bytecode::{Function, FunctionId, Module, Op, PatternSlots};
+use crate::ast::*;
+use crate::error::CylError;
+use crate::interpreter::Value;
+use crate::typeck::{Constructors, TypedProgram};
+use std::collections::HashMap;
+
+/// Compile `program` to bytecode. Programs using something the VM does not
+/// handle yet fail with a `CodeGenError`, so callers can fall back to the
+/// tree-walking interpreter; so do mistakes the interpreter would only report
+/// when reaching them, like calling an unknown function.
+pub fn compile(typed: &TypedProgram) -> Result<Module, CylError> {
+    let program = &typed.program;
+    let mut compiler = Compiler::new(program, &typed.constructors)?;
+    let script = compiler.script(program)?;
+    compiler.module.functions.push(script);
+    for stmt in &program.statements {
+        if let StatementKind::Function(decl) = &stmt.kind {
+            let function = compiler.function(decl, stmt.span)?;
+            compiler.module.functions.push(function);
+        }
+    }
+    Ok(compiler.module)
+}
+
+/// State a `break` or `continue` must unwind when it jumps out of the
+/// statement that set it up.
+#[derive(Debug, Clone, Copy)]
+enum Cleanup {
+    Iterator,
+    Handler,
+}
+
+struct Loop {
+    label: Option<String>,
+    /// Where `continue` jumps.
+    start: u32,
+    /// Jumps to patch with the end of the loop.
+    breaks: Vec<usize>,
+    /// Length of the cleanup stack inside the loop body.
+    cleanups: usize,
+}
+
+struct Local {
+    name: String,
+    slot: u32,
+    mutable: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Variable {
+    Local(u32, bool),
+    Global(u32, bool),
+}
+
+impl Variable {
+    fn mutable(self) -> bool {
+        match self {
+            Variable::Local(_, mutable) | Variable::Global(_, mutable) => mutable,
+        }
+    }
+}
+
+/// The function being compiled.
+#[derive(Default)]
+struct Builder {
+    function: Function,
+    /// Block scopes, innermost last. The top-level statements of the script
+    /// start with none, so their `let`s define globals.
+    scopes: Vec<Vec<Local>>,
+    loops: Vec<Loop>,
+    cleanups: Vec<Cleanup>,
+    script: bool,
+}
+
+struct Compiler<'a> {
+    module: Module,
+    functions: HashMap<&'a str, (FunctionId, &'a FunctionDeclaration)>,
+    structs: HashMap<&'a str, (u32, &'a StructDeclaration)>,
+    /// Slot and mutability of each name a top-level `let` defines.
+    globals: HashMap<&'a str, (u32, bool)>,
+    names: HashMap<String, u32>,
+    variants: HashMap<(String, String), u32>,
+    /// The enum variants constructor expressions build.
+    constructors: &'a Constructors,
+    builder: Builder,
+}
+
+impl<'a> Compiler<'a> {
+    fn new(program: &'a Program, constructors: &'a Constructors) -> Result<Self, CylError> {
+        let mut compiler = Compiler {
+            module: Module {
+                functions: Vec::new(),
+                constants: Vec::new(),
+                names: Vec::new(),
+                structs: Vec::new(),
+                variants: Vec::new(),
+                patterns: Vec::new(),
+                types: Vec::new(),
+                globals: Vec::new(),
+                enums: HashMap::new(),
+            },
+            functions: HashMap::new(),
+            structs: HashMap::new(),
+            globals: HashMap::new(),
+            names: HashMap::new(),
+            variants: HashMap::new(),
+            constructors,
+            builder: Builder::default(),
+        };
+        // Function 0 is the script
+        let mut next_function = 1;
+        for stmt in &program.statements {
+            match &stmt.kind {
+                StatementKind::Function(decl) => {
+                    if decl.is_async {
+                        return Err(unsupported("Async functions are", stmt.span));
+                    }
+                    compiler.functions.insert(&decl.name, (next_function, decl));
+                    next_function += 1;
+                }
+                StatementKind::Struct(decl) => {
+                    let fields = decl.fields.iter().map(|f| f.name.clone()).collect();
+                    let index = compiler.module.structs.len() as u32;
+                    compiler.module.structs.push((decl.name.clone(), fields));
+                    compiler.structs.insert(&decl.name, (index, decl));
+                }
+                StatementKind::Enum(decl) => {
+                    let variants = decl.variants.iter().map(|v| v.name.clone()).collect();
+                    compiler.module.enums.insert(decl.name.clone(), variants);
+                }
+                StatementKind::Declare(decl) => {
+                    match compiler.globals.get(decl.name.as_str()) {
+                        Some(&(_, mutable)) if mutable != decl.is_mutable => {
+                            return Err(unsupported(
+                                "Redeclaring a global with a different mutability is",
+                                stmt.span,
+                            ));
+                        }
+                        Some(_) => {}
+                        None => {
+                            let slot = compiler.module.globals.len() as u32;
+                            compiler.globals.insert(&decl.name, (slot, decl.is_mutable));
+                            compiler.module.globals.push(decl.name.clone());
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(compiler)
+    }
+
+    /// Function 0: the top-level statements, then a call to `main`.
+    fn script(&mut self, program: &'a Program) -> Result<Function, CylError> {
+        self.builder = Builder {
+            function: Function {
+                name: "<script>".to_string(),
+                ..Function::default()
+            },
+            script: true,
+            ..Builder::default()
+        };
+        for stmt in &program.statements {
+            self.statement(stmt)?;
+        }
+        if let Some(&(main, decl)) = self.functions.get("main") {
+            self.check_arguments(decl, 0, decl.body.span)?;
+            self.emit(Op::Call { function: main, arguments: 0 }, decl.body.span);
+            self.emit(Op::Pop, decl.body.span);
+        }
+        self.emit(Op::Void, Span::default());
+        self.emit(Op::Return, Span::default());
+        Ok(std::mem::take(&mut self.builder).function)
+    }
+
+    fn function(&mut self, decl: &'a FunctionDeclaration, span: Span) -> Result<Function, CylError> {
+        self.builder = Builder {
+            function: Function {
+                name: decl.name.clone(),
+                parameters: decl.parameters.len() as u32,
+                ..Function::default()
+            },
+            scopes: vec![Vec::new()],
+            ..Builder::default()
+        };
+        for param in &decl.parameters {
+            self.declare(&param.name, param.is_mutable);
+        }
+        // Defaults may refer to the parameters before them
+        for (index, param) in decl.parameters.iter().enumerate() {
+            let Some(default) = &param.default_value else {
+                continue;
+            };
+            let skip = self.emit(
+                Op::JumpIfArgument {
+                    parameter: index as u32,
+                    target: 0,
+                },
+                param.span,
+            );
+            self.expression(default)?;
+            self.emit(Op::SetLocal(index as u32), param.span);
+            self.patch(skip);
+        }
+        self.block(&decl.body)?;
+        self.emit(Op::Void, span);
+        self.emit(Op::Return, span);
+        Ok(std::mem::take(&mut self.builder).function)
+    }
+
+    fn block(&mut self, block: &'a BlockStatement) -> Result<(), CylError> {
+        self.builder.scopes.push(Vec::new());
+        for stmt in &block.statements {
+            self.statement(stmt)?;
+        }
+        self.builder.scopes.pop();
+        Ok(())
+    }
+
+    fn statement(&mut self, stmt: &'a Statement) -> Result<(), CylError> {
+        let span = stmt.span;
+        match &stmt.kind {
+            StatementKind::Declare(decl) => {
+                self.expression(&decl.value)?;
+                if self.builder.scopes.is_empty() {
+                    let (slot, _) = self.globals[decl.name.as_str()];
+                    self.emit(Op::SetGlobal(slot), span);
+                } else {
+                    let slot = self.declare(&decl.name, decl.is_mutable);
+                    self.emit(Op::SetLocal(slot), span);
+                }
+            }
+            StatementKind::Expression(expr) => {
+                self.expression(expr)?;
+                self.emit(Op::Pop, span);
+            }
+            StatementKind::Return(ret) => {
+                if self.builder.script {
+                    return Err(unsupported("`return` outside a function is", span));
+                }
+                match &ret.value {
+                    Some(value) => self.expression(value)?,
+                    None => {
+                        self.emit(Op::Void, span);
+                    }
+                }
+                self.emit(Op::Return, span);
+            }
+            StatementKind::If(if_stmt) => {
+                self.expression(&if_stmt.condition)?;
+                let skip = self.emit(Op::JumpIfFalse(0), span);
+                self.block(&if_stmt.then_block)?;
+                match &if_stmt.else_block {
+                    Some(else_block) => {
+                        let end = self.emit(Op::Jump(0), span);
+                        self.patch(skip);
+                        self.statement(else_block)?;
+                        self.patch(end);
+                    }
+                    None => self.patch(skip),
+                }
+            }
+            StatementKind::While(while_stmt) => {
+                let start = self.here();
+                self.expression(&while_stmt.condition)?;
+                let exit = self.emit(Op::JumpIfFalse(0), span);
+                self.loop_body(&while_stmt.label, start, &while_stmt.body)?;
+                self.emit(Op::Jump(start), span);
+                self.patch(exit);
+                self.end_loop();
+            }
+            StatementKind::For(for_stmt) => {
+                match &for_stmt.iterable.kind {
+                    // Loop over ranges without building their array
+                    ExpressionKind::Range { start, end, inclusive } => {
+                        self.expression(start)?;
+                        self.expression(end)?;
+                        self.emit(Op::IterRange { inclusive: *inclusive }, for_stmt.iterable.span);
+                    }
+                    _ => {
+                        self.expression(&for_stmt.iterable)?;
+                        self.emit(Op::IterStart, for_stmt.iterable.span);
+                    }
+                }
+                self.builder.cleanups.push(Cleanup::Iterator);
+                let start = self.here();
+                let exit = self.emit(Op::IterNext(0), span);
+                // The loop variables live in a scope around the body's own
+                self.builder.scopes.push(Vec::new());
+                let pattern = &for_stmt.pattern;
+                match &pattern.kind {
+                    PatternKind::Identifier(name) => {
+                        let slot = self.declare(name, false);
+                        self.emit(Op::SetLocal(slot), pattern.span);
+                    }
+                    PatternKind::Wildcard => {
+                        self.emit(Op::Pop, pattern.span);
+                    }
+                    _ => {
+                        let index = self.pattern(pattern);
+                        self.emit(Op::Destructure(index), pattern.span);
+                    }
+                }
+                self.loop_body(&for_stmt.label, start, &for_stmt.body)?;
+                self.builder.scopes.pop();
+                self.emit(Op::Jump(start), span);
+                self.patch(exit);
+                self.end_loop();
+                self.builder.cleanups.pop();
+                self.emit(Op::IterEnd, span);
+            }
+            StatementKind::Match(m) => {
+                self.expression(&m.expression)?;
+                let scrutinee = self.temporary();
+                self.emit(Op::SetLocal(scrutinee), m.expression.span);
+                let mut ends = Vec::new();
+                for arm in &m.arms {
+                    self.builder.scopes.push(Vec::new());
+                    let pattern = self.pattern(&arm.pattern);
+                    self.emit(Op::GetLocal(scrutinee), arm.span);
+                    let test = self.emit(Op::TestPattern { pattern, fail: 0 }, arm.pattern.span);
+                    let guard = match &arm.guard {
+                        Some(guard) => {
+                            self.expression(guard)?;
+                            Some(self.emit(Op::Guard(0), guard.span))
+                        }
+                        None => None,
+                    };
+                    self.block(&arm.body)?;
+                    self.builder.scopes.pop();
+                    ends.push(self.emit(Op::Jump(0), arm.span));
+                    self.patch(test);
+                    if let Some(guard) = guard {
+                        self.patch(guard);
+                    }
+                }
+                self.emit(Op::NoMatch(scrutinee), m.expression.span);
+                for end in ends {
+                    self.patch(end);
+                }
+            }
+            StatementKind::Try(try_stmt) => {
+                let handler = self.emit(Op::PushHandler(0), span);
+                self.builder.cleanups.push(Cleanup::Handler);
+                self.block(&try_stmt.body)?;
+                self.builder.cleanups.pop();
+                self.emit(Op::PopHandler, span);
+                let mut ends = vec![self.emit(Op::Jump(0), span)];
+                // The handler jumps here with the thrown value on the stack
+                self.patch(handler);
+                for clause in &try_stmt.catch_clauses {
+                    let next = match &clause.exception_type {
+                        Some(ty) => {
+                            let index = self.module.types.len() as u32;
+                            self.module.types.push(ty.clone());
+                            self.emit(Op::Dup, clause.span);
+                            self.emit(Op::HasType(index), clause.span);
+                            Some(self.emit(Op::JumpIfFalse(0), clause.span))
+                        }
+                        None => None,
+                    };
+                    self.builder.scopes.push(Vec::new());
+                    match &clause.variable {
+                        Some(variable) => {
+                            let slot = self.declare(variable, false);
+                            self.emit(Op::SetLocal(slot), clause.span);
+                        }
+                        None => {
+                            self.emit(Op::Pop, clause.span);
+                        }
+                    }
+                    self.block(&clause.body)?;
+                    self.builder.scopes.pop();
+                    ends.push(self.emit(Op::Jump(0), clause.span));
+                    if let Some(next) = next {
+                        self.patch(next);
+                    }
+                }
+                self.emit(Op::Rethrow, span);
+                for end in ends {
+                    self.patch(end);
+                }
+            }
+            StatementKind::Throw(throw) => {
+                self.expression(&throw.value)?;
+                self.emit(Op::Throw, span);
+            }
+            StatementKind::Break(label) => {
+                let index = self.target_loop(label, "break", span)?;
+                self.unwind_to(index, span);
+                let jump = self.emit(Op::Jump(0), span);
+                self.builder.loops[index].breaks.push(jump);
+            }
+            StatementKind::Continue(label) => {
+                let index = self.target_loop(label, "continue", span)?;
+                self.unwind_to(index, span);
+                let start = self.builder.loops[index].start;
+                self.emit(Op::Jump(start), span);
+            }
+            StatementKind::Block(block) => self.block(block)?,
+            StatementKind::Function(_) if !self.builder.script => {
+                return Err(unsupported("Nested functions are", span));
+            }
+            StatementKind::Function(_)
+            | StatementKind::Struct(_)
+            | StatementKind::Enum(_)
+            | StatementKind::Import(_) => {}
+        }
+        Ok(())
+    }
+
+    /// Compile the body of a loop whose `continue`s jump to `start`.
+    fn loop_body(
+        &mut self,
+        label: &Option<String>,
+        start: u32,
+        body: &'a BlockStatement,
+    ) -> Result<(), CylError> {
+        self.builder.loops.push(Loop {
+            label: label.clone(),
+            start,
+            breaks: Vec::new(),
+            cleanups: self.builder.cleanups.len(),
+        });
+        self.block(body)
+    }
+
+    /// Close the innermost loop, sending its `break`s here.
+    fn end_loop(&mut self) {
+        let finished = self.builder.loops.pop().expect("a loop is open");
+        for jump in finished.breaks {
+            self.patch(jump);
+        }
+    }
+
+    /// The loop a `break` or `continue` with `label` leaves.
+    fn target_loop(
+        &self,
+        label: &Option<String>,
+        keyword: &str,
+        span: Span,
+    ) -> Result<usize, CylError> {
+        let loops = &self.builder.loops;
+        let found = match label {
+            None => loops.len().checked_sub(1),
+            Some(label) => loops
+                .iter()
+                .rposition(|l| l.label.as_deref() == Some(label.as_str())),
+        };
+        found.ok_or_else(|| CylError::CodeGenError {
+            message: format!("`{keyword}` outside of a matching loop"),
+            span: Some(span),
+        })
+    }
+
+    /// Undo the iterations and `try`s entered inside loop `index`.
+    fn unwind_to(&mut self, index: usize, span: Span) {
+        let depth = self.builder.loops[index].cleanups;
+        let cleanups: Vec<Cleanup> = self.builder.cleanups[depth..].to_vec();
+        for cleanup in cleanups.into_iter().rev() {
+            match cleanup {
+                Cleanup::Iterator => self.emit(Op::IterEnd, span),
+                Cleanup::Handler => self.emit(Op::PopHandler, span),
+            };
+        }
+    }
+
+    fn expression(&mut self, expr: &'a Expression) -> Result<(), CylError> {
+        let span = expr.span;
+        match &expr.kind {
+            ExpressionKind::IntLiteral(i) => self.constant(Value::Int(*i), span),
+            ExpressionKind::FloatLiteral(f) => self.constant(Value::Float(*f), span),
+            ExpressionKind::StringLiteral(s) => self.constant(Value::String(s.clone()), span),
+            ExpressionKind::BoolLiteral(b) => self.constant(Value::Bool(*b), span),
+            ExpressionKind::CharLiteral(c) => self.constant(Value::String(c.to_string()), span),
+            ExpressionKind::Null => {
+                self.emit(Op::Void, span);
+            }
+            ExpressionKind::Identifier(name) => match self.resolve(name) {
+                Some(variable) => self.load(variable, span),
+                None if self.functions.contains_key(name.as_str()) => {
+                    return Err(unsupported(format!("Using function `{name}` as a value is"), span))
+                }
+                None => match self.constructors.get(expr) {
+                    Some((enum_name, variant)) => self.variant(enum_name.to_string(), variant, 0, span)?,
+                    None => {
+                        return Err(CylError::CodeGenError {
+                            message: format!("Undefined variable `{name}`"),
+                            span: Some(span),
+                        })
+                    }
+                },
+            },
+            ExpressionKind::BinaryOp { left, operator, right } => {
+                self.expression(left)?;
+                // `&&` and `||` only evaluate their right operand when needed
+                let short_circuit = match operator {
+                    BinaryOperator::And => Some(self.emit(Op::JumpIfBool { value: false, target: 0 }, span)),
+                    BinaryOperator::Or => Some(self.emit(Op::JumpIfBool { value: true, target: 0 }, span)),
+                    _ => None,
+                };
+                self.expression(right)?;
+                self.emit(Op::Binary(*operator), span);
+                if let Some(jump) = short_circuit {
+                    self.patch(jump);
+                }
+            }
+            ExpressionKind::UnaryOp { operator, operand } => {
+                self.expression(operand)?;
+                self.emit(Op::Unary(*operator), span);
+            }
+            ExpressionKind::Assignment { target, value } => {
+                self.expression(value)?;
+                self.assign(target)?;
+            }
+            ExpressionKind::Call { callee, arguments } => match self.constructors.get(expr) {
+                Some((enum_name, variant)) => {
+                    self.expressions(arguments)?;
+                    self.variant(enum_name.to_string(), variant, arguments.len() as u32, span)?;
+                }
+                None => self.call(callee, arguments, span)?,
+            },
+            ExpressionKind::MemberAccess { object, property } => {
+                if let Some((enum_name, variant)) = self.constructors.get(expr) {
+                    return self.variant(enum_name.to_string(), variant, 0, span);
+                }
+                self.expression(object)?;
+                let name = self.name(property);
+                self.emit(Op::GetField(name), span);
+            }
+            ExpressionKind::IndexAccess { object, index } => {
+                self.expression(object)?;
+                self.expression(index)?;
+                self.emit(Op::Index, index.span);
+            }
+            ExpressionKind::ArrayLiteral(items) => {
+                self.expressions(items)?;
+                self.emit(Op::MakeArray(items.len() as u32), span);
+            }
+            ExpressionKind::TupleLiteral(items) => {
+                self.expressions(items)?;
+                self.emit(Op::MakeTuple(items.len() as u32), span);
+            }
+            ExpressionKind::ObjectLiteral(map) => self.object_literal(map, span)?,
+            ExpressionKind::Range { start, end, inclusive } => {
+                self.expression(start)?;
+                self.expression(end)?;
+                self.emit(Op::MakeRange { inclusive: *inclusive }, span);
+            }
+            ExpressionKind::Propagate(inner) => {
+                if self.builder.script {
+                    return Err(unsupported("`?` outside a function is", span));
+                }
+                self.expression(inner)?;
+                self.emit(Op::Propagate, span);
+            }
+            ExpressionKind::Await(_) => return Err(unsupported("`await` is", span)),
+            ExpressionKind::Dynamic => return Err(unsupported("`dynamic` is", span)),
+        }
+        Ok(())
+    }
+
+    fn expressions(&mut self, exprs: &'a [Expression]) -> Result<(), CylError> {
+        for expr in exprs {
+            self.expression(expr)?;
+        }
+        Ok(())
+    }
+
+    fn call(
+        &mut self,
+        callee: &'a Expression,
+        arguments: &'a [Expression],
+        span: Span,
+    ) -> Result<(), CylError> {
+        let count = arguments.len() as u32;
+        match &callee.kind {
+            ExpressionKind::MemberAccess { object, property } => {
+                self.expression(object)?;
+                self.expressions(arguments)?;
+                let name = self.name(property);
+                self.emit(Op::CallMethod { name, arguments: count }, span);
+            }
+            ExpressionKind::Identifier(name) => {
+                self.expressions(arguments)?;
+                match (name.as_str(), self.functions.get(name.as_str())) {
+                    ("print" | "println" | "print_int", _) => {
+                        let name = self.name(name);
+                        self.emit(Op::Print { name, arguments: count }, span);
+                    }
+                    (_, Some(&(function, decl))) => {
+                        self.check_arguments(decl, arguments.len(), span)?;
+                        self.emit(Op::Call { function, arguments: count }, span);
+                    }
+                    (_, None) => {
+                        return Err(CylError::CodeGenError {
+                            message: format!("Unknown function `{name}`"),
+                            span: Some(span),
+                        })
+                    }
+                }
+            }
+            _ => return Err(unsupported("Calling anything but a named function is", callee.span)),
+        }
+        Ok(())
+    }
+
+    /// Calls with the wrong number of arguments are left to the interpreter,
+    /// which reports them when they run.
+    fn check_arguments(
+        &self,
+        decl: &FunctionDeclaration,
+        given: usize,
+        span: Span,
+    ) -> Result<(), CylError> {
+        let required = decl
+            .parameters
+            .iter()
+            .rposition(|p| p.default_value.is_none())
+            .map_or(0, |index| index + 1);
+        if given < required || given > decl.parameters.len() {
+            return Err(CylError::CodeGenError {
+                message: format!(
+                    "Function `{}` takes {} argument(s) but {given} were given",
+                    decl.name,
+                    decl.parameters.len()
+                ),
+                span: Some(span),
+            });
+        }
+        Ok(())
+    }
+
+    fn object_literal(
+        &mut self,
+        map: &'a HashMap<String, Expression>,
+        span: Span,
+    ) -> Result<(), CylError> {
+        let Some(ExpressionKind::StringLiteral(name)) = map.get("__struct_name__").map(|e| &e.kind)
+        else {
+            // Anonymous object literals keep their fields sorted by name
+            let mut names: Vec<&String> = map.keys().collect();
+            names.sort();
+            for name in &names {
+                self.expression(&map[*name])?;
+            }
+            let index = self.module.structs.len() as u32;
+            let fields = names.into_iter().cloned().collect();
+            self.module.structs.push(("<anon>".to_string(), fields));
+            self.emit(Op::MakeStruct(index), span);
+            return Ok(());
+        };
+        let Some(&(index, decl)) = self.structs.get(name.as_str()) else {
+            return Err(CylError::CodeGenError {
+                message: format!("Unknown struct `{name}`"),
+                span: Some(span),
+            });
+        };
+        let known = |field: &String| field == "__struct_name__" || decl.fields.iter().any(|f| &f.name == field);
+        if let Some(field) = map.keys().find(|field| !known(field)) {
+            return Err(CylError::CodeGenError {
+                message: format!("Struct `{name}` has no field `{field}`"),
+                span: Some(map[field].span),
+            });
+        }
+        for field in &decl.fields {
+            let Some(value) = map.get(&field.name) else {
+                return Err(CylError::CodeGenError {
+                    message: format!("Missing field `{}` in literal of struct `{name}`", field.name),
+                    span: Some(span),
+                });
+            };
+            self.expression(value)?;
+        }
+        self.emit(Op::MakeStruct(index), span);
+        Ok(())
+    }
+
+    /// Store the value on top of the stack in `target`, leaving it there as
+    /// the result of the assignment.
+    fn assign(&mut self, target: &'a Expression) -> Result<(), CylError> {
+        let mut path = Vec::new();
+        let mut root = target;
+        while let ExpressionKind::MemberAccess { object, property } = &root.kind {
+            path.push((property.as_str(), root.span));
+            root = object;
+        }
+        path.reverse();
+        let ExpressionKind::Identifier(name) = &root.kind else {
+            return Err(unsupported("Assigning to anything but variables and fields is", target.span));
+        };
+        let Some(variable) = self.resolve(name) else {
+            return Err(CylError::CodeGenError {
+                message: format!("Cannot assign to undefined variable `{name}`"),
+                span: Some(target.span),
+            });
+        };
+        if !variable.mutable() {
+            return Err(CylError::CodeGenError {
+                message: format!("Cannot assign to immutable variable `{name}`"),
+                span: Some(target.span),
+            });
+        }
+        let span = target.span;
+        self.emit(Op::Dup, span);
+        if let Some(((last, last_span), outer)) = path.split_last() {
+            // Rebuild the structs along the path around the new value
+            let value = self.temporary();
+            self.emit(Op::SetLocal(value), span);
+            self.load(variable, root.span);
+            let mut names = Vec::new();
+            for (field, field_span) in outer {
+                let name = self.name(field);
+                self.emit(Op::Dup, *field_span);
+                self.emit(Op::GetField(name), *field_span);
+                names.push((name, *field_span));
+            }
+            self.emit(Op::GetLocal(value), span);
+            let last = self.name(last);
+            self.emit(Op::SetField(last), *last_span);
+            for (name, field_span) in names.into_iter().rev() {
+                self.emit(Op::SetField(name), field_span);
+            }
+        }
+        match variable {
+            Variable::Local(slot, _) => self.emit(Op::SetLocal(slot), span),
+            Variable::Global(slot, _) => self.emit(Op::SetGlobal(slot), span),
+        };
+        Ok(())
+    }
+
+    /// Record a `match` or `for` pattern, declaring a local for each name it
+    /// binds. Names of enum variants match the variant rather than binding.
+    fn pattern(&mut self, pattern: &Pattern) -> u32 {
+        let mut names = Vec::new();
+        pattern_names(pattern, &mut names);
+        let mut bindings = Vec::new();
+        for name in names {
+            let is_variant = self.module.enums.values().any(|variants| variants.contains(&name));
+            if !is_variant && !bindings.iter().any(|(bound, _)| bound == &name) {
+                let slot = self.declare(&name, false);
+                bindings.push((name, slot));
+            }
+        }
+        let index = self.module.patterns.len() as u32;
+        self.module.patterns.push(PatternSlots {
+            pattern: pattern.clone(),
+            bindings,
+        });
+        index
+    }
+
+    fn variant(&mut self, enum_name: String, variant: &str, fields: u32, span: Span) -> Result<(), CylError> {
+        if !self.module.enums[&enum_name].iter().any(|v| v == variant) {
+            return Err(CylError::CodeGenError {
+                message: format!("Enum `{enum_name}` has no variant `{variant}`"),
+                span: Some(span),
+            });
+        }
+        let key = (enum_name, variant.to_string());
+        let next = self.module.variants.len() as u32;
+        let index = *self.variants.entry(key.clone()).or_insert(next);
+        if index == next {
+            self.module.variants.push(key);
+        }
+        self.emit(Op::MakeVariant { variant: index, fields }, span);
+        Ok(())
+    }
+
+    fn resolve(&self, name: &str) -> Option<Variable> {
+        let local = self
+            .builder
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.iter().rev().find(|local| local.name == name));
+        match local {
+            Some(local) => Some(Variable::Local(local.slot, local.mutable)),
+            None => self
+                .globals
+                .get(name)
+                .map(|&(slot, mutable)| Variable::Global(slot, mutable)),
+        }
+    }
+
+    fn load(&mut self, variable: Variable, span: Span) {
+        match variable {
+            Variable::Local(slot, _) => self.emit(Op::GetLocal(slot), span),
+            Variable::Global(slot, _) => self.emit(Op::GetGlobal(slot), span),
+        };
+    }
+
+    /// A new local slot for `name` in the innermost scope.
+    fn declare(&mut self, name: &str, mutable: bool) -> u32 {
+        let slot = self.temporary();
+        let scope = self.builder.scopes.last_mut().expect("locals live in a scope");
+        scope.push(Local {
+            name: name.to_string(),
+            slot,
+            mutable,
+        });
+        slot
+    }
+
+    /// A new local slot no name refers to.
+    fn temporary(&mut self) -> u32 {
+        let function = &mut self.builder.function;
+        function.locals += 1;
+        function.locals - 1
+    }
+
+    fn constant(&mut self, value: Value, span: Span) {
+        let index = self.module.constants.len() as u32;
+        self.module.constants.push(value);
+        self.emit(Op::Constant(index), span);
+    }
+
+    fn name(&mut self, name: &str) -> u32 {
+        if let Some(&index) = self.names.get(name) {
+            return index;
+        }
+        let index = self.module.names.len() as u32;
+        self.module.names.push(name.to_string());
+        self.names.insert(name.to_string(), index);
+        index
+    }
+
+    fn emit(&mut self, op: Op, span: Span) -> usize {
+        let function = &mut self.builder.function;
+        function.code.push(op);
+        function.spans.push(span);
+        function.code.len() - 1
+    }
+
+    fn here(&self) -> u32 {
+        self.builder.function.code.len() as u32
+    }
+
+    /// Point the jump at `at` to the next instruction.
+    fn patch(&mut self, at: usize) {
+        let here = self.here();
+        match &mut self.builder.function.code[at] {
+            Op::Jump(target)
+            | Op::JumpIfFalse(target)
+            | Op::JumpIfBool { target, .. }
+            | Op::JumpIfArgument { target, .. }
+            | Op::Guard(target)
+            | Op::IterNext(target)
+            | Op::TestPattern { fail: target, .. }
+            | Op::PushHandler(target) => *target = here,
+            op => unreachable!("{op:?} does not jump"),
+        }
+    }
+}
+
+fn unsupported(what: impl std::fmt::Display, span: Span) -> CylError {
+    CylError::CodeGenError {
+        message: format!("{what} not supported by the VM yet"),
+        span: Some(span),
+    }
+}
+
+/// The names `pattern` may bind, in source order.
+fn pattern_names(pattern: &Pattern, names: &mut Vec<String>) {
+    match &pattern.kind {
+        PatternKind::Identifier(name) => names.push(name.clone()),
+        PatternKind::Tuple(items)
+        | PatternKind::TupleOrEnum(_, items)
+        | PatternKind::Enum { fields: items, .. } => {
+            for item in items {
+                pattern_names(item, names);
+            }
+        }
+        PatternKind::Struct { fields, .. } => {
+            for (_, item) in fields {
+                pattern_names(item, names);
+            }
+        }
+        PatternKind::Literal(_) | PatternKind::Wildcard => {}
+    }
+}
@@ -0,0 +1,502 @@
+use super::bytecode::{Function, FunctionId, Module, Op, PatternSlots};
+use super::compiler::compile;
+use crate::ast::Span;
+use crate::error::{CylError, StackFrame};
+use crate::interpreter::ops::{binary_op, call_method, int_range, iterate, unary_op};
+use crate::interpreter::{
+    match_pattern, printed_lines, value_to_string, Value, DEFAULT_MAX_CALL_DEPTH,
+};
+use crate::typeck::TypedProgram;
+
+/// Runs compiled [`Module`]s.
+///
+/// Calls do not recurse on the Rust stack, so unlike the tree-walking
+/// interpreter the VM needs no large host stack for deep programs; the call
+/// depth limit still applies so both fail the same way.
+pub struct Vm {
+    pub max_call_depth: usize,
+    pub output_buffer: Vec<String>, // Captures printed output for tests
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        Vm {
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            output_buffer: Vec::new(),
+        }
+    }
+
+    /// Compile and run `typed`.
+    pub fn run(&mut self, typed: &TypedProgram) -> Result<(), CylError> {
+        let module = compile(typed)?;
+        self.execute(&module)
+    }
+
+    /// Run the top-level statements of `module` and then its `main`.
+    pub fn execute(&mut self, module: &Module) -> Result<(), CylError> {
+        let mut machine = Machine {
+            module,
+            max_call_depth: self.max_call_depth,
+            output: &mut self.output_buffer,
+            stack: Vec::new(),
+            locals: Vec::new(),
+            globals: vec![None; module.globals.len()],
+            frames: Vec::new(),
+            iterators: Vec::new(),
+            handlers: Vec::new(),
+            uncaught: None,
+        };
+        machine.run()
+    }
+}
+
+impl Default for Vm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An active call.
+struct Frame {
+    function: FunctionId,
+    /// Where the function resumes once its callee returns.
+    pc: usize,
+    /// Index of local slot 0 in `Machine::locals`.
+    base: usize,
+    arguments: u32,
+    call_span: Span,
+    /// Heights of the operand and iterator stacks below the call.
+    stack: usize,
+    iterators: usize,
+}
+
+/// An active `try`, with the state to restore when it catches a value.
+struct Handler {
+    frame: usize,
+    stack: usize,
+    iterators: usize,
+    target: u32,
+}
+
+struct Machine<'m> {
+    module: &'m Module,
+    max_call_depth: usize,
+    output: &'m mut Vec<String>,
+    stack: Vec<Value>,
+    /// Local slots of every active call, innermost last.
+    locals: Vec<Value>,
+    /// `None` until the global's `let` runs.
+    globals: Vec<Option<Value>>,
+    frames: Vec<Frame>,
+    /// Active `for` loops, innermost last.
+    iterators: Vec<Box<dyn Iterator<Item = Value>>>,
+    handlers: Vec<Handler>,
+    /// Error of the latest `throw`, raised if no handler catches its value.
+    uncaught: Option<CylError>,
+}
+
+impl<'m> Machine<'m> {
+    fn run(&mut self) -> Result<(), CylError> {
+        let module = self.module;
+        let script = &module.functions[0];
+        self.locals.resize(script.locals as usize, Value::Void);
+        self.frames.push(Frame {
+            function: 0,
+            pc: 0,
+            base: 0,
+            arguments: 0,
+            call_span: Span::default(),
+            stack: 0,
+            iterators: 0,
+        });
+        let mut function = script;
+        let mut pc = 0;
+        let mut base = 0;
+        loop {
+            let op = function.code[pc];
+            pc += 1;
+            match op {
+                Op::Constant(index) => self.stack.push(module.constants[index as usize].clone()),
+                Op::Void => self.stack.push(Value::Void),
+                Op::Pop => {
+                    self.pop();
+                }
+                Op::Dup => {
+                    let top = self.stack.last().expect("operand stack underflow").clone();
+                    self.stack.push(top);
+                }
+                Op::GetLocal(slot) => self.stack.push(self.locals[base + slot as usize].clone()),
+                Op::SetLocal(slot) => self.locals[base + slot as usize] = self.pop(),
+                Op::GetGlobal(slot) => match &self.globals[slot as usize] {
+                    Some(value) => self.stack.push(value.clone()),
+                    None => {
+                        let name = &module.globals[slot as usize];
+                        return Err(self.error(format!("Undefined variable `{name}`"), function.spans[pc - 1]));
+                    }
+                },
+                Op::SetGlobal(slot) => self.globals[slot as usize] = Some(self.pop()),
+                Op::Binary(operator) => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    let value = binary_op(&operator, left, right)
+                        .map_err(|message| self.error(message, function.spans[pc - 1]))?;
+                    self.stack.push(value);
+                }
+                Op::Unary(operator) => {
+                    let operand = self.pop();
+                    let value = unary_op(&operator, operand)
+                        .map_err(|message| self.error(message, function.spans[pc - 1]))?;
+                    self.stack.push(value);
+                }
+                Op::Jump(target) => pc = target as usize,
+                Op::JumpIfFalse(target) => {
+                    if !self.pop().is_truthy() {
+                        pc = target as usize;
+                    }
+                }
+                Op::JumpIfBool { value, target } => {
+                    if self.stack.last() == Some(&Value::Bool(value)) {
+                        pc = target as usize;
+                    }
+                }
+                Op::Guard(target) => match self.pop() {
+                    Value::Bool(true) => {}
+                    Value::Bool(false) => pc = target as usize,
+                    other => {
+                        return Err(self.error(
+                            format!("Match guard must be a bool, found `{}`", other.type_name()),
+                            function.spans[pc - 1],
+                        ))
+                    }
+                },
+                Op::JumpIfArgument { parameter, target } => {
+                    if self.frames.last().expect("a call is running").arguments > parameter {
+                        pc = target as usize;
+                    }
+                }
+                Op::Call { function: callee, arguments } => {
+                    let span = function.spans[pc - 1];
+                    let target = &module.functions[callee as usize];
+                    // The script's frame is not a call
+                    if self.frames.len() > self.max_call_depth {
+                        return Err(self.error(
+                            format!(
+                                "Maximum call depth of {} exceeded in call to `{}`",
+                                self.max_call_depth, target.name
+                            ),
+                            span,
+                        ));
+                    }
+                    self.frames.last_mut().expect("a call is running").pc = pc;
+                    let first = self.stack.len() - arguments as usize;
+                    base = self.locals.len();
+                    self.locals.extend(self.stack.drain(first..));
+                    self.locals.resize(base + target.locals as usize, Value::Void);
+                    self.frames.push(Frame {
+                        function: callee,
+                        pc: 0,
+                        base,
+                        arguments,
+                        call_span: span,
+                        stack: self.stack.len(),
+                        iterators: self.iterators.len(),
+                    });
+                    function = target;
+                    pc = 0;
+                }
+                Op::Print { name, arguments } => {
+                    let first = self.stack.len() - arguments as usize;
+                    let lines = printed_lines(&module.names[name as usize], &self.stack[first..]);
+                    self.stack.truncate(first);
+                    self.output.extend(lines);
+                    self.stack.push(Value::Void);
+                }
+                Op::CallMethod { name, arguments } => {
+                    let first = self.stack.len() - arguments as usize;
+                    let args: Vec<Value> = self.stack.drain(first..).collect();
+                    let receiver = self.pop();
+                    let value = call_method(receiver, &module.names[name as usize], &args)
+                        .map_err(|message| self.error(message, function.spans[pc - 1]))?;
+                    self.stack.push(value);
+                }
+                Op::Return => {
+                    let value = self.pop();
+                    if !self.leave(value) {
+                        return Ok(());
+                    }
+                    (function, pc, base) = self.resume();
+                }
+                Op::MakeArray(count) => {
+                    let items = self.pop_many(count);
+                    self.stack.push(Value::Array(items));
+                }
+                Op::MakeTuple(count) => {
+                    let items = self.pop_many(count);
+                    self.stack.push(Value::Tuple(items));
+                }
+                Op::MakeStruct(index) => {
+                    let (name, names) = &module.structs[index as usize];
+                    let values = self.pop_many(names.len() as u32);
+                    let fields = names.iter().cloned().zip(values).collect();
+                    self.stack.push(Value::Struct(name.clone(), fields));
+                }
+                Op::MakeVariant { variant, fields } => {
+                    let (enum_name, variant) = &module.variants[variant as usize];
+                    let values = self.pop_many(fields);
+                    self.stack.push(Value::Enum(enum_name.clone(), variant.clone(), values));
+                }
+                Op::Index => {
+                    let index = self.pop();
+                    let object = self.pop();
+                    let value = index_value(object, index)
+                        .map_err(|message| self.error(message, function.spans[pc - 1]))?;
+                    self.stack.push(value);
+                }
+                Op::GetField(name) => {
+                    let object = self.pop();
+                    let value = take_field(object, &module.names[name as usize])
+                        .map_err(|message| self.error(message, function.spans[pc - 1]))?;
+                    self.stack.push(value);
+                }
+                Op::SetField(name) => {
+                    let value = self.pop();
+                    let mut object = self.pop();
+                    let name = &module.names[name as usize];
+                    match &mut object {
+                        Value::Struct(type_name, fields) => match fields.iter_mut().find(|(field, _)| field == name) {
+                            Some((_, slot)) => *slot = value,
+                            None => {
+                                let message = format!("No field `{name}` on a value of type `{type_name}`");
+                                return Err(self.error(message, function.spans[pc - 1]));
+                            }
+                        },
+                        other => {
+                            let message = format!("Cannot access `{name}` on a value of type `{}`", other.type_name());
+                            return Err(self.error(message, function.spans[pc - 1]));
+                        }
+                    }
+                    self.stack.push(object);
+                }
+                Op::MakeRange { inclusive } => {
+                    let range = self.range(inclusive, function.spans[pc - 1])?;
+                    self.stack.push(Value::Array(range.map(Value::Int).collect()));
+                }
+                Op::IterStart => {
+                    let iterable = self.pop();
+                    let iterator = iterate(iterable)
+                        .map_err(|message| self.error(message, function.spans[pc - 1]))?;
+                    self.iterators.push(iterator);
+                }
+                Op::IterRange { inclusive } => {
+                    let range = self.range(inclusive, function.spans[pc - 1])?;
+                    self.iterators.push(Box::new(range.map(Value::Int)));
+                }
+                Op::IterNext(target) => {
+                    match self.iterators.last_mut().expect("a loop is running").next() {
+                        Some(value) => self.stack.push(value),
+                        None => pc = target as usize,
+                    }
+                }
+                Op::IterEnd => {
+                    self.iterators.pop();
+                }
+                Op::TestPattern { pattern, fail } => {
+                    let value = self.pop();
+                    if !self.bind(&module.patterns[pattern as usize], &value, base) {
+                        pc = fail as usize;
+                    }
+                }
+                Op::Destructure(pattern) => {
+                    let value = self.pop();
+                    if !self.bind(&module.patterns[pattern as usize], &value, base) {
+                        return Err(self.error(
+                            format!("Cannot destructure `{}` in the loop head", value_to_string(&value)),
+                            function.spans[pc - 1],
+                        ));
+                    }
+                }
+                Op::NoMatch(slot) => {
+                    let value = &self.locals[base + slot as usize];
+                    return Err(self.error(
+                        format!("Non-exhaustive match: no arm matches `{}`", value_to_string(value)),
+                        function.spans[pc - 1],
+                    ));
+                }
+                Op::Propagate => match self.pop() {
+                    Value::Enum(_, variant, mut fields)
+                        if (variant == "Ok" || variant == "Some") && fields.len() == 1 =>
+                    {
+                        self.stack.push(fields.remove(0));
+                    }
+                    value @ (Value::Enum(..) | Value::Void) => {
+                        if !self.leave(value) {
+                            return Ok(());
+                        }
+                        (function, pc, base) = self.resume();
+                    }
+                    // A non-null `T?` value
+                    value => self.stack.push(value),
+                },
+                Op::PushHandler(target) => self.handlers.push(Handler {
+                    frame: self.frames.len() - 1,
+                    stack: self.stack.len(),
+                    iterators: self.iterators.len(),
+                    target,
+                }),
+                Op::PopHandler => {
+                    self.handlers.pop();
+                }
+                Op::Throw => {
+                    let value = self.pop();
+                    let message = format!("Uncaught exception: {}", value_to_string(&value));
+                    self.uncaught = Some(self.error(message, function.spans[pc - 1]));
+                    self.unwind(value)?;
+                    (function, pc, base) = self.resume();
+                }
+                Op::Rethrow => {
+                    let value = self.pop();
+                    self.unwind(value)?;
+                    (function, pc, base) = self.resume();
+                }
+                Op::HasType(index) => {
+                    let top = self.stack.last().expect("operand stack underflow");
+                    let matches = top.has_type(&module.types[index as usize]);
+                    self.stack.push(Value::Bool(matches));
+                }
+            }
+        }
+    }
+
+    /// The function, instruction and locals base of the innermost call.
+    fn resume(&self) -> (&'m Function, usize, usize) {
+        let frame = self.frames.last().expect("a call is running");
+        (&self.module.functions[frame.function as usize], frame.pc, frame.base)
+    }
+
+    /// Return `value` from the innermost call, or report that the script
+    /// itself finished.
+    fn leave(&mut self, value: Value) -> bool {
+        let frame = self.frames.pop().expect("a call is running");
+        self.locals.truncate(frame.base);
+        self.stack.truncate(frame.stack);
+        self.iterators.truncate(frame.iterators);
+        let depth = self.frames.len();
+        while self.handlers.last().is_some_and(|handler| handler.frame >= depth) {
+            self.handlers.pop();
+        }
+        if self.frames.is_empty() {
+            return false;
+        }
+        self.stack.push(value);
+        true
+    }
+
+    /// Hand a thrown value to the innermost handler, or fail with the error
+    /// of its `throw`.
+    fn unwind(&mut self, value: Value) -> Result<(), CylError> {
+        let Some(handler) = self.handlers.pop() else {
+            return Err(self.uncaught.take().expect("a value is being thrown"));
+        };
+        self.frames.truncate(handler.frame + 1);
+        let frame = self.frames.last_mut().expect("a call is running");
+        frame.pc = handler.target as usize;
+        let locals = self.module.functions[frame.function as usize].locals;
+        self.locals.truncate(frame.base + locals as usize);
+        self.stack.truncate(handler.stack);
+        self.iterators.truncate(handler.iterators);
+        self.stack.push(value);
+        Ok(())
+    }
+
+    /// Match `value` against a pattern, storing its bindings in their slots.
+    fn bind(&mut self, slots: &PatternSlots, value: &Value, base: usize) -> bool {
+        let mut bindings = Vec::new();
+        if !match_pattern(&slots.pattern, value, &self.module.enums, &mut bindings) {
+            return false;
+        }
+        for (name, value) in bindings {
+            if let Some((_, slot)) = slots.bindings.iter().find(|(bound, _)| *bound == name) {
+                self.locals[base + *slot as usize] = value;
+            }
+        }
+        true
+    }
+
+    /// Pop the end and start of a range.
+    fn range(
+        &mut self,
+        inclusive: bool,
+        span: Span,
+    ) -> Result<std::ops::RangeInclusive<i64>, CylError> {
+        let end = self.pop();
+        let start = self.pop();
+        match (start, end) {
+            (Value::Int(start), Value::Int(end)) => Ok(int_range(start, end, inclusive)),
+            (Value::Int(_), other) | (other, _) => Err(self.error(
+                format!("Range bounds must be integers, found `{}`", other.type_name()),
+                span,
+            )),
+        }
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("operand stack underflow")
+    }
+
+    fn pop_many(&mut self, count: u32) -> Vec<Value> {
+        let first = self.stack.len() - count as usize;
+        self.stack.split_off(first)
+    }
+
+    /// A runtime error at `span`, with the current Cyl call stack.
+    fn error(&self, message: impl Into<String>, span: Span) -> CylError {
+        let mut stack = Vec::new();
+        let mut location = span;
+        for frame in self.frames.iter().skip(1).rev() {
+            stack.push(StackFrame {
+                function: self.module.functions[frame.function as usize].name.clone(),
+                span: location,
+            });
+            location = frame.call_span;
+        }
+        CylError::RuntimeError {
+            message: message.into(),
+            span: Some(span),
+            stack,
+        }
+    }
+}
+
+fn index_value(object: Value, index: Value) -> Result<Value, String> {
+    let kind = if matches!(object, Value::Tuple(_)) { "tuple" } else { "array" };
+    match (object, index) {
+        (Value::Array(mut elements) | Value::Tuple(mut elements), Value::Int(idx)) => {
+            match usize::try_from(idx).ok().filter(|i| *i < elements.len()) {
+                Some(i) => Ok(elements.swap_remove(i)),
+                None => Err(format!(
+                    "Index {idx} is out of bounds for an {kind} of length {}",
+                    elements.len()
+                )),
+            }
+        }
+        (Value::Array(_) | Value::Tuple(_), index) => Err(format!(
+            "Cannot index with a value of type `{}`",
+            index.type_name()
+        )),
+        (object, _) => Err(format!("Cannot index into a value of type `{}`", object.type_name())),
+    }
+}
+
+fn take_field(object: Value, name: &str) -> Result<Value, String> {
+    match object {
+        Value::Struct(type_name, fields) => fields
+            .into_iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value)
+            .ok_or_else(|| format!("No field `{name}` on a value of type `{type_name}`")),
+        other => Err(format!(
+            "Cannot access `{name}` on a value of type `{}`",
+            other.type_name()
+        )),
+    }
+}
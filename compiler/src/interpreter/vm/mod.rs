@@ -0,0 +1,18 @@
+//! Bytecode virtual machine.
+//!
+//! [`compile`] lowers a checked [`Program`](crate::ast::Program) to a
+//! [`Module`] of stack machine code, resolving variables to numbered slots and
+//! literals to a constant pool once, and [`Vm`] runs it in a single dispatch
+//! loop. It computes the same output and runtime errors as the tree-walking
+//! [`Interpreter`](super::Interpreter), which remains the reference: programs
+//! using something the compiler does not support yet, like `await` or function
+//! values, are rejected with a `CodeGenError` so callers can fall back to it.
+//! Python plugin hooks are not consulted.
+
+mod bytecode;
+mod compiler;
+mod machine;
+
+pub use bytecode::{Function, FunctionId, Module, Op, PatternSlots};
+pub use compiler::compile;
+pub use machine::Vm;
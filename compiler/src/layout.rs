@@ -0,0 +1,231 @@
+//! Memory layout of enum, struct, string and array values, shared by the
+//! native backends and the C runtime.
+//!
+//! An enum value is a tagged union: a 64-bit tag holding the index of its
+//! variant in the declaration, followed by the payload of the largest
+//! variant. Every payload field occupies one 8-byte slot whatever its type,
+//! so a field's offset only depends on its position:
+//!
+//! ```text
+//! enum Shape { Circle(float), Rect(float, float), Empty }
+//!
+//! offset   0      8        16
+//!        [ tag  | field 0 | field 1 ]   size 24
+//! ```
+//!
+//! A struct value is a pointer to a heap object holding its fields in
+//! declaration order, each at the next offset aligned to its size: one byte
+//! for a `bool`, eight for anything else, since nested structs, strings,
+//! arrays and enums are all pointers. The object is padded to the largest
+//! alignment of its fields:
+//!
+//! ```text
+//! struct Cell { alive: bool, age: int, marked: bool }
+//!
+//! offset   0       1         8     16       17
+//!        [ alive | padding | age | marked | padding ]   size 24, align 8
+//! ```
+//!
+//! Assigning to a field stores to the struct object in place. A struct read
+//! from a variable, a field or an array element is copied, along with the
+//! structs its fields hold, whenever it is bound or stored elsewhere, so
+//! every struct object has one owner and struct values behave like the
+//! interpreter's.
+//!
+//! A string is a pointer to an immutable string object: its length in bytes
+//! and then its UTF-8 bytes, with a NUL after them for the C library. String
+//! literals are objects in read-only data, and the runtime allocates the
+//! results of concatenation on the heap. Compiled code frees a string made
+//! for one operation, such as `a + b` in `a + b + c`, once it is used; the
+//! strings and arrays a program stores are never freed.
+//!
+//! ```text
+//! offset   0        8
+//!        [ length | bytes ... | 0 ]
+//! ```
+//!
+//! An array is a pointer to a heap object: its length, then one slot per
+//! element, sized like enum payload slots whatever the element type:
+//!
+//! ```text
+//! [1.5, 2.5, 4.0]
+//!
+//! offset   0        8           16          24
+//!        [ length | element 0 | element 1 | element 2 ]   size 32
+//! ```
+
+use crate::ast::{EnumDeclaration, StructDeclaration, Type};
+use crate::typeck::{substitute, type_arguments};
+
+/// Size of the tag and of each payload slot, in bytes.
+pub const SLOT_SIZE: u32 = 8;
+
+/// Byte offset of the length of a string object.
+pub const STRING_LENGTH_OFFSET: u32 = 0;
+
+/// Byte offset of the bytes of a string object.
+pub const STRING_BYTES_OFFSET: u32 = 8;
+
+/// Alignment of a string object, in bytes.
+pub const STRING_ALIGN: u32 = 8;
+
+/// Byte offset of the length of an array.
+pub const ARRAY_LENGTH_OFFSET: u32 = 0;
+
+/// Byte offset of the first element of an array.
+pub const ARRAY_ELEMENTS_OFFSET: u32 = 8;
+
+/// Byte offset of element `index` of an array.
+pub fn array_element_offset(index: usize) -> u32 {
+    ARRAY_ELEMENTS_OFFSET + SLOT_SIZE * index as u32
+}
+
+/// The string object holding `value`, in the byte order of the host.
+pub fn string_object(value: &str) -> Vec<u8> {
+    let mut object = Vec::with_capacity(STRING_BYTES_OFFSET as usize + value.len() + 1);
+    object.extend_from_slice(&(value.len() as i64).to_ne_bytes());
+    object.extend_from_slice(value.as_bytes());
+    object.push(0);
+    object
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantLayout {
+    pub name: String,
+    pub tag: i64,
+    pub fields: Vec<Type>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumLayout {
+    pub name: String,
+    pub type_parameters: Vec<String>,
+    pub variants: Vec<VariantLayout>,
+}
+
+impl EnumLayout {
+    pub fn new(decl: &EnumDeclaration) -> Self {
+        let variants = decl
+            .variants
+            .iter()
+            .enumerate()
+            .map(|(tag, variant)| VariantLayout {
+                name: variant.name.clone(),
+                tag: tag as i64,
+                fields: variant.fields.clone().unwrap_or_default(),
+            })
+            .collect();
+        EnumLayout {
+            name: decl.name.clone(),
+            type_parameters: decl.type_parameters.clone(),
+            variants,
+        }
+    }
+
+    pub fn variant(&self, name: &str) -> Option<&VariantLayout> {
+        self.variants.iter().find(|v| v.name == name)
+    }
+
+    /// Field types of `variant` in a value of type `ty`, with the enum's type
+    /// parameters replaced by the arguments of `ty`, as in `Option<int>`.
+    pub fn field_types(&self, variant: &VariantLayout, ty: &Type) -> Vec<Type> {
+        let args = match ty {
+            Type::Generic(_, args) => args.as_slice(),
+            _ => &[],
+        };
+        let subst = type_arguments(&self.type_parameters, args);
+        variant
+            .fields
+            .iter()
+            .map(|field| substitute(field, &subst))
+            .collect()
+    }
+
+    /// Payload slots needed by the largest variant.
+    pub fn payload_slots(&self) -> u32 {
+        self.variants
+            .iter()
+            .map(|v| v.fields.len() as u32)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Total size of a value in bytes.
+    pub fn size(&self) -> u32 {
+        SLOT_SIZE * (1 + self.payload_slots())
+    }
+
+    /// Byte offset of payload field `index`.
+    pub fn field_offset(index: usize) -> u32 {
+        SLOT_SIZE * (1 + index as u32)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldLayout {
+    pub name: String,
+    pub field_type: Type,
+    pub offset: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructLayout {
+    pub name: String,
+    pub type_parameters: Vec<String>,
+    pub fields: Vec<FieldLayout>,
+    /// Total size of a value in bytes, a multiple of `align`.
+    pub size: u32,
+    pub align: u32,
+}
+
+impl StructLayout {
+    pub fn new(decl: &StructDeclaration) -> Self {
+        let mut size = 0u32;
+        let mut align = 1;
+        let fields = decl
+            .fields
+            .iter()
+            .map(|field| {
+                let field_size = value_size(&field.field_type);
+                let offset = size.next_multiple_of(field_size);
+                size = offset + field_size;
+                align = align.max(field_size);
+                FieldLayout {
+                    name: field.name.clone(),
+                    field_type: field.field_type.clone(),
+                    offset,
+                }
+            })
+            .collect();
+        StructLayout {
+            name: decl.name.clone(),
+            type_parameters: decl.type_parameters.clone(),
+            fields,
+            size: size.next_multiple_of(align),
+            align,
+        }
+    }
+
+    pub fn field(&self, name: &str) -> Option<&FieldLayout> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+
+    /// Type of `field` in a value of type `ty`, with the struct's type
+    /// parameters replaced by the arguments of `ty`, as in `Pair<int>`.
+    pub fn field_type(&self, field: &FieldLayout, ty: &Type) -> Type {
+        let args = match ty {
+            Type::Generic(_, args) => args.as_slice(),
+            _ => &[],
+        };
+        substitute(&field.field_type, &type_arguments(&self.type_parameters, args))
+    }
+}
+
+/// Size, and alignment, of a struct field of type `ty` in bytes. A type
+/// parameter may stand for any type, so it takes a full slot.
+fn value_size(ty: &Type) -> u32 {
+    match ty {
+        Type::Bool => 1,
+        _ => SLOT_SIZE,
+    }
+}
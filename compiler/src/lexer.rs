@@ -82,9 +82,13 @@ pub enum Token {
     #[token("false", |_| false)]
     BoolLiteral(bool),
 
-    #[regex(r"'[^']*'", |lex| lex.slice().chars().nth(1))]
+    #[regex(r"'([^'\\]|\\.)'", |lex| lex.slice().chars().nth(1))]
     CharLiteral(char),
 
+    // Loop labels, as in `'outer: while` and `break 'outer;`
+    #[regex(r"'[a-zA-Z_][a-zA-Z0-9_]*", |lex| lex.slice()[1..].to_owned())]
+    Label(String),
+
     // Identifiers
     #[regex(r"[a-zA-Z_][a-zA-Z0-9_]*", |lex| lex.slice().to_owned())]
     Identifier(String),
@@ -130,6 +134,8 @@ pub enum Token {
     LeftShift,
     #[token(">>")]
     RightShift,
+    #[token("~")]
+    BitwiseNot,
 
     // Punctuation
     #[token("(")]
@@ -156,6 +162,10 @@ pub enum Token {
     Colon,
     #[token(".")]
     Dot,
+    #[token("..")]
+    DotDot,
+    #[token("..=")]
+    DotDotEqual,
     #[token("->")]
     Arrow,
     #[token("=>")]
@@ -180,6 +190,7 @@ pub struct Lexer<'source> {
     lexer: logos::Lexer<'source, Token>,
     current_line: usize,
     current_column: usize,
+    position: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -200,6 +211,7 @@ impl<'source> Lexer<'source> {
             lexer: Token::lexer(static_source),
             current_line: 1,
             current_column: 1,
+            position: 0,
         }
     }
 
@@ -208,19 +220,15 @@ impl<'source> Lexer<'source> {
 
         while let Some(result) = self.lexer.next() {
             let span = self.lexer.span();
+
+            // Skipped whitespace and comments are not tokens, so count
+            // everything between the previous token and the end of this one
+            let source = self.lexer.source();
+            self.advance_position(&source[self.position..span.start]);
             let token_line = self.current_line;
             let token_column = self.current_column;
-
-            // Update line and column tracking first
-            let slice = self.lexer.slice();
-            for ch in slice.chars() {
-                if ch == '\n' {
-                    self.current_line += 1;
-                    self.current_column = 1;
-                } else {
-                    self.current_column += 1;
-                }
-            }
+            self.advance_position(&source[span.start..span.end]);
+            self.position = span.end;
 
             match result {
                 Ok(token) => {
@@ -241,13 +249,26 @@ impl<'source> Lexer<'source> {
             }
         }
 
+        let source = self.lexer.source();
+        self.advance_position(&source[self.position..]);
         tokens.push(TokenWithLocation {
             token: Token::Eof,
             line: self.current_line,
             column: self.current_column,
-            span: self.lexer.span(),
+            span: source.len()..source.len(),
         });
 
         Ok(tokens)
     }
+
+    fn advance_position(&mut self, text: &str) {
+        for ch in text.chars() {
+            if ch == '\n' {
+                self.current_line += 1;
+                self.current_column = 1;
+            } else {
+                self.current_column += 1;
+            }
+        }
+    }
 }
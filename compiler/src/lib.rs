@@ -4,9 +4,17 @@ pub mod ast;
 pub mod codegen;
 #[cfg(feature = "cranelift")]
 pub mod cranelift_codegen;
+pub mod diagnostics;
 pub mod error;
+#[cfg(any(feature = "cranelift", feature = "llvm"))]
+pub mod exceptions;
 pub mod lexer;
 pub mod parser;
+pub mod prelude;
 pub mod stdlib;
+pub mod typeck;
 pub mod interpreter;
+pub mod layout;
 pub mod plugins;
+#[cfg(any(feature = "cranelift", feature = "llvm"))]
+pub mod runtime;
@@ -1,16 +1,20 @@
 use anyhow::Result;
-pub use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+pub use clap::{Parser, Subcommand, ValueEnum};
+use std::path::{Path, PathBuf};
 
-use cylc::ast::Program;
 #[cfg(feature = "llvm")]
 use cylc::codegen::LLVMCodegen;
 #[cfg(feature = "cranelift")]
 use cylc::cranelift_codegen::CraneliftCodegen;
-use cylc::error::CylError;
-use cylc::interpreter::Interpreter;
+#[cfg(any(feature = "cranelift", feature = "llvm"))]
+use cylc::runtime::LinkOptions;
+use cylc::diagnostics::{self, Diagnostic};
+use cylc::error::{CylError, Warning};
+use cylc::interpreter::vm::{self, Vm};
+use cylc::interpreter::{Interpreter, DEFAULT_MAX_CALL_DEPTH};
 use cylc::lexer::Lexer;
 use cylc::parser;
+use cylc::typeck::{CheckFailure, TypeChecker, TypedProgram};
 #[cfg(feature = "llvm")]
 use inkwell::context::Context;
 // If you need plugins, import like:
@@ -25,6 +29,24 @@ struct Cli {
     command: Commands,
 }
 
+/// How diagnostics are printed.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum MessageFormat {
+    /// Labelled source snippets for people
+    Human,
+    /// One JSON object per diagnostic per line, for tools
+    Json,
+}
+
+/// What `cylc build` writes.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Emit {
+    /// An object file, to link yourself
+    Obj,
+    /// A linked executable
+    Exe,
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Compile and run a Cyl program
@@ -37,12 +59,22 @@ enum Commands {
         /// Enable debug information
         #[arg(short, long)]
         debug: bool,
-        /// Backend to use: interpreter, cranelift, llvm
+        /// Backend to use: interpreter, vm, cranelift, llvm
         #[arg(long, default_value = "cranelift")]
         backend: String,
         /// Suppress output messages
         #[arg(short, long)]
         quiet: bool,
+        /// Diagnostic output format (human, json)
+        #[arg(long, value_enum, default_value_t = MessageFormat::Human)]
+        message_format: MessageFormat,
+        /// Maximum nesting of function calls in the interpreter and VM
+        #[arg(long, default_value_t = DEFAULT_MAX_CALL_DEPTH)]
+        max_call_depth: usize,
+        /// Run the program with the interpreter, with a warning, when the
+        /// selected backend cannot compile it
+        #[arg(long)]
+        fallback: bool,
     },
     /// Compile a Cyl program to executable
     Build {
@@ -60,11 +92,29 @@ enum Commands {
         /// Backend to use: cranelift, llvm, interpreter
         #[arg(long, default_value = "cranelift")]
         backend: String,
+        /// Output kind (obj, exe)
+        #[arg(long, value_enum, default_value_t = Emit::Exe)]
+        emit: Emit,
+        /// Link the C library statically
+        #[arg(long = "static")]
+        static_linking: bool,
+        /// Link with a library (repeatable)
+        #[arg(short = 'l', value_name = "LIB")]
+        libraries: Vec<String>,
+        /// Add a directory to the library search path (repeatable)
+        #[arg(short = 'L', value_name = "DIR")]
+        library_paths: Vec<PathBuf>,
+        /// Diagnostic output format (human, json)
+        #[arg(long, value_enum, default_value_t = MessageFormat::Human)]
+        message_format: MessageFormat,
     },
     /// Check syntax without compiling
     Check {
         /// Input file to check
         file: PathBuf,
+        /// Diagnostic output format (human, json)
+        #[arg(long, value_enum, default_value_t = MessageFormat::Human)]
+        message_format: MessageFormat,
     },
     /// Show AST for a Cyl program
     Ast {
@@ -74,6 +124,11 @@ enum Commands {
         #[arg(short, long, default_value = "pretty")]
         format: String,
     },
+    /// Explain a diagnostic code, e.g. `cylc explain E0003`
+    Explain {
+        /// Diagnostic code to explain
+        code: String,
+    },
     /// Run automated tests
     Test {
         /// Test pattern to filter tests (optional)
@@ -91,87 +146,218 @@ enum Commands {
 fn main() -> Result<()> {
     pyo3::prepare_freethreaded_python();
     let cli = Cli::parse();
-    // Restore 'run' command logic up to file reading, fix match arms
+
     match cli.command {
-        Commands::Run { ref file, backend, quiet, .. } => {
-            // Read source file
-            let source = match std::fs::read_to_string(file) {
-                Ok(s) => s,
-                Err(e) => {
-                    eprintln!("Failed to read file {}: {}", file.display(), e);
-                    std::process::exit(1);
-                }
+        Commands::Run {
+            file,
+            opt_level,
+            debug,
+            backend,
+            quiet,
+            message_format,
+            max_call_depth,
+            fallback,
+        } => compile_and_run(
+            &file,
+            opt_level,
+            debug,
+            &backend,
+            quiet,
+            message_format,
+            Execution {
+                fallback,
+                max_call_depth,
+            },
+        ),
+        Commands::Build {
+            file,
+            output,
+            opt_level,
+            debug,
+            backend,
+            emit,
+            static_linking,
+            libraries,
+            library_paths,
+            message_format,
+        } => {
+            let link = BuildLink {
+                emit,
+                static_linking,
+                libraries,
+                library_paths,
             };
+            compile_to_executable(&file, output, opt_level, debug, &backend, link, message_format)
+        }
+        Commands::Check {
+            file,
+            message_format,
+        } => check_syntax(&file, message_format),
+        Commands::Ast { file, format } => show_ast(&file, &format),
+        Commands::Explain { code } => explain_code(&code),
+        Commands::Test {
+            pattern,
+            verbose,
+            continue_on_failure,
+        } => run_tests(pattern, verbose, continue_on_failure),
+    }
+}
 
-            // Lex and parse
-            let mut lexer = Lexer::new(&source);
-            let tokens = match lexer.tokenize() {
-                Ok(t) => t,
-                Err(e) => {
-                    print_error_with_context(&e, &source);
-                    std::process::exit(1);
-                }
-            };
-            let mut parser = parser::helpers::Parser::new(tokens);
-            let program = match parser.parse() {
-                Ok(p) => p,
-                Err(e) => {
-                    print_error_with_context(&e, &source);
-                    std::process::exit(1);
-                }
-            };
+/// Print every error in `errors` as a diagnostic against `source` to stderr.
+/// Human output ends with a pointer to `cylc explain`; JSON output is one
+/// object per line.
+fn report_errors(file: &Path, source: &str, errors: &[CylError], format: MessageFormat) {
+    let diagnostics: Vec<_> = errors
+        .iter()
+        .map(|error| Diagnostic::from_error(error, source))
+        .collect();
+    emit_diagnostics(file, source, &diagnostics, format);
+    if format == MessageFormat::Json {
+        return;
+    }
+    if let Some(error) = errors.first() {
+        eprintln!(
+            "For more information about an error, try `cylc explain {}`.",
+            error.code()
+        );
+    }
+}
 
-            // Only run interpreter backend for now
-            let mut interpreter = Interpreter::new();
-            eprintln!("[debug] About to call interpreter.run");
-            if let Err(e) = interpreter.run(&program) {
-                eprintln!("Interpreter error: {e}");
-                std::process::exit(1);
-            }
-            eprintln!("[debug] interpreter.run returned");
-            // Print captured output buffer to stdout, one line at a time
-            for line in &interpreter.output_buffer {
-                println!("{}", line);
+/// Print compiler warnings as diagnostics against `source` to stderr.
+fn report_warnings(file: &Path, source: &str, warnings: &[Warning], format: MessageFormat) {
+    let diagnostics: Vec<_> = warnings.iter().map(Diagnostic::from_warning).collect();
+    emit_diagnostics(file, source, &diagnostics, format);
+}
+
+fn emit_diagnostics(file: &Path, source: &str, diagnostics: &[Diagnostic], format: MessageFormat) {
+    let file_name = file.display().to_string();
+    for diagnostic in diagnostics {
+        match format {
+            MessageFormat::Human => diagnostic.emit(&file_name, source),
+            MessageFormat::Json => {
+                let json = diagnostic.to_json(Some(&file_name));
+                match serde_json::to_string(&json) {
+                    Ok(line) => eprintln!("{line}"),
+                    Err(e) => eprintln!("{e}"),
+                }
             }
         }
-        Commands::Check { ref file, .. } => {
-            println!("Check command selected: {:?}", file);
-        }
-        Commands::Build { .. } => {
-            println!("Build command selected");
-        }
-        Commands::Ast { .. } => {
-            println!("Ast command selected");
+    }
+}
+
+/// Lex, parse and type check `source`.
+fn parse_and_check(source: &str) -> Result<TypedProgram, CheckFailure> {
+    let mut lexer = Lexer::new(source);
+    let tokens = lexer.tokenize().map_err(|e| vec![e])?;
+    let mut parser = parser::helpers::Parser::new(tokens);
+    let program = parser.parse()?;
+    TypeChecker::new().check_program(program)
+}
+
+/// Read and check `file`, printing any warnings, and every error before
+/// exiting on failure. Returns the source alongside the program for later diagnostics.
+fn load_program(file: &PathBuf, format: MessageFormat) -> Result<(String, TypedProgram)> {
+    let source = std::fs::read_to_string(file)?;
+    match parse_and_check(&source) {
+        Ok(program) => {
+            report_warnings(file, &source, &program.warnings, format);
+            Ok((source, program))
         }
-        Commands::Test { .. } => {
-            println!("Test command selected");
+        Err(failure) => {
+            report_warnings(file, &source, &failure.warnings, format);
+            report_errors(file, &source, &failure.errors, format);
+            std::process::exit(1);
         }
     }
-    Ok(())
 }
 
-fn print_error_with_context(error: &CylError, source: &str) {
-    match error {
-        CylError::LexError { message, line, column }
-        | CylError::ParseError { message, line, column } => {
-            eprintln!("[error] {message} at line {line}, column {column}");
-            if let Some(src_line) = source.lines().nth(line.saturating_sub(1)) {
-                eprintln!("   {src_line}");
-                let mut caret = String::new();
-                for _ in 1..*column {
-                    caret.push(' ');
-                }
-                caret.push('^');
-                eprintln!("   {caret}");
-            }
-        }
-        _ => {
-            eprintln!("[error] {error}");
+/// Report `error` against `file` and exit with a failure status.
+fn fail(file: &Path, source: &str, error: CylError, format: MessageFormat) -> ! {
+    report_errors(file, source, &[error], format);
+    std::process::exit(1);
+}
+
+/// Stack for the interpreter thread: deep enough for `--max-call-depth`
+/// nested calls even in unoptimized builds, where frames are large.
+const INTERPRETER_STACK_SIZE: usize = 256 * 1024 * 1024;
+
+fn run_interpreter(
+    program: &TypedProgram,
+    file: &Path,
+    source: &str,
+    format: MessageFormat,
+    max_call_depth: usize,
+) {
+    let (output, result) = interpret(program, max_call_depth);
+    // Print captured output buffer to stdout, one line at a time
+    for line in &output {
+        println!("{line}");
+    }
+    if let Err(error) = result {
+        fail(file, source, error, format);
+    }
+}
+
+/// Run `program` on the interpreter, on a thread of its own, returning the
+/// lines it printed and how it finished.
+fn interpret(program: &TypedProgram, max_call_depth: usize) -> (Vec<String>, Result<(), CylError>) {
+    std::thread::scope(|scope| {
+        std::thread::Builder::new()
+            .name("interpreter".to_string())
+            .stack_size(INTERPRETER_STACK_SIZE)
+            .spawn_scoped(scope, || {
+                let mut interpreter = Interpreter::new();
+                interpreter.max_call_depth = max_call_depth;
+                let result = interpreter.run(program);
+                (interpreter.output_buffer, result)
+            })
+            .expect("failed to spawn interpreter thread")
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    })
+}
+
+/// Run `program` on the bytecode VM, or on the interpreter if it uses
+/// something the VM does not support yet.
+fn run_vm(program: &TypedProgram, file: &Path, source: &str, format: MessageFormat, execution: &Execution) {
+    let module = match vm::compile(program) {
+        Ok(module) => module,
+        Err(e) if execution.fallback => {
+            eprintln!("warning: VM compilation failed ({e}), falling back to interpreter...");
+            return run_interpreter(program, file, source, format, execution.max_call_depth);
         }
+        Err(e) => fail(file, source, e, format),
+    };
+    let mut machine = Vm::new();
+    machine.max_call_depth = execution.max_call_depth;
+    let result = machine.execute(&module);
+    for line in &machine.output_buffer {
+        println!("{line}");
+    }
+    if let Err(error) = result {
+        fail(file, source, error, format);
     }
 }
 
-fn compile_and_run(file: &PathBuf, _opt_level: u8, _debug: bool, backend: &str, quiet: bool) -> Result<()> {
+/// How `cylc run` runs a program that type checks.
+struct Execution {
+    /// Run the program with the interpreter when the backend cannot.
+    fallback: bool,
+    max_call_depth: usize,
+}
+
+fn compile_and_run(
+    file: &PathBuf,
+    _opt_level: u8,
+    _debug: bool,
+    backend: &str,
+    quiet: bool,
+    format: MessageFormat,
+    execution: Execution,
+) -> Result<()> {
+    let max_call_depth = execution.max_call_depth;
+    // Keep stdout to the program's own output when tools read diagnostics
+    let quiet = quiet || format == MessageFormat::Json;
     if !quiet {
         println!(
             "Compiling and running: {} (Backend: {})",
@@ -180,26 +366,7 @@ fn compile_and_run(file: &PathBuf, _opt_level: u8, _debug: bool, backend: &str,
         );
     }
 
-    // Read source file
-    let source = std::fs::read_to_string(file)?;
-
-    // Lex and parse
-    let mut lexer = Lexer::new(&source);
-    let tokens = match lexer.tokenize() {
-        Ok(t) => t,
-        Err(e) => {
-            print_error_with_context(&e, &source);
-            std::process::exit(1);
-        }
-    };
-    let mut parser = parser::helpers::Parser::new(tokens);
-    let program = match parser.parse() {
-        Ok(p) => p,
-        Err(e) => {
-            print_error_with_context(&e, &source);
-            std::process::exit(1);
-        }
-    };
+    let (source, program) = load_program(file, format)?;
 
     match backend {
         "llvm" => {
@@ -217,20 +384,15 @@ fn compile_and_run(file: &PathBuf, _opt_level: u8, _debug: bool, backend: &str,
 
                 match llvm_codegen.compile_program(&program) {
                     Ok(()) => {
-                        println!("Successfully compiled with LLVM!");
-                        llvm_codegen.print_ir();
-                        
-                        // For now, also run with interpreter to get output
-                        let mut interpreter = Interpreter::new();
-                        if let Err(e) = interpreter.run(&program) {
-                            eprintln!("Interpreter error: {e}");
-                            std::process::exit(1);
+                        if !quiet {
+                            println!("Successfully compiled with LLVM!");
+                            llvm_codegen.print_ir();
                         }
+
+                        // For now, also run with interpreter to get output
+                        run_interpreter(&program, file, &source, format, max_call_depth);
                     }
-                    Err(e) => {
-                        eprintln!("LLVM compilation error: {e}");
-                        std::process::exit(1);
-                    }
+                    Err(e) => fail(file, &source, e, format),
                 }
             }
             #[cfg(not(feature = "llvm"))]
@@ -242,8 +404,8 @@ fn compile_and_run(file: &PathBuf, _opt_level: u8, _debug: bool, backend: &str,
         "cranelift" => {
             #[cfg(feature = "cranelift")]
             {
-                // Use Cranelift backend for compilation, then interpreter for execution
-                let mut cranelift_codegen = match CraneliftCodegen::new() {
+                // Compile to memory and run main natively
+                let mut cranelift_codegen = match CraneliftCodegen::jit() {
                     Ok(cg) => cg,
                     Err(e) => {
                         eprintln!("Failed to initialize Cranelift codegen: {e}");
@@ -251,33 +413,22 @@ fn compile_and_run(file: &PathBuf, _opt_level: u8, _debug: bool, backend: &str,
                     }
                 };
 
-                match cranelift_codegen.compile_program(&program) {
-                    Ok(()) => {
+                let result = cranelift_codegen.compile_program(&program).and_then(|()| {
+                    if !quiet {
                         println!("Successfully compiled with Cranelift!");
                         cranelift_codegen.print_ir();
-                        
-                        // Always run with interpreter to get output
-                        let mut interpreter = Interpreter::new();
-                        if let Err(e) = interpreter.run(&program) {
-                            eprintln!("Interpreter error: {e}");
-                            std::process::exit(1);
-                        }
                     }
-                    Err(e) => {
-                        // If Cranelift compilation fails due to unimplemented features,
-                        // fall back to interpreter-only mode
-                        if e.to_string().contains("not implemented") || e.to_string().contains("String literals") {
-                            eprintln!("Cranelift compilation failed due to unimplemented features, falling back to interpreter...");
-                            let mut interpreter = Interpreter::new();
-                            if let Err(e) = interpreter.run(&program) {
-                                eprintln!("Interpreter error: {e}");
-                                std::process::exit(1);
-                            }
-                        } else {
-                            eprintln!("Cranelift compilation error: {e}");
-                            std::process::exit(1);
-                        }
+                    cranelift_codegen.run_main()
+                });
+                // The program type checked, so any failure here is a
+                // feature Cranelift does not lower or run yet
+                match result {
+                    Ok(()) => {}
+                    Err(e) if execution.fallback => {
+                        eprintln!("warning: Cranelift could not run the program ({e}), falling back to interpreter...");
+                        run_interpreter(&program, file, &source, format, max_call_depth);
                     }
+                    Err(e) => fail(file, &source, e, format),
                 }
             }
             #[cfg(not(feature = "cranelift"))]
@@ -286,49 +437,46 @@ fn compile_and_run(file: &PathBuf, _opt_level: u8, _debug: bool, backend: &str,
                 std::process::exit(1);
             }
         }
-        "interpreter" | _ => {
+        "vm" => run_vm(&program, file, &source, format, &execution),
+        _ => {
             // Use interpreter (fallback for any unrecognized backend)
-            let mut interpreter = Interpreter::new();
-            if let Err(e) = interpreter.run(&program) {
-                eprintln!("Interpreter error: {e}");
-                std::process::exit(1);
-            }
-            // Print captured output buffer to stdout, one line at a time
-            for line in &interpreter.output_buffer {
-                println!("{}", line);
-            }
+            run_interpreter(&program, file, &source, format, max_call_depth);
         }
     }
 
     Ok(())
 }
 
+/// What `cylc build` writes and how it links it.
+struct BuildLink {
+    emit: Emit,
+    static_linking: bool,
+    libraries: Vec<String>,
+    library_paths: Vec<PathBuf>,
+}
+
 fn compile_to_executable(
     file: &PathBuf,
     output: Option<PathBuf>,
     _opt_level: u8,
     _debug: bool,
     backend: &str,
+    link: BuildLink,
+    format: MessageFormat,
 ) -> Result<()> {
     let output_name = output.unwrap_or_else(|| file.with_extension(""));
+    let quiet = format == MessageFormat::Json;
 
-    println!(
-        "Compiling {} to {} (Backend: {})",
-        file.display(),
-        output_name.display(),
-        backend
-    );
-
-    // Read source file
-    let source = std::fs::read_to_string(file)?;
-
-    // Lexical analysis
-    let mut lexer = Lexer::new(&source);
-    let tokens = lexer.tokenize()?;
+    if !quiet {
+        println!(
+            "Compiling {} to {} (Backend: {})",
+            file.display(),
+            output_name.display(),
+            backend
+        );
+    }
 
-    // Parsing
-    let mut parser = parser::helpers::Parser::new(tokens);
-    let ast = parser.parse()?;
+    let (source, program) = load_program(file, format)?;
 
     match backend {
         "llvm" => {
@@ -337,14 +485,32 @@ fn compile_to_executable(
                 // Use LLVM backend
                 let context = Context::create();
                 let mut llvm_codegen = LLVMCodegen::new(&context)?;
-                llvm_codegen.compile_program(&ast)?;
+                if let Err(e) = llvm_codegen.compile_program(&program) {
+                    fail(file, &source, e, format);
+                }
+
+                if link.emit == Emit::Obj {
+                    let obj_name = output_name.with_extension("o");
+                    llvm_codegen.compile_to_object(&obj_name, _opt_level)?;
+                    if !quiet {
+                        println!("Successfully generated object file: {}", obj_name.display());
+                    }
+                    return Ok(());
+                }
 
                 // Generate executable
-                llvm_codegen.compile_to_executable(&output_name, opt_level)?;
-                println!(
-                    "Successfully generated executable: {}",
-                    output_name.display()
-                );
+                let options = LinkOptions {
+                    static_linking: link.static_linking,
+                    libraries: link.libraries,
+                    library_paths: link.library_paths,
+                };
+                llvm_codegen.compile_to_executable(&output_name, _opt_level, &options)?;
+                if !quiet {
+                    println!(
+                        "Successfully generated executable: {}",
+                        output_name.display()
+                    );
+                }
             }
             #[cfg(not(feature = "llvm"))]
             {
@@ -352,20 +518,38 @@ fn compile_to_executable(
                 std::process::exit(1);
             }
         }
-        "cranelift" | _ => {
+        _ => {
             #[cfg(feature = "cranelift")]
             {
                 // Use Cranelift backend
                 let mut cranelift_codegen = CraneliftCodegen::new()?;
-                cranelift_codegen.compile_program(&ast)?;
-
-                // Generate object file (for now)
-                let obj_name = output_name.with_extension("o");
-                cranelift_codegen.write_object_file(obj_name.to_str().unwrap())?;
-                println!(
-                    "Successfully generated object file: {} (linking to executable not yet implemented)",
-                    obj_name.display()
-                );
+                if let Err(e) = cranelift_codegen.compile_program(&program) {
+                    fail(file, &source, e, format);
+                }
+
+                if link.emit == Emit::Obj {
+                    let obj_name = output_name.with_extension("o");
+                    cranelift_codegen.write_object_file(obj_name.to_str().unwrap())?;
+                    if !quiet {
+                        println!("Successfully generated object file: {}", obj_name.display());
+                    }
+                    return Ok(());
+                }
+
+                let options = LinkOptions {
+                    static_linking: link.static_linking,
+                    libraries: link.libraries,
+                    library_paths: link.library_paths,
+                };
+                if let Err(e) = cranelift_codegen.write_executable(&output_name, &options) {
+                    fail(file, &source, e, format);
+                }
+                if !quiet {
+                    println!(
+                        "Successfully generated executable: {}",
+                        output_name.display()
+                    );
+                }
             }
             #[cfg(not(feature = "cranelift"))]
             {
@@ -378,63 +562,48 @@ fn compile_to_executable(
     Ok(())
 }
 
-fn check_syntax(file: &PathBuf) -> Result<()> {
-    println!("Checking syntax: {}", file.display());
+fn check_syntax(file: &PathBuf, format: MessageFormat) -> Result<()> {
+    let quiet = format == MessageFormat::Json;
+    if !quiet {
+        println!("Checking: {}", file.display());
+    }
 
-    // Read source file
-    let source = std::fs::read_to_string(file)?;
+    load_program(file, format)?;
 
-    // Print tokens for debugging
-    print_tokens(&source);
+    if !quiet {
+        println!("✓ No errors found");
+    }
 
-    // Lexical analysis
-    let mut lexer = Lexer::new(&source);
-    let tokens = match lexer.tokenize() {
-        Ok(t) => t,
-        Err(e) => {
-            print_error_with_context(&e, &source);
-            std::process::exit(1);
-        }
-    };
+    Ok(())
+}
 
-    // Parsing (syntax check)
-    let mut parser = parser::helpers::Parser::new(tokens);
-    let _ast = match parser.parse() {
-        Ok(a) => a,
-        Err(e) => {
-            print_error_with_context(&e, &source);
-            std::process::exit(1);
+fn explain_code(code: &str) -> Result<()> {
+    match diagnostics::explain(code) {
+        Some(text) => {
+            println!("{text}");
+            Ok(())
         }
-    };
-
-    println!("✓ Syntax is valid");
-
-    Ok(())
+        None => anyhow::bail!("No explanation for `{}`; codes look like E0003", code),
+    }
 }
 
 fn show_ast(file: &PathBuf, format: &str) -> Result<()> {
     // Read source file
     let source = std::fs::read_to_string(file)?;
 
-    // Print tokens for debugging
-    print_tokens(&source);
-
     // Lexical analysis
     let mut lexer = Lexer::new(&source);
     let tokens = match lexer.tokenize() {
         Ok(t) => t,
-        Err(e) => {
-            print_error_with_context(&e, &source);
-            std::process::exit(1);
-        }
+        Err(e) => fail(file, &source, e, MessageFormat::Human),
     };
 
     // Parsing
     let mut parser = parser::helpers::Parser::new(tokens);
     let ast = match parser.parse() {
         Ok(a) => a,
-        Err(e) => {
-            print_error_with_context(&e, &source);
+        Err(errors) => {
+            report_errors(file, &source, &errors, MessageFormat::Human);
             std::process::exit(1);
         }
     };
@@ -455,42 +624,15 @@ fn show_ast(file: &PathBuf, format: &str) -> Result<()> {
     Ok(())
 }
 
-fn print_tokens(source: &str) {
-    let mut lexer = Lexer::new(source);
-    match lexer.tokenize() {
-        Ok(tokens) => {
-            for t in tokens {
-                println!("{:?} (line {}, col {})", t.token, t.line, t.column);
-            }
-        }
-        Err(e) => {
-            println!("Lexer error: {e}");
-        }
-    }
-}
-
-fn try_parse_file(source: &str) -> Result<Program> {
-    print_tokens(source); // Print tokens for debugging
-
-    // Lexical analysis
-    let mut lexer = Lexer::new(source);
-    let tokens = lexer.tokenize()?;
-    let mut parser = parser::helpers::Parser::new(tokens);
-    let ast = parser.parse();
-    match &ast {
-        Ok(prog) => eprintln!("[test debug] AST: {prog:#?}"),
-        Err(e) => eprintln!("[test debug] Parse error: {e}"),
-    }
-    ast.map_err(Into::into)
+fn try_parse_file(source: &str) -> Result<TypedProgram> {
+    parse_and_check(source).map_err(|failure| {
+        let messages: Vec<String> = failure.errors.iter().map(|e| e.to_string()).collect();
+        anyhow::anyhow!(messages.join("\n"))
+    })
 }
 
 fn run_tests(pattern: Option<String>, verbose: bool, continue_on_failure: bool) -> Result<()> {
-    use std::env;
     println!("🧪 Running Cyl automated tests...\n");
-    println!(
-        "[debug] Current working directory: {}",
-        env::current_dir()?.display()
-    );
 
     let tests_dir = PathBuf::from("tests");
     if !tests_dir.exists() {
@@ -504,6 +646,7 @@ fn run_tests(pattern: Option<String>, verbose: bool, continue_on_failure: bool)
 
     let valid_dir = fixtures_dir.join("valid");
     let invalid_dir = fixtures_dir.join("invalid");
+    let runtime_errors_dir = fixtures_dir.join("runtime_errors");
 
     let mut total_tests = 0;
     let mut passed_tests = 0;
@@ -514,7 +657,7 @@ fn run_tests(pattern: Option<String>, verbose: bool, continue_on_failure: bool)
         println!("📂 Running valid test cases...");
         let (total, passed, failed) = run_test_directory(
             &valid_dir,
-            true,
+            Expectation::Parse,
             pattern.as_deref(),
             verbose,
             continue_on_failure,
@@ -530,7 +673,23 @@ fn run_tests(pattern: Option<String>, verbose: bool, continue_on_failure: bool)
         println!("📂 Running invalid test cases...");
         let (total, passed, failed) = run_test_directory(
             &invalid_dir,
-            false,
+            Expectation::FailToParse,
+            pattern.as_deref(),
+            verbose,
+            continue_on_failure,
+        )?;
+        total_tests += total;
+        passed_tests += passed;
+        failed_tests += failed;
+        println!();
+    }
+
+    // Run runtime error tests (should check, then fail when run)
+    if runtime_errors_dir.exists() {
+        println!("📂 Running runtime error test cases...");
+        let (total, passed, failed) = run_test_directory(
+            &runtime_errors_dir,
+            Expectation::RuntimeError,
             pattern.as_deref(),
             verbose,
             continue_on_failure,
@@ -559,9 +718,20 @@ fn run_tests(pattern: Option<String>, verbose: bool, continue_on_failure: bool)
     Ok(())
 }
 
+/// What `cylc test` expects of the fixtures in a directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Expectation {
+    /// Parse and type check.
+    Parse,
+    /// Fail to parse or type check.
+    FailToParse,
+    /// Check, then stop with a runtime error (`E0006`) on the interpreter.
+    RuntimeError,
+}
+
 fn run_test_directory(
     dir: &PathBuf,
-    should_succeed: bool,
+    expectation: Expectation,
     pattern: Option<&str>,
     verbose: bool,
     continue_on_failure: bool,
@@ -597,7 +767,7 @@ fn run_test_directory(
 
         total += 1;
 
-        let test_result = run_single_test(&path, should_succeed, verbose);
+        let test_result = run_single_test(&path, expectation, verbose);
 
         match test_result {
             Ok(true) => {
@@ -630,7 +800,7 @@ fn run_test_directory(
     Ok((total, passed, failed))
 }
 
-fn run_single_test(file: &PathBuf, should_succeed: bool, verbose: bool) -> Result<bool> {
+fn run_single_test(file: &PathBuf, expectation: Expectation, verbose: bool) -> Result<bool> {
     // Read source file
     let source = match std::fs::read_to_string(file) {
         Ok(s) => s,
@@ -645,26 +815,48 @@ fn run_single_test(file: &PathBuf, should_succeed: bool, verbose: bool) -> Resul
     // Try to parse the file
     let parse_result = try_parse_file(&source);
 
-    match (should_succeed, parse_result) {
-        (true, Ok(_)) => {
+    match (expectation, parse_result) {
+        (Expectation::Parse, Ok(_)) => {
             if verbose {
                 println!("    Parsed successfully (as expected)");
             }
             Ok(true)
         }
-        (true, Err(e)) => {
+        (Expectation::Parse | Expectation::RuntimeError, Err(e)) => {
             if verbose {
                 println!("    Parse failed unexpectedly: {e}");
             }
             Ok(false)
         }
-        (false, Err(_)) => {
+        (Expectation::RuntimeError, Ok(program)) => {
+            match interpret(&program, DEFAULT_MAX_CALL_DEPTH).1 {
+                Err(e) if e.code() == "E0006" => {
+                    if verbose {
+                        println!("    Failed at runtime (as expected): {e}");
+                    }
+                    Ok(true)
+                }
+                Err(e) => {
+                    if verbose {
+                        println!("    Failed with something other than a runtime error: {e}");
+                    }
+                    Ok(false)
+                }
+                Ok(()) => {
+                    if verbose {
+                        println!("    Ran without a runtime error");
+                    }
+                    Ok(false)
+                }
+            }
+        }
+        (Expectation::FailToParse, Err(_)) => {
             if verbose {
                 println!("    Parse failed (as expected)");
             }
             Ok(true)
         }
-        (false, Ok(_)) => {
+        (Expectation::FailToParse, Ok(_)) => {
             if verbose {
                 println!("    Parse succeeded unexpectedly");
             }
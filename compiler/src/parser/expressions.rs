@@ -12,6 +12,11 @@ impl Parser {
         self.parse_expression_internal(false)
     }
 
+    /// Parse a single operand with its prefix operators, e.g. a literal pattern.
+    pub fn parse_unary(&mut self) -> Result<Expression, CylError> {
+        self.parse_unary_internal(false)
+    }
+
     pub fn parse_expression_stop_at_left_brace(&mut self) -> Result<Expression, CylError> {
         self.parse_expression_internal(true)
     }
@@ -27,17 +32,54 @@ impl Parser {
         &mut self,
         stop_at_left_brace: bool,
     ) -> Result<Expression, CylError> {
-        let expr = self.parse_logical_or_internal(stop_at_left_brace)?;
+        let expr = self.parse_range_internal(stop_at_left_brace)?;
         if self.match_token(&Token::Assign) {
             let value = self.parse_assignment_internal(stop_at_left_brace)?;
-            return Ok(Expression::Assignment {
-                target: Box::new(expr),
-                value: Box::new(value),
-            });
+            let span = expr.span.to(value.span);
+            return Ok(Expression::new(
+                ExpressionKind::Assignment {
+                    target: Box::new(expr),
+                    value: Box::new(value),
+                },
+                span,
+            ));
         }
         Ok(expr)
     }
 
+    fn binary(left: Expression, operator: BinaryOperator, right: Expression) -> Expression {
+        let span = left.span.to(right.span);
+        Expression::new(
+            ExpressionKind::BinaryOp {
+                left: Box::new(left),
+                operator,
+                right: Box::new(right),
+            },
+            span,
+        )
+    }
+
+    fn parse_range_internal(&mut self, stop_at_left_brace: bool) -> Result<Expression, CylError> {
+        let start = self.parse_logical_or_internal(stop_at_left_brace)?;
+        let inclusive = if self.match_token(&Token::DotDotEqual) {
+            true
+        } else if self.match_token(&Token::DotDot) {
+            false
+        } else {
+            return Ok(start);
+        };
+        let end = self.parse_logical_or_internal(stop_at_left_brace)?;
+        let span = start.span.to(end.span);
+        Ok(Expression::new(
+            ExpressionKind::Range {
+                start: Box::new(start),
+                end: Box::new(end),
+                inclusive,
+            },
+            span,
+        ))
+    }
+
     fn parse_logical_or_internal(
         &mut self,
         stop_at_left_brace: bool,
@@ -45,11 +87,7 @@ impl Parser {
         let mut expr = self.parse_logical_and_internal(stop_at_left_brace)?;
         while self.match_token(&Token::Or) {
             let right = self.parse_logical_and_internal(stop_at_left_brace)?;
-            expr = Expression::BinaryOp {
-                left: Box::new(expr),
-                operator: BinaryOperator::Or,
-                right: Box::new(right),
-            };
+            expr = Self::binary(expr, BinaryOperator::Or, right);
         }
         Ok(expr)
     }
@@ -61,11 +99,7 @@ impl Parser {
         let mut expr = self.parse_equality_internal(stop_at_left_brace)?;
         while self.match_token(&Token::And) {
             let right = self.parse_equality_internal(stop_at_left_brace)?;
-            expr = Expression::BinaryOp {
-                left: Box::new(expr),
-                operator: BinaryOperator::And,
-                right: Box::new(right),
-            };
+            expr = Self::binary(expr, BinaryOperator::And, right);
         }
         Ok(expr)
     }
@@ -77,11 +111,7 @@ impl Parser {
         let mut expr = self.parse_comparison_internal(stop_at_left_brace)?;
         while let Some(op) = self.match_binary_op(&[Token::Equal, Token::NotEqual]) {
             let right = self.parse_comparison_internal(stop_at_left_brace)?;
-            expr = Expression::BinaryOp {
-                left: Box::new(expr),
-                operator: op,
-                right: Box::new(right),
-            };
+            expr = Self::binary(expr, op, right);
         }
         Ok(expr)
     }
@@ -90,19 +120,62 @@ impl Parser {
         &mut self,
         stop_at_left_brace: bool,
     ) -> Result<Expression, CylError> {
-        let mut expr = self.parse_term_internal(stop_at_left_brace)?;
+        let mut expr = self.parse_bitwise_or_internal(stop_at_left_brace)?;
         while let Some(op) = self.match_binary_op(&[
             Token::Less,
             Token::LessEqual,
             Token::Greater,
             Token::GreaterEqual,
         ]) {
+            let right = self.parse_bitwise_or_internal(stop_at_left_brace)?;
+            expr = Self::binary(expr, op, right);
+        }
+        Ok(expr)
+    }
+
+    // Bitwise operators bind tighter than comparisons, so `x & 1 == 0` means
+    // `(x & 1) == 0`, and shifts tighter still, below `+` and `-`.
+    fn parse_bitwise_or_internal(
+        &mut self,
+        stop_at_left_brace: bool,
+    ) -> Result<Expression, CylError> {
+        let mut expr = self.parse_bitwise_xor_internal(stop_at_left_brace)?;
+        while let Some(op) = self.match_binary_op(&[Token::BitwiseOr]) {
+            let right = self.parse_bitwise_xor_internal(stop_at_left_brace)?;
+            expr = Self::binary(expr, op, right);
+        }
+        Ok(expr)
+    }
+
+    fn parse_bitwise_xor_internal(
+        &mut self,
+        stop_at_left_brace: bool,
+    ) -> Result<Expression, CylError> {
+        let mut expr = self.parse_bitwise_and_internal(stop_at_left_brace)?;
+        while let Some(op) = self.match_binary_op(&[Token::BitwiseXor]) {
+            let right = self.parse_bitwise_and_internal(stop_at_left_brace)?;
+            expr = Self::binary(expr, op, right);
+        }
+        Ok(expr)
+    }
+
+    fn parse_bitwise_and_internal(
+        &mut self,
+        stop_at_left_brace: bool,
+    ) -> Result<Expression, CylError> {
+        let mut expr = self.parse_shift_internal(stop_at_left_brace)?;
+        while let Some(op) = self.match_binary_op(&[Token::BitwiseAnd]) {
+            let right = self.parse_shift_internal(stop_at_left_brace)?;
+            expr = Self::binary(expr, op, right);
+        }
+        Ok(expr)
+    }
+
+    fn parse_shift_internal(&mut self, stop_at_left_brace: bool) -> Result<Expression, CylError> {
+        let mut expr = self.parse_term_internal(stop_at_left_brace)?;
+        while let Some(op) = self.match_binary_op(&[Token::LeftShift, Token::RightShift]) {
             let right = self.parse_term_internal(stop_at_left_brace)?;
-            expr = Expression::BinaryOp {
-                left: Box::new(expr),
-                operator: op,
-                right: Box::new(right),
-            };
+            expr = Self::binary(expr, op, right);
         }
         Ok(expr)
     }
@@ -111,11 +184,7 @@ impl Parser {
         let mut expr = self.parse_factor_internal(stop_at_left_brace)?;
         while let Some(op) = self.match_binary_op(&[Token::Plus, Token::Minus]) {
             let right = self.parse_factor_internal(stop_at_left_brace)?;
-            expr = Expression::BinaryOp {
-                left: Box::new(expr),
-                operator: op,
-                right: Box::new(right),
-            };
+            expr = Self::binary(expr, op, right);
         }
         Ok(expr)
     }
@@ -125,38 +194,33 @@ impl Parser {
         while let Some(op) = self.match_binary_op(&[Token::Multiply, Token::Divide, Token::Modulo])
         {
             let right = self.parse_unary_internal(stop_at_left_brace)?;
-            expr = Expression::BinaryOp {
-                left: Box::new(expr),
-                operator: op,
-                right: Box::new(right),
-            };
+            expr = Self::binary(expr, op, right);
         }
         Ok(expr)
     }
 
     fn parse_unary_internal(&mut self, stop_at_left_brace: bool) -> Result<Expression, CylError> {
-        if self.match_token(&Token::Minus) {
-            let right = self.parse_unary_internal(stop_at_left_brace)?;
-            Ok(Expression::UnaryOp {
-                operator: UnaryOperator::Minus,
-                operand: Box::new(right),
-            })
+        let start = self.start_span();
+        let operator = if self.match_token(&Token::Minus) {
+            UnaryOperator::Minus
         } else if self.match_token(&Token::Not) {
-            let right = self.parse_unary_internal(stop_at_left_brace)?;
-            Ok(Expression::UnaryOp {
-                operator: UnaryOperator::Not,
-                operand: Box::new(right),
-            })
+            UnaryOperator::Not
+        } else if self.match_token(&Token::BitwiseNot) {
+            UnaryOperator::BitwiseNot
         } else if self.match_token(&Token::Await) {
-            let right = self.parse_unary_internal(stop_at_left_brace)?;
-            Ok(Expression::UnaryOp {
-                operator: UnaryOperator::Await,
-                operand: Box::new(right),
-            })
+            UnaryOperator::Await
         } else {
             let primary = self.parse_primary_internal(stop_at_left_brace)?;
-            self.parse_postfix_internal(primary, stop_at_left_brace)
-        }
+            return self.parse_postfix_internal(primary, stop_at_left_brace);
+        };
+        let right = self.parse_unary_internal(stop_at_left_brace)?;
+        Ok(Expression::new(
+            ExpressionKind::UnaryOp {
+                operator,
+                operand: Box::new(right),
+            },
+            self.span_from(start),
+        ))
     }
 
     fn parse_postfix_internal(
@@ -181,10 +245,14 @@ impl Parser {
                         });
                     }
                 };
-                expr = Expression::MemberAccess {
-                    object: Box::new(expr),
-                    property: member,
-                };
+                let span = self.span_from(expr.span);
+                expr = Expression::new(
+                    ExpressionKind::MemberAccess {
+                        object: Box::new(expr),
+                        property: member,
+                    },
+                    span,
+                );
             } else if self.check(&Token::LeftParen) {
                 // Function call: expr(...)
                 self.advance();
@@ -201,19 +269,31 @@ impl Parser {
                     Token::RightParen,
                     "Expected ')' after function call arguments",
                 )?;
-                expr = Expression::Call {
-                    callee: Box::new(expr),
-                    arguments: args,
-                };
+                let span = self.span_from(expr.span);
+                expr = Expression::new(
+                    ExpressionKind::Call {
+                        callee: Box::new(expr),
+                        arguments: args,
+                    },
+                    span,
+                );
+            } else if self.match_token(&Token::Question) {
+                // Error propagation: expr?
+                let span = self.span_from(expr.span);
+                expr = Expression::new(ExpressionKind::Propagate(Box::new(expr)), span);
             } else if self.check(&Token::LeftBracket) {
                 // Array indexing: expr[index]
                 self.advance();
                 let index = self.parse_expression()?;
                 self.consume(Token::RightBracket, "Expected ']' after array index")?;
-                expr = Expression::IndexAccess {
-                    object: Box::new(expr),
-                    index: Box::new(index),
-                };
+                let span = self.span_from(expr.span);
+                expr = Expression::new(
+                    ExpressionKind::IndexAccess {
+                        object: Box::new(expr),
+                        index: Box::new(index),
+                    },
+                    span,
+                );
             } else {
                 break;
             }
@@ -222,22 +302,25 @@ impl Parser {
     }
 
     fn parse_primary_internal(&mut self, stop_at_left_brace: bool) -> Result<Expression, CylError> {
-        match &self.peek().token {
-            Token::Match => Err(CylError::ParseError {
-                message: "'match' can only be used as a statement, not as an expression"
-                    .to_string(),
-                line: self.peek().line,
-                column: self.peek().column,
-            }),
+        let start = self.start_span();
+        let kind = match &self.peek().token {
+            Token::Match => {
+                return Err(CylError::ParseError {
+                    message: "'match' can only be used as a statement, not as an expression"
+                        .to_string(),
+                    line: self.peek().line,
+                    column: self.peek().column,
+                })
+            }
             Token::IntLiteral(value) => {
                 let value = *value;
                 self.advance();
-                Ok(Expression::IntLiteral(value))
+                ExpressionKind::IntLiteral(value)
             }
             Token::FloatLiteral(value) => {
                 let value = *value;
                 self.advance();
-                Ok(Expression::FloatLiteral(value))
+                ExpressionKind::FloatLiteral(value)
             }
             Token::StringLiteral(value) => {
                 let mut value = value.clone();
@@ -246,25 +329,25 @@ impl Parser {
                 if value.starts_with('"') && value.ends_with('"') && value.len() >= 2 {
                     value = value[1..value.len()-1].to_string();
                 }
-                Ok(Expression::StringLiteral(value))
+                ExpressionKind::StringLiteral(value)
             }
             Token::BoolLiteral(value) => {
                 let value = *value;
                 self.advance();
-                Ok(Expression::BoolLiteral(value))
+                ExpressionKind::BoolLiteral(value)
             }
             Token::CharLiteral(value) => {
                 let value = *value;
                 self.advance();
-                Ok(Expression::CharLiteral(value))
+                ExpressionKind::CharLiteral(value)
             }
             Token::Null => {
                 self.advance();
-                Ok(Expression::Null)
+                ExpressionKind::Null
             }
             Token::Dynamic => {
                 self.advance();
-                Ok(Expression::Dynamic)
+                ExpressionKind::Dynamic
             }
             Token::Identifier(name) => {
                 let name = name.clone();
@@ -272,7 +355,7 @@ impl Parser {
                 if self.check(&Token::LeftBrace) && stop_at_left_brace {
                     // When stop_at_left_brace is true, we're in a pattern context
                     // or similar where we need to stop at '{' - just return the identifier
-                    Ok(Expression::Identifier(name))
+                    ExpressionKind::Identifier(name)
                 } else if self.check(&Token::LeftBrace) && !stop_at_left_brace {
                     self.advance();
                     let mut fields = std::collections::HashMap::new();
@@ -298,18 +381,18 @@ impl Parser {
                     let mut obj = fields;
                     obj.insert(
                         "__struct_name__".to_string(),
-                        Expression::StringLiteral(name.clone()),
+                        Expression::new(ExpressionKind::StringLiteral(name.clone()), start),
                     );
-                    Ok(Expression::ObjectLiteral(obj))
+                    ExpressionKind::ObjectLiteral(obj)
                 } else if self.check(&Token::Assign) {
                     self.advance();
                     let value = self.parse_expression()?;
-                    Ok(Expression::Assignment {
-                        target: Box::new(Expression::Identifier(name)),
+                    ExpressionKind::Assignment {
+                        target: Box::new(Expression::new(ExpressionKind::Identifier(name), start)),
                         value: Box::new(value),
-                    })
+                    }
                 } else {
-                    Ok(Expression::Identifier(name))
+                    ExpressionKind::Identifier(name)
                 }
             }
             Token::LeftParen => {
@@ -327,11 +410,11 @@ impl Parser {
                         elements.push(self.parse_expression()?);
                     }
                     self.consume(Token::RightParen, "Expected ')' after tuple literal")?;
-                    Ok(Expression::TupleLiteral(elements))
+                    ExpressionKind::TupleLiteral(elements)
                 } else {
                     // Parenthesized expression
                     self.consume(Token::RightParen, "Expected ')' after expression")?;
-                    Ok(first)
+                    first.kind
                 }
             }
             Token::LeftBracket => {
@@ -346,13 +429,40 @@ impl Parser {
                     }
                 }
                 self.consume(Token::RightBracket, "Expected ']' after array elements")?;
-                Ok(Expression::ArrayLiteral(elements))
+                ExpressionKind::ArrayLiteral(elements)
             }
-            _ => Err(CylError::ParseError {
-                message: "Expected expression".to_string(),
-                line: self.peek().line,
-                column: self.peek().column,
-            }),
-        }
+            Token::LeftBrace => {
+                // Map literal: { "key": value, ... }, an object literal
+                // without a struct name
+                self.advance();
+                let mut entries = std::collections::HashMap::new();
+                while !self.check(&Token::RightBrace) && !self.is_at_end() {
+                    let Token::StringLiteral(key) = &self.peek().token else {
+                        return Err(CylError::ParseError {
+                            message: "Expected a string key in map literal".to_string(),
+                            line: self.peek().line,
+                            column: self.peek().column,
+                        });
+                    };
+                    let key = key[1..key.len() - 1].to_string();
+                    self.advance();
+                    self.consume(Token::Colon, "Expected ':' after map key")?;
+                    entries.insert(key, self.parse_expression()?);
+                    if !self.match_token(&Token::Comma) {
+                        break;
+                    }
+                }
+                self.consume(Token::RightBrace, "Expected '}' after map literal")?;
+                ExpressionKind::ObjectLiteral(entries)
+            }
+            _ => {
+                return Err(CylError::ParseError {
+                    message: "Expected expression".to_string(),
+                    line: self.peek().line,
+                    column: self.peek().column,
+                })
+            }
+        };
+        Ok(Expression::new(kind, self.span_from(start)))
     }
 }
@@ -13,6 +13,7 @@ impl Parser {
     // pub fn parse_enum(&mut self) -> Result<Statement, CylError> { ... }
 
     pub fn parse_function(&mut self, is_async: bool) -> Result<Statement, CylError> {
+        let start = self.start_span();
         let name = match &self.peek().token {
             Token::Identifier(n) => {
                 let n = n.clone();
@@ -40,6 +41,8 @@ impl Parser {
         let mut parameters = Vec::new();
         if !self.check(&Token::RightParen) {
             loop {
+                let param_start = self.start_span();
+                let is_mutable = self.match_token(&Token::Mut);
                 let param_name = match &self.peek().token {
                     Token::Identifier(n) => {
                         let n = n.clone();
@@ -55,40 +58,17 @@ impl Parser {
                     }
                 };
                 // Accept <type> or :type or nothing (type inference)
-                let param_type = if self.check(&Token::Less) {
-                    self.advance();
-                    let mut type_names = Vec::new();
-                    let mut saw_identifier = false;
-                    loop {
-                        match &self.peek().token {
-                            Token::Identifier(tn) => {
-                                type_names.push(tn.clone());
-                                saw_identifier = true;
-                                self.advance();
-                            }
-                            Token::Greater => {
-                                self.advance();
-                                break;
-                            }
-                            _ => {
-                                self.advance();
-                            }
-                        }
-                    }
-                    if saw_identifier {
-                        if type_names.len() == 1 {
-                            Type::Custom(type_names.remove(0))
-                        } else {
-                            Type::Generic("_anon".to_string(), type_names)
-                        }
-                    } else {
-                        Type::Infer
-                    }
+                let annotation = if self.check(&Token::Less) {
+                    self.parse_angle_type()?
                 } else if self.check(&Token::Colon) {
                     self.advance();
-                    self.parse_type()?
+                    Some(self.parse_annotation()?)
                 } else {
-                    Type::Infer
+                    None
+                };
+                let (param_type, type_span) = match annotation {
+                    Some((param_type, span)) => (param_type, Some(span)),
+                    None => (Type::Infer, None),
                 };
                 let default_value = if self.check(&Token::Assign) {
                     self.advance();
@@ -99,8 +79,10 @@ impl Parser {
                 parameters.push(Parameter {
                     name: param_name,
                     param_type,
-                    is_mutable: false,
+                    type_span,
+                    is_mutable,
                     default_value,
+                    span: self.span_from(param_start),
                 });
                 if !self.match_token(&Token::Comma) {
                     break;
@@ -109,24 +91,30 @@ impl Parser {
         }
         self.consume(Token::RightParen, "Expected ')' after parameters")?;
         // Return type (optional, supports tuple types)
-        let return_type = if self.check(&Token::Arrow) {
+        let (return_type, return_type_span) = if self.check(&Token::Arrow) {
             self.advance();
-            Some(self.parse_type()?)
+            let (return_type, span) = self.parse_annotation()?;
+            (Some(return_type), Some(span))
         } else {
-            None
+            (None, None)
         };
         let body = self.parse_block()?;
-        Ok(Statement::Function(FunctionDeclaration {
-            name,
-            parameters,
-            return_type,
-            body,
-            is_async,
-            type_parameters,
-        }))
+        Ok(Statement::new(
+            StatementKind::Function(FunctionDeclaration {
+                name,
+                parameters,
+                return_type,
+                return_type_span,
+                body,
+                is_async,
+                type_parameters,
+            }),
+            self.span_from(start),
+        ))
     }
 
     pub fn parse_struct(&mut self) -> Result<Statement, CylError> {
+        let start = self.start_span();
         // Do NOT consume Token::Struct here; it is already consumed in parse_statement
         let name = match &self.peek().token {
             Token::Identifier(n) => {
@@ -154,16 +142,19 @@ impl Parser {
         )?;
         let mut fields = Vec::new();
         while !self.check(&Token::RightBrace) && !self.is_at_end() {
+            let field_start = self.start_span();
             if let Token::Identifier(field_name) = &self.peek().token {
                 let field_name = field_name.clone();
                 self.advance();
                 self.consume(Token::Colon, "Expected ':' after field name")?;
-                let field_type = self.parse_type()?;
+                let (field_type, type_span) = self.parse_annotation()?;
                 let is_public = false; // TODO: support pub fields
                 fields.push(StructField {
                     name: field_name,
                     field_type,
+                    type_span,
                     is_public,
+                    span: self.span_from(field_start),
                 });
                 if self.match_token(&Token::Comma) || self.check(&Token::RightBrace) {
                     // continue
@@ -179,14 +170,18 @@ impl Parser {
             }
         }
         self.consume(Token::RightBrace, "Expected '}' after struct fields")?;
-        Ok(Statement::Struct(StructDeclaration {
-            name,
-            fields,
-            type_parameters,
-        }))
+        Ok(Statement::new(
+            StatementKind::Struct(StructDeclaration {
+                name,
+                fields,
+                type_parameters,
+            }),
+            self.span_from(start),
+        ))
     }
 
     pub fn parse_enum(&mut self) -> Result<Statement, CylError> {
+        let start = self.start_span();
         // Do NOT consume Token::Enum here; it is already consumed in parse_statement
         let name = match &self.peek().token {
             Token::Identifier(n) => {
@@ -214,15 +209,19 @@ impl Parser {
         )?;
         let mut variants = Vec::new();
         while !self.check(&Token::RightBrace) && !self.is_at_end() {
+            let variant_start = self.start_span();
             if let Token::Identifier(variant_name) = &self.peek().token {
                 let variant_name = variant_name.clone();
                 self.advance();
+                let mut field_spans = Vec::new();
                 let fields = if self.check(&Token::LeftParen) {
                     self.advance();
                     let mut types = Vec::new();
                     if !self.check(&Token::RightParen) {
                         loop {
-                            types.push(self.parse_type()?);
+                            let (field_type, span) = self.parse_annotation()?;
+                            types.push(field_type);
+                            field_spans.push(span);
                             if !self.match_token(&Token::Comma) {
                                 break;
                             }
@@ -236,6 +235,8 @@ impl Parser {
                 variants.push(EnumVariant {
                     name: variant_name,
                     fields,
+                    field_spans,
+                    span: self.span_from(variant_start),
                 });
                 if self.match_token(&Token::Comma) || self.check(&Token::RightBrace) {
                     // continue
@@ -251,11 +252,14 @@ impl Parser {
             }
         }
         self.consume(Token::RightBrace, "Expected '}' after enum variants")?;
-        Ok(Statement::Enum(EnumDeclaration {
-            name,
-            variants,
-            type_parameters,
-        }))
+        Ok(Statement::new(
+            StatementKind::Enum(EnumDeclaration {
+                name,
+                variants,
+                type_parameters,
+            }),
+            self.span_from(start),
+        ))
     }
 
     #[allow(dead_code)]
@@ -1,15 +1,21 @@
-use crate::ast::{BinaryOperator, BlockStatement, Type, UnaryOperator};
+use crate::ast::{BinaryOperator, BlockStatement, Span, Type, UnaryOperator};
 use crate::error::CylError;
 use crate::lexer::Token;
 
 pub struct Parser {
     pub tokens: Vec<crate::lexer::TokenWithLocation>,
     pub current: usize,
+    /// Errors recovered from so far; `parse` returns them all at the end.
+    pub errors: Vec<CylError>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<crate::lexer::TokenWithLocation>) -> Self {
-        Self { tokens, current: 0 }
+        Self {
+            tokens,
+            current: 0,
+            errors: Vec::new(),
+        }
     }
 
     pub fn is_at_end(&self) -> bool {
@@ -31,6 +37,86 @@ impl Parser {
         self.previous()
     }
 
+    /// Span of the next token, marking where the node about to be parsed starts.
+    pub fn start_span(&self) -> Span {
+        let token = self.peek();
+        Span::new(token.span.start, token.span.end, token.line, token.column)
+    }
+
+    /// Extend `start` to the end of the most recently consumed token.
+    pub fn span_from(&self, start: Span) -> Span {
+        if self.current == 0 {
+            return start;
+        }
+        Span {
+            end: self.previous().span.end.max(start.start),
+            ..start
+        }
+    }
+
+    /// Record `error` and skip ahead to the next statement boundary: just past
+    /// a `;`, or before a `}` closing the enclosing block or a token that can
+    /// start a new statement. `from` is where the failed statement began; any
+    /// braces it left open, and nested `{ ... }` groups, are skipped whole.
+    /// Always consumes at least one token if the error occurred at `from`.
+    pub fn recover(&mut self, error: CylError, from: usize) {
+        self.errors.push(error);
+        let mut depth = 0usize;
+        for token in &self.tokens[from..self.current] {
+            match token.token {
+                Token::LeftBrace => depth += 1,
+                Token::RightBrace => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+        while !self.is_at_end() {
+            match self.peek().token {
+                Token::Semicolon if depth == 0 => {
+                    self.advance();
+                    return;
+                }
+                Token::LeftBrace => depth += 1,
+                Token::RightBrace if depth == 0 => break,
+                Token::RightBrace => depth -= 1,
+                _ if depth == 0
+                    && self.current != from
+                    && Self::starts_statement(&self.peek().token) =>
+                {
+                    break
+                }
+                _ => {}
+            }
+            self.advance();
+        }
+        if self.current == from && !self.is_at_end() && !self.check(&Token::RightBrace) {
+            self.advance();
+        }
+    }
+
+    /// Keywords that can only begin a statement, used as synchronization points.
+    pub fn starts_statement(token: &Token) -> bool {
+        matches!(
+            token,
+            Token::Import
+                | Token::Fn
+                | Token::Async
+                | Token::Struct
+                | Token::Enum
+                | Token::Let
+                | Token::Const
+                | Token::Return
+                | Token::If
+                | Token::While
+                | Token::For
+                | Token::Match
+                | Token::Try
+                | Token::Throw
+                | Token::Break
+                | Token::Continue
+                | Token::Label(_)
+        )
+    }
+
     pub fn check(&self, token: &Token) -> bool {
         if self.is_at_end() {
             false
@@ -82,6 +168,11 @@ impl Parser {
                     Token::GreaterEqual => BinaryOperator::GreaterEqual,
                     Token::And => BinaryOperator::And,
                     Token::Or => BinaryOperator::Or,
+                    Token::BitwiseAnd => BinaryOperator::BitwiseAnd,
+                    Token::BitwiseOr => BinaryOperator::BitwiseOr,
+                    Token::BitwiseXor => BinaryOperator::BitwiseXor,
+                    Token::LeftShift => BinaryOperator::LeftShift,
+                    Token::RightShift => BinaryOperator::RightShift,
                     _ => unreachable!(),
                 });
             }
@@ -139,6 +230,13 @@ impl Parser {
         Ok(type_parameters)
     }
 
+    /// Parse a type annotation, along with where it is written.
+    pub fn parse_annotation(&mut self) -> Result<(Type, Span), CylError> {
+        let start = self.start_span();
+        let annotated = self.parse_type()?;
+        Ok((annotated, self.span_from(start)))
+    }
+
     pub fn parse_type(&mut self) -> Result<Type, CylError> {
         if self.check(&Token::LeftParen) {
             self.advance();
@@ -202,9 +300,7 @@ impl Parser {
                         && !self.check(&Token::RightAngle)
                         && !self.is_at_end()
                     {
-                        let t = self.parse_type()?;
-                        // Convert type to string for AST compatibility
-                        generic_types.push(format!("{t:?}"));
+                        generic_types.push(self.parse_type()?);
                         if self.check(&Token::Comma) {
                             self.advance();
                         } else {
@@ -246,7 +342,26 @@ impl Parser {
         }
     }
 
+    /// Parse the angle-bracket annotation used by declarations and parameters
+    /// (`y <float> = 3.14`). An empty `<>` means the type is inferred.
+    pub fn parse_angle_type(&mut self) -> Result<Option<(Type, Span)>, CylError> {
+        self.consume(Token::Less, "Expected '<' before type annotation")?;
+        if self.match_token(&Token::Greater) {
+            return Ok(None);
+        }
+        let annotated = self.parse_annotation()?;
+        if !self.match_token(&Token::Greater) && !self.match_token(&Token::RightAngle) {
+            return Err(CylError::ParseError {
+                message: "Expected '>' after type annotation".to_string(),
+                line: self.peek().line,
+                column: self.peek().column,
+            });
+        }
+        Ok(Some(annotated))
+    }
+
     pub fn parse_block(&mut self) -> Result<BlockStatement, CylError> {
+        let start = self.start_span();
         self.consume(Token::LeftBrace, "Expected '{'")?;
         let mut statements = Vec::new();
         while !self.check(&Token::RightBrace) && !self.is_at_end() {
@@ -257,9 +372,16 @@ impl Parser {
             if self.check(&Token::RightBrace) || self.is_at_end() {
                 break;
             }
-            statements.push(self.parse_statement()?);
+            let from = self.current;
+            match self.parse_statement() {
+                Ok(stmt) => statements.push(stmt),
+                Err(e) => self.recover(e, from),
+            }
         }
         self.consume(Token::RightBrace, "Expected '}'")?;
-        Ok(BlockStatement { statements })
+        Ok(BlockStatement {
+            statements,
+            span: self.span_from(start),
+        })
     }
 }
@@ -8,83 +8,45 @@ mod statements;
 
 use crate::ast::*;
 use crate::error::CylError;
+use crate::lexer::Token;
 use crate::parser::helpers::Parser;
 
 impl Parser {
-    pub fn parse(&mut self) -> Result<Program, CylError> {
+    /// Parse a whole program, recovering at statement boundaries so that every
+    /// syntax error in the file is reported, not just the first.
+    pub fn parse(&mut self) -> Result<Program, Vec<CylError>> {
         let mut statements = Vec::new();
         while !self.is_at_end() {
-            // Skip stray semicolons or right braces at the top level (before and after parsing a statement)
-            while self.peek().token == crate::lexer::Token::Semicolon
-                || self.peek().token == crate::lexer::Token::RightBrace
-            {
+            // Skip stray semicolons at the top level
+            while self.check(&Token::Semicolon) {
                 self.advance();
             }
-            // If the next token is Eof, break cleanly
-            if self.peek().token == crate::lexer::Token::Eof {
+            if self.is_at_end() {
                 break;
             }
+            let from = self.current;
             // Only parse statements for valid top-level starters
-            match &self.peek().token {
-                crate::lexer::Token::Import
-                | crate::lexer::Token::Fn
-                | crate::lexer::Token::Async
-                | crate::lexer::Token::Struct
-                | crate::lexer::Token::Enum
-                | crate::lexer::Token::Let
-                | crate::lexer::Token::Const
-                | crate::lexer::Token::Identifier(_)
-                | crate::lexer::Token::Return
-                | crate::lexer::Token::If
-                | crate::lexer::Token::While
-                | crate::lexer::Token::For
-                | crate::lexer::Token::Match
-                | crate::lexer::Token::Try
-                | crate::lexer::Token::Break
-                | crate::lexer::Token::Continue => {
-                    let stmt = self.parse_statement()?;
-                    statements.push(stmt);
-                    // Debug: print the next token after parsing a statement
-
-                    // After parsing a statement, skip any stray semicolons or right braces
-                    while self.peek().token == crate::lexer::Token::Semicolon
-                        || self.peek().token == crate::lexer::Token::RightBrace
-                    {
-                        self.advance();
-                    }
-                    // If the next token is not a valid top-level starter or EOF, break
-                    match &self.peek().token {
-                        crate::lexer::Token::Import
-                        | crate::lexer::Token::Fn
-                        | crate::lexer::Token::Async
-                        | crate::lexer::Token::Struct
-                        | crate::lexer::Token::Enum
-                        | crate::lexer::Token::Let
-                        | crate::lexer::Token::Const
-                        | crate::lexer::Token::Identifier(_)
-                        | crate::lexer::Token::Return
-                        | crate::lexer::Token::If
-                        | crate::lexer::Token::While
-                        | crate::lexer::Token::For
-                        | crate::lexer::Token::Match
-                        | crate::lexer::Token::Try
-                        | crate::lexer::Token::Break
-                        | crate::lexer::Token::Continue
-                        | crate::lexer::Token::Eof => {
-                            // continue
-                        }
-                        _ => {
-                            // Unexpected token at top level, break
-                            break;
-                        }
-                    }
-                }
-                _ => {
-                    // Unexpected token at top level, break
-                    break;
+            if Self::starts_statement(&self.peek().token)
+                || matches!(self.peek().token, Token::Identifier(_))
+            {
+                match self.parse_statement() {
+                    Ok(stmt) => statements.push(stmt),
+                    Err(e) => self.recover(e, from),
                 }
+            } else {
+                let token = self.advance();
+                let error = CylError::ParseError {
+                    message: format!("Unexpected token {:?} at top level", token.token),
+                    line: token.line,
+                    column: token.column,
+                };
+                self.recover(error, from);
             }
         }
-        Ok(Program { statements })
+        if self.errors.is_empty() {
+            Ok(Program { statements })
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
     }
 }
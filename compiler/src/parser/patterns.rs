@@ -1,6 +1,158 @@
-// Pattern matching parsing logic will be moved here.
-impl super::helpers::Parser {
-    // Pattern parsing logic
-    // TODO: Move the real implementation from the old parser.rs
-    // pub fn parse_pattern(&mut self) -> Result<Pattern, CylError> { ... }
+use super::helpers::*;
+use crate::ast::*;
+use crate::error::CylError;
+use crate::lexer::Token;
+
+// Pattern parsing for match arms.
+
+impl Parser {
+    /// Recursively parse a pattern for match arms, supporting literal, qualified, tuple, and
+    /// nested patterns.
+    pub fn parse_pattern(&mut self) -> Result<Pattern, CylError> {
+        let start = self.start_span();
+        // Accept wildcard '_'
+        if self.check(&Token::Underscore) {
+            self.advance();
+            return Ok(Pattern::new(PatternKind::Wildcard, start));
+        }
+        if self.check(&Token::LeftParen) {
+            return self.parse_tuple_pattern();
+        }
+        if self.starts_literal_pattern() {
+            let literal = self.parse_unary()?;
+            return Ok(Pattern::new(PatternKind::Literal(literal), self.span_from(start)));
+        }
+        // Parse qualified names: e.g., Ok, JsonValue.Object, etc.
+        let mut path = Vec::new();
+        while let Token::Identifier(name) = &self.peek().token {
+            path.push(name.clone());
+            self.advance();
+            if self.check(&Token::Dot) {
+                self.advance();
+            } else {
+                break;
+            }
+        }
+        if path.is_empty() {
+            return Err(CylError::ParseError {
+                message: "Expected pattern identifier".to_string(),
+                line: self.peek().line,
+                column: self.peek().column,
+            });
+        }
+        // If next is '(', parse tuple/enum/constructor pattern (possibly nested)
+        if self.check(&Token::LeftParen) {
+            self.advance();
+            let mut subpatterns = Vec::new();
+            if !self.check(&Token::RightParen) {
+                loop {
+                    let subpat = self.parse_pattern()?;
+                    subpatterns.push(subpat);
+                    if !self.match_token(&Token::Comma) {
+                        break;
+                    }
+                }
+            }
+            self.consume(Token::RightParen, "Expected ')' in pattern")?;
+            Ok(Pattern::new(
+                PatternKind::TupleOrEnum(path.join("."), subpatterns),
+                self.span_from(start),
+            ))
+        } else if self.check(&Token::LeftBrace) {
+            // Struct pattern: Name { field1, field2, ... }
+            let name = path.join(".");
+            self.advance();
+            let mut fields = Vec::new();
+            let mut first = true;
+            while !self.check(&Token::RightBrace) && !self.is_at_end() {
+                if !first {
+                    // Only consume comma between fields, not after last field
+                    self.consume(Token::Comma, "Expected ',' between struct pattern fields")?;
+                }
+                first = false;
+                // Parse field: field_name [: pattern]
+                let field_start = self.start_span();
+                let field_name = match &self.peek().token {
+                    Token::Identifier(f) => {
+                        let f = f.clone();
+                        self.advance();
+                        f
+                    }
+                    _ => {
+                        return Err(CylError::ParseError {
+                            message: "Expected field name in struct pattern".to_string(),
+                            line: self.peek().line,
+                            column: self.peek().column,
+                        });
+                    }
+                };
+                let pat = if self.check(&Token::Colon) {
+                    self.advance();
+                    self.parse_pattern()?
+                } else {
+                    Pattern::new(
+                        PatternKind::Identifier(field_name.clone()),
+                        field_start,
+                    )
+                };
+                fields.push((field_name, pat));
+            }
+            self.consume(Token::RightBrace, "Expected '}' after struct pattern")?;
+            Ok(Pattern::new(
+                PatternKind::Struct { name, fields },
+                self.span_from(start),
+            ))
+        } else if path.len() == 1 {
+            Ok(Pattern::new(
+                PatternKind::Identifier(path.remove(0)),
+                self.span_from(start),
+            ))
+        } else {
+            // Qualified enum/variant without tuple: e.g., JsonValue.Null
+            Ok(Pattern::new(
+                PatternKind::TupleOrEnum(path.join("."), vec![]),
+                self.span_from(start),
+            ))
+        }
+    }
+
+    /// `(p1, p2, ...)`; a single parenthesized pattern without a trailing comma is just grouped.
+    fn parse_tuple_pattern(&mut self) -> Result<Pattern, CylError> {
+        let start = self.start_span();
+        self.consume(Token::LeftParen, "Expected '(' in tuple pattern")?;
+        let mut items = Vec::new();
+        let mut trailing_comma = false;
+        while !self.check(&Token::RightParen) && !self.is_at_end() {
+            items.push(self.parse_pattern()?);
+            trailing_comma = self.match_token(&Token::Comma);
+            if !trailing_comma {
+                break;
+            }
+        }
+        self.consume(Token::RightParen, "Expected ')' after tuple pattern")?;
+        if items.len() == 1 && !trailing_comma {
+            return Ok(items.remove(0));
+        }
+        Ok(Pattern::new(PatternKind::Tuple(items), self.span_from(start)))
+    }
+
+    /// Literal patterns: numbers (optionally negated), strings, chars, booleans and `null`.
+    fn starts_literal_pattern(&self) -> bool {
+        let is_number = |token: Option<&Token>| {
+            matches!(token, Some(Token::IntLiteral(_) | Token::FloatLiteral(_)))
+        };
+        match &self.peek().token {
+            Token::Minus => is_number(self.tokens.get(self.current + 1).map(|t| &t.token)),
+            token => {
+                is_number(Some(token))
+                    || matches!(
+                        token,
+                        Token::StringLiteral(_)
+                            | Token::CharLiteral(_)
+                            | Token::BoolLiteral(_)
+                            | Token::Null
+                    )
+            }
+        }
+    }
 }
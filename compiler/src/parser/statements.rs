@@ -13,11 +13,13 @@ impl Parser {
             // Optionally, return a Statement::NoOp or just skip
             return self.parse_statement();
         }
+        let start = self.start_span();
         match &self.peek().token {
             Token::Import => self.parse_import(),
             Token::Fn => {
                 self.advance(); // Advance past 'fn' so parse_function sees the name
-                let stmt = self.parse_function(false)?;
+                let mut stmt = self.parse_function(false)?;
+                stmt.span = self.span_from(start);
                 // Do NOT advance past RightBrace here; parse_block already consumes the function body
                 // Skip optional semicolon after function declaration (forgiveness)
                 if self.check(&Token::Semicolon) {
@@ -28,7 +30,8 @@ impl Parser {
             Token::Async => {
                 self.advance();
                 self.consume(Token::Fn, "Expected 'fn' after 'async'")?;
-                let stmt = self.parse_function(true)?;
+                let mut stmt = self.parse_function(true)?;
+                stmt.span = self.span_from(start);
                 // Skip optional semicolon after async function declaration (forgiveness)
                 if self.check(&Token::Semicolon) {
                     self.advance();
@@ -37,7 +40,8 @@ impl Parser {
             }
             Token::Struct => {
                 self.consume(Token::Struct, "Expected 'struct'")?;
-                let stmt = self.parse_struct()?;
+                let mut stmt = self.parse_struct()?;
+                stmt.span = self.span_from(start);
                 // Skip optional semicolon after struct declaration (forgiveness)
                 if self.check(&Token::Semicolon) {
                     self.advance();
@@ -46,7 +50,8 @@ impl Parser {
             }
             Token::Enum => {
                 self.consume(Token::Enum, "Expected 'enum'")?;
-                let stmt = self.parse_enum()?;
+                let mut stmt = self.parse_enum()?;
+                stmt.span = self.span_from(start);
                 // Skip optional semicolon after enum declaration (forgiveness)
                 if self.check(&Token::Semicolon) {
                     self.advance();
@@ -54,8 +59,11 @@ impl Parser {
                 Ok(stmt)
             }
             Token::Let | Token::Const => {
-                let stmt = self.parse_declare()?;
-
+                let mut stmt = self.parse_declare()?;
+                if self.check(&Token::Semicolon) {
+                    self.advance();
+                    stmt.span = self.span_from(start);
+                }
                 Ok(stmt)
             }
             Token::Return => self.parse_return(),
@@ -64,28 +72,36 @@ impl Parser {
             Token::For => self.parse_for(),
             Token::Match => {
                 self.advance();
-                self.parse_match()
+                let mut stmt = self.parse_match()?;
+                stmt.span = self.span_from(start);
+                Ok(stmt)
             }
             Token::Try => self.parse_try(),
+            Token::Throw => self.parse_throw(),
+            Token::Label(_) => self.parse_labelled_loop(),
             Token::Break => {
                 self.advance();
+                let label = self.parse_label_reference();
                 self.consume(Token::Semicolon, "Expected ';' after break")?;
-                Ok(Statement::Break)
+                Ok(Statement::new(StatementKind::Break(label), self.span_from(start)))
             }
             Token::Continue => {
                 self.advance();
+                let label = self.parse_label_reference();
                 self.consume(Token::Semicolon, "Expected ';' after continue")?;
-                Ok(Statement::Continue)
+                Ok(Statement::new(StatementKind::Continue(label), self.span_from(start)))
             }
             Token::Identifier(_) => {
+                // `x = value;` is an assignment; only annotated names declare
                 let is_decl = self
                     .tokens
                     .get(self.current + 1)
-                    .is_some_and(|t| matches!(t.token, Token::Less | Token::Colon | Token::Assign));
+                    .is_some_and(|t| matches!(t.token, Token::Less | Token::Colon));
                 if is_decl {
-                    let stmt = self.parse_declare()?;
+                    let mut stmt = self.parse_declare()?;
                     if self.check(&Token::Semicolon) {
                         self.advance();
+                        stmt.span = self.span_from(start);
                     } else if !self.check(&Token::RightBrace) {
                         return Err(CylError::ParseError {
                             message: "Expected ';' after declaration".to_string(),
@@ -98,27 +114,36 @@ impl Parser {
                     // Accept member access (dot) as valid expression statement
                     let expr = self.parse_expression()?;
                     self.consume(Token::Semicolon, "Expected ';' after expression")?;
-                    Ok(Statement::Expression(expr))
+                    Ok(Statement::new(
+                        StatementKind::Expression(expr),
+                        self.span_from(start),
+                    ))
                 }
             }
             Token::LeftBrace => {
                 // Parse a block as a statement (do not require semicolon after)
                 let block = self.parse_block()?;
-                Ok(Statement::Block(block))
+                let span = block.span;
+                Ok(Statement::new(StatementKind::Block(block), span))
             }
             _ => {
                 let expr = self.parse_expression()?;
                 self.consume(Token::Semicolon, "Expected ';' after expression")?;
-                Ok(Statement::Expression(expr))
+                Ok(Statement::new(
+                    StatementKind::Expression(expr),
+                    self.span_from(start),
+                ))
             }
         }
     }
 
     pub fn parse_declare(&mut self) -> Result<Statement, CylError> {
-        // Accept: let x = 42; const PI <float> = 3.14; x = 42; y <float> = 3.14; z: int = 1;
+        let start = self.start_span();
+        // Accept: let x = 42; let mut n = 0; const PI <float> = 3.14; y <float> = 3.14; z: int = 1;
         let (is_mutable, name) = match &self.peek().token {
             Token::Let => {
                 self.advance();
+                let is_mutable = self.match_token(&Token::Mut);
                 let name = match &self.peek().token {
                     Token::Identifier(n) => {
                         let n = n.clone();
@@ -133,7 +158,7 @@ impl Parser {
                         })
                     }
                 };
-                (true, name)
+                (is_mutable, name)
             }
             Token::Const => {
                 self.advance();
@@ -156,7 +181,7 @@ impl Parser {
             Token::Identifier(n) => {
                 let name = n.clone();
                 self.advance();
-                (true, name)
+                (false, name)
             }
             _ => {
                 return Err(CylError::ParseError {
@@ -167,279 +192,275 @@ impl Parser {
             }
         };
         // For variable declarations, treat <...> as a type annotation, not a generic
-        let var_type = if self.check(&Token::Less) {
-            self.advance();
-            let mut type_names = Vec::new();
-            let mut saw_identifier = false;
-            // Accept any sequence of tokens until '>' for type inference, only collect identifiers
-            loop {
-                match &self.peek().token {
-                    Token::Identifier(tn) => {
-                        type_names.push(tn.clone());
-                        saw_identifier = true;
-                        self.advance();
-                    }
-                    Token::Greater => {
-                        self.advance();
-                        break;
-                    }
-                    // Skip any other token (comma, whitespace, comments, etc.)
-                    _ => {
-                        self.advance();
-                    }
-                }
-            }
-            if saw_identifier {
-                if type_names.len() == 1 {
-                    Some(Type::Custom(type_names.remove(0)))
-                } else {
-                    Some(Type::Generic("_anon".to_string(), type_names))
-                }
-            } else {
-                None
-            }
+        let annotation = if self.check(&Token::Less) {
+            self.parse_angle_type()?
         } else if self.check(&Token::Colon) {
             self.advance();
-            Some(self.parse_type()?)
+            Some(self.parse_annotation()?)
         } else {
             None
         };
+        let (var_type, type_span) = annotation.unzip();
         self.consume(Token::Assign, "Expected '=' in declaration")?;
         let value = self.parse_expression()?;
-        Ok(Statement::Declare(DeclareStatement {
-            name,
-            var_type,
-            value,
-            is_mutable,
-        }))
+        Ok(Statement::new(
+            StatementKind::Declare(DeclareStatement {
+                name,
+                var_type,
+                type_span,
+                value,
+                is_mutable,
+            }),
+            self.span_from(start),
+        ))
     }
     pub fn parse_return(&mut self) -> Result<Statement, CylError> {
+        let start = self.start_span();
         self.consume(Token::Return, "Expected 'return'")?;
         if self.check(&Token::Semicolon) {
             self.advance();
-            Ok(Statement::Return(ReturnStatement { value: None }))
+            Ok(Statement::new(
+                StatementKind::Return(ReturnStatement { value: None }),
+                self.span_from(start),
+            ))
         } else {
             let expr = self.parse_expression()?;
             self.consume(Token::Semicolon, "Expected ';' after return value")?;
-            Ok(Statement::Return(ReturnStatement { value: Some(expr) }))
+            Ok(Statement::new(
+                StatementKind::Return(ReturnStatement { value: Some(expr) }),
+                self.span_from(start),
+            ))
         }
     }
     pub fn parse_if(&mut self) -> Result<Statement, CylError> {
+        let start = self.start_span();
         self.consume(Token::If, "Expected 'if'")?;
-        let condition = self.parse_expression()?;
+        let condition = self.parse_expression_stop_at_left_brace()?;
         let then_block = self.parse_block()?;
         let else_block = if self.match_token(&Token::Else) {
             if self.check(&Token::If) {
                 // else if
                 Some(Box::new(self.parse_if()?))
             } else {
-                // else { ... } -- parse as a block, wrap as Statement::BlockStatement
+                // else { ... } -- keep the whole block as a block statement
                 let block = self.parse_block()?;
-                // For now, wrap the block as a Statement::Expression with a Block expression if needed
-                // But since Statement does not have Block, just return the first statement or a dummy if empty
-                let stmt = block
-                    .statements
-                    .into_iter()
-                    .next()
-                    .unwrap_or(Statement::Break);
-                Some(Box::new(stmt))
+                let span = block.span;
+                Some(Box::new(Statement::new(StatementKind::Block(block), span)))
             }
         } else {
             None
         };
-        Ok(Statement::If(IfStatement {
-            condition,
-            then_block,
-            else_block,
-        }))
+        Ok(Statement::new(
+            StatementKind::If(IfStatement {
+                condition,
+                then_block,
+                else_block,
+            }),
+            self.span_from(start),
+        ))
     }
 
-    pub fn parse_while(&mut self) -> Result<Statement, CylError> {
-        self.consume(Token::While, "Expected 'while'")?;
-        let condition = self.parse_expression()?;
-        let body = self.parse_block()?;
-        Ok(Statement::While(WhileStatement { condition, body }))
-    }
-    pub fn parse_for(&mut self) -> Result<Statement, CylError> {
-        self.consume(Token::For, "Expected 'for'")?;
-
-        // Parse the loop variable identifier
-        let variable = match &self.peek().token {
-            Token::Identifier(name) => {
-                let name = name.clone();
-                self.advance();
-                name
-            }
+    /// `'label: while ...` or `'label: for ...`
+    fn parse_labelled_loop(&mut self) -> Result<Statement, CylError> {
+        let start = self.start_span();
+        let Token::Label(label) = self.advance().token.clone() else {
+            unreachable!("called on a label");
+        };
+        self.consume(Token::Colon, "Expected ':' after loop label")?;
+        let mut stmt = match self.peek().token {
+            Token::While => self.parse_while()?,
+            Token::For => self.parse_for()?,
             _ => {
                 return Err(CylError::ParseError {
-                    message: "Expected identifier after 'for'".to_string(),
+                    message: format!("Expected a loop after label '{label}"),
                     line: self.peek().line,
                     column: self.peek().column,
                 })
             }
         };
+        match &mut stmt.kind {
+            StatementKind::While(while_stmt) => while_stmt.label = Some(label),
+            StatementKind::For(for_stmt) => for_stmt.label = Some(label),
+            _ => unreachable!("parsed a loop"),
+        }
+        stmt.span = self.span_from(start);
+        Ok(stmt)
+    }
+
+    /// The optional `'label` after `break` or `continue`.
+    fn parse_label_reference(&mut self) -> Option<String> {
+        match &self.peek().token {
+            Token::Label(label) => {
+                let label = label.clone();
+                self.advance();
+                Some(label)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn parse_while(&mut self) -> Result<Statement, CylError> {
+        let start = self.start_span();
+        self.consume(Token::While, "Expected 'while'")?;
+        let condition = self.parse_expression_stop_at_left_brace()?;
+        let body = self.parse_block()?;
+        Ok(Statement::new(
+            StatementKind::While(WhileStatement {
+                label: None,
+                condition,
+                body,
+            }),
+            self.span_from(start),
+        ))
+    }
+    pub fn parse_for(&mut self) -> Result<Statement, CylError> {
+        let start = self.start_span();
+        self.consume(Token::For, "Expected 'for'")?;
+
+        // Parse the loop pattern: a name, `_` or a tuple of them
+        let (line, column) = (self.peek().line, self.peek().column);
+        let pattern = self.parse_pattern()?;
+        if !is_binding_pattern(&pattern) {
+            return Err(CylError::ParseError {
+                message: "Expected a name or a tuple of names after 'for'".to_string(),
+                line,
+                column,
+            });
+        }
 
         // Parse the 'in' keyword
         self.consume(Token::In, "Expected 'in' after loop variable")?;
 
-        // Parse the iterable expression
-        let iterable = self.parse_expression()?;
+        // Parse the iterable expression; its '{' starts the body
+        let iterable = self.parse_expression_stop_at_left_brace()?;
 
         // Parse the loop body
         let body = self.parse_block()?;
 
-        Ok(Statement::For(ForStatement {
-            variable,
-            iterable,
-            body,
-        }))
+        Ok(Statement::new(
+            StatementKind::For(ForStatement {
+                label: None,
+                pattern,
+                iterable,
+                body,
+            }),
+            self.span_from(start),
+        ))
     }
     pub fn parse_match(&mut self) -> Result<Statement, CylError> {
+        let start = self.start_span();
         let expr = self.parse_expression_stop_at_left_brace()?;
         // DEBUG: After parsing match subject, print current token
 
         self.consume(Token::LeftBrace, "Expected '{' after match expression")?;
-        // DEBUG: Print parser state after consuming '{'
-
-        // HARD ERROR: If next token is not Identifier or Underscore, panic
-        match &self.peek().token {
-            Token::Identifier(_) | Token::Underscore | Token::RightBrace => {}
-            other => {
-                return Err(CylError::ParseError {
-                    message: format!("BUG: Unexpected token after '{{' in match: {other:?}"),
-                    line: self.peek().line,
-                    column: self.peek().column,
-                });
-            }
-        }
         let mut arms = Vec::new();
         while !self.check(&Token::RightBrace) && !self.is_at_end() {
             // DEBUG: At start of match arms loop
 
             // DEBUG: Print the current token and position before parsing the pattern
 
+            let arm_start = self.start_span();
             let pattern = self.parse_pattern()?;
+            let guard = if self.match_token(&Token::If) {
+                Some(self.parse_expression()?)
+            } else {
+                None
+            };
             self.consume(Token::FatArrow, "Expected '=>' after pattern")?;
             let body = if self.check(&Token::LeftBrace) {
                 self.parse_block()?
             } else {
                 let value = self.parse_expression()?;
+                let span = value.span;
                 BlockStatement {
-                    statements: vec![Statement::Expression(value)],
+                    statements: vec![Statement::new(StatementKind::Expression(value), span)],
+                    span,
                 }
             };
             arms.push(MatchArm {
                 pattern,
-                guard: None,
+                guard,
                 body,
+                span: self.span_from(arm_start),
             });
             if self.check(&Token::Comma) {
                 self.advance();
             }
         }
         self.consume(Token::RightBrace, "Expected '}' after match arms")?;
-        Ok(Statement::Match(MatchStatement {
-            expression: expr,
-            arms,
-        }))
+        Ok(Statement::new(
+            StatementKind::Match(MatchStatement {
+                expression: expr,
+                arms,
+                scrutinee_type: None,
+            }),
+            self.span_from(start),
+        ))
     }
 
-    /// Recursively parse a pattern for match arms, supporting qualified, tuple, and nested patterns.
-    fn parse_pattern(&mut self) -> Result<Pattern, CylError> {
-        // DEBUG: Entering parse_pattern
-
-        // Accept wildcard '_'
-        if self.check(&Token::Underscore) {
-            self.advance();
-            return Ok(Pattern::Wildcard);
-        }
-        // Parse qualified names: e.g., Ok, JsonValue.Object, etc.
-        let mut path = Vec::new();
-        while let Token::Identifier(name) = &self.peek().token {
-            path.push(name.clone());
+    /// `try { ... } catch e: Type { ... } catch e { ... } catch { ... }`
+    pub fn parse_try(&mut self) -> Result<Statement, CylError> {
+        let start = self.start_span();
+        self.consume(Token::Try, "Expected 'try'")?;
+        let body = self.parse_block()?;
+        let mut catch_clauses = Vec::new();
+        while self.check(&Token::Catch) {
+            let clause_start = self.start_span();
             self.advance();
-            if self.check(&Token::Dot) {
-                self.advance();
-            } else {
-                break;
-            }
+            let variable = match &self.peek().token {
+                Token::Identifier(name) => {
+                    let name = name.clone();
+                    self.advance();
+                    Some(name)
+                }
+                _ => None,
+            };
+            let (exception_type, type_span) =
+                if variable.is_some() && self.match_token(&Token::Colon) {
+                    let (exception_type, span) = self.parse_annotation()?;
+                    (Some(exception_type), Some(span))
+                } else {
+                    (None, None)
+                };
+            let body = self.parse_block()?;
+            catch_clauses.push(CatchClause {
+                exception_type,
+                type_span,
+                variable,
+                body,
+                span: self.span_from(clause_start),
+            });
         }
-        if path.is_empty() {
+        if catch_clauses.is_empty() {
             return Err(CylError::ParseError {
-                message: "Expected pattern identifier".to_string(),
+                message: "Expected 'catch' after try block".to_string(),
                 line: self.peek().line,
                 column: self.peek().column,
             });
         }
-        // If next is '(', parse tuple/enum/constructor pattern (possibly nested)
-        if self.check(&Token::LeftParen) {
-            self.advance();
-            let mut subpatterns = Vec::new();
-            if !self.check(&Token::RightParen) {
-                loop {
-                    let subpat = self.parse_pattern()?;
-                    subpatterns.push(subpat);
-                    if !self.match_token(&Token::Comma) {
-                        break;
-                    }
-                }
-            }
-            self.consume(Token::RightParen, "Expected ')' in pattern")?;
-            Ok(Pattern::TupleOrEnum(path.join("."), subpatterns))
-        } else if self.check(&Token::LeftBrace) {
-            // Struct pattern: Name { field1, field2, ... }
-            let name = path.join(".");
-            self.advance();
-            let mut fields = Vec::new();
-            let mut first = true;
-            while !self.check(&Token::RightBrace) && !self.is_at_end() {
-                if !first {
-                    // Only consume comma between fields, not after last field
-                    self.consume(Token::Comma, "Expected ',' between struct pattern fields")?;
-                }
-                first = false;
-                // Parse field: field_name [: pattern]
-                let field_name = match &self.peek().token {
-                    Token::Identifier(f) => {
-                        let f = f.clone();
-                        self.advance();
-                        f
-                    }
-                    _ => {
-                        return Err(CylError::ParseError {
-                            message: "Expected field name in struct pattern".to_string(),
-                            line: self.peek().line,
-                            column: self.peek().column,
-                        });
-                    }
-                };
-                let pat = if self.check(&Token::Colon) {
-                    self.advance();
-                    self.parse_pattern()?
-                } else {
-                    Pattern::Identifier(field_name.clone())
-                };
-                fields.push((field_name, pat));
-            }
-            self.consume(Token::RightBrace, "Expected '}' after struct pattern")?;
-            // DEBUG: After parsing struct pattern, print next token
-
-            Ok(Pattern::Struct { name, fields })
-        } else if path.len() == 1 {
-            Ok(Pattern::Identifier(path.remove(0)))
-        } else {
-            // Qualified enum/variant without tuple: e.g., JsonValue.Null
-            Ok(Pattern::TupleOrEnum(path.join("."), vec![]))
-        }
+        Ok(Statement::new(
+            StatementKind::Try(TryStatement {
+                body,
+                catch_clauses,
+            }),
+            self.span_from(start),
+        ))
     }
-    pub fn parse_try(&mut self) -> Result<Statement, CylError> {
-        Err(CylError::ParseError {
-            message: "parse_try not yet implemented".to_string(),
-            line: 0,
-            column: 0,
-        })
+    pub fn parse_throw(&mut self) -> Result<Statement, CylError> {
+        let start = self.start_span();
+        self.consume(Token::Throw, "Expected 'throw'")?;
+        let value = self.parse_expression()?;
+        self.consume(Token::Semicolon, "Expected ';' after thrown value")?;
+        Ok(Statement::new(
+            StatementKind::Throw(ThrowStatement {
+                value,
+                value_type: None,
+            }),
+            self.span_from(start),
+        ))
     }
     pub fn parse_import(&mut self) -> Result<Statement, CylError> {
+        let start = self.start_span();
         self.consume(Token::Import, "Expected 'import'")?;
         let module = match &self.peek().token {
             Token::Identifier(name) => {
@@ -477,9 +498,22 @@ impl Parser {
         };
         self.consume(Token::Semicolon, "Expected ';' after import statement")?;
 
-        Ok(Statement::Import(ImportStatement {
-            module,
-            items: None,
-        }))
+        Ok(Statement::new(
+            StatementKind::Import(ImportStatement {
+                module,
+                items: None,
+            }),
+            self.span_from(start),
+        ))
+    }
+}
+
+/// Whether `pattern` always matches, binding names without testing anything,
+/// as the pattern of a `for` loop must.
+fn is_binding_pattern(pattern: &Pattern) -> bool {
+    match &pattern.kind {
+        PatternKind::Wildcard | PatternKind::Identifier(_) => true,
+        PatternKind::Tuple(items) => items.iter().all(is_binding_pattern),
+        _ => false,
     }
 }
@@ -0,0 +1,54 @@
+//! Declarations every program can use without an import.
+//!
+//! The type checker adds them to the front of each program it checks, so the
+//! interpreter and the backends see them as ordinary enums. A program that
+//! declares a type with the same name replaces the prelude's version.
+
+use crate::ast::{Program, Statement, StatementKind};
+use crate::lexer::Lexer;
+use crate::parser::helpers::Parser;
+
+pub const SOURCE: &str = "
+enum Option<T> { Some(T), None }
+enum Result<T, E> { Ok(T), Err(E) }
+";
+
+/// The prelude's declarations, parsed.
+pub fn declarations() -> Vec<Statement> {
+    let tokens = Lexer::new(SOURCE)
+        .tokenize()
+        .expect("the prelude is valid Cyl");
+    Parser::new(tokens)
+        .parse()
+        .expect("the prelude is valid Cyl")
+        .statements
+}
+
+/// Name of the prelude enum whose variant `name` can be written unqualified,
+/// as in `Some(1)` or `Err("failed")`.
+pub fn constructor_enum(name: &str) -> Option<&'static str> {
+    match name {
+        "Some" | "None" => Some("Option"),
+        "Ok" | "Err" => Some("Result"),
+        _ => None,
+    }
+}
+
+/// Put the prelude's declarations in front of `program`'s, leaving out the
+/// ones it declares itself.
+pub fn add_to(program: &mut Program) {
+    let declared: Vec<&str> = program.statements.iter().filter_map(type_name).collect();
+    let prelude: Vec<Statement> = declarations()
+        .into_iter()
+        .filter(|stmt| type_name(stmt).map_or(true, |name| !declared.contains(&name)))
+        .collect();
+    program.statements.splice(0..0, prelude);
+}
+
+fn type_name(stmt: &Statement) -> Option<&str> {
+    match &stmt.kind {
+        StatementKind::Enum(decl) => Some(&decl.name),
+        StatementKind::Struct(decl) => Some(&decl.name),
+        _ => None,
+    }
+}
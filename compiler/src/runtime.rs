@@ -0,0 +1,177 @@
+//! The C runtime in `runtime/`, shared by the native backends.
+//!
+//! `build.rs` compiles it twice: into the compiler itself, so code the
+//! Cranelift JIT compiles can call it, and into an archive embedded here that
+//! [`link_executable`] links into every executable. Values it handles use the
+//! layouts in [`crate::layout`].
+
+use crate::ast::{BinaryOperator, Expression, ExpressionKind};
+use crate::error::CylError;
+use std::path::{Path, PathBuf};
+
+/// The runtime archive, with the C `main` that calls the program.
+const ARCHIVE: &[u8] = include_bytes!(env!("CYL_RUNTIME_ARCHIVE"));
+
+/// How `cylc build` links an object file into an executable.
+#[derive(Debug, Clone, Default)]
+pub struct LinkOptions {
+    /// Link the C library statically instead of dynamically.
+    pub static_linking: bool,
+    /// Extra libraries, passed to the linker as `-l`.
+    pub libraries: Vec<String>,
+    /// Extra library search directories, passed to the linker as `-L`.
+    pub library_paths: Vec<PathBuf>,
+}
+
+/// Link `obj_path` with the runtime and the C library into the executable
+/// `output_path`, using the system C compiler as the linker.
+pub fn link_executable(
+    obj_path: &Path,
+    output_path: &Path,
+    options: &LinkOptions,
+) -> Result<(), CylError> {
+    let runtime_path = scratch_file("libcyl_runtime.a", ARCHIVE)?;
+    let result = run_linker(obj_path, &runtime_path, output_path, options);
+    std::fs::remove_file(&runtime_path).ok();
+    result
+}
+
+/// Write `contents` to a file named after `name` in the temporary directory,
+/// unique to this process.
+pub fn scratch_file(name: &str, contents: &[u8]) -> Result<PathBuf, CylError> {
+    let path = std::env::temp_dir().join(format!("cylc-{}-{name}", std::process::id()));
+    std::fs::write(&path, contents).map_err(|e| CylError::CodeGenError {
+        message: format!("Failed to write {name} for linking: {e}"),
+        span: None,
+    })?;
+    Ok(path)
+}
+
+fn run_linker(
+    obj_path: &Path,
+    runtime_path: &Path,
+    output_path: &Path,
+    options: &LinkOptions,
+) -> Result<(), CylError> {
+    use std::process::Command;
+
+    let mut cmd = Command::new("cc");
+    if options.static_linking {
+        cmd.arg("-static");
+    } else if cfg!(target_os = "linux") {
+        // Position Independent Executable, as modern distributions expect
+        cmd.arg("-pie");
+    }
+    cmd.arg("-o").arg(output_path).arg(obj_path).arg(runtime_path);
+    for path in &options.library_paths {
+        cmd.arg("-L").arg(path);
+    }
+    for library in &options.libraries {
+        cmd.arg(format!("-l{library}"));
+    }
+    // Float modulo calls `fmod`, which glibc keeps out of the C library
+    if cfg!(target_os = "linux") {
+        cmd.arg("-lm");
+    }
+
+    let output = cmd.output().map_err(|e| CylError::CodeGenError {
+        message: format!("Failed to run linker: {e}"),
+        span: None,
+    })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(CylError::CodeGenError {
+            message: format!("Linker failed: {}", stderr.trim_end()),
+            span: None,
+        });
+    }
+
+    Ok(())
+}
+
+/// Whether `expr` is a `+`, whose result, when it is a string, is a new
+/// string that only the code using it refers to. That code frees it with
+/// `cyl_string_free` once done.
+pub fn is_concatenation(expr: &Expression) -> bool {
+    matches!(
+        expr.kind,
+        ExpressionKind::BinaryOp {
+            operator: BinaryOperator::Add,
+            ..
+        }
+    )
+}
+
+/// Runtime functions by symbol, for the JIT to resolve calls to.
+#[cfg(feature = "cranelift")]
+pub fn jit_symbols() -> Vec<(&'static str, *const u8)> {
+    vec![
+        ("cyl_print_int", ffi::cyl_print_int as *const u8),
+        ("cyl_print_float", ffi::cyl_print_float as *const u8),
+        ("cyl_print_bool", ffi::cyl_print_bool as *const u8),
+        ("cyl_print_str", ffi::cyl_print_str as *const u8),
+        ("cyl_print_newline", ffi::cyl_print_newline as *const u8),
+        ("cyl_string_concat", ffi::cyl_string_concat as *const u8),
+        ("cyl_string_free", ffi::cyl_string_free as *const u8),
+        ("cyl_string_from_int", ffi::cyl_string_from_int as *const u8),
+        ("cyl_string_from_float", ffi::cyl_string_from_float as *const u8),
+        ("cyl_string_from_bool", ffi::cyl_string_from_bool as *const u8),
+        ("cyl_string_compare", ffi::cyl_string_compare as *const u8),
+        ("cyl_string_length", ffi::cyl_string_length as *const u8),
+        ("cyl_alloc", ffi::cyl_alloc as *const u8),
+        ("cyl_array_new", ffi::cyl_array_new as *const u8),
+        ("cyl_index_out_of_bounds", ffi::cyl_index_out_of_bounds as *const u8),
+        ("cyl_runtime_error", ffi::cyl_runtime_error as *const u8),
+        ("fmod", fmod as *const u8),
+    ]
+}
+
+/// The C library's `fmod` for the JIT, which cannot always find it in the
+/// running process.
+#[cfg(feature = "cranelift")]
+extern "C" fn fmod(x: f64, y: f64) -> f64 {
+    x % y
+}
+
+/// Flush the C standard output the runtime prints to, which buffers
+/// separately from Rust's.
+#[cfg(feature = "cranelift")]
+pub fn flush() {
+    // SAFETY: flushing every C stream has no preconditions
+    unsafe { libc::fflush(std::ptr::null_mut()) };
+}
+
+#[cfg(feature = "cranelift")]
+mod ffi {
+    /// A string object; see [`crate::layout`].
+    #[repr(C)]
+    pub struct CylString {
+        _private: [u8; 0],
+    }
+
+    /// An array object; see [`crate::layout`].
+    #[repr(C)]
+    pub struct CylArray {
+        _private: [u8; 0],
+    }
+
+    extern "C" {
+        pub fn cyl_print_int(value: i64);
+        pub fn cyl_print_float(value: f64);
+        pub fn cyl_print_bool(value: i8);
+        pub fn cyl_print_str(value: *const CylString);
+        pub fn cyl_print_newline();
+        pub fn cyl_string_concat(left: *const CylString, right: *const CylString) -> *const CylString;
+        pub fn cyl_string_free(value: *const CylString);
+        pub fn cyl_string_from_int(value: i64) -> *const CylString;
+        pub fn cyl_string_from_float(value: f64) -> *const CylString;
+        pub fn cyl_string_from_bool(value: i8) -> *const CylString;
+        pub fn cyl_string_compare(left: *const CylString, right: *const CylString) -> i64;
+        pub fn cyl_string_length(value: *const CylString) -> i64;
+        pub fn cyl_alloc(size: i64) -> *mut u8;
+        pub fn cyl_array_new(length: i64) -> *mut CylArray;
+        pub fn cyl_index_out_of_bounds(index: i64, length: i64, line: i64, column: i64) -> !;
+        pub fn cyl_runtime_error(message: *const CylString, line: i64, column: i64) -> !;
+    }
+}
@@ -0,0 +1,1723 @@
+use super::exhaustiveness::check_match;
+use super::types::*;
+use crate::ast::*;
+use crate::diagnostics::{Label, Suggestion};
+use crate::error::{CylError, ErrorDetails, Warning};
+use crate::prelude;
+use crate::stdlib::StdLib;
+use std::collections::{HashMap, HashSet};
+
+/// Functions provided by every backend without an import.
+const BUILTIN_FUNCTIONS: &[&str] = &["print", "println", "print_int"];
+
+/// Most instances of generic and untyped functions a program may have, which
+/// stops instances calling ever larger instances of themselves.
+const MAX_INSTANCES: usize = 256;
+
+/// Static type checker run between the parser and the backends.
+///
+/// Checking happens in three passes: declarations (structs, enums, imports and
+/// function signatures) are collected first so they can be used before they
+/// appear, then top-level statements are checked in order to build the global
+/// scope, and finally every function body is checked against its signature.
+/// Generic and untyped functions are then checked again for the argument
+/// types of each call to them, giving the instances backends compile.
+pub struct TypeChecker {
+    info: TypeInfo,
+    /// Declared struct/enum names and how many type parameters each takes.
+    type_names: HashMap<String, usize>,
+    modules: HashSet<String>,
+    scopes: Vec<HashMap<String, Type>>,
+    type_params: Vec<String>,
+    current_function: Option<String>,
+    return_type: Option<Type>,
+    /// Labels of the enclosing loops, innermost last.
+    loops: Vec<Option<String>>,
+    /// Span of the innermost node being checked; errors point here.
+    span: Span,
+    errors: Vec<CylError>,
+    warnings: Vec<Warning>,
+    /// Types of the expressions checked so far, in the program or in the
+    /// instance being checked.
+    types: ExpressionTypes,
+    /// The enum variants constructor expressions build.
+    constructors: Constructors,
+    /// The generic and untyped functions as written, with their spans.
+    templates: HashMap<String, (FunctionDeclaration, Span)>,
+    /// Instances called so far, in order: their names, the functions they
+    /// copy and their signatures.
+    requested: Vec<(String, String, FunctionSignature)>,
+}
+
+impl TypeChecker {
+    pub fn new() -> Self {
+        TypeChecker {
+            info: TypeInfo::default(),
+            type_names: HashMap::new(),
+            modules: HashSet::new(),
+            scopes: vec![HashMap::new()],
+            type_params: Vec::new(),
+            current_function: None,
+            return_type: None,
+            loops: Vec::new(),
+            span: Span::default(),
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            types: ExpressionTypes::default(),
+            constructors: Constructors::default(),
+            templates: HashMap::new(),
+            requested: Vec::new(),
+        }
+    }
+
+    /// Check `program`, resolving every type annotation in place. The
+    /// checked program starts with the declarations of the [`prelude`].
+    pub fn check_program(mut self, mut program: Program) -> Result<TypedProgram, CheckFailure> {
+        prelude::add_to(&mut program);
+        self.collect_declarations(&mut program);
+        for stmt in &program.statements {
+            if let StatementKind::Function(func) = &stmt.kind {
+                let template = self.info.functions.get(&func.name).is_some_and(|sig| {
+                    !sig.type_parameters.is_empty() || sig.parameters.contains(&Type::Dynamic)
+                });
+                if template {
+                    self.templates.insert(func.name.clone(), (func.clone(), stmt.span));
+                }
+            }
+        }
+
+        for stmt in program.statements.iter_mut() {
+            match &stmt.kind {
+                StatementKind::Function(_)
+                | StatementKind::Struct(_)
+                | StatementKind::Enum(_)
+                | StatementKind::Import(_) => {}
+                _ => self.check_statement(stmt),
+            }
+        }
+
+        for stmt in program.statements.iter_mut() {
+            if let StatementKind::Function(func) = &mut stmt.kind {
+                self.span = stmt.span;
+                self.check_function(func);
+            }
+        }
+        let instances = self.check_instances();
+
+        if self.errors.is_empty() {
+            Ok(TypedProgram {
+                program,
+                info: self.info,
+                types: self.types,
+                instances,
+                constructors: self.constructors,
+                warnings: self.warnings,
+            })
+        } else {
+            Err(CheckFailure {
+                errors: self.errors,
+                warnings: self.warnings,
+            })
+        }
+    }
+
+    /// Check the instances called so far, and those they call in turn. An
+    /// instance reports no warnings, and one that fails to check is left
+    /// out: its errors would only repeat those of the runtime checks the
+    /// interpreter makes of untyped code.
+    fn check_instances(&mut self) -> Vec<Instance> {
+        let mut instances = Vec::new();
+        let mut next = 0;
+        while let Some((name, template, signature)) = self.requested.get(next).cloned() {
+            next += 1;
+            let (mut function, span) = self.templates[&template].clone();
+            function.name = name.clone();
+            function.type_parameters.clear();
+            self.info.functions.insert(name, signature);
+
+            let outer = std::mem::take(&mut self.types);
+            let (errors, warnings) = (self.errors.len(), self.warnings.len());
+            self.span = span;
+            self.check_function(&mut function);
+            let types = std::mem::replace(&mut self.types, outer);
+            self.warnings.truncate(warnings);
+            if self.errors.len() > errors {
+                self.errors.truncate(errors);
+            } else {
+                instances.push(Instance { function, types });
+            }
+        }
+        instances
+    }
+
+    // ----- Errors -------------------------------------------------------
+
+    fn context(&self) -> String {
+        match &self.current_function {
+            Some(name) => format!(" (in function `{name}`)"),
+            None => String::new(),
+        }
+    }
+
+    fn type_error(&mut self, message: String) {
+        self.type_error_with(message, ErrorDetails::default());
+    }
+
+    fn type_error_with(&mut self, message: String, details: ErrorDetails) {
+        let message = format!("{message}{}", self.context());
+        self.errors.push(CylError::TypeError {
+            message,
+            span: Some(self.span),
+            details: Box::new(details),
+        });
+    }
+
+    fn semantic_error(&mut self, message: String) {
+        self.semantic_error_with(message, ErrorDetails::default());
+    }
+
+    fn semantic_error_with(&mut self, message: String, details: ErrorDetails) {
+        let message = format!("{message}{}", self.context());
+        self.errors.push(CylError::SemanticError {
+            message,
+            span: Some(self.span),
+            details: Box::new(details),
+        });
+    }
+
+    /// Report a name written at `span` as undefined, suggesting the defined
+    /// name `similar` to it, if there is one.
+    fn unknown_name(&mut self, message: String, span: Span, similar: Option<String>) {
+        let mut details = ErrorDetails {
+            label: "not found in this scope".to_string(),
+            ..ErrorDetails::default()
+        };
+        if let Some(similar) = similar {
+            details.suggestions.push(Suggestion {
+                span,
+                replacement: similar,
+                message: "a similar name exists".to_string(),
+            });
+        }
+        let outer = std::mem::replace(&mut self.span, span);
+        self.semantic_error_with(message, details);
+        self.span = outer;
+    }
+
+    fn warning(&mut self, code: &'static str, message: String) {
+        let message = format!("{message}{}", self.context());
+        self.warnings.push(Warning {
+            code,
+            message,
+            span: self.span,
+        });
+    }
+
+    // ----- Declarations -------------------------------------------------
+
+    fn collect_declarations(&mut self, program: &mut Program) {
+        let stdlib = StdLib::new();
+        for stmt in &program.statements {
+            self.span = stmt.span;
+            match &stmt.kind {
+                StatementKind::Struct(decl) => {
+                    self.declare_type_name(&decl.name, decl.type_parameters.len())
+                }
+                StatementKind::Enum(decl) => {
+                    self.declare_type_name(&decl.name, decl.type_parameters.len())
+                }
+                StatementKind::Import(import) => {
+                    if stdlib.modules.contains_key(&import.module) {
+                        self.modules.insert(import.module.clone());
+                    } else {
+                        self.semantic_error(format!("Unknown module `{}`", import.module));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for stmt in program.statements.iter_mut() {
+            match &mut stmt.kind {
+                StatementKind::Struct(decl) => {
+                    self.type_params = decl.type_parameters.clone();
+                    let mut seen = HashSet::new();
+                    for field in decl.fields.iter_mut() {
+                        self.span = field.span;
+                        if !seen.insert(field.name.clone()) {
+                            self.semantic_error(format!(
+                                "Field `{}` is declared more than once in struct `{}`",
+                                field.name, decl.name
+                            ));
+                        }
+                        field.field_type = self.resolve_annotation(&field.field_type, field.type_span);
+                    }
+                    self.info.structs.insert(decl.name.clone(), decl.clone());
+                }
+                StatementKind::Enum(decl) => {
+                    self.type_params = decl.type_parameters.clone();
+                    let mut seen = HashSet::new();
+                    for variant in decl.variants.iter_mut() {
+                        self.span = variant.span;
+                        if !seen.insert(variant.name.clone()) {
+                            self.semantic_error(format!(
+                                "Variant `{}` is declared more than once in enum `{}`",
+                                variant.name, decl.name
+                            ));
+                        }
+                        if let Some(fields) = variant.fields.as_mut() {
+                            for (field, span) in fields.iter_mut().zip(&variant.field_spans) {
+                                *field = self.resolve_annotation(field, *span);
+                            }
+                        }
+                    }
+                    self.info.enums.insert(decl.name.clone(), decl.clone());
+                }
+                _ => {}
+            }
+            self.type_params.clear();
+        }
+
+        for stmt in &program.statements {
+            if let StatementKind::Function(func) = &stmt.kind {
+                self.span = stmt.span;
+                if self.info.functions.contains_key(&func.name)
+                    || BUILTIN_FUNCTIONS.contains(&func.name.as_str())
+                {
+                    self.semantic_error(format!(
+                        "Function `{}` is defined more than once",
+                        func.name
+                    ));
+                    continue;
+                }
+                let signature = self.resolve_signature(func);
+                self.info.functions.insert(func.name.clone(), signature);
+            }
+        }
+    }
+
+    fn declare_type_name(&mut self, name: &str, arity: usize) {
+        if self.type_names.insert(name.to_string(), arity).is_some() {
+            self.semantic_error(format!("Type `{name}` is defined more than once"));
+        }
+    }
+
+    fn resolve_signature(&mut self, func: &FunctionDeclaration) -> FunctionSignature {
+        self.type_params = func.type_parameters.clone();
+        let func_span = self.span;
+        let parameters = func
+            .parameters
+            .iter()
+            .map(|param| {
+                self.span = param.span;
+                match (&param.param_type, &param.default_value) {
+                    (Type::Infer, Some(default)) => literal_type(default).unwrap_or(Type::Dynamic),
+                    (Type::Infer, None) => Type::Dynamic,
+                    (ty, _) => self.resolve_annotation(ty, param.type_span.unwrap_or(param.span)),
+                }
+            })
+            .collect();
+        self.span = func_span;
+        let required = func
+            .parameters
+            .iter()
+            .rposition(|p| p.default_value.is_none())
+            .map_or(0, |i| i + 1);
+        let return_type = match &func.return_type {
+            Some(ty) => self.resolve_annotation(ty, func.return_type_span.unwrap_or(func_span)),
+            None => Type::Void,
+        };
+        self.type_params.clear();
+        FunctionSignature {
+            type_parameters: func.type_parameters.clone(),
+            parameters,
+            required,
+            return_type,
+        }
+    }
+
+    /// Resolve a type annotation written at `span`, where any error about it
+    /// is reported.
+    fn resolve_annotation(&mut self, ty: &Type, span: Span) -> Type {
+        let outer = std::mem::replace(&mut self.span, span);
+        let resolved = self.resolve_type(ty);
+        self.span = outer;
+        resolved
+    }
+
+    /// Resolve a written type against the declared types and type parameters in scope.
+    fn resolve_type(&mut self, ty: &Type) -> Type {
+        match ty {
+            Type::Custom(name) => {
+                if self.type_params.contains(name) {
+                    return ty.clone();
+                }
+                match name.as_str() {
+                    "i8" | "i16" | "i32" | "i64" | "u8" | "u16" | "u32" | "u64" | "int8"
+                    | "int16" | "int32" | "int64" | "uint8" | "uint16" | "uint32" | "uint64" => {
+                        Type::Int
+                    }
+                    "f32" | "f64" | "float32" | "float64" => Type::Float,
+                    _ => match self.type_names.get(name).copied() {
+                        Some(0) => ty.clone(),
+                        Some(arity) => {
+                            self.type_error(format!(
+                                "Type `{name}` expects {arity} type argument(s) but 0 were given"
+                            ));
+                            Type::Dynamic
+                        }
+                        None => {
+                            self.type_error(format!("Unknown type `{name}`"));
+                            Type::Dynamic
+                        }
+                    },
+                }
+            }
+            Type::Generic(name, args) => {
+                let args: Vec<Type> = args.iter().map(|a| self.resolve_type(a)).collect();
+                if name == "Array" && args.len() == 1 {
+                    return Type::Array(Box::new(args[0].clone()));
+                }
+                match self.type_names.get(name).copied() {
+                    Some(arity) if arity == args.len() => Type::Generic(name.clone(), args),
+                    Some(arity) => {
+                        self.type_error(format!(
+                            "Type `{name}` expects {arity} type argument(s) but {} were given",
+                            args.len()
+                        ));
+                        Type::Dynamic
+                    }
+                    None => {
+                        self.type_error(format!("Unknown type `{name}`"));
+                        Type::Dynamic
+                    }
+                }
+            }
+            Type::Array(inner) => Type::Array(Box::new(self.resolve_type(inner))),
+            Type::Optional(inner) => Type::Optional(Box::new(self.resolve_type(inner))),
+            Type::Tuple(items) => Type::Tuple(items.iter().map(|t| self.resolve_type(t)).collect()),
+            Type::Function {
+                parameters,
+                return_type,
+            } => Type::Function {
+                parameters: parameters.iter().map(|t| self.resolve_type(t)).collect(),
+                return_type: Box::new(self.resolve_type(return_type)),
+            },
+            _ => ty.clone(),
+        }
+    }
+
+    // ----- Scopes -------------------------------------------------------
+
+    fn lookup(&self, name: &str) -> Option<&Type> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn define(&mut self, name: &str, ty: Type) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), ty);
+        }
+    }
+
+    fn is_type_param(&self, ty: &Type) -> bool {
+        matches!(ty, Type::Custom(name) if self.type_params.contains(name))
+    }
+
+    // ----- Compatibility ------------------------------------------------
+
+    /// Whether a value of type `actual` may be used where `expected` is required.
+    fn is_assignable(&self, expected: &Type, actual: &Type) -> bool {
+        if expected == actual || self.is_type_param(expected) || self.is_type_param(actual) {
+            return true;
+        }
+        match (expected, actual) {
+            (Type::Dynamic, _) | (_, Type::Dynamic) => true,
+            (Type::Optional(_), Type::Null) => true,
+            // `T?` and `Option<T>` are interchangeable
+            (Type::Generic(name, _), Type::Null) if name == "Option" => true,
+            (Type::Optional(e), Type::Generic(..)) if option_inner(actual).is_some() => {
+                self.is_assignable(e, option_inner(actual).unwrap())
+            }
+            (Type::Generic(..), Type::Optional(a)) if option_inner(expected).is_some() => {
+                self.is_assignable(option_inner(expected).unwrap(), a)
+            }
+            (Type::Optional(e), Type::Optional(a)) => self.is_assignable(e, a),
+            (Type::Optional(e), a) => self.is_assignable(e, a),
+            (Type::Array(e), Type::Array(a)) => self.is_assignable(e, a),
+            (Type::Tuple(e), Type::Tuple(a)) => {
+                e.len() == a.len() && e.iter().zip(a).all(|(e, a)| self.is_assignable(e, a))
+            }
+            (Type::Generic(en, ea), Type::Generic(an, aa)) => {
+                en == an
+                    && ea.len() == aa.len()
+                    && ea.iter().zip(aa).all(|(e, a)| self.is_assignable(e, a))
+            }
+            (Type::Generic(en, _), Type::Custom(an)) | (Type::Custom(en), Type::Generic(an, _)) => {
+                en == an
+            }
+            (
+                Type::Function {
+                    parameters: ep,
+                    return_type: er,
+                },
+                Type::Function {
+                    parameters: ap,
+                    return_type: ar,
+                },
+            ) => {
+                ep.len() == ap.len()
+                    && ep.iter().zip(ap).all(|(e, a)| self.is_assignable(a, e))
+                    && self.is_assignable(er, ar)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether values of the two types can be compared with `==`.
+    fn is_comparable(&self, left: &Type, right: &Type) -> bool {
+        self.is_assignable(left, right)
+            || self.is_assignable(right, left)
+            || (is_numeric(left) && is_numeric(right))
+    }
+
+    fn expect_type(&mut self, expected: &Type, actual: &Type, what: &str) {
+        self.expect_type_with(expected, actual, what, ErrorDetails::default());
+    }
+
+    /// Like [`Self::expect_type`], adding `details` to the error on a mismatch.
+    fn expect_type_with(
+        &mut self,
+        expected: &Type,
+        actual: &Type,
+        what: &str,
+        mut details: ErrorDetails,
+    ) {
+        if self.is_assignable(expected, actual) {
+            return;
+        }
+        details.label = format!("expected `{expected}`, found `{actual}`");
+        if is_numeric(expected) && is_numeric(actual) {
+            details
+                .help
+                .push("`int` and `float` values do not convert implicitly".to_string());
+        }
+        self.type_error_with(
+            format!("Mismatched types in {what}: expected `{expected}`, found `{actual}`"),
+            details,
+        );
+    }
+
+    // ----- Statements ---------------------------------------------------
+
+    fn check_function(&mut self, func: &mut FunctionDeclaration) {
+        let Some(signature) = self.info.functions.get(&func.name).cloned() else {
+            return;
+        };
+        self.current_function = Some(func.name.clone());
+        self.type_params = signature.type_parameters.clone();
+        self.return_type = Some(signature.return_type.clone());
+        let func_span = self.span;
+
+        self.scopes.push(HashMap::new());
+        for (param, ty) in func.parameters.iter_mut().zip(&signature.parameters) {
+            self.span = param.span;
+            if let Some(default) = &param.default_value {
+                let default_type = self.check_expression(default);
+                self.expect_type(
+                    ty,
+                    &default_type,
+                    &format!("default value of `{}`", param.name),
+                );
+            }
+            param.param_type = ty.clone();
+            self.define(&param.name, ty.clone());
+        }
+        if func.return_type.is_some() {
+            func.return_type = Some(signature.return_type.clone());
+        }
+
+        for stmt in func.body.statements.iter_mut() {
+            self.check_statement(stmt);
+        }
+        self.scopes.pop();
+
+        self.span = func_span;
+        if signature.return_type != Type::Void && !block_returns(&func.body) {
+            self.type_error(format!(
+                "Function `{}` must return a value of type `{}` on every path",
+                func.name, signature.return_type
+            ));
+        }
+
+        self.current_function = None;
+        self.return_type = None;
+        self.type_params.clear();
+    }
+
+    fn check_block(&mut self, block: &mut BlockStatement) {
+        self.scopes.push(HashMap::new());
+        for stmt in block.statements.iter_mut() {
+            self.check_statement(stmt);
+        }
+        self.scopes.pop();
+    }
+
+    fn check_statement(&mut self, stmt: &mut Statement) {
+        let outer = std::mem::replace(&mut self.span, stmt.span);
+        self.check_statement_kind(&mut stmt.kind);
+        self.span = outer;
+    }
+
+    fn check_statement_kind(&mut self, stmt: &mut StatementKind) {
+        match stmt {
+            StatementKind::Declare(decl) => self.check_declare(decl),
+            StatementKind::Expression(expr) => {
+                self.check_expression(expr);
+            }
+            StatementKind::Return(ret) => {
+                let value_type = match &ret.value {
+                    Some(expr) => self.check_expression(expr),
+                    None => Type::Void,
+                };
+                match self.return_type.clone() {
+                    Some(expected) if expected == Type::Void && value_type != Type::Void => {
+                        self.type_error(format!(
+                            "Cannot return a value of type `{value_type}` from a function returning `void`"
+                        ));
+                    }
+                    Some(expected) if expected != Type::Void && ret.value.is_none() => {
+                        self.type_error(format!(
+                            "Missing return value: expected a value of type `{expected}`"
+                        ));
+                    }
+                    Some(expected) => self.expect_type(&expected, &value_type, "return value"),
+                    None => self.semantic_error("`return` outside of a function".to_string()),
+                }
+            }
+            StatementKind::If(if_stmt) => {
+                let condition = self.check_expression(&if_stmt.condition);
+                self.expect_type(&Type::Bool, &condition, "`if` condition");
+                self.check_block(&mut if_stmt.then_block);
+                if let Some(else_block) = if_stmt.else_block.as_mut() {
+                    self.check_statement(else_block);
+                }
+            }
+            StatementKind::While(while_stmt) => {
+                let condition = self.check_expression(&while_stmt.condition);
+                self.expect_type(&Type::Bool, &condition, "`while` condition");
+                self.loops.push(while_stmt.label.clone());
+                self.check_block(&mut while_stmt.body);
+                self.loops.pop();
+            }
+            StatementKind::For(for_stmt) => {
+                let iterable = self.check_expression(&for_stmt.iterable);
+                let element = match &iterable {
+                    // `for i in n` counts from 0 to n - 1
+                    Type::Int => Type::Int,
+                    Type::Array(inner) => (**inner).clone(),
+                    Type::String => Type::Char,
+                    Type::Dynamic => Type::Dynamic,
+                    other => {
+                        self.type_error(format!("Cannot iterate over a value of type `{other}`"));
+                        Type::Dynamic
+                    }
+                };
+                self.scopes.push(HashMap::new());
+                self.check_pattern(&for_stmt.pattern, &element);
+                self.loops.push(for_stmt.label.clone());
+                self.check_block(&mut for_stmt.body);
+                self.loops.pop();
+                self.scopes.pop();
+            }
+            StatementKind::Match(match_stmt) => {
+                let errors = self.errors.len();
+                let scrutinee = match self.check_expression(&match_stmt.expression) {
+                    // `T?` values match like `Option<T>` ones
+                    Type::Optional(inner) if self.info.enums.contains_key("Option") => {
+                        Type::Generic("Option".to_string(), vec![*inner])
+                    }
+                    ty => ty,
+                };
+                for arm in match_stmt.arms.iter_mut() {
+                    self.scopes.push(HashMap::new());
+                    self.check_pattern(&arm.pattern, &scrutinee);
+                    if let Some(guard) = &arm.guard {
+                        let guard_type = self.check_expression(guard);
+                        self.expect_type(&Type::Bool, &guard_type, "match guard");
+                    }
+                    self.check_block(&mut arm.body);
+                    self.scopes.pop();
+                }
+                // Patterns that failed to check would only add noise
+                if self.errors.len() == errors {
+                    self.check_match_coverage(match_stmt, &scrutinee);
+                }
+                match_stmt.scrutinee_type = Some(scrutinee);
+            }
+            StatementKind::Throw(throw) => {
+                let value_type = self.check_expression(&throw.value);
+                if value_type == Type::Void {
+                    self.type_error("Cannot throw a value of type `void`".to_string());
+                }
+                throw.value_type = Some(value_type);
+            }
+            StatementKind::Try(try_stmt) => {
+                self.check_block(&mut try_stmt.body);
+                for clause in try_stmt.catch_clauses.iter_mut() {
+                    let exception_type = match &clause.exception_type {
+                        Some(ty) => {
+                            self.resolve_annotation(ty, clause.type_span.unwrap_or(clause.span))
+                        }
+                        None => Type::Dynamic,
+                    };
+                    if clause.exception_type.is_some() {
+                        clause.exception_type = Some(exception_type.clone());
+                    }
+                    self.scopes.push(HashMap::new());
+                    if let Some(variable) = &clause.variable {
+                        self.define(variable, exception_type);
+                    }
+                    self.check_block(&mut clause.body);
+                    self.scopes.pop();
+                }
+            }
+            StatementKind::Break(label) | StatementKind::Continue(label) => {
+                let label = label.clone();
+                let keyword = if matches!(stmt, StatementKind::Break(_)) {
+                    "break"
+                } else {
+                    "continue"
+                };
+                if self.loops.is_empty() {
+                    self.semantic_error(format!("`{keyword}` outside of a loop"));
+                } else if let Some(label) = label {
+                    if !self.loops.iter().any(|l| l.as_ref() == Some(&label)) {
+                        self.semantic_error(format!(
+                            "`{keyword} '{label}` does not name an enclosing loop"
+                        ));
+                    }
+                }
+            }
+            StatementKind::Block(block) => self.check_block(block),
+            StatementKind::Function(func) => self.semantic_error(format!(
+                "Function `{}` must be declared at the top level",
+                func.name
+            )),
+            StatementKind::Struct(decl) => self.semantic_error(format!(
+                "Struct `{}` must be declared at the top level",
+                decl.name
+            )),
+            StatementKind::Enum(decl) => self.semantic_error(format!(
+                "Enum `{}` must be declared at the top level",
+                decl.name
+            )),
+            StatementKind::Import(import) => self.semantic_error(format!(
+                "Import of `{}` must appear at the top level",
+                import.module
+            )),
+        }
+    }
+
+    fn check_declare(&mut self, decl: &mut DeclareStatement) {
+        let value_type = self.check_expression(&decl.value);
+        if value_type == Type::Void {
+            self.type_error(format!("Cannot assign a `void` value to `{}`", decl.name));
+        }
+
+        // A declaration always introduces a new binding, shadowing any outer one
+        let declared = match &decl.var_type {
+            Some(annotation) => {
+                let annotation =
+                    self.resolve_annotation(annotation, decl.type_span.unwrap_or(self.span));
+                let mut details = ErrorDetails::default();
+                if let Some(span) = decl.type_span {
+                    details
+                        .secondary_labels
+                        .push(Label::secondary(span, "expected due to this type"));
+                    if !matches!(value_type, Type::Dynamic | Type::Null | Type::Void) {
+                        details.suggestions.push(Suggestion {
+                            span,
+                            replacement: value_type.to_string(),
+                            message: format!("change the type of `{}`", decl.name),
+                        });
+                    }
+                }
+                if let (Type::Float, ExpressionKind::IntLiteral(n)) = (&annotation, &decl.value.kind)
+                {
+                    details.suggestions.push(Suggestion {
+                        span: decl.value.span,
+                        replacement: format!("{n}.0"),
+                        message: "write the value as a float".to_string(),
+                    });
+                }
+                self.expect_type_with(
+                    &annotation,
+                    &value_type,
+                    &format!("declaration of `{}`", decl.name),
+                    details,
+                );
+                annotation
+            }
+            None if value_type == Type::Null => {
+                self.type_error(format!(
+                    "Cannot infer the type of `{}` from `null`; add a type annotation",
+                    decl.name
+                ));
+                Type::Dynamic
+            }
+            None => value_type,
+        };
+        decl.var_type = Some(declared.clone());
+        self.define(&decl.name, declared);
+    }
+
+    // ----- Patterns -----------------------------------------------------
+
+    /// Warn about arms that can never run and reject matches that leave
+    /// values unhandled. Matches on `dynamic` values are checked at runtime.
+    fn check_match_coverage(&mut self, match_stmt: &MatchStatement, scrutinee: &Type) {
+        let outer = self.span;
+        let coverage = check_match(&self.info, scrutinee, &match_stmt.arms);
+        for i in coverage.unreachable {
+            self.span = match_stmt.arms[i].pattern.span;
+            self.warning(
+                "W0001",
+                "Unreachable pattern: earlier arms already match every value it matches"
+                    .to_string(),
+            );
+        }
+        if !coverage.missing.is_empty() && *scrutinee != Type::Dynamic {
+            self.span = match_stmt.expression.span;
+            let mut missing: Vec<String> =
+                coverage.missing.iter().map(|p| format!("`{p}`")).collect();
+            if coverage.more_missing {
+                missing.push("...".to_string());
+            }
+            let mut details = ErrorDetails {
+                label: format!("{} not covered", missing.join(", ")),
+                help: vec![
+                    "add arms for the missing patterns, or a `_` arm to handle the rest"
+                        .to_string(),
+                ],
+                ..ErrorDetails::default()
+            };
+            if let Some(last) = match_stmt.arms.last() {
+                // New arms go after the last one, at its indentation
+                let indent = " ".repeat(last.span.column.saturating_sub(1));
+                let patterns = if coverage.more_missing {
+                    vec!["_".to_string()]
+                } else {
+                    coverage.missing.iter().map(|p| p.to_string()).collect()
+                };
+                details.suggestions.push(Suggestion {
+                    span: Span {
+                        start: last.span.end,
+                        ..last.span
+                    },
+                    replacement: patterns
+                        .iter()
+                        .map(|pattern| format!("\n{indent}{pattern} => {{}}"))
+                        .collect(),
+                    message: "add the missing arms".to_string(),
+                });
+            }
+            self.semantic_error_with(
+                format!(
+                    "Non-exhaustive match on a value of type `{scrutinee}`: {} not covered",
+                    missing.join(", ")
+                ),
+                details,
+            );
+        }
+        self.span = outer;
+    }
+
+    /// Check `pattern` against a value of type `expected`, binding its variables in the current scope.
+    fn check_pattern(&mut self, pattern: &Pattern, expected: &Type) {
+        let outer = std::mem::replace(&mut self.span, pattern.span);
+        self.check_pattern_kind(&pattern.kind, expected);
+        self.span = outer;
+    }
+
+    fn check_pattern_kind(&mut self, pattern: &PatternKind, expected: &Type) {
+        match pattern {
+            PatternKind::Wildcard => {}
+            PatternKind::Identifier(name) => {
+                let is_variant = self.info.enum_name(expected).is_some_and(|enum_name| {
+                    self.info.enums[enum_name]
+                        .variants
+                        .iter()
+                        .any(|v| &v.name == name)
+                });
+                if is_variant {
+                    self.check_variant_pattern(name, &[], expected);
+                } else {
+                    self.define(name, expected.clone());
+                }
+            }
+            PatternKind::Literal(expr) => {
+                let literal = self.check_expression(expr);
+                if !self.is_comparable(expected, &literal) {
+                    self.type_error(format!(
+                        "Pattern of type `{literal}` cannot match a value of type `{expected}`"
+                    ));
+                }
+            }
+            PatternKind::TupleOrEnum(path, fields) => {
+                self.check_variant_pattern(path, fields, expected)
+            }
+            PatternKind::Enum { variant, fields } => {
+                self.check_variant_pattern(variant, fields, expected)
+            }
+            PatternKind::Struct { name, fields } => {
+                let struct_type = match expected {
+                    Type::Dynamic => Type::Custom(name.clone()),
+                    Type::Custom(n) | Type::Generic(n, _) if n == name => expected.clone(),
+                    _ => {
+                        self.type_error(format!(
+                            "Struct pattern `{name}` cannot match a value of type `{expected}`"
+                        ));
+                        Type::Dynamic
+                    }
+                };
+                if struct_type != Type::Dynamic && !self.info.structs.contains_key(name) {
+                    self.type_error(format!("Unknown struct `{name}` in pattern"));
+                }
+                for (field, sub) in fields {
+                    let field_type = if self.info.structs.contains_key(name) {
+                        match self.info.field_type(&struct_type, field) {
+                            Some(ty) => ty,
+                            None => {
+                                self.type_error(format!("Struct `{name}` has no field `{field}`"));
+                                Type::Dynamic
+                            }
+                        }
+                    } else {
+                        Type::Dynamic
+                    };
+                    self.check_pattern(sub, &field_type);
+                }
+            }
+            PatternKind::Tuple(items) => match expected {
+                Type::Tuple(types) if types.len() == items.len() => {
+                    for (item, ty) in items.iter().zip(types.clone()) {
+                        self.check_pattern(item, &ty);
+                    }
+                }
+                Type::Dynamic => {
+                    for item in items {
+                        self.check_pattern(item, &Type::Dynamic);
+                    }
+                }
+                _ => self.type_error(format!(
+                    "Tuple pattern with {} element(s) cannot match a value of type `{expected}`",
+                    items.len()
+                )),
+            },
+        }
+    }
+
+    fn check_variant_pattern(&mut self, path: &str, fields: &[Pattern], expected: &Type) {
+        let (qualifier, variant_name) = match path.rsplit_once('.') {
+            Some((q, v)) => (Some(q), v),
+            None => (None, path),
+        };
+
+        let enum_name = match (self.info.enum_name(expected), qualifier) {
+            (Some(name), Some(q)) if q != name => {
+                self.type_error(format!(
+                    "Pattern `{path}` cannot match a value of type `{expected}`"
+                ));
+                None
+            }
+            (Some(name), _) => Some(name.to_string()),
+            (None, Some(q)) if *expected == Type::Dynamic && self.info.enums.contains_key(q) => {
+                Some(q.to_string())
+            }
+            (None, _) if *expected == Type::Dynamic => None,
+            (None, _) => {
+                self.type_error(format!(
+                    "Pattern `{path}` cannot match a value of type `{expected}`"
+                ));
+                None
+            }
+        };
+
+        let Some(enum_name) = enum_name else {
+            for field in fields {
+                self.check_pattern(field, &Type::Dynamic);
+            }
+            return;
+        };
+
+        let decl = self.info.enums[&enum_name].clone();
+        let Some(variant) = decl.variants.iter().find(|v| v.name == variant_name) else {
+            self.type_error(format!(
+                "Enum `{enum_name}` has no variant `{variant_name}`"
+            ));
+            for field in fields {
+                self.check_pattern(field, &Type::Dynamic);
+            }
+            return;
+        };
+
+        let args = match expected {
+            Type::Generic(_, args) => args.clone(),
+            _ => Vec::new(),
+        };
+        let subst = type_arguments(&decl.type_parameters, &args);
+        let field_types = variant.fields.clone().unwrap_or_default();
+        if field_types.len() != fields.len() {
+            self.type_error(format!(
+                "Variant `{enum_name}.{variant_name}` has {} field(s) but the pattern has {}",
+                field_types.len(),
+                fields.len()
+            ));
+        }
+        for (i, field) in fields.iter().enumerate() {
+            let ty = field_types
+                .get(i)
+                .map_or(Type::Dynamic, |t| substitute(t, &subst));
+            self.check_pattern(field, &ty);
+        }
+    }
+
+    // ----- Expressions --------------------------------------------------
+
+    /// Infer the type of `expr`, reporting any errors. Erroneous
+    /// sub-expressions are given the type `dynamic` to avoid cascading errors.
+    fn check_expression(&mut self, expr: &Expression) -> Type {
+        let outer = std::mem::replace(&mut self.span, expr.span);
+        let ty = self.check_expression_kind(&expr.kind);
+        self.span = outer;
+        self.types.insert(expr, ty.clone());
+        ty
+    }
+
+    fn check_expression_kind(&mut self, expr: &ExpressionKind) -> Type {
+        match expr {
+            ExpressionKind::IntLiteral(_) => Type::Int,
+            ExpressionKind::FloatLiteral(_) => Type::Float,
+            ExpressionKind::StringLiteral(_) => Type::String,
+            ExpressionKind::BoolLiteral(_) => Type::Bool,
+            ExpressionKind::CharLiteral(_) => Type::Char,
+            ExpressionKind::Range { start, end, .. } => {
+                let start = self.check_expression(start);
+                self.expect_type(&Type::Int, &start, "range start");
+                let end = self.check_expression(end);
+                self.expect_type(&Type::Int, &end, "range end");
+                Type::Array(Box::new(Type::Int))
+            }
+            ExpressionKind::Null => Type::Null,
+            ExpressionKind::Dynamic => Type::Dynamic,
+            ExpressionKind::Identifier(name) => {
+                if let Some(ty) = self.lookup(name) {
+                    return ty.clone();
+                }
+                if let Some(sig) = self.info.functions.get(name) {
+                    return Type::Function {
+                        parameters: sig.parameters.clone(),
+                        return_type: Box::new(sig.return_type.clone()),
+                    };
+                }
+                if let Some(enum_name) = self.prelude_enum(name) {
+                    return self.check_variant_construction(enum_name, name, None);
+                }
+                let scopes = self.scopes.iter().flat_map(|scope| scope.keys());
+                let candidates = scopes.chain(self.info.functions.keys()).map(String::as_str);
+                let similar = most_similar(name, candidates.collect()).map(str::to_string);
+                self.unknown_name(format!("Undefined variable `{name}`"), self.span, similar);
+                Type::Dynamic
+            }
+            ExpressionKind::ArrayLiteral(items) => {
+                let mut element = Type::Dynamic;
+                for item in items {
+                    let ty = self.check_expression(item);
+                    if element == Type::Dynamic {
+                        element = ty;
+                    } else if !self.is_assignable(&element, &ty) {
+                        self.type_error(format!(
+                            "Array elements must share one type: expected `{element}`, found `{ty}`"
+                        ));
+                    }
+                }
+                Type::Array(Box::new(element))
+            }
+            ExpressionKind::TupleLiteral(items) => Type::Tuple(
+                items
+                    .iter()
+                    .map(|item| self.check_expression(item))
+                    .collect(),
+            ),
+            ExpressionKind::ObjectLiteral(fields) => self.check_struct_literal(fields),
+            ExpressionKind::BinaryOp {
+                left,
+                operator,
+                right,
+            } => {
+                let left = self.check_expression(left);
+                let right = self.check_expression(right);
+                self.check_binary(operator, &left, &right)
+            }
+            ExpressionKind::UnaryOp { operator, operand } => {
+                let operand = self.check_expression(operand);
+                match (operator, &operand) {
+                    (_, Type::Dynamic) => Type::Dynamic,
+                    (UnaryOperator::Await, ty) => ty.clone(),
+                    (UnaryOperator::Not, Type::Bool) => Type::Bool,
+                    (UnaryOperator::Minus | UnaryOperator::Plus, ty) if is_numeric(ty) => {
+                        ty.clone()
+                    }
+                    (UnaryOperator::BitwiseNot, Type::Int) => Type::Int,
+                    (_, ty) if self.is_type_param(ty) => Type::Dynamic,
+                    (op, ty) => {
+                        self.type_error(format!(
+                            "Cannot apply unary `{op:?}` to a value of type `{ty}`"
+                        ));
+                        Type::Dynamic
+                    }
+                }
+            }
+            ExpressionKind::Await(inner) => self.check_expression(inner),
+            ExpressionKind::Propagate(inner) => {
+                let operand = self.check_expression(inner);
+                self.check_propagate(&operand)
+            }
+            ExpressionKind::Call { callee, arguments } => self.check_call(callee, arguments),
+            ExpressionKind::MemberAccess { object, property } => {
+                if let ExpressionKind::Identifier(name) = &object.kind {
+                    if self.lookup(name).is_none() {
+                        if self.modules.contains(name) {
+                            return Type::Dynamic;
+                        }
+                        if self.info.enums.contains_key(name) {
+                            return self.check_variant_construction(name, property, None);
+                        }
+                    }
+                }
+                let object_type = self.check_expression(object);
+                self.member_type(&object_type, property)
+            }
+            ExpressionKind::IndexAccess { object, index } => {
+                let object_type = self.check_expression(object);
+                let index_type = self.check_expression(index);
+                if !matches!(index_type, Type::Int | Type::Dynamic) {
+                    self.type_error(format!("Index must be of type `int`, found `{index_type}`"));
+                }
+                match object_type {
+                    Type::Array(inner) => *inner,
+                    Type::Tuple(items) => match &index.kind {
+                        ExpressionKind::IntLiteral(i) if (*i as usize) < items.len() => {
+                            items[*i as usize].clone()
+                        }
+                        _ => {
+                            self.type_error(
+                                "Tuples can only be indexed by an in-range integer literal"
+                                    .to_string(),
+                            );
+                            Type::Dynamic
+                        }
+                    },
+                    Type::Dynamic => Type::Dynamic,
+                    other => {
+                        self.type_error(format!("Cannot index into a value of type `{other}`"));
+                        Type::Dynamic
+                    }
+                }
+            }
+            ExpressionKind::Assignment { target, value } => {
+                let value_type = self.check_expression(value);
+                let target_type = match &target.kind {
+                    ExpressionKind::Identifier(_)
+                    | ExpressionKind::MemberAccess { .. }
+                    | ExpressionKind::IndexAccess { .. } => self.check_expression(target),
+                    _ => {
+                        self.semantic_error("Invalid assignment target".to_string());
+                        Type::Dynamic
+                    }
+                };
+                self.expect_type(&target_type, &value_type, "assignment");
+                target_type
+            }
+        }
+    }
+
+    fn check_binary(&mut self, operator: &BinaryOperator, left: &Type, right: &Type) -> Type {
+        use BinaryOperator::*;
+        let dynamic = matches!(left, Type::Dynamic)
+            || matches!(right, Type::Dynamic)
+            || self.is_type_param(left)
+            || self.is_type_param(right);
+        let result = match operator {
+            Add if matches!(left, Type::String) || matches!(right, Type::String) => {
+                Some(Type::String)
+            }
+            Add | Subtract | Multiply | Divide | Modulo => match (left, right) {
+                _ if dynamic => Some(Type::Dynamic),
+                (Type::Int, Type::Int) => Some(Type::Int),
+                (Type::Float, Type::Float) => Some(Type::Float),
+                _ => None,
+            },
+            Equal | NotEqual => (dynamic || self.is_comparable(left, right)).then_some(Type::Bool),
+            Less | LessEqual | Greater | GreaterEqual => match (left, right) {
+                _ if dynamic => Some(Type::Bool),
+                (Type::Int, Type::Int)
+                | (Type::Float, Type::Float)
+                | (Type::String, Type::String)
+                | (Type::Char, Type::Char) => Some(Type::Bool),
+                _ => None,
+            },
+            And | Or => match (left, right) {
+                _ if dynamic => Some(Type::Bool),
+                (Type::Bool, Type::Bool) => Some(Type::Bool),
+                _ => None,
+            },
+            BitwiseAnd | BitwiseOr | BitwiseXor | LeftShift | RightShift => match (left, right) {
+                _ if dynamic => Some(Type::Dynamic),
+                (Type::Int, Type::Int) => Some(Type::Int),
+                _ => None,
+            },
+        };
+        result.unwrap_or_else(|| {
+            self.type_error(format!(
+                "Cannot apply `{operator:?}` to values of type `{left}` and `{right}`"
+            ));
+            Type::Dynamic
+        })
+    }
+
+    fn check_struct_literal(&mut self, fields: &HashMap<String, Expression>) -> Type {
+        let Some(ExpressionKind::StringLiteral(name)) =
+            fields.get("__struct_name__").map(|e| &e.kind)
+        else {
+            for value in fields.values() {
+                self.check_expression(value);
+            }
+            return Type::Dynamic;
+        };
+        let Some(decl) = self.info.structs.get(name).cloned() else {
+            self.type_error(format!("Unknown struct `{name}`"));
+            for (field, value) in fields {
+                if field != "__struct_name__" {
+                    self.check_expression(value);
+                }
+            }
+            return Type::Dynamic;
+        };
+
+        let mut subst = HashMap::new();
+        let mut provided: Vec<(&String, Type)> = Vec::new();
+        for (field, value) in fields {
+            if field == "__struct_name__" {
+                continue;
+            }
+            let value_type = self.check_expression(value);
+            match decl.fields.iter().find(|f| &f.name == field) {
+                Some(field_decl) => {
+                    bind(
+                        &field_decl.field_type,
+                        &value_type,
+                        &decl.type_parameters,
+                        &mut subst,
+                    );
+                    provided.push((field, value_type));
+                }
+                None => self.type_error(format!("Struct `{name}` has no field `{field}`")),
+            }
+        }
+        for field_decl in &decl.fields {
+            match provided.iter().find(|(n, _)| **n == field_decl.name) {
+                Some((_, value_type)) => {
+                    let expected = substitute(&field_decl.field_type, &subst);
+                    self.expect_type(
+                        &expected,
+                        value_type,
+                        &format!("field `{name}.{}`", field_decl.name),
+                    );
+                }
+                None => self.type_error(format!(
+                    "Missing field `{}` in literal of struct `{name}`",
+                    field_decl.name
+                )),
+            }
+        }
+
+        if decl.type_parameters.is_empty() {
+            Type::Custom(name.clone())
+        } else {
+            Type::Generic(
+                name.clone(),
+                decl.type_parameters
+                    .iter()
+                    .map(|p| subst.get(p).cloned().unwrap_or(Type::Dynamic))
+                    .collect(),
+            )
+        }
+    }
+
+    fn member_type(&mut self, object_type: &Type, property: &str) -> Type {
+        match object_type {
+            Type::Dynamic => Type::Dynamic,
+            ty if self.is_type_param(ty) => Type::Dynamic,
+            Type::Custom(name) | Type::Generic(name, _) if self.info.structs.contains_key(name) => {
+                match self.info.field_type(object_type, property) {
+                    Some(ty) => ty,
+                    None => {
+                        self.type_error(format!("Struct `{name}` has no field `{property}`"));
+                        Type::Dynamic
+                    }
+                }
+            }
+            other => {
+                self.type_error(format!("Type `{other}` has no field `{property}`"));
+                Type::Dynamic
+            }
+        }
+    }
+
+    fn check_call(&mut self, callee: &Expression, arguments: &[Expression]) -> Type {
+        let arg_types: Vec<Type> = arguments.iter().map(|a| self.check_expression(a)).collect();
+
+        match &callee.kind {
+            ExpressionKind::Identifier(name) if self.lookup(name).is_none() => {
+                if BUILTIN_FUNCTIONS.contains(&name.as_str()) {
+                    return self.check_builtin_call(name, &arg_types);
+                }
+                if let Some(signature) = self.info.functions.get(name).cloned() {
+                    let ty = self.check_signature_call(name, &signature, &arg_types);
+                    self.request_instance(name, &signature, &arg_types);
+                    return ty;
+                }
+                if let Some(enum_name) = self.prelude_enum(name) {
+                    return self.check_variant_construction(enum_name, name, Some(&arg_types));
+                }
+                let functions = self.info.functions.keys().map(String::as_str);
+                let candidates = functions.chain(BUILTIN_FUNCTIONS.iter().copied());
+                let similar = most_similar(name, candidates.collect()).map(str::to_string);
+                self.unknown_name(format!("Unknown function `{name}`"), callee.span, similar);
+                Type::Dynamic
+            }
+            ExpressionKind::MemberAccess { object, property } => {
+                if let ExpressionKind::Identifier(name) = &object.kind {
+                    if self.lookup(name).is_none() {
+                        if self.modules.contains(name) {
+                            return Type::Dynamic;
+                        }
+                        if self.info.enums.contains_key(name) {
+                            return self.check_variant_construction(
+                                name,
+                                property,
+                                Some(&arg_types),
+                            );
+                        }
+                    }
+                }
+                let receiver = self.check_expression(object);
+                self.check_method_call(&receiver, property, &arg_types)
+            }
+            _ => match self.check_expression(callee) {
+                Type::Function {
+                    parameters,
+                    return_type,
+                } => {
+                    let signature = FunctionSignature {
+                        type_parameters: Vec::new(),
+                        required: parameters.len(),
+                        parameters,
+                        return_type: *return_type,
+                    };
+                    self.check_signature_call("closure", &signature, &arg_types)
+                }
+                Type::Dynamic => Type::Dynamic,
+                other => {
+                    self.type_error(format!("Cannot call a value of type `{other}`"));
+                    Type::Dynamic
+                }
+            },
+        }
+    }
+
+    fn check_builtin_call(&mut self, name: &str, args: &[Type]) -> Type {
+        match name {
+            "print_int" => {
+                if args.len() != 1 {
+                    self.type_error(format!(
+                        "`print_int` takes 1 argument but {} were given",
+                        args.len()
+                    ));
+                } else {
+                    self.expect_type(&Type::Int, &args[0], "argument to `print_int`");
+                }
+            }
+            _ => {
+                if args.len() > 1 {
+                    self.type_error(format!(
+                        "`{name}` takes at most 1 argument but {} were given",
+                        args.len()
+                    ));
+                }
+                if args.first() == Some(&Type::Void) {
+                    self.type_error(format!("Cannot pass a `void` value to `{name}`"));
+                }
+            }
+        }
+        Type::Void
+    }
+
+    fn check_signature_call(
+        &mut self,
+        name: &str,
+        signature: &FunctionSignature,
+        args: &[Type],
+    ) -> Type {
+        if args.len() < signature.required || args.len() > signature.parameters.len() {
+            let expected = if signature.required == signature.parameters.len() {
+                signature.required.to_string()
+            } else {
+                format!("{} to {}", signature.required, signature.parameters.len())
+            };
+            self.type_error(format!(
+                "Function `{name}` takes {expected} argument(s) but {} were given",
+                args.len()
+            ));
+        }
+
+        let mut subst = HashMap::new();
+        for (param, arg) in signature.parameters.iter().zip(args) {
+            bind(param, arg, &signature.type_parameters, &mut subst);
+        }
+        for param in &signature.type_parameters {
+            subst.entry(param.clone()).or_insert(Type::Dynamic);
+        }
+        for (i, (param, arg)) in signature.parameters.iter().zip(args).enumerate() {
+            let expected = substitute(param, &subst);
+            self.expect_type(&expected, arg, &format!("argument {} to `{name}`", i + 1));
+        }
+        substitute(&signature.return_type, &subst)
+    }
+
+    /// Make the call being checked, a call to the function `name` with
+    /// arguments of the types `args`, call the instance of `name` for those
+    /// types if it is a generic or untyped function.
+    fn request_instance(&mut self, name: &str, signature: &FunctionSignature, args: &[Type]) {
+        if !self.templates.contains_key(name) {
+            return;
+        }
+        let mut subst = HashMap::new();
+        for (param, arg) in signature.parameters.iter().zip(args) {
+            bind(param, arg, &signature.type_parameters, &mut subst);
+        }
+        let parameters: Vec<Type> = signature
+            .parameters
+            .iter()
+            .zip(args.iter().map(Some).chain(std::iter::repeat(None)))
+            .map(|(param, arg)| match (param, arg) {
+                (Type::Dynamic, Some(arg)) => arg.clone(),
+                _ => substitute(param, &subst),
+            })
+            .collect();
+        let unknown = |ty: &Type| {
+            mentions(ty, &|ty| match ty {
+                Type::Dynamic | Type::Null | Type::Infer => true,
+                Type::Custom(name) => {
+                    self.type_params.contains(name) || signature.type_parameters.contains(name)
+                }
+                _ => false,
+            })
+        };
+        if parameters.iter().any(unknown) {
+            return;
+        }
+
+        let instance = format!(
+            "{name}({})",
+            parameters.iter().map(Type::to_string).collect::<Vec<_>>().join(", ")
+        );
+        if !self.requested.iter().any(|(requested, ..)| *requested == instance) {
+            if self.requested.len() == MAX_INSTANCES {
+                return;
+            }
+            for param in &signature.type_parameters {
+                subst.entry(param.clone()).or_insert(Type::Dynamic);
+            }
+            let instance_signature = FunctionSignature {
+                type_parameters: Vec::new(),
+                parameters,
+                required: signature.required,
+                return_type: substitute(&signature.return_type, &subst),
+            };
+            self.requested
+                .push((instance.clone(), name.to_string(), instance_signature));
+        }
+        self.types.insert_callee(self.span, instance);
+    }
+
+    fn check_method_call(&mut self, receiver: &Type, method: &str, args: &[Type]) -> Type {
+        match (receiver, method) {
+            (Type::Dynamic, _) => Type::Dynamic,
+            (Type::String | Type::Array(_), "len") if args.is_empty() => Type::Int,
+            (Type::Array(element), "enumerate") if args.is_empty() => Type::Array(Box::new(
+                Type::Tuple(vec![Type::Int, (**element).clone()]),
+            )),
+            (Type::String, "enumerate") if args.is_empty() => {
+                Type::Array(Box::new(Type::Tuple(vec![Type::Int, Type::Char])))
+            }
+            (ty, _) if self.is_type_param(ty) => Type::Dynamic,
+            _ => {
+                self.type_error(format!("Type `{receiver}` has no method `{method}`"));
+                Type::Dynamic
+            }
+        }
+    }
+
+    /// The prelude enum an unqualified variant name such as `Some` constructs.
+    fn prelude_enum(&self, name: &str) -> Option<&'static str> {
+        prelude::constructor_enum(name).filter(|enum_name| self.info.enums.contains_key(*enum_name))
+    }
+
+    /// Type of `operand?`: the `Ok` or `Some` value, while the `Err` or `None`
+    /// is returned from the enclosing function, which must be able to return it.
+    fn check_propagate(&mut self, operand: &Type) -> Type {
+        let Some(return_type) = self.return_type.clone() else {
+            self.semantic_error("`?` outside of a function".to_string());
+            return Type::Dynamic;
+        };
+        match operand {
+            Type::Dynamic => Type::Dynamic,
+            Type::Generic(name, args) if name == "Result" && args.len() == 2 => {
+                match &return_type {
+                    Type::Generic(name, returned) if name == "Result" && returned.len() == 2 => {
+                        if !self.is_assignable(&returned[1], &args[1]) {
+                            self.type_error(format!(
+                                "`?` cannot return an error of type `{}` from a function returning `{return_type}`",
+                                args[1]
+                            ));
+                        }
+                    }
+                    Type::Dynamic => {}
+                    _ => self.type_error(format!(
+                        "`?` on a `Result` requires the function to return a `Result`, but it returns `{return_type}`"
+                    )),
+                }
+                args[0].clone()
+            }
+            Type::Optional(inner) => {
+                self.expect_option_return(&return_type);
+                (**inner).clone()
+            }
+            _ if option_inner(operand).is_some() => {
+                self.expect_option_return(&return_type);
+                option_inner(operand).unwrap().clone()
+            }
+            other => {
+                self.type_error(format!(
+                    "`?` can only be applied to a `Result` or `Option`, found `{other}`"
+                ));
+                Type::Dynamic
+            }
+        }
+    }
+
+    fn expect_option_return(&mut self, return_type: &Type) {
+        let returns_option = matches!(return_type, Type::Optional(_) | Type::Dynamic)
+            || option_inner(return_type).is_some();
+        if !returns_option {
+            self.type_error(format!(
+                "`?` on an `Option` requires the function to return an `Option`, but it returns `{return_type}`"
+            ));
+        }
+    }
+
+    /// Check `Enum.Variant` (unit) or `Enum.Variant(args)` and return the enum type.
+    fn check_variant_construction(
+        &mut self,
+        enum_name: &str,
+        variant: &str,
+        args: Option<&[Type]>,
+    ) -> Type {
+        let decl = self.info.enums[enum_name].clone();
+        let Some(variant_decl) = decl.variants.iter().find(|v| v.name == variant) else {
+            self.type_error(format!("Enum `{enum_name}` has no variant `{variant}`"));
+            return Type::Dynamic;
+        };
+        self.constructors.insert(self.span, enum_name, variant);
+        let fields = variant_decl.fields.clone().unwrap_or_default();
+        let args = args.unwrap_or(&[]);
+        if fields.len() != args.len() {
+            self.type_error(format!(
+                "Variant `{enum_name}.{variant}` takes {} argument(s) but {} were given",
+                fields.len(),
+                args.len()
+            ));
+        }
+        let mut subst = HashMap::new();
+        for (field, arg) in fields.iter().zip(args) {
+            bind(field, arg, &decl.type_parameters, &mut subst);
+        }
+        for (field, arg) in fields.iter().zip(args) {
+            let expected = substitute(field, &subst);
+            self.expect_type(&expected, arg, &format!("field of `{enum_name}.{variant}`"));
+        }
+        if decl.type_parameters.is_empty() {
+            Type::Custom(enum_name.to_string())
+        } else {
+            Type::Generic(
+                enum_name.to_string(),
+                decl.type_parameters
+                    .iter()
+                    .map(|p| subst.get(p).cloned().unwrap_or(Type::Dynamic))
+                    .collect(),
+            )
+        }
+    }
+}
+
+impl Default for TypeChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Record what the type parameters in `param` stand for, given an argument of type `arg`.
+pub fn bind(param: &Type, arg: &Type, generics: &[String], subst: &mut HashMap<String, Type>) {
+    if matches!(arg, Type::Dynamic | Type::Null) {
+        return;
+    }
+    match (param, arg) {
+        (Type::Custom(name), _) if generics.contains(name) => {
+            subst.entry(name.clone()).or_insert_with(|| arg.clone());
+        }
+        (Type::Array(p), Type::Array(a)) | (Type::Optional(p), Type::Optional(a)) => {
+            bind(p, a, generics, subst)
+        }
+        (Type::Optional(p), a) if option_inner(a).is_some() => {
+            bind(p, option_inner(a).unwrap(), generics, subst)
+        }
+        (p, Type::Optional(a)) if option_inner(p).is_some() => {
+            bind(option_inner(p).unwrap(), a, generics, subst)
+        }
+        (Type::Optional(p), a) => bind(p, a, generics, subst),
+        (Type::Tuple(ps), Type::Tuple(args)) => {
+            for (p, a) in ps.iter().zip(args) {
+                bind(p, a, generics, subst);
+            }
+        }
+        (Type::Generic(pn, ps), Type::Generic(an, args)) if pn == an => {
+            for (p, a) in ps.iter().zip(args) {
+                bind(p, a, generics, subst);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The one of `candidates` closest to the misspelled `name`, if any is close
+/// enough to be what was meant.
+fn most_similar<'a>(name: &str, mut candidates: Vec<&'a str>) -> Option<&'a str> {
+    // Sorted first so ties resolve the same way every run
+    candidates.sort_unstable();
+    candidates.dedup();
+    let limit = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Edits between `a` and `b`, counting insertions, deletions, substitutions
+/// and swaps of adjacent characters (optimal string alignment distance).
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // distances[i][j] is the distance between the first i and j characters
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution = distances[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = substitution
+                .min(distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+/// Whether `ty` or any type it is made of satisfies `test`.
+fn mentions(ty: &Type, test: &dyn Fn(&Type) -> bool) -> bool {
+    test(ty)
+        || match ty {
+            Type::Array(inner) | Type::Optional(inner) => mentions(inner, test),
+            Type::Tuple(items) | Type::Generic(_, items) => {
+                items.iter().any(|item| mentions(item, test))
+            }
+            Type::Function {
+                parameters,
+                return_type,
+            } => parameters.iter().any(|p| mentions(p, test)) || mentions(return_type, test),
+            _ => false,
+        }
+}
+
+/// `T` when `ty` is `Option<T>`.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    match ty {
+        Type::Generic(name, args) if name == "Option" && args.len() == 1 => Some(&args[0]),
+        _ => None,
+    }
+}
+
+/// Type of a literal expression, used to infer untyped parameters from their defaults.
+fn literal_type(expr: &Expression) -> Option<Type> {
+    match &expr.kind {
+        ExpressionKind::IntLiteral(_) => Some(Type::Int),
+        ExpressionKind::FloatLiteral(_) => Some(Type::Float),
+        ExpressionKind::StringLiteral(_) => Some(Type::String),
+        ExpressionKind::BoolLiteral(_) => Some(Type::Bool),
+        ExpressionKind::CharLiteral(_) => Some(Type::Char),
+        _ => None,
+    }
+}
+
+/// Whether control can never fall off the end of `block`.
+fn block_returns(block: &BlockStatement) -> bool {
+    block.statements.iter().any(statement_returns)
+}
+
+fn statement_returns(stmt: &Statement) -> bool {
+    match &stmt.kind {
+        StatementKind::Return(_) | StatementKind::Throw(_) => true,
+        StatementKind::Block(block) => block_returns(block),
+        StatementKind::Try(try_stmt) => {
+            block_returns(&try_stmt.body)
+                && try_stmt
+                    .catch_clauses
+                    .iter()
+                    .all(|clause| block_returns(&clause.body))
+        }
+        StatementKind::If(if_stmt) => {
+            block_returns(&if_stmt.then_block)
+                && if_stmt.else_block.as_deref().is_some_and(statement_returns)
+        }
+        StatementKind::While(while_stmt) => {
+            matches!(while_stmt.condition.kind, ExpressionKind::BoolLiteral(true))
+        }
+        StatementKind::Match(match_stmt) => {
+            !match_stmt.arms.is_empty()
+                && match_stmt.arms.iter().all(|arm| block_returns(&arm.body))
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,420 @@
+//! Exhaustiveness and reachability of `match` arms.
+//!
+//! Patterns are lowered to constructors applied to sub-patterns and checked
+//! with the usefulness algorithm from Maranget's "Warnings for pattern
+//! matching": an arm is reachable if its pattern is useful after the
+//! unguarded arms above it, and a match is exhaustive if a wildcard is not
+//! useful after all of its unguarded arms. Guarded arms never count towards
+//! coverage, since their guard may fail.
+
+use super::types::{substitute, type_arguments, TypeInfo};
+use crate::ast::{Expression, ExpressionKind, MatchArm, Pattern, PatternKind, Type, UnaryOperator};
+
+/// Missing patterns listed in a non-exhaustive match error.
+const MAX_MISSING_PATTERNS: usize = 5;
+
+/// What the checker found out about one `match`.
+pub struct MatchCoverage {
+    /// Indices of arms that can never run.
+    pub unreachable: Vec<usize>,
+    /// Example patterns for values no arm matches, at most
+    /// `MAX_MISSING_PATTERNS` of them.
+    pub missing: Vec<String>,
+    /// Whether more values are missing than are listed.
+    pub more_missing: bool,
+}
+
+/// Analyse the arms of a match on a value of type `scrutinee`.
+pub fn check_match(info: &TypeInfo, scrutinee: &Type, arms: &[MatchArm]) -> MatchCoverage {
+    let mut analysis = Analysis {
+        info,
+        next_opaque: 0,
+    };
+    let types = [scrutinee.clone()];
+    let mut rows = Vec::new();
+    let mut unreachable = Vec::new();
+    for (i, arm) in arms.iter().enumerate() {
+        let row = vec![analysis.lower(&arm.pattern, scrutinee)];
+        if !analysis.useful(&rows, &row, &types) {
+            unreachable.push(i);
+        }
+        if arm.guard.is_none() {
+            rows.push(row);
+        }
+    }
+    let mut missing: Vec<String> = analysis
+        .missing(&rows, &types, MAX_MISSING_PATTERNS + 1)
+        .iter()
+        .map(|witness| analysis.display(&witness[0], scrutinee))
+        .collect();
+    let more_missing = missing.len() > MAX_MISSING_PATTERNS;
+    missing.truncate(MAX_MISSING_PATTERNS);
+    MatchCoverage {
+        unreachable,
+        missing,
+        more_missing,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Ctor {
+    /// An enum variant, by its index in the declaration.
+    Variant(usize),
+    Bool(bool),
+    /// The only constructor of a tuple or struct.
+    Single,
+    /// A literal of a type with too many values to enumerate.
+    Literal(String),
+    /// A pattern that does not fit the scrutinee's type; it matches nothing
+    /// but itself, so it neither covers nor shadows other arms.
+    Opaque(usize),
+}
+
+#[derive(Debug, Clone)]
+enum Pat {
+    Wild,
+    Ctor(Ctor, Vec<Pat>),
+}
+
+/// The values of a type, as far as matching is concerned.
+enum Space {
+    Enum {
+        name: String,
+        variants: Vec<(String, Vec<Type>)>,
+    },
+    Bool,
+    Tuple(Vec<Type>),
+    Struct {
+        name: String,
+        fields: Vec<(String, Type)>,
+    },
+    /// Numbers, strings, `dynamic` and everything else that only a
+    /// wildcard or binding can cover.
+    Open,
+}
+
+impl Space {
+    /// Every constructor of the type, or `None` when they cannot be listed.
+    fn ctors(&self) -> Option<Vec<Ctor>> {
+        match self {
+            Space::Enum { variants, .. } => Some((0..variants.len()).map(Ctor::Variant).collect()),
+            Space::Bool => Some(vec![Ctor::Bool(true), Ctor::Bool(false)]),
+            Space::Tuple(_) | Space::Struct { .. } => Some(vec![Ctor::Single]),
+            Space::Open => None,
+        }
+    }
+
+    /// Types of the sub-patterns `ctor` is applied to.
+    fn fields(&self, ctor: &Ctor) -> Vec<Type> {
+        match (self, ctor) {
+            (Space::Enum { variants, .. }, Ctor::Variant(i)) => variants[*i].1.clone(),
+            (Space::Tuple(items), Ctor::Single) => items.clone(),
+            (Space::Struct { fields, .. }, Ctor::Single) => {
+                fields.iter().map(|(_, ty)| ty.clone()).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+struct Analysis<'a> {
+    info: &'a TypeInfo,
+    next_opaque: usize,
+}
+
+impl Analysis<'_> {
+    fn space(&self, ty: &Type) -> Space {
+        let (name, args) = match ty {
+            Type::Bool => return Space::Bool,
+            Type::Tuple(items) => return Space::Tuple(items.clone()),
+            Type::Custom(name) => (name, &[][..]),
+            Type::Generic(name, args) => (name, &args[..]),
+            _ => return Space::Open,
+        };
+        if let Some(decl) = self.info.enums.get(name) {
+            let subst = type_arguments(&decl.type_parameters, args);
+            let variants = decl
+                .variants
+                .iter()
+                .map(|v| {
+                    let fields = v.fields.iter().flatten();
+                    (v.name.clone(), fields.map(|t| substitute(t, &subst)).collect())
+                })
+                .collect();
+            Space::Enum {
+                name: name.clone(),
+                variants,
+            }
+        } else if let Some(decl) = self.info.structs.get(name) {
+            let subst = type_arguments(&decl.type_parameters, args);
+            let fields = decl
+                .fields
+                .iter()
+                .map(|f| (f.name.clone(), substitute(&f.field_type, &subst)))
+                .collect();
+            Space::Struct {
+                name: name.clone(),
+                fields,
+            }
+        } else {
+            Space::Open
+        }
+    }
+
+    fn opaque(&mut self) -> Pat {
+        self.next_opaque += 1;
+        Pat::Ctor(Ctor::Opaque(self.next_opaque), Vec::new())
+    }
+
+    /// Lower `pattern`, matched against a value of type `ty`.
+    fn lower(&mut self, pattern: &Pattern, ty: &Type) -> Pat {
+        let space = self.space(ty);
+        match (&pattern.kind, &space) {
+            (PatternKind::Wildcard, _) => Pat::Wild,
+            (PatternKind::Identifier(name), Space::Enum { variants, .. }) => {
+                match variants.iter().position(|(v, _)| v == name) {
+                    Some(i) => Pat::Ctor(Ctor::Variant(i), wilds(variants[i].1.len())),
+                    None => Pat::Wild,
+                }
+            }
+            (PatternKind::Identifier(_), _) => Pat::Wild,
+            (PatternKind::Literal(expr), Space::Bool) => match expr.kind {
+                ExpressionKind::BoolLiteral(b) => Pat::Ctor(Ctor::Bool(b), Vec::new()),
+                _ => self.opaque(),
+            },
+            (PatternKind::Literal(expr), Space::Open) => match literal_key(expr) {
+                Some(key) => Pat::Ctor(Ctor::Literal(key), Vec::new()),
+                None => self.opaque(),
+            },
+            (
+                PatternKind::TupleOrEnum(path, subs)
+                | PatternKind::Enum {
+                    variant: path,
+                    fields: subs,
+                },
+                Space::Enum { name, variants },
+            ) => {
+                let (qualifier, variant) = match path.rsplit_once('.') {
+                    Some((qualifier, variant)) => (Some(qualifier), variant),
+                    None => (None, path.as_str()),
+                };
+                let index = variants.iter().position(|(v, _)| v == variant);
+                match index {
+                    Some(i) if qualifier.map_or(true, |q| q == name) => {
+                        Pat::Ctor(Ctor::Variant(i), self.lower_all(subs, &variants[i].1))
+                    }
+                    _ => self.opaque(),
+                }
+            }
+            (
+                PatternKind::Struct { name, fields },
+                Space::Struct {
+                    name: decl,
+                    fields: decl_fields,
+                },
+            ) if name == decl => {
+                let subs = decl_fields
+                    .iter()
+                    .map(|(field, ty)| match fields.iter().find(|(name, _)| name == field) {
+                        Some((_, sub)) => self.lower(sub, ty),
+                        None => Pat::Wild,
+                    })
+                    .collect();
+                Pat::Ctor(Ctor::Single, subs)
+            }
+            (PatternKind::Tuple(items), Space::Tuple(types)) if items.len() == types.len() => {
+                Pat::Ctor(Ctor::Single, self.lower_all(items, types))
+            }
+            _ => self.opaque(),
+        }
+    }
+
+    fn lower_all(&mut self, patterns: &[Pattern], types: &[Type]) -> Vec<Pat> {
+        types
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| match patterns.get(i) {
+                Some(pattern) => self.lower(pattern, ty),
+                None => Pat::Wild,
+            })
+            .collect()
+    }
+
+    /// The constructors of `space` when the heads of `rows` use all of them.
+    fn complete_ctors(&self, rows: &[Vec<Pat>], space: &Space) -> Option<Vec<Ctor>> {
+        let all = space.ctors()?;
+        let used = |ctor: &Ctor| {
+            rows.iter()
+                .any(|row| matches!(&row[0], Pat::Ctor(c, _) if c == ctor))
+        };
+        all.iter().all(used).then_some(all)
+    }
+
+    /// Whether some value matched by `row` is matched by none of `rows`.
+    fn useful(&self, rows: &[Vec<Pat>], row: &[Pat], types: &[Type]) -> bool {
+        let Some((head, tail)) = row.split_first() else {
+            return rows.is_empty();
+        };
+        let space = self.space(&types[0]);
+        match head {
+            Pat::Ctor(ctor, subs) => {
+                let fields = space.fields(ctor);
+                self.useful(
+                    &specialize(rows, ctor, fields.len()),
+                    &[subs.as_slice(), tail].concat(),
+                    &[fields.as_slice(), &types[1..]].concat(),
+                )
+            }
+            Pat::Wild => match self.complete_ctors(rows, &space) {
+                Some(all) => all.iter().any(|ctor| {
+                    let fields = space.fields(ctor);
+                    self.useful(
+                        &specialize(rows, ctor, fields.len()),
+                        &[wilds(fields.len()).as_slice(), tail].concat(),
+                        &[fields.as_slice(), &types[1..]].concat(),
+                    )
+                }),
+                None => self.useful(&default_rows(rows), tail, &types[1..]),
+            },
+        }
+    }
+
+    /// Up to `limit` rows of patterns that match values none of `rows` do.
+    fn missing(&self, rows: &[Vec<Pat>], types: &[Type], limit: usize) -> Vec<Vec<Pat>> {
+        let Some((ty, rest_types)) = types.split_first() else {
+            return if rows.is_empty() { vec![Vec::new()] } else { Vec::new() };
+        };
+        let space = self.space(ty);
+        let mut witnesses = Vec::new();
+        if let Some(all) = self.complete_ctors(rows, &space) {
+            for ctor in all {
+                let fields = space.fields(&ctor);
+                let arity = fields.len();
+                let sub_types = [fields.as_slice(), rest_types].concat();
+                let specialized = specialize(rows, &ctor, arity);
+                for witness in self.missing(&specialized, &sub_types, limit - witnesses.len()) {
+                    let (subs, rest) = witness.split_at(arity);
+                    let head = Pat::Ctor(ctor.clone(), subs.to_vec());
+                    witnesses.push([vec![head], rest.to_vec()].concat());
+                }
+                if witnesses.len() >= limit {
+                    break;
+                }
+            }
+            return witnesses;
+        }
+
+        let rest = self.missing(&default_rows(rows), rest_types, limit);
+        if rest.is_empty() {
+            return rest;
+        }
+        let heads = match space.ctors() {
+            Some(all) => all
+                .into_iter()
+                .filter(|ctor| {
+                    !rows
+                        .iter()
+                        .any(|row| matches!(&row[0], Pat::Ctor(c, _) if c == ctor))
+                })
+                .map(|ctor| {
+                    let arity = space.fields(&ctor).len();
+                    Pat::Ctor(ctor, wilds(arity))
+                })
+                .collect(),
+            None => vec![Pat::Wild],
+        };
+        for head in heads {
+            for witness in &rest {
+                if witnesses.len() >= limit {
+                    return witnesses;
+                }
+                witnesses.push([vec![head.clone()], witness.clone()].concat());
+            }
+        }
+        witnesses
+    }
+
+    /// Render `pat` as Cyl pattern syntax.
+    fn display(&self, pat: &Pat, ty: &Type) -> String {
+        let Pat::Ctor(ctor, subs) = pat else {
+            return "_".to_string();
+        };
+        let list = |types: &[Type]| {
+            subs.iter()
+                .zip(types)
+                .map(|(sub, ty)| self.display(sub, ty))
+                .collect::<Vec<_>>()
+        };
+        match (ctor, self.space(ty)) {
+            (Ctor::Variant(i), Space::Enum { name, variants }) => {
+                let (variant, types) = &variants[*i];
+                if types.is_empty() {
+                    format!("{name}.{variant}")
+                } else {
+                    format!("{name}.{variant}({})", list(types).join(", "))
+                }
+            }
+            (Ctor::Bool(b), _) => b.to_string(),
+            (Ctor::Single, Space::Tuple(types)) if types.len() == 1 => {
+                format!("({},)", list(&types)[0])
+            }
+            (Ctor::Single, Space::Tuple(types)) => format!("({})", list(&types).join(", ")),
+            (Ctor::Single, Space::Struct { name, fields }) => {
+                let types: Vec<Type> = fields.iter().map(|(_, ty)| ty.clone()).collect();
+                let fields: Vec<String> = fields
+                    .iter()
+                    .zip(list(&types))
+                    .map(|((field, _), sub)| format!("{field}: {sub}"))
+                    .collect();
+                format!("{name} {{ {} }}", fields.join(", "))
+            }
+            (Ctor::Literal(key), _) => key.clone(),
+            _ => "_".to_string(),
+        }
+    }
+}
+
+fn wilds(n: usize) -> Vec<Pat> {
+    vec![Pat::Wild; n]
+}
+
+/// Rows that match a value built with `ctor`, with the head replaced by
+/// its `arity` sub-patterns.
+fn specialize(rows: &[Vec<Pat>], ctor: &Ctor, arity: usize) -> Vec<Vec<Pat>> {
+    rows.iter()
+        .filter_map(|row| {
+            let head = match &row[0] {
+                Pat::Wild => wilds(arity),
+                Pat::Ctor(c, subs) if c == ctor => subs.clone(),
+                Pat::Ctor(..) => return None,
+            };
+            Some([head.as_slice(), &row[1..]].concat())
+        })
+        .collect()
+}
+
+/// Rows whose head matches anything, without the head.
+fn default_rows(rows: &[Vec<Pat>]) -> Vec<Vec<Pat>> {
+    rows.iter()
+        .filter(|row| matches!(row[0], Pat::Wild))
+        .map(|row| row[1..].to_vec())
+        .collect()
+}
+
+/// A key identifying the value of a literal pattern; equal literals get
+/// equal keys.
+fn literal_key(expr: &Expression) -> Option<String> {
+    match &expr.kind {
+        ExpressionKind::IntLiteral(i) => Some(i.to_string()),
+        ExpressionKind::FloatLiteral(f) => Some(format!("{f:?}")),
+        ExpressionKind::StringLiteral(s) => Some(format!("{s:?}")),
+        ExpressionKind::CharLiteral(c) => Some(format!("{c:?}")),
+        ExpressionKind::BoolLiteral(b) => Some(b.to_string()),
+        ExpressionKind::Null => Some("null".to_string()),
+        ExpressionKind::UnaryOp {
+            operator: UnaryOperator::Minus,
+            operand,
+        } => literal_key(operand).map(|key| format!("-{key}")),
+        _ => None,
+    }
+}
@@ -0,0 +1,6 @@
+mod checker;
+mod exhaustiveness;
+mod types;
+
+pub use checker::*;
+pub use types::*;
@@ -0,0 +1,248 @@
+use crate::ast::{
+    EnumDeclaration, Expression, FunctionDeclaration, Program, Span, StatementKind,
+    StructDeclaration, Type,
+};
+use crate::error::{CylError, Warning};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Resolved signature of a user-defined function.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionSignature {
+    pub type_parameters: Vec<String>,
+    pub parameters: Vec<Type>,
+    /// Minimum number of arguments a call must pass (parameters after this all have defaults).
+    pub required: usize,
+    pub return_type: Type,
+}
+
+/// Declarations collected and resolved by the type checker.
+#[derive(Debug, Clone, Default)]
+pub struct TypeInfo {
+    pub structs: HashMap<String, StructDeclaration>,
+    pub enums: HashMap<String, EnumDeclaration>,
+    pub functions: HashMap<String, FunctionSignature>,
+}
+
+/// A program that passed type checking. Every declaration, parameter and
+/// return type in `program` is resolved, and the type of every expression
+/// recorded, so backends never have to guess.
+#[derive(Debug, Clone)]
+pub struct TypedProgram {
+    pub program: Program,
+    pub info: TypeInfo,
+    /// Types of the expressions in `program`.
+    pub types: ExpressionTypes,
+    /// Copies of the generic and untyped functions for the argument types
+    /// they are called with; see [`is_template`].
+    pub instances: Vec<Instance>,
+    /// The expressions that construct enum variants.
+    pub constructors: Constructors,
+    pub warnings: Vec<Warning>,
+}
+
+/// A generic or untyped function checked again for the types of the
+/// arguments of some call, with those types for its parameters.
+#[derive(Debug, Clone)]
+pub struct Instance {
+    /// The function, renamed after its parameter types, as in `max(int, int)`.
+    pub function: FunctionDeclaration,
+    pub types: ExpressionTypes,
+}
+
+/// What the type checker resolved for the expressions of a program or an
+/// instance, keyed by the span of each expression.
+#[derive(Debug, Clone, Default)]
+pub struct ExpressionTypes {
+    types: HashMap<Span, Type>,
+    /// The instance each call to a generic or untyped function calls.
+    callees: HashMap<Span, String>,
+}
+
+/// The enum variant each constructor expression builds, keyed by the span
+/// of the `Call`, `MemberAccess` or `Identifier` naming the variant, as in
+/// `Shape.Circle(1.0)`, `Color.Red` and `None`.
+#[derive(Debug, Clone, Default)]
+pub struct Constructors {
+    variants: HashMap<Span, (String, String)>,
+}
+
+/// A program that failed type checking: its errors, and the warnings found
+/// along the way, which are still worth reporting.
+#[derive(Debug)]
+pub struct CheckFailure {
+    pub errors: Vec<CylError>,
+    pub warnings: Vec<Warning>,
+}
+
+impl From<Vec<CylError>> for CheckFailure {
+    fn from(errors: Vec<CylError>) -> Self {
+        CheckFailure {
+            errors,
+            warnings: Vec::new(),
+        }
+    }
+}
+
+impl TypedProgram {
+    /// The functions backends compile, with the types of their expressions:
+    /// every function but the generic and untyped ones, and the instances
+    /// of those.
+    pub fn functions(&self) -> impl Iterator<Item = (&FunctionDeclaration, &ExpressionTypes)> {
+        let declared = self.program.statements.iter().filter_map(|stmt| match &stmt.kind {
+            StatementKind::Function(func) if !is_template(func) => Some((func, &self.types)),
+            _ => None,
+        });
+        let instances = self
+            .instances
+            .iter()
+            .map(|instance| (&instance.function, &instance.types));
+        declared.chain(instances)
+    }
+
+    /// Types of the expressions of the function `name`, which are those of
+    /// the program unless it is an instance.
+    pub fn types_of(&self, name: &str) -> &ExpressionTypes {
+        self.instances
+            .iter()
+            .find(|instance| instance.function.name == name)
+            .map_or(&self.types, |instance| &instance.types)
+    }
+}
+
+impl ExpressionTypes {
+    /// The type of `expr`.
+    pub fn get(&self, expr: &Expression) -> Option<&Type> {
+        self.types.get(&expr.span)
+    }
+
+    /// The name of the instance the call `call` calls, if it calls a
+    /// generic or untyped function.
+    pub fn callee(&self, call: &Expression) -> Option<&str> {
+        self.callees.get(&call.span).map(String::as_str)
+    }
+
+    pub(super) fn insert(&mut self, expr: &Expression, ty: Type) {
+        self.types.insert(expr.span, ty);
+    }
+
+    pub(super) fn insert_callee(&mut self, call: Span, instance: String) {
+        self.callees.insert(call, instance);
+    }
+}
+
+impl Constructors {
+    /// The enum and the variant `expr` constructs, if it is a constructor.
+    pub fn get(&self, expr: &Expression) -> Option<(&str, &str)> {
+        self.variants
+            .get(&expr.span)
+            .map(|(enum_name, variant)| (enum_name.as_str(), variant.as_str()))
+    }
+
+    pub(super) fn insert(&mut self, expr: Span, enum_name: &str, variant: &str) {
+        self.variants.insert(expr, (enum_name.to_string(), variant.to_string()));
+    }
+}
+
+/// Whether `func` takes type parameters or parameters without a type.
+/// Backends compile only its [`Instance`]s, which have concrete types.
+pub fn is_template(func: &FunctionDeclaration) -> bool {
+    !func.type_parameters.is_empty()
+        || func.parameters.iter().any(|param| param.param_type == Type::Dynamic)
+}
+
+impl TypeInfo {
+    /// Type of `field` on a struct type, with the struct's type arguments substituted.
+    pub fn field_type(&self, struct_type: &Type, field: &str) -> Option<Type> {
+        let (name, args) = match struct_type {
+            Type::Custom(name) => (name, &[][..]),
+            Type::Generic(name, args) => (name, &args[..]),
+            _ => return None,
+        };
+        let decl = self.structs.get(name)?;
+        let field_decl = decl.fields.iter().find(|f| f.name == field)?;
+        let subst = type_arguments(&decl.type_parameters, args);
+        Some(substitute(&field_decl.field_type, &subst))
+    }
+
+    /// Name of the enum behind `ty`, if it is one.
+    pub fn enum_name<'a>(&self, ty: &'a Type) -> Option<&'a str> {
+        match ty {
+            Type::Custom(name) | Type::Generic(name, _) if self.enums.contains_key(name) => {
+                Some(name)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Pair declared type parameters with the arguments supplied for them.
+/// Missing arguments become `dynamic`.
+pub fn type_arguments(params: &[String], args: &[Type]) -> HashMap<String, Type> {
+    params
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (p.clone(), args.get(i).cloned().unwrap_or(Type::Dynamic)))
+        .collect()
+}
+
+/// Replace type parameters in `ty` according to `subst`.
+pub fn substitute(ty: &Type, subst: &HashMap<String, Type>) -> Type {
+    if subst.is_empty() {
+        return ty.clone();
+    }
+    match ty {
+        Type::Custom(name) => subst.get(name).cloned().unwrap_or_else(|| ty.clone()),
+        Type::Array(inner) => Type::Array(Box::new(substitute(inner, subst))),
+        Type::Optional(inner) => Type::Optional(Box::new(substitute(inner, subst))),
+        Type::Tuple(items) => Type::Tuple(items.iter().map(|t| substitute(t, subst)).collect()),
+        Type::Generic(name, args) => Type::Generic(
+            name.clone(),
+            args.iter().map(|t| substitute(t, subst)).collect(),
+        ),
+        Type::Function {
+            parameters,
+            return_type,
+        } => Type::Function {
+            parameters: parameters.iter().map(|t| substitute(t, subst)).collect(),
+            return_type: Box::new(substitute(return_type, subst)),
+        },
+        _ => ty.clone(),
+    }
+}
+
+pub fn is_numeric(ty: &Type) -> bool {
+    matches!(ty, Type::Int | Type::Float)
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn list(types: &[Type]) -> String {
+            types
+                .iter()
+                .map(|t| t.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Float => write!(f, "float"),
+            Type::String => write!(f, "string"),
+            Type::Bool => write!(f, "bool"),
+            Type::Char => write!(f, "char"),
+            Type::Void => write!(f, "void"),
+            Type::Dynamic => write!(f, "dynamic"),
+            Type::Null => write!(f, "null"),
+            Type::Infer => write!(f, "_"),
+            Type::Array(inner) => write!(f, "[{inner}]"),
+            Type::Tuple(items) => write!(f, "({})", list(items)),
+            Type::Custom(name) => write!(f, "{name}"),
+            Type::Generic(name, args) => write!(f, "{name}<{}>", list(args)),
+            Type::Function {
+                parameters,
+                return_type,
+            } => write!(f, "fn({}) -> {return_type}", list(parameters)),
+            Type::Optional(inner) => write!(f, "{inner}?"),
+        }
+    }
+}
@@ -0,0 +1,313 @@
+// Tests for diagnostic rendering, error codes and `cylc explain` texts
+
+use cylc::ast::Span;
+use cylc::diagnostics::{explain, Diagnostic, Label, Severity, Suggestion};
+use cylc::error::{CylError, Warning};
+use cylc::lexer::Lexer;
+use cylc::parser::helpers::Parser;
+use cylc::typeck::TypeChecker;
+
+const SOURCE: &str = "fn main() -> void {\n    let y: string = 1 + 2;\n}\n";
+
+/// The diagnostics for the errors type checking `src` finds.
+fn check_diagnostics(src: &str) -> Vec<Diagnostic> {
+    let tokens = Lexer::new(src).tokenize().expect("lexing failed");
+    let program = Parser::new(tokens).parse().expect("parsing failed");
+    let failure = TypeChecker::new()
+        .check_program(program)
+        .expect_err("expected errors");
+    failure
+        .errors
+        .iter()
+        .map(|error| Diagnostic::from_error(error, src))
+        .collect()
+}
+
+#[test]
+fn test_every_error_variant_has_an_explained_code() {
+    let errors = [
+        CylError::LexError {
+            message: String::new(),
+            line: 1,
+            column: 1,
+        },
+        CylError::ParseError {
+            message: String::new(),
+            line: 1,
+            column: 1,
+        },
+        CylError::TypeError {
+            message: String::new(),
+            span: None,
+            details: Default::default(),
+        },
+        CylError::SemanticError {
+            message: String::new(),
+            span: None,
+            details: Default::default(),
+        },
+        CylError::CodeGenError {
+            message: String::new(),
+            span: None,
+        },
+        CylError::RuntimeError {
+            message: String::new(),
+            span: None,
+            stack: Vec::new(),
+        },
+        CylError::IoError(std::io::Error::other("disk")),
+        CylError::JsonError(serde_json::from_str::<u8>("x").unwrap_err()),
+    ];
+    let mut codes: Vec<_> = errors.iter().map(|e| e.code()).collect();
+    for code in &codes {
+        let text = explain(code).unwrap_or_else(|| panic!("no explanation for {code}"));
+        assert!(text.starts_with(code));
+    }
+    codes.dedup();
+    assert_eq!(codes.len(), errors.len(), "codes must be distinct");
+    assert_eq!(explain("e0003"), explain("E0003"));
+    assert!(explain("E9999").is_none());
+}
+
+/// The indented code examples of an explanation, without the indentation.
+/// Blank lines may sit inside an example; a line of prose ends it.
+fn explanation_examples(text: &str) -> Vec<String> {
+    let mut examples = Vec::new();
+    let mut current = String::new();
+    for line in text.lines() {
+        if let Some(code) = line.strip_prefix("    ") {
+            current.push_str(code);
+            current.push('\n');
+        } else if line.is_empty() {
+            current.push('\n');
+        } else {
+            if !current.trim().is_empty() {
+                examples.push(current.trim().to_string());
+            }
+            current.clear();
+        }
+    }
+    if !current.trim().is_empty() {
+        examples.push(current.trim().to_string());
+    }
+    examples
+}
+
+#[test]
+fn test_explanation_examples_parse_as_documented() {
+    let codes = ["E0001", "E0002", "E0003", "E0004", "E0005", "E0006", "E0007", "E0008", "W0001"];
+    let mut examples = 0;
+    for code in codes {
+        for example in explanation_examples(explain(code).unwrap()) {
+            examples += 1;
+            let parsed = Lexer::new(&example)
+                .tokenize()
+                .map_err(|error| vec![error])
+                .and_then(|tokens| Parser::new(tokens).parse());
+            match code {
+                "E0001" | "E0002" => {
+                    let errors = parsed.expect_err(&format!("{code} example parses:\n{example}"));
+                    assert_eq!(errors[0].code(), code, "{example}");
+                }
+                _ => assert!(parsed.is_ok(), "{code} example does not parse:\n{example}"),
+            }
+        }
+    }
+    assert_eq!(examples, 7);
+}
+
+#[test]
+fn test_type_error_renders_with_code_location_and_marker() {
+    let error = CylError::TypeError {
+        message: "expected `string`, found `int`".to_string(),
+        span: Some(Span::new(40, 45, 2, 21)),
+        details: Default::default(),
+    };
+    let rendered = Diagnostic::from_error(&error, SOURCE).render("main.cyl", SOURCE);
+    assert!(
+        rendered.starts_with("error[E0003]: expected `string`, found `int`"),
+        "{rendered}"
+    );
+    assert!(rendered.contains("main.cyl:2:21"), "{rendered}");
+    assert!(
+        rendered.contains("    let y: string = 1 + 2;"),
+        "{rendered}"
+    );
+    assert!(rendered.contains("                    ^^^^^"), "{rendered}");
+}
+
+#[test]
+fn test_parse_error_is_located_from_line_and_column() {
+    let error = CylError::ParseError {
+        message: "Expected ';'".to_string(),
+        line: 3,
+        column: 1,
+    };
+    let diagnostic = Diagnostic::from_error(&error, SOURCE);
+    assert_eq!(diagnostic.code, "E0002");
+    assert_eq!(diagnostic.labels[0].span, Span::new(47, 48, 3, 1));
+    assert!(diagnostic
+        .render("main.cyl", SOURCE)
+        .contains("main.cyl:3:1"));
+}
+
+#[test]
+fn test_secondary_labels_notes_and_help() {
+    let rendered = Diagnostic::error("E0003", "mismatched types")
+        .with_label(Label::primary(Span::new(40, 45, 2, 21), "this is an `int`"))
+        .with_label(Label::secondary(
+            Span::new(31, 37, 2, 12),
+            "expected due to this",
+        ))
+        .with_note("strings and integers do not convert implicitly")
+        .with_help("use `dynamic` to opt out of checking")
+        .render("main.cyl", SOURCE);
+    assert!(rendered.contains("^^^^^ this is an `int`"), "{rendered}");
+    assert!(rendered.contains("------"), "{rendered}");
+    assert!(rendered.contains("expected due to this"), "{rendered}");
+    assert!(
+        rendered.contains("= note: strings and integers do not convert implicitly"),
+        "{rendered}"
+    );
+    assert!(
+        rendered.contains("= help: use `dynamic` to opt out of checking"),
+        "{rendered}"
+    );
+}
+
+#[test]
+fn test_errors_serialize_to_the_json_schema() {
+    let error = CylError::TypeError {
+        message: "expected `string`, found `int`".to_string(),
+        span: Some(Span::new(40, 45, 2, 21)),
+        details: Default::default(),
+    };
+    let json = serde_json::to_value(&error).unwrap();
+    assert_eq!(json["severity"], "error");
+    assert_eq!(json["code"], "E0003");
+    assert_eq!(json["message"], "expected `string`, found `int`");
+    assert_eq!(json["file"], serde_json::Value::Null);
+    assert_eq!(
+        (json["line"].clone(), json["column"].clone()),
+        (2.into(), 21.into())
+    );
+    assert_eq!(json["span"]["start"], 40);
+    assert_eq!(json["labels"][0]["primary"], true);
+    assert_eq!(json["suggestions"], serde_json::json!([]));
+
+    let error = CylError::ParseError {
+        message: "Expected ';'".to_string(),
+        line: 3,
+        column: 1,
+    };
+    let json = serde_json::to_value(&error).unwrap();
+    assert_eq!(json["code"], "E0002");
+    assert_eq!(json["line"], 3);
+    assert_eq!(json["span"], serde_json::Value::Null);
+}
+
+#[test]
+fn test_json_diagnostic_resolves_file_span_and_suggestions() {
+    let error = CylError::ParseError {
+        message: "Expected ';'".to_string(),
+        line: 2,
+        column: 26,
+    };
+    let json = Diagnostic::from_error(&error, SOURCE)
+        .with_suggestion(Suggestion {
+            span: Span::new(45, 45, 2, 26),
+            replacement: ";".to_string(),
+            message: "add a semicolon".to_string(),
+        })
+        .to_json(Some("main.cyl"));
+    let value = serde_json::to_value(&json).unwrap();
+    assert_eq!(value["file"], "main.cyl");
+    assert_eq!(value["span"]["start"], 45);
+    assert_eq!(value["suggestions"][0]["replacement"], ";");
+}
+
+#[test]
+fn test_warnings_render_with_their_code() {
+    let warning = Warning {
+        code: "W0001",
+        message: "Unreachable pattern".to_string(),
+        span: Span::new(24, 27, 2, 5),
+    };
+    let diagnostic = Diagnostic::from_warning(&warning);
+    let rendered = diagnostic.render("main.cyl", SOURCE);
+    assert!(
+        rendered.starts_with("warning[W0001]: Unreachable pattern"),
+        "{rendered}"
+    );
+    assert!(rendered.contains("main.cyl:2:5"), "{rendered}");
+    assert_eq!(diagnostic.to_json(None).severity, Severity::Warning);
+    assert!(explain("w0001").is_some_and(|text| text.starts_with("W0001")));
+}
+
+#[test]
+fn test_type_mismatches_label_the_types_and_suggest_fixes() {
+    let src = "fn main() -> void {\n    let ratio: float = 3;\n}\n";
+    let diagnostics = check_diagnostics(src);
+    let rendered = diagnostics[0].render("main.cyl", src);
+    assert!(rendered.contains("expected `float`, found `int`\n"), "{rendered}");
+    assert!(rendered.contains("expected due to this type"), "{rendered}");
+    assert!(
+        rendered.contains("= help: `int` and `float` values do not convert implicitly"),
+        "{rendered}"
+    );
+    assert!(
+        rendered.contains("= help: change the type of `ratio`: `int`"),
+        "{rendered}"
+    );
+    assert!(
+        rendered.contains("= help: write the value as a float: `3.0`"),
+        "{rendered}"
+    );
+    let fixes: Vec<_> = diagnostics[0]
+        .suggestions
+        .iter()
+        .map(|fix| (&src[fix.span.start..fix.span.end], fix.replacement.as_str()))
+        .collect();
+    assert_eq!(fixes, [("float", "int"), ("3", "3.0")]);
+}
+
+#[test]
+fn test_unknown_names_suggest_similar_ones() {
+    let src = "fn main() -> void {\n    let count = 1;\n    print(coutn);\n    pritn(count);\n    print(zebra);\n}\n";
+    let diagnostics = check_diagnostics(src);
+    let rendered = diagnostics[0].render("main.cyl", src);
+    assert!(rendered.contains("^^^^^ not found in this scope"), "{rendered}");
+    assert!(
+        rendered.contains("= help: a similar name exists: `count`"),
+        "{rendered}"
+    );
+    let json = diagnostics[1].to_json(Some("main.cyl"));
+    assert_eq!((json.line, json.column), (Some(4), Some(5)));
+    assert_eq!(json.suggestions[0].replacement, "print");
+    let start = src.find("pritn").unwrap();
+    assert_eq!(json.suggestions[0].span, Span::new(start, start + 5, 4, 5));
+    assert!(diagnostics[2].suggestions.is_empty(), "{:?}", diagnostics[2]);
+}
+
+#[test]
+fn test_non_exhaustive_matches_suggest_the_missing_arms() {
+    let src = "enum Shape { Circle(float), Square(float), Empty }\n\
+               fn area(s: Shape) -> float {\n    match s {\n        Shape.Circle(r) => { return r; }\n    }\n    return 0.0;\n}\n";
+    let diagnostics = check_diagnostics(src);
+    let rendered = diagnostics[0].render("main.cyl", src);
+    assert!(
+        rendered.contains("^ `Shape.Square(_)`, `Shape.Empty` not covered"),
+        "{rendered}"
+    );
+    assert!(
+        rendered.contains("= help: add the missing arms:\n        Shape.Square(_) => {}\n        Shape.Empty => {}"),
+        "{rendered}"
+    );
+    let fix = &diagnostics[0].suggestions[0];
+    assert_eq!(&src[..fix.span.start], &src[..src.find("return r; }").unwrap() + 11]);
+    assert_eq!((fix.span.line, fix.span.column), (4, 41));
+    assert_eq!(
+        fix.replacement,
+        "\n        Shape.Square(_) => {}\n        Shape.Empty => {}"
+    );
+}
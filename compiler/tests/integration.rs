@@ -33,9 +33,8 @@ pub fn compile_and_run_cyl_file(cyl_file_path: &str) -> Result<CylTestResult, Bo
     compile_and_run_cyl_file_with_backend(cyl_file_path, "interpreter")
 }
 
-/// Compile and run a Cyl program with a specific backend, returning the result
-pub fn compile_and_run_cyl_file_with_backend(cyl_file_path: &str, backend: &str) -> Result<CylTestResult, Box<dyn std::error::Error>> {
-    // Try to find the cylc binary - check both debug and release directories
+/// Find the cylc binary - check both debug and release directories
+fn find_cylc_binary() -> Result<&'static str, Box<dyn std::error::Error>> {
     let possible_binaries = [
         "../target/release/cylc",
         "../target/debug/cylc", 
@@ -49,7 +48,12 @@ pub fn compile_and_run_cyl_file_with_backend(cyl_file_path: &str, backend: &str)
             break;
         }
     }
-    let cylc_binary = cylc_binary.ok_or("Could not find cylc binary in target/debug or target/release")?;
+    Ok(cylc_binary.ok_or("Could not find cylc binary in target/debug or target/release")?)
+}
+
+/// Compile and run a Cyl program with a specific backend, returning the result
+pub fn compile_and_run_cyl_file_with_backend(cyl_file_path: &str, backend: &str) -> Result<CylTestResult, Box<dyn std::error::Error>> {
+    let cylc_binary = find_cylc_binary()?;
 
     // Set up environment for subprocess
     let python_lib = "/opt/homebrew/opt/python@3.11/lib";
@@ -59,7 +63,7 @@ pub fn compile_and_run_cyl_file_with_backend(cyl_file_path: &str, backend: &str)
         .arg("--backend")
         .arg(backend)
         .arg("--quiet")
-        .arg(&format!("../{}", cyl_file_path));
+        .arg(format!("../{}", cyl_file_path));
 
     // Set env vars for Homebrew Python
     cmd.env("DYLD_LIBRARY_PATH", python_lib)
@@ -104,7 +108,7 @@ pub fn compile_and_run_cyl_file_with_backend(cyl_file_path: &str, backend: &str)
 fn discover_cyl_files(dir_path: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
     let mut cyl_files = Vec::new();
     
-    let entries = fs::read_dir(&format!("../{}", dir_path))?;
+    let entries = fs::read_dir(format!("../{}", dir_path))?;
     for entry in entries {
         let entry = entry?;
         let path = entry.path();
@@ -271,6 +275,602 @@ mod tests {
         assert_eq!(result.stdout.trim(), expected);
     }
 
+    #[test]
+    fn test_functions_fixture() {
+        ensure_dyld_library_path();
+        let result = compile_and_run_cyl_file_with_backend("tests/fixtures/valid/functions_test.cyl", "interpreter")
+            .expect("Failed to run functions_test.cyl");
+        assert!(result.success(), "Functions test should succeed: {:?}", result);
+        let expected = "49\n3\n7\n3628800";
+        assert_eq!(result.stdout.trim(), expected);
+    }
+
+    #[test]
+    fn test_enum_fixture() {
+        ensure_dyld_library_path();
+        let result = compile_and_run_cyl_file_with_backend("tests/fixtures/valid/enum_test.cyl", "interpreter")
+            .expect("Failed to run enum_test.cyl");
+        assert!(result.success(), "Enum test should succeed: {:?}", result);
+        let expected = "12\n6\n16\n0\n2\n3\nShape.Rect(2, 3)";
+        assert_eq!(result.stdout.trim(), expected);
+    }
+
+    /// Build a fixture to an executable with the Cranelift backend, run it,
+    /// and compare its output with the interpreter's.
+    fn assert_builds_with_cranelift(fixture: &str) {
+        let dir = tempfile::tempdir().unwrap();
+        let executable = dir.path().join(Path::new(fixture).file_stem().unwrap());
+        let output = Command::new(find_cylc_binary().unwrap())
+            .args(["build", "--backend", "cranelift", "-o"])
+            .arg(&executable)
+            .arg(format!("../{fixture}"))
+            .output()
+            .expect("Failed to run cylc build");
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        let run = Command::new(&executable).output().expect("Failed to run the executable");
+        assert!(run.status.success(), "{run:?}");
+        let expected = compile_and_run_cyl_file(fixture).unwrap();
+        assert_eq!(String::from_utf8_lossy(&run.stdout), expected.stdout, "Executable output differs for {fixture}");
+    }
+
+    #[test]
+    fn test_enum_fixture_builds_with_cranelift() {
+        ensure_dyld_library_path();
+        assert_builds_with_cranelift("tests/fixtures/valid/enum_test.cyl");
+    }
+
+    /// Build a fixture to an executable with the LLVM backend, run it, and
+    /// compare its output with the interpreter's. This uses the binary Cargo
+    /// built for this test run, since only that one has the `llvm` feature.
+    #[cfg(feature = "llvm")]
+    fn assert_builds_with_llvm(fixture: &str) {
+        let dir = tempfile::tempdir().unwrap();
+        let executable = dir.path().join(Path::new(fixture).file_stem().unwrap());
+        let output = Command::new(env!("CARGO_BIN_EXE_cylc"))
+            .args(["build", "--backend", "llvm", "-o"])
+            .arg(&executable)
+            .arg(format!("../{fixture}"))
+            .output()
+            .expect("Failed to run cylc build");
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        let run = Command::new(&executable).output().expect("Failed to run the executable");
+        assert!(run.status.success(), "{run:?}");
+        let expected = compile_and_run_cyl_file(fixture).unwrap();
+        assert_eq!(String::from_utf8_lossy(&run.stdout), expected.stdout, "Executable output differs for {fixture}");
+    }
+
+    #[cfg(feature = "llvm")]
+    #[test]
+    fn test_try_catch_fixture_builds_with_llvm() {
+        ensure_dyld_library_path();
+        assert_builds_with_llvm("tests/fixtures/valid/try_catch_test.cyl");
+    }
+
+    #[cfg(feature = "llvm")]
+    #[test]
+    fn test_match_return_fixture_builds_with_llvm() {
+        ensure_dyld_library_path();
+        assert_builds_with_llvm("tests/fixtures/valid/match_return_test.cyl");
+    }
+
+    #[cfg(feature = "llvm")]
+    #[test]
+    fn test_generics_fixture_builds_with_llvm() {
+        ensure_dyld_library_path();
+        assert_builds_with_llvm("tests/fixtures/valid/generics_test.cyl");
+    }
+
+    #[test]
+    fn test_cranelift_build_emits_object_files() {
+        ensure_dyld_library_path();
+        let dir = tempfile::tempdir().unwrap();
+        let object = dir.path().join("enum_test.o");
+        let output = Command::new(find_cylc_binary().unwrap())
+            .args(["build", "--backend", "cranelift", "--emit", "obj", "-o"])
+            .arg(&object)
+            .arg("../tests/fixtures/valid/enum_test.cyl")
+            .output()
+            .expect("Failed to run cylc build");
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        assert!(object.exists());
+    }
+
+    #[test]
+    fn test_cranelift_build_links_a_runnable_executable() {
+        ensure_dyld_library_path();
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("exit_cleanly.cyl");
+        fs::write(
+            &source,
+            "fn square(n: int) -> int { return n * n; }
+             fn main() -> void { let area = 3 * 4; }",
+        )
+        .unwrap();
+        let executable = dir.path().join("exit_cleanly");
+        let output = Command::new(find_cylc_binary().unwrap())
+            .args(["build", "-l", "m", "-L"])
+            .arg(dir.path())
+            .arg("-o")
+            .arg(&executable)
+            .arg(&source)
+            .output()
+            .expect("Failed to run cylc build");
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        let run = Command::new(&executable).output().expect("Failed to run the executable");
+        assert!(run.status.success(), "{run:?}");
+        assert!(run.stdout.is_empty());
+    }
+
+    #[test]
+    fn test_cranelift_build_reports_linker_errors() {
+        ensure_dyld_library_path();
+        let dir = tempfile::tempdir().unwrap();
+        let output = Command::new(find_cylc_binary().unwrap())
+            .args(["build", "-l", "cyl_no_such_library", "-o"])
+            .arg(dir.path().join("never_linked"))
+            .arg("../tests/fixtures/valid/enum_test.cyl")
+            .output()
+            .expect("Failed to run cylc build");
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("Linker failed"), "{stderr}");
+        assert!(stderr.contains("cyl_no_such_library"), "{stderr}");
+        assert!(!dir.path().join("never_linked").exists());
+    }
+
+    #[test]
+    fn test_cranelift_run_executes_main_with_the_jit() {
+        ensure_dyld_library_path();
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("jit_main.cyl");
+        fs::write(
+            &source,
+            "enum Shape { Circle(int), Empty }
+             fn main() {
+                 match Shape.Circle(3) {
+                     Shape.Circle(r) => { let area = 3 * r * r; }
+                     Shape.Empty => {}
+                 }
+             }",
+        )
+        .unwrap();
+        let output = Command::new(find_cylc_binary().unwrap())
+            .args(["run", "--backend", "cranelift"])
+            .arg(&source)
+            .output()
+            .expect("Failed to run cylc run");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success(), "{stderr}");
+        assert!(!stderr.contains("falling back"), "{stderr}");
+        assert!(String::from_utf8_lossy(&output.stdout).contains("Successfully compiled with Cranelift!"));
+    }
+
+    /// Run `source` with `cylc run --backend cranelift`, asserting that the
+    /// JIT ran it rather than the interpreter.
+    fn run_on_cranelift_jit(source: &str) -> std::process::Output {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("jit.cyl");
+        fs::write(&path, source).unwrap();
+        let output = Command::new(find_cylc_binary().unwrap())
+            .args(["run", "--backend", "cranelift"])
+            .arg(&path)
+            .output()
+            .expect("Failed to run cylc run");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!stderr.contains("falling back"), "{stderr}");
+        output
+    }
+
+    #[test]
+    fn test_cranelift_run_lowers_loops_and_operators() {
+        ensure_dyld_library_path();
+        // A wrong result divides by zero, which traps
+        let output = run_on_cranelift_jit(
+            "fn main() {
+                 let mut total = 0;
+                 'outer: for i in 1..=5 {
+                     for j in 0..i {
+                         if j == 3 { continue 'outer; }
+                         total = total + i * j;
+                     }
+                 }
+                 let mut k = 0;
+                 while true {
+                     k = k + 1;
+                     if k < 10 { continue; }
+                     break;
+                 }
+                 for _ in 3 { k = k - 1; }
+                 if total != 38 || k != 7 { let fail = 1 / 0; }
+                 if 7.5 % 2.0 != 1.5 || -7 % 3 != -1 { let fail = 1 / 0; }
+                 if !(0.1 + 0.2 == 0.3) || 2.5 >= 3.0 { let fail = 1 / 0; }
+             }",
+        );
+        assert!(output.status.success(), "{output:?}");
+    }
+
+    #[test]
+    fn test_cranelift_run_ends_inclusive_ranges_at_the_largest_int() {
+        ensure_dyld_library_path();
+        let output = run_on_cranelift_jit(
+            "fn main() {
+                 for i in 9223372036854775806..=9223372036854775807 {
+                     print_int(i);
+                 }
+                 for i in 5..=4 {
+                     print_int(i);
+                 }
+             }",
+        );
+        assert!(output.status.success(), "{output:?}");
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.ends_with("9223372036854775806\n9223372036854775807\n"), "{stdout}");
+    }
+
+    #[test]
+    fn test_cranelift_run_reports_integer_overflow() {
+        ensure_dyld_library_path();
+        let output = run_on_cranelift_jit(
+            "fn main() {
+    let max = 9223372036854775807;
+    print(max);
+    let wrapped = max + 1;
+    print(wrapped);
+}",
+        );
+        assert_eq!(output.status.code(), Some(1), "{output:?}");
+        assert_eq!(String::from_utf8_lossy(&output.stdout).lines().last(), Some("9223372036854775807"));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("error[E0006]: Integer overflow in `Add`\n  at line 4, column 19"),
+            "{stderr}"
+        );
+    }
+
+    #[test]
+    fn test_cranelift_run_reports_division_by_zero_and_bad_shifts() {
+        ensure_dyld_library_path();
+        for (operation, message) in [
+            ("7 / zero", "Division by zero"),
+            ("7 % zero", "Modulo by zero"),
+            ("7.0 / 0.0", "Division by zero"),
+            ("1 << zero - 1", "Shift amount -1 is out of range"),
+        ] {
+            let output = run_on_cranelift_jit(&format!(
+                "fn main() {{\n    let zero = 0;\n    print(zero);\n    print({operation});\n}}"
+            ));
+            assert_eq!(output.status.code(), Some(1), "{output:?}");
+            assert_eq!(String::from_utf8_lossy(&output.stdout).lines().last(), Some("0"));
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            assert!(stderr.contains(&format!("error[E0006]: {message}\n  at line 4, column 11")), "{stderr}");
+        }
+    }
+
+    #[test]
+    fn test_cranelift_run_reports_out_of_bounds_indices() {
+        ensure_dyld_library_path();
+        let output = run_on_cranelift_jit(
+            "fn main() {
+    let items = [1, 2, 3];
+    print(items[0]);
+    print(items[-1]);
+}",
+        );
+        assert_eq!(output.status.code(), Some(1), "{output:?}");
+        assert_eq!(String::from_utf8_lossy(&output.stdout).lines().last(), Some("1"));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("Index -1 is out of bounds for an array of length 3\n  at line 4, column 17"),
+            "{stderr}"
+        );
+    }
+
+    #[test]
+    fn test_cranelift_run_reports_code_the_jit_cannot_run() {
+        ensure_dyld_library_path();
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("unsupported.cyl");
+        fs::write(
+            &source,
+            "fn main() { for (i, n) in [3, 5].enumerate() { print(i + n); } }",
+        )
+        .unwrap();
+        let output = Command::new(find_cylc_binary().unwrap())
+            .args(["run", "--backend", "cranelift", "--quiet"])
+            .arg(&source)
+            .output()
+            .expect("Failed to run cylc run");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(1), "{stderr}");
+        assert!(
+            stderr.contains("error[E0005]: Method `enumerate` on `[int]` values is not supported by the Cranelift backend yet"),
+            "{stderr}"
+        );
+        assert!(output.stdout.is_empty(), "{output:?}");
+
+        // The interpreter only takes over when asked to, and never silently
+        let output = Command::new(find_cylc_binary().unwrap())
+            .args(["run", "--backend", "cranelift", "--quiet", "--fallback"])
+            .arg(&source)
+            .output()
+            .expect("Failed to run cylc run");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success(), "{stderr}");
+        assert!(stderr.contains("warning: Cranelift could not run the program"), "{stderr}");
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n6\n");
+    }
+
+    #[test]
+    fn test_try_catch_fixture() {
+        ensure_dyld_library_path();
+        let result = compile_and_run_cyl_file_with_backend("tests/fixtures/valid/try_catch_test.cyl", "interpreter")
+            .expect("Failed to run try_catch_test.cyl");
+        assert!(result.success(), "Try/catch test should succeed: {:?}", result);
+        let expected = "7\n42\n-1\n50";
+        assert_eq!(result.stdout.trim(), expected);
+        assert_builds_with_cranelift("tests/fixtures/valid/try_catch_test.cyl");
+    }
+
+    #[test]
+    fn test_exception_propagation_fixture() {
+        ensure_dyld_library_path();
+        let result = compile_and_run_cyl_file_with_backend("tests/fixtures/valid/exception_propagation_test.cyl", "interpreter")
+            .expect("Failed to run exception_propagation_test.cyl");
+        assert!(result.success(), "Exception propagation test should succeed: {:?}", result);
+        let expected = "risky returned\n51\ncaught zero\n-1\ncaught two\n2\ncaught anything\n1.5\n4";
+        assert_eq!(result.stdout.trim(), expected);
+        assert_builds_with_cranelift("tests/fixtures/valid/exception_propagation_test.cyl");
+    }
+
+    #[test]
+    fn test_cranelift_reports_uncaught_exceptions() {
+        ensure_dyld_library_path();
+        let fixture = "tests/fixtures/runtime_errors/uncaught_exception.cyl";
+        let jit = run_on_cranelift_jit(&fs::read_to_string(format!("../{fixture}")).unwrap());
+        let dir = tempfile::tempdir().unwrap();
+        let executable = dir.path().join("uncaught");
+        let build = Command::new(find_cylc_binary().unwrap())
+            .args(["build", "-o"])
+            .arg(&executable)
+            .arg(format!("../{fixture}"))
+            .output()
+            .expect("Failed to run cylc build");
+        assert!(build.status.success(), "{}", String::from_utf8_lossy(&build.stderr));
+        let run = Command::new(&executable).output().expect("Failed to run the executable");
+        for output in [jit, run] {
+            assert_eq!(output.status.code(), Some(1), "{output:?}");
+            assert_eq!(String::from_utf8_lossy(&output.stdout).lines().last(), Some("1"));
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            assert!(
+                stderr.contains("error[E0006]: Uncaught exception: not a digit: x\n  at line 6, column 5"),
+                "{stderr}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_option_result_fixture() {
+        ensure_dyld_library_path();
+        let result = compile_and_run_cyl_file_with_backend("tests/fixtures/valid/option_result_test.cyl", "interpreter")
+            .expect("Failed to run option_result_test.cyl");
+        assert!(result.success(), "Option/Result test should succeed: {:?}", result);
+        let expected = "Ok(7)\nErr(too large)\nnegative\nSome(3)\nNone\nnothing\n4";
+        assert_eq!(result.stdout.trim(), expected);
+    }
+
+    #[test]
+    fn test_match_return_fixture() {
+        ensure_dyld_library_path();
+        let result = compile_and_run_cyl_file_with_backend("tests/fixtures/valid/match_return_test.cyl", "interpreter")
+            .expect("Failed to run match_return_test.cyl");
+        assert!(result.success(), "Match return test should succeed: {:?}", result);
+        assert_eq!(result.stdout.trim(), "12\n6\n1\n0\n2");
+        assert_builds_with_cranelift("tests/fixtures/valid/match_return_test.cyl");
+    }
+
+    #[test]
+    fn test_generics_fixture() {
+        ensure_dyld_library_path();
+        let result = compile_and_run_cyl_file_with_backend("tests/fixtures/valid/generics_test.cyl", "cranelift")
+            .expect("Failed to run generics_test.cyl");
+        assert!(result.success(), "Generic functions should run on the JIT: {:?}", result);
+        assert_eq!(result.stdout.trim(), "3\nthree\n1.5\n5\nHello, Cyl!\nHello, Cyl?\ncaught boom");
+    }
+
+    #[test]
+    fn test_enum_return_fixture() {
+        ensure_dyld_library_path();
+        let result = compile_and_run_cyl_file_with_backend("tests/fixtures/valid/enum_return_test.cyl", "cranelift")
+            .expect("Failed to run enum_return_test.cyl");
+        assert!(result.success(), "Enum-returning functions should run on the JIT: {:?}", result);
+        assert_eq!(
+            result.stdout.trim(),
+            "Ok(7)\nErr(too large)\nSome(2)\nNone\n4\n25\nbox\n9\n332833500\nShape.Circle(1.5)"
+        );
+    }
+
+    #[test]
+    fn test_break_continue_fixture() {
+        ensure_dyld_library_path();
+        let result = compile_and_run_cyl_file_with_backend("tests/fixtures/valid/break_continue_test.cyl", "interpreter")
+            .expect("Failed to run break_continue_test.cyl");
+        assert!(result.success(), "Break/continue test should succeed: {:?}", result);
+        let expected = "1\n2\n4\n5\n0\n1\n10\n11\ndone";
+        assert_eq!(result.stdout.trim(), expected);
+    }
+
+    #[test]
+    fn test_operators_fixture() {
+        ensure_dyld_library_path();
+        let result = compile_and_run_cyl_file_with_backend("tests/fixtures/valid/operators_test.cyl", "interpreter")
+            .expect("Failed to run operators_test.cyl");
+        assert!(result.success(), "Operators test should succeed: {:?}", result);
+        let expected = "111\n1\n-1\n1.5\ntrue\ntrue\ntrue\n38";
+        assert_eq!(result.stdout.trim(), expected);
+        assert_builds_with_cranelift("tests/fixtures/valid/operators_test.cyl");
+    }
+
+    #[test]
+    fn test_bitwise_fixture() {
+        ensure_dyld_library_path();
+        let result = compile_and_run_cyl_file_with_backend("tests/fixtures/valid/bitwise_test.cyl", "interpreter")
+            .expect("Failed to run bitwise_test.cyl");
+        assert!(result.success(), "Bitwise test should succeed: {:?}", result);
+        let expected = "8\n14\n6\n-13\n1024\n-4\n24\ntrue\n7\n8\n5";
+        assert_eq!(result.stdout.trim(), expected);
+        assert_builds_with_cranelift("tests/fixtures/valid/bitwise_test.cyl");
+    }
+
+    #[test]
+    fn test_strings_fixture() {
+        ensure_dyld_library_path();
+        let result = compile_and_run_cyl_file_with_backend("tests/fixtures/valid/strings_test.cyl", "interpreter")
+            .expect("Failed to run strings_test.cyl");
+        assert!(result.success(), "Strings test should succeed: {:?}", result);
+        let expected = "Hello, wörld!\n13\nn=42, f=1.5, b=true\n3 apples\nsecond\ntrue\ntrue\ntrue";
+        assert_eq!(result.stdout.trim(), expected);
+        assert_builds_with_cranelift("tests/fixtures/valid/strings_test.cyl");
+    }
+
+    /// Concatenating in a loop only keeps the strings the program holds on
+    /// to: the executable runs within an address space far smaller than the
+    /// temporaries it makes add up to.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_cranelift_executables_free_temporary_strings() {
+        ensure_dyld_library_path();
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("temporaries.cyl");
+        fs::write(
+            &source,
+            "fn main() {
+                 let mut i = 0;
+                 let mut same = 0;
+                 while i < 2000000 {
+                     if \"item \" + i + \" of \" + 2000000 == \"item \" + i + \" of 2000000\" {
+                         same = same + 1;
+                     }
+                     i = i + 1;
+                 }
+                 print(\"same \" + same);
+             }",
+        )
+        .unwrap();
+        let executable = dir.path().join("temporaries");
+        let build = Command::new(find_cylc_binary().unwrap())
+            .args(["build", "-o"])
+            .arg(&executable)
+            .arg(&source)
+            .output()
+            .expect("Failed to run cylc build");
+        assert!(build.status.success(), "{}", String::from_utf8_lossy(&build.stderr));
+        let run = Command::new("sh")
+            .arg("-c")
+            .arg("ulimit -v 200000 && exec \"$0\"")
+            .arg(&executable)
+            .output()
+            .expect("Failed to run the executable");
+        assert!(run.status.success(), "{run:?}");
+        assert_eq!(String::from_utf8_lossy(&run.stdout), "same 2000000\n");
+    }
+
+    /// Storing to a field writes the struct in place rather than copying it,
+    /// so a loop updating a field needs no memory at all.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_cranelift_executables_store_fields_in_place() {
+        ensure_dyld_library_path();
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("fields.cyl");
+        fs::write(
+            &source,
+            "struct Point { x: int, y: int }
+             fn main() {
+                 let mut p = Point { x: 0, y: 0 };
+                 let mut i = 0;
+                 while i < 20000000 {
+                     p.x = p.x + 1;
+                     i = i + 1;
+                 }
+                 print(p.x);
+             }",
+        )
+        .unwrap();
+        let executable = dir.path().join("fields");
+        let build = Command::new(find_cylc_binary().unwrap())
+            .args(["build", "-o"])
+            .arg(&executable)
+            .arg(&source)
+            .output()
+            .expect("Failed to run cylc build");
+        assert!(build.status.success(), "{}", String::from_utf8_lossy(&build.stderr));
+        let run = Command::new("sh")
+            .arg("-c")
+            .arg("ulimit -v 200000 && exec \"$0\"")
+            .arg(&executable)
+            .output()
+            .expect("Failed to run the executable");
+        assert!(run.status.success(), "{run:?}");
+        assert_eq!(String::from_utf8_lossy(&run.stdout), "20000000\n");
+    }
+
+    #[test]
+    fn test_struct_mutation_fixture() {
+        ensure_dyld_library_path();
+        let result = compile_and_run_cyl_file_with_backend("tests/fixtures/valid/struct_mutation_test.cyl", "cranelift")
+            .expect("Failed to run struct_mutation_test.cyl");
+        assert!(result.success(), "Struct mutation test should succeed: {:?}", result);
+        assert_eq!(
+            result.stdout.split_whitespace().collect::<Vec<_>>(),
+            ["1001", "2", "50", "1001", "1006", "1001", "50", "7", "8", "7", "9", "2", "11", "1001", "12", "12", "12", "0", "16", "0"]
+        );
+    }
+
+    #[test]
+    fn test_array_elements_fixture() {
+        ensure_dyld_library_path();
+        let result = compile_and_run_cyl_file_with_backend("tests/fixtures/valid/array_elements_test.cyl", "interpreter")
+            .expect("Failed to run array_elements_test.cyl");
+        assert!(result.success(), "Array elements test should succeed: {:?}", result);
+        let expected = "15\n3\nabc\n7\nfalse\n1.5\n\n2.25\n3\n\n5\n\n7\n0";
+        assert_eq!(result.stdout.trim(), expected);
+        assert_builds_with_cranelift("tests/fixtures/valid/array_elements_test.cyl");
+    }
+
+    #[test]
+    fn test_structs_fixture() {
+        ensure_dyld_library_path();
+        let result = compile_and_run_cyl_file_with_backend("tests/fixtures/valid/structs_test.cyl", "interpreter")
+            .expect("Failed to run structs_test.cyl");
+        assert!(result.success(), "Structs test should succeed: {:?}", result);
+        let expected = "25\n17\n4\ntrue\n5\n3\nbox b 2.5\n21\n\
+            Line { start: Point { x: 7, y: 0 }, end: Point { x: 3, y: 7 }, visible: true }\n\
+            Named { name: box, tags: [a, b], weight: 2.5 }";
+        assert_eq!(result.stdout.trim(), expected);
+        assert_builds_with_cranelift("tests/fixtures/valid/structs_test.cyl");
+    }
+
+    #[test]
+    fn test_globals_fixture() {
+        ensure_dyld_library_path();
+        let result = compile_and_run_cyl_file_with_backend("tests/fixtures/valid/globals_test.cyl", "interpreter")
+            .expect("Failed to run globals_test.cyl");
+        assert!(result.success(), "Globals test should succeed: {:?}", result);
+        let expected = "hi there\n5\n15\n5\n24\n9";
+        assert_eq!(result.stdout.trim(), expected);
+        assert_builds_with_cranelift("tests/fixtures/valid/globals_test.cyl");
+    }
+
+    #[test]
+    fn test_for_in_fixture() {
+        ensure_dyld_library_path();
+        let result = compile_and_run_cyl_file_with_backend("tests/fixtures/valid/for_in_test.cyl", "interpreter")
+            .expect("Failed to run for_in_test.cyl");
+        assert!(result.success(), "For-in test should succeed: {:?}", result);
+        let expected = "15\n3\n105\n207\n2\n3\n4\n10\nh\ne\ny\nal\n1\nbo\n2\ntick\ntick\ntick";
+        assert_eq!(result.stdout.trim(), expected);
+    }
+
     #[test]
     fn test_all_valid_fixtures() {
         ensure_dyld_library_path();
@@ -291,7 +891,7 @@ mod tests {
             println!("Testing: {}", cyl_file);
             
             let result = compile_and_run_cyl_file(&cyl_file)
-                .expect(&format!("Failed to run {}", cyl_file));
+                .unwrap_or_else(|_| panic!("Failed to run {}", cyl_file));
             
             assert!(result.success(), "Test failed for {}: {:?}", cyl_file, result);
             tested_files += 1;
@@ -301,6 +901,175 @@ mod tests {
         println!("Successfully tested {} valid files", tested_files);
     }
 
+    #[test]
+    fn test_vm_backend_matches_interpreter_on_valid_fixtures() {
+        ensure_dyld_library_path();
+        let cyl_files = discover_cyl_files("tests/fixtures/valid")
+            .expect("Failed to discover valid test files");
+        for cyl_file in cyl_files.iter().filter(|f| !f.ends_with(".disabled")) {
+            let expected = compile_and_run_cyl_file(cyl_file)
+                .unwrap_or_else(|_| panic!("Failed to run {}", cyl_file));
+            let result = compile_and_run_cyl_file_with_backend(cyl_file, "vm")
+                .unwrap_or_else(|_| panic!("Failed to run {} on the VM", cyl_file));
+            assert!(result.success(), "VM run failed for {}: {:?}", cyl_file, result);
+            assert_eq!(result.stdout, expected.stdout, "VM output differs for {}", cyl_file);
+        }
+    }
+
+    /// Fixtures using features the Cranelift JIT does not lower yet.
+    const JIT_UNSUPPORTED: [&str; 2] = ["for_in_test.cyl", "option_result_test.cyl"];
+
+    #[test]
+    fn test_cranelift_backend_matches_interpreter_on_valid_fixtures() {
+        ensure_dyld_library_path();
+        let cyl_files = discover_cyl_files("tests/fixtures/valid")
+            .expect("Failed to discover valid test files");
+        for cyl_file in cyl_files.iter().filter(|f| !f.ends_with(".disabled")) {
+            let expected = compile_and_run_cyl_file(cyl_file)
+                .unwrap_or_else(|_| panic!("Failed to run {}", cyl_file));
+            let result = compile_and_run_cyl_file_with_backend(cyl_file, "cranelift")
+                .unwrap_or_else(|_| panic!("Failed to run {} with Cranelift", cyl_file));
+            if JIT_UNSUPPORTED.iter().any(|name| cyl_file.ends_with(name)) {
+                assert_eq!(result.exit_code, 1, "{:?}", result);
+                assert!(result.stderr.contains("error[E0005]"), "{}", result.stderr);
+                continue;
+            }
+            assert!(result.success(), "Cranelift run failed for {}: {:?}", cyl_file, result);
+            assert_eq!(result.stdout, expected.stdout, "Cranelift output differs for {}", cyl_file);
+        }
+    }
+
+    #[test]
+    fn test_cranelift_executables_print_like_the_interpreter() {
+        ensure_dyld_library_path();
+        let dir = tempfile::tempdir().unwrap();
+        for fixture in [
+            "tests/fixtures/valid/functions_test.cyl",
+            "tests/fixtures/valid/generics_test.cyl",
+            "tests/fixtures/valid/generic_struct_test.cyl",
+            "tests/fixtures/valid/enum_return_test.cyl",
+            "tests/fixtures/valid/struct_mutation_test.cyl",
+            "tests/fixtures/valid/array_elements_test.cyl",
+            "tests/fixtures/valid/bitwise_test.cyl",
+            "tests/fixtures/valid/exception_propagation_test.cyl",
+            "tests/fixtures/valid/globals_test.cyl",
+            "tests/fixtures/valid/operators_test.cyl",
+            "tests/fixtures/valid/strings_test.cyl",
+            "tests/fixtures/valid/structs_test.cyl",
+            "tests/fixtures/valid/try_catch_test.cyl",
+        ] {
+            let expected = compile_and_run_cyl_file(fixture).unwrap();
+            let jit = run_on_cranelift_jit(&fs::read_to_string(format!("../{fixture}")).unwrap());
+            let jit_stdout = String::from_utf8_lossy(&jit.stdout);
+            assert!(jit_stdout.ends_with(&expected.stdout), "JIT output differs for {fixture}: {jit_stdout}");
+
+            let executable = dir.path().join("program");
+            let build = Command::new(find_cylc_binary().unwrap())
+                .args(["build", "-o"])
+                .arg(&executable)
+                .arg(format!("../{fixture}"))
+                .output()
+                .expect("Failed to run cylc build");
+            assert!(build.status.success(), "{}", String::from_utf8_lossy(&build.stderr));
+            let run = Command::new(&executable).output().expect("Failed to run the executable");
+            assert!(run.status.success(), "{run:?}");
+            assert_eq!(String::from_utf8_lossy(&run.stdout), expected.stdout, "Executable output differs for {fixture}");
+        }
+    }
+
+    #[test]
+    fn test_vm_runtime_error_reports_location_and_call_stack() {
+        ensure_dyld_library_path();
+        let result = compile_and_run_cyl_file_with_backend("tests/fixtures/runtime_errors/division_by_zero.cyl", "vm")
+            .expect("Failed to run division_by_zero.cyl");
+        assert_eq!(result.exit_code, 1, "{:?}", result);
+        assert!(result.stderr.contains("error[E0006]: Division by zero"), "{}", result.stderr);
+        assert!(result.stderr.contains("in `average` at line 7, column 12"), "{}", result.stderr);
+    }
+
+    #[test]
+    fn test_check_json_diagnostics() {
+        ensure_dyld_library_path();
+        let output = Command::new(find_cylc_binary().unwrap())
+            .args(["check", "--message-format=json"])
+            .arg("../tests/fixtures/invalid/missing_semicolon.cyl")
+            .output()
+            .expect("Failed to run cylc check");
+        assert!(!output.status.success());
+        assert!(output.stdout.is_empty(), "JSON mode should keep stdout clean");
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let diagnostics: Vec<serde_json::Value> = stderr
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("each line is one JSON diagnostic"))
+            .collect();
+        assert_eq!(diagnostics.len(), 1, "{stderr}");
+        let diagnostic = &diagnostics[0];
+        assert_eq!(diagnostic["severity"], "error");
+        assert_eq!(diagnostic["code"], "E0002");
+        assert!(diagnostic["file"].as_str().unwrap().ends_with("missing_semicolon.cyl"));
+        assert!(diagnostic["line"].is_u64());
+        assert_eq!(diagnostic["span"]["line"], diagnostic["line"]);
+        assert!(diagnostic["labels"].is_array());
+        assert!(diagnostic["suggestions"].is_array());
+    }
+
+    #[test]
+    fn test_check_reports_warnings_alongside_errors() {
+        ensure_dyld_library_path();
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("both.cyl");
+        fs::write(&file, "fn main() -> void {\n    match 3 { _ => {} 0 => {} }\n    let x: int = \"no\";\n}\n").unwrap();
+        let output = Command::new(find_cylc_binary().unwrap())
+            .args(["check", "--message-format=json"])
+            .arg(&file)
+            .output()
+            .expect("Failed to run cylc check");
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let codes: Vec<String> = stderr
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["code"].to_string())
+            .collect();
+        assert_eq!(codes, ["\"W0001\"", "\"E0003\""], "{stderr}");
+    }
+
+    #[test]
+    fn test_runtime_error_reports_location_and_call_stack() {
+        ensure_dyld_library_path();
+        let result = compile_and_run_cyl_file("tests/fixtures/runtime_errors/division_by_zero.cyl")
+            .expect("Failed to run division_by_zero.cyl");
+        assert_eq!(result.exit_code, 1, "{:?}", result);
+        assert!(result.stderr.contains("error[E0006]: Division by zero"), "{}", result.stderr);
+        assert!(result.stderr.contains("division_by_zero.cyl:3:12"), "{}", result.stderr);
+        let trace = [
+            "in `divide` at line 3, column 12",
+            "in `average` at line 7, column 12",
+            "in `main` at line 11, column 15",
+        ];
+        for frame in trace {
+            assert!(result.stderr.contains(frame), "missing {frame:?}: {}", result.stderr);
+        }
+    }
+
+    #[test]
+    fn test_cylc_test_passes_on_the_fixtures() {
+        ensure_dyld_library_path();
+        let binary = fs::canonicalize(find_cylc_binary().unwrap()).unwrap();
+        let output = Command::new(binary)
+            .args(["test", "--verbose", "--continue-on-failure"])
+            .current_dir("..")
+            .output()
+            .expect("Failed to run cylc test");
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(output.status.success(), "{stdout}");
+        assert!(stdout.contains("Failed: 0 "), "{stdout}");
+        assert!(
+            stdout.contains("Failed at runtime (as expected): Runtime error: Division by zero"),
+            "{stdout}"
+        );
+    }
+
     #[test]
     fn test_all_invalid_fixtures() {
         ensure_dyld_library_path();
@@ -315,7 +1084,7 @@ mod tests {
             println!("Testing (expecting failure): {}", cyl_file);
             
             let result = compile_and_run_cyl_file(&cyl_file)
-                .expect(&format!("Failed to run {}", cyl_file));
+                .unwrap_or_else(|_| panic!("Failed to run {}", cyl_file));
             
             assert!(result.compilation_failed(), 
                 "Expected compilation to fail for {}, but it succeeded: {:?}", 
@@ -0,0 +1,693 @@
+// Tests for the tree-walking interpreter
+
+use cylc::error::CylError;
+use cylc::interpreter::{match_pattern, EnumVariants, Interpreter, Value};
+use cylc::lexer::Lexer;
+use cylc::parser::helpers::Parser;
+use cylc::typeck::{TypeChecker, TypedProgram};
+
+fn run_with(src: &str, configure: impl FnOnce(&mut Interpreter)) -> Result<Vec<String>, CylError> {
+    let mut lexer = Lexer::new(src);
+    let tokens = lexer.tokenize().expect("lexing failed");
+    let program = Parser::new(tokens).parse().expect("parsing failed");
+    let typed = TypeChecker::new()
+        .check_program(program)
+        .expect("type checking failed");
+    let mut interpreter = Interpreter::new();
+    configure(&mut interpreter);
+    interpreter.run(&typed)?;
+    Ok(interpreter.output_buffer)
+}
+
+fn run(src: &str) -> Vec<String> {
+    run_with(src, |_| {}).expect("runtime error")
+}
+
+/// Run without the type checker, to reach errors it would report statically.
+fn run_unchecked(src: &str) -> Result<Vec<String>, CylError> {
+    let mut lexer = Lexer::new(src);
+    let tokens = lexer.tokenize().expect("lexing failed");
+    let program = Parser::new(tokens).parse().expect("parsing failed");
+    let unchecked = TypedProgram {
+        program,
+        info: Default::default(),
+        types: Default::default(),
+        instances: Vec::new(),
+        constructors: Default::default(),
+        warnings: Vec::new(),
+    };
+    let mut interpreter = Interpreter::new();
+    interpreter.run(&unchecked)?;
+    Ok(interpreter.output_buffer)
+}
+
+fn runtime_error(src: &str) -> CylError {
+    run_with(src, |_| {}).expect_err("expected a runtime error")
+}
+
+#[test]
+fn test_call_binds_arguments_and_returns_value() {
+    let output = run("fn add(a: int, b: int) -> int {
+             return a + b;
+         }
+         fn main() -> void {
+             let x = 40;
+             print_int(add(x, 2));
+             print_int(add(add(1, 2), 3));
+         }");
+    assert_eq!(output, vec!["42", "6"]);
+}
+
+#[test]
+fn test_missing_arguments_use_parameter_defaults() {
+    let output = run("fn scale(value: int, factor: int = 10) -> int {
+             return value * factor;
+         }
+         fn main() -> void {
+             print_int(scale(4));
+             print_int(scale(4, 2));
+         }");
+    assert_eq!(output, vec!["40", "8"]);
+}
+
+#[test]
+fn test_return_leaves_nested_loops_and_blocks() {
+    let output = run("fn first_over(limit: int) -> int {
+             let mut i = 0;
+             while i < 100 {
+                 for j in 10 {
+                     if limit < (i * 10 + j) {
+                         return i * 10 + j;
+                     }
+                 }
+                 i = i + 1;
+             }
+             return 0 - 1;
+         }
+         fn main() -> void {
+             print_int(first_over(23));
+             print_int(first_over(5000));
+         }");
+    assert_eq!(output, vec!["24", "-1"]);
+}
+
+#[test]
+fn test_recursion_keeps_locals_per_call() {
+    let output = run("fn fib(n: int) -> int {
+             if n < 2 {
+                 return n;
+             }
+             let a = fib(n - 1);
+             let b = fib(n - 2);
+             return a + b;
+         }
+         fn main() -> void {
+             print_int(fib(15));
+         }");
+    assert_eq!(output, vec!["610"]);
+}
+
+#[test]
+fn test_call_depth_limit_is_a_runtime_error() {
+    let src = "fn down(n: int) -> int {
+             if n == 0 {
+                 return 0;
+             }
+             return down(n - 1);
+         }
+         fn main() -> void {
+             print_int(down(10));
+         }";
+    let error = run_with(src, |interpreter| interpreter.max_call_depth = 5)
+        .expect_err("expected the call depth limit to be hit");
+    assert!(
+        error
+            .message()
+            .contains("Maximum call depth of 5 exceeded in call to `down`"),
+        "{error}"
+    );
+    let output = run_with(src, |interpreter| interpreter.max_call_depth = 20).unwrap();
+    assert_eq!(output, vec!["0"]);
+}
+
+#[test]
+fn test_blocks_scope_and_shadow_variables() {
+    let output = run("fn main() -> void {
+             let x = 1;
+             {
+                 let x = 2;
+                 print_int(x);
+                 let x = x * 10;
+                 print_int(x);
+             }
+             print_int(x);
+             let x = x + 100;
+             print_int(x);
+         }");
+    assert_eq!(output, vec!["2", "20", "1", "101"]);
+}
+
+#[test]
+fn test_function_locals_do_not_clobber_globals() {
+    let output = run("let x = 10;
+         let mut calls = 0;
+         fn touch() -> void {
+             let x = 1;
+             print_int(x);
+             calls = calls + 1;
+         }
+         fn main() -> void {
+             touch();
+             touch();
+             print_int(x);
+             print_int(calls);
+         }");
+    assert_eq!(output, vec!["1", "1", "10", "2"]);
+}
+
+#[test]
+fn test_loop_variables_are_fresh_each_iteration() {
+    let output = run("fn main() -> void {
+             let mut total = 0;
+             for i in 3 {
+                 let doubled = i * 2;
+                 total = total + doubled;
+             }
+             print_int(total);
+         }");
+    assert_eq!(output, vec!["6"]);
+}
+
+#[test]
+fn test_assignment_requires_a_mutable_binding() {
+    let error = runtime_error("fn main() -> void { let x = 1; x = 2; }");
+    assert!(
+        error
+            .message()
+            .contains("Cannot assign twice to immutable variable `x`"),
+        "{error}"
+    );
+    let error = runtime_error("const LIMIT = 3; fn main() -> void { LIMIT = 4; }");
+    assert!(
+        error.message().contains("immutable variable `LIMIT`"),
+        "{error}"
+    );
+    let error = runtime_error("fn f(n: int) -> void { n = n - 1; } fn main() -> void { f(1); }");
+    assert!(
+        error.message().contains("immutable variable `n`"),
+        "{error}"
+    );
+
+    let output = run("fn countdown(mut n: int) -> void {
+             while n > 0 {
+                 print_int(n);
+                 n = n - 1;
+             }
+         }
+         fn main() -> void {
+             let mut x = 1;
+             x = x + 1;
+             print_int(x);
+             countdown(2);
+         }");
+    assert_eq!(output, vec!["2", "2", "1"]);
+}
+
+#[test]
+fn test_undefined_variables_are_runtime_errors() {
+    let error = run_unchecked("fn main() -> void { { let inner = 1; } print_int(inner); }")
+        .expect_err("block locals must not leak");
+    assert!(
+        error.message().contains("Undefined variable `inner`"),
+        "{error}"
+    );
+
+    let error = run_unchecked(
+        "fn callee() -> void { print_int(local); }
+         fn main() -> void { let local = 1; callee(); }",
+    )
+    .expect_err("callees must not see the caller's locals");
+    assert!(
+        error.message().contains("Undefined variable `local`"),
+        "{error}"
+    );
+
+    let error = run_unchecked("fn main() -> void { missing = 1; }")
+        .expect_err("assignment does not declare");
+    assert!(
+        error
+            .message()
+            .contains("Cannot assign to undefined variable `missing`"),
+        "{error}"
+    );
+}
+
+#[test]
+fn test_runtime_errors_carry_span_and_call_stack() {
+    let src = "fn pick(xs: [int], i: int) -> int {
+    return xs[i];
+}
+fn main() -> void {
+    print_int(pick([1, 2, 3], 3));
+}";
+    let error = runtime_error(src);
+    assert_eq!(
+        error.message(),
+        "Index 3 is out of bounds for an array of length 3"
+    );
+    let span = error.span().expect("runtime error without span");
+    assert_eq!(&src[span.start..span.end], "i");
+    match error {
+        CylError::RuntimeError { stack, .. } => {
+            let frames: Vec<_> = stack
+                .iter()
+                .map(|f| (f.function.as_str(), f.span.line))
+                .collect();
+            assert_eq!(frames, vec![("pick", 2), ("main", 5)]);
+        }
+        other => panic!("expected a runtime error, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_operator_failures_are_runtime_errors() {
+    let error = runtime_error("fn main() -> void { let a: dynamic = \"x\"; print_int(a - 1); }");
+    assert_eq!(
+        error.message(),
+        "Cannot apply `Subtract` to values of type `string` and `int`"
+    );
+    let error = runtime_error("fn main() -> void { let zero = 0; print_int(7 % zero); }");
+    assert_eq!(error.message(), "Modulo by zero");
+    let error = runtime_error("fn main() -> void { print_int(9223372036854775807 + 1); }");
+    assert_eq!(error.message(), "Integer overflow in `Add`");
+}
+
+#[test]
+fn test_logical_operators_short_circuit() {
+    let output = run("fn boom() -> bool {
+             let zero = 0;
+             return 1 / zero == 0;
+         }
+         fn main() -> void {
+             if false && boom() {
+                 print_int(1);
+             }
+             if true || boom() {
+                 print_int(2);
+             }
+             print_int(0 - 5);
+         }");
+    assert_eq!(output, vec!["2", "-5"]);
+}
+
+#[test]
+fn test_match_literal_patterns_and_guards() {
+    let output = run("fn describe(n: int) -> string {
+             match n {
+                 0 => { return \"zero\"; }
+                 -1 => { return \"minus one\"; }
+                 k if k > 100 => { return \"big\"; }
+                 k if k > 10 => { return \"medium\"; }
+                 _ => { return \"small\"; }
+             }
+             return \"unreachable\";
+         }
+         fn main() -> void {
+             println(describe(0));
+             println(describe(-1));
+             println(describe(500));
+             println(describe(50));
+             println(describe(7));
+             match \"hi\" {
+                 \"bye\" => { println(\"farewell\"); }
+                 \"hi\" => { println(\"greeting\"); }
+                 _ => {}
+             }
+         }");
+    assert_eq!(
+        output,
+        vec!["zero", "minus one", "big", "medium", "small", "greeting"]
+    );
+}
+
+#[test]
+fn test_match_destructures_tuples_and_structs() {
+    let output = run("struct Point { x: int, y: int }
+         fn main() -> void {
+             let pair = (1, \"one\");
+             match pair {
+                 (2, s) => { println(\"two\"); }
+                 (1, s) => { println(s); }
+                 _ => { println(\"none\"); }
+             }
+             let p = Point { x: 3, y: 4 };
+             match p {
+                 Point { x: 0, y } => { println(\"on the y axis\"); }
+                 Point { x, y: py } if x == py => { println(\"diagonal\"); }
+                 Point { x, y } => { print_int(x * 10 + y); }
+             }
+         }");
+    assert_eq!(output, vec!["one", "34"]);
+}
+
+#[test]
+fn test_match_bindings_are_scoped_to_their_arm() {
+    let output = run("fn main() -> void {
+             let x = 1;
+             match 5 {
+                 x => { print_int(x); }
+             }
+             print_int(x);
+         }");
+    assert_eq!(output, vec!["5", "1"]);
+}
+
+#[test]
+fn test_non_exhaustive_match_is_a_runtime_error() {
+    let error = runtime_error(
+        "fn main() -> void {
+             let value: dynamic = 5;
+             match value {
+                 1 => { println(\"one\"); }
+                 n if n > 10 => { println(\"big\"); }
+             }
+         }",
+    );
+    assert_eq!(error.message(), "Non-exhaustive match: no arm matches `5`");
+    assert_eq!(error.span().map(|s| (s.line, s.column)), Some((3, 20)));
+}
+
+#[test]
+fn test_enum_patterns_match_variant_and_bind_fields() {
+    let enums: EnumVariants = [(
+        "Shape".to_string(),
+        vec!["Circle".to_string(), "Empty".to_string()],
+    )]
+    .into_iter()
+    .collect();
+    let circle = Value::Enum(
+        "Shape".to_string(),
+        "Circle".to_string(),
+        vec![Value::Float(2.0)],
+    );
+    let empty = Value::Enum("Shape".to_string(), "Empty".to_string(), vec![]);
+    let pattern = |src: &str| {
+        let tokens = Lexer::new(src).tokenize().expect("lexing failed");
+        Parser::new(tokens).parse_pattern().expect("parsing failed")
+    };
+
+    let mut bindings = Vec::new();
+    assert!(match_pattern(
+        &pattern("Shape.Circle(r)"),
+        &circle,
+        &enums,
+        &mut bindings
+    ));
+    assert_eq!(bindings, vec![("r".to_string(), Value::Float(2.0))]);
+    assert!(match_pattern(
+        &pattern("Circle(_)"),
+        &circle,
+        &enums,
+        &mut Vec::new()
+    ));
+    assert!(!match_pattern(
+        &pattern("Other.Circle(_)"),
+        &circle,
+        &enums,
+        &mut Vec::new()
+    ));
+    assert!(!match_pattern(
+        &pattern("Shape.Circle(1.0)"),
+        &circle,
+        &enums,
+        &mut Vec::new()
+    ));
+
+    // A bare variant name matches that variant rather than binding the value.
+    assert!(match_pattern(
+        &pattern("Empty"),
+        &empty,
+        &enums,
+        &mut Vec::new()
+    ));
+    assert!(!match_pattern(
+        &pattern("Empty"),
+        &circle,
+        &enums,
+        &mut Vec::new()
+    ));
+    let mut bindings = Vec::new();
+    assert!(match_pattern(
+        &pattern("shape"),
+        &circle,
+        &enums,
+        &mut bindings
+    ));
+    assert_eq!(bindings, vec![("shape".to_string(), circle.clone())]);
+}
+
+#[test]
+fn test_structs_print_in_declared_order_and_compare_by_value() {
+    let output = run("struct Point { x: int, y: int }
+         fn main() -> void {
+             let p = Point { y: 2, x: 1 };
+             println(p);
+             print_int(p.x * 10 + p.y);
+             println(p == Point { x: 1, y: 2 });
+             println(p == Point { x: 1, y: 3 });
+         }");
+    assert_eq!(output, vec!["Point { x: 1, y: 2 }", "12", "true", "false"]);
+}
+
+#[test]
+fn test_field_assignment_updates_nested_paths() {
+    let output = run("struct Point { x: int, y: int }
+         struct Line { start: Point, end: Point }
+         fn main() -> void {
+             let mut p = Point { x: 1, y: 2 };
+             p.x = 3;
+             let mut line = Line { start: p, end: Point { x: 9, y: 9 } };
+             line.start.y = 7;
+             println(line);
+             println(p);
+         }");
+    assert_eq!(
+        output,
+        vec![
+            "Line { start: Point { x: 3, y: 7 }, end: Point { x: 9, y: 9 } }",
+            "Point { x: 3, y: 2 }"
+        ]
+    );
+}
+
+#[test]
+fn test_struct_errors_are_runtime_errors() {
+    let error = runtime_error(
+        "struct Point { x: int, y: int }
+         fn main() -> void {
+             let p = Point { x: 1, y: 2 };
+             p.x = 3;
+         }",
+    );
+    assert_eq!(error.message(), "Cannot mutate immutable variable `p`");
+
+    let error = run_unchecked(
+        "struct Point { x: int, y: int }
+         let p = Point { x: 1 };",
+    )
+    .unwrap_err();
+    assert_eq!(
+        error.message(),
+        "Missing field `y` in literal of struct `Point`"
+    );
+
+    let error = run_unchecked(
+        "struct Point { x: int, y: int }
+         let mut p = Point { x: 1, y: 2 };
+         p.z = 3;",
+    )
+    .unwrap_err();
+    assert_eq!(error.message(), "No field `z` on a value of type `Point`");
+    assert_eq!(error.span().map(|s| (s.line, s.column)), Some((3, 10)));
+}
+
+#[test]
+fn test_enum_variants_are_constructed_by_name() {
+    let output = run("enum Shape { Circle(float), Empty }
+         enum Color { Red, Green }
+         fn main() -> void {
+             let c = Shape.Circle(2.5);
+             print(c);
+             print(Color.Red);
+             print(c == Shape.Circle(2.5));
+             match Color.Green {
+                 Color.Red => { print(\"red\"); }
+                 Color.Green => { print(\"green\"); }
+             }
+         }");
+    assert_eq!(
+        output,
+        vec!["Shape.Circle(2.5)", "Color.Red", "true", "green"]
+    );
+}
+
+#[test]
+fn test_throw_unwinds_to_first_matching_catch_clause() {
+    let output = run("struct Oops { code: int }
+         fn fail(code: int) -> int {
+             throw Oops { code: code };
+         }
+         fn main() -> void {
+             try {
+                 print(fail(404));
+                 print(\"unreachable\");
+             } catch e: string {
+                 print(\"string\");
+             } catch e: Oops {
+                 print(e.code);
+             }
+             try {
+                 try {
+                     throw \"inner\";
+                 } catch e: int {
+                     print(\"int\");
+                 }
+             } catch e {
+                 print(e);
+             }
+             try {
+                 throw 1;
+             } catch {
+                 print(\"caught\");
+             }
+         }");
+    assert_eq!(output, vec!["404", "inner", "caught"]);
+}
+
+#[test]
+fn test_catch_clauses_check_array_element_types() {
+    let output = run("fn main() -> void {
+             try {
+                 throw [\"x\"];
+             } catch e: [int] {
+                 print(\"ints\");
+             } catch e: [string] {
+                 print(e[0]);
+             }
+         }");
+    assert_eq!(output, vec!["x"]);
+}
+
+#[test]
+fn test_uncaught_exceptions_report_the_throw_and_call_stack() {
+    let error = runtime_error(
+        "fn fail() -> int {
+             throw \"boom\";
+         }
+         fn main() -> void {
+             try {
+                 fail();
+             } catch e: int {
+                 print(e);
+             }
+         }",
+    );
+    assert_eq!(error.message(), "Uncaught exception: boom");
+    assert_eq!(error.span().map(|s| (s.line, s.column)), Some((2, 14)));
+    match error {
+        CylError::RuntimeError { stack, .. } => {
+            let functions: Vec<_> = stack.iter().map(|f| f.function.as_str()).collect();
+            assert_eq!(functions, vec!["fail", "main"]);
+        }
+        other => panic!("expected a runtime error, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_question_mark_returns_err_and_none_early() {
+    let output = run("fn check(n: int) -> Result<int, string> {
+             if n < 0 { return Err(\"negative\"); }
+             return Ok(n);
+         }
+         fn double(n: int) -> Result<int, string> {
+             let checked = check(n)?;
+             print(\"checked\");
+             return Ok(checked * 2);
+         }
+         fn first(n: int?) -> int? {
+             let value = n?;
+             return value + 1;
+         }
+         fn main() -> void {
+             print(double(4));
+             print(double(0 - 4));
+             print(first(1));
+             print(first(null));
+             print(None);
+         }");
+    assert_eq!(
+        output,
+        vec!["checked", "Ok(8)", "Err(negative)", "2", "<void>", "None"]
+    );
+}
+
+#[test]
+fn test_break_and_continue_leave_the_targeted_loop() {
+    let output = run("fn first_even_over(limit: int) -> int {
+             let mut n = limit;
+             while true {
+                 n = n + 1;
+                 if n % 2 == 1 {
+                     continue;
+                 }
+                 break;
+             }
+             return n;
+         }
+         fn main() -> void {
+             print(first_even_over(6));
+             'rows: for row in 4 {
+                 for col in 4 {
+                     if col - row > 0 {
+                         continue 'rows;
+                     }
+                     if row == 3 {
+                         break 'rows;
+                     }
+                     match col {
+                         1 => { continue; }
+                         _ => {}
+                     }
+                     print(row * 10 + col);
+                 }
+             }
+             print(\"done\");
+         }");
+    assert_eq!(output, vec!["8", "0", "10", "20", "22", "done"]);
+}
+
+#[test]
+fn test_for_loops_bind_elements_not_indices() {
+    let output = run("fn main() -> void {
+             let names = [\"ann\", \"bob\"];
+             for name in names {
+                 print(name);
+             }
+             for (i, name) in names.enumerate() {
+                 print(i);
+             }
+             let r = 3..6;
+             print(r[0] + r[2]);
+             for i in 5..=5 {
+                 print(i);
+             }
+             for c in \"ok\".enumerate() {
+                 print(c);
+             }
+             print(names.len());
+         }");
+    assert_eq!(
+        output,
+        vec!["ann", "bob", "0", "1", "8", "5", "(0, o)", "(1, k)", "2"]
+    );
+}
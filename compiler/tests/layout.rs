@@ -0,0 +1,81 @@
+// Tests for the enum, struct, string and array layouts shared by the native
+// backends
+
+use cylc::ast::{Statement, StatementKind, Type};
+use cylc::layout::{self, EnumLayout, StructLayout};
+use cylc::lexer::Lexer;
+use cylc::parser::helpers::Parser;
+
+fn layout(src: &str) -> EnumLayout {
+    match &parse(src).kind {
+        StatementKind::Enum(decl) => EnumLayout::new(decl),
+        other => panic!("expected an enum, found {other:?}"),
+    }
+}
+
+fn struct_layout(src: &str) -> StructLayout {
+    match &parse(src).kind {
+        StatementKind::Struct(decl) => StructLayout::new(decl),
+        other => panic!("expected a struct, found {other:?}"),
+    }
+}
+
+fn parse(src: &str) -> Statement {
+    let tokens = Lexer::new(src).tokenize().expect("lexing failed");
+    let program = Parser::new(tokens).parse().expect("parsing failed");
+    program.statements.into_iter().next().expect("expected a declaration")
+}
+
+#[test]
+fn test_enum_layout_tags_variants_and_sizes_for_largest_payload() {
+    let shape = layout("enum Shape { Circle(float), Rect(float, int), Empty }");
+    let tags: Vec<i64> = shape.variants.iter().map(|v| v.tag).collect();
+    assert_eq!(tags, vec![0, 1, 2]);
+    assert_eq!(
+        shape.variant("Rect").map(|v| v.fields.clone()),
+        Some(vec![Type::Float, Type::Int])
+    );
+    assert_eq!(shape.payload_slots(), 2);
+    assert_eq!(shape.size(), 24);
+    assert_eq!(EnumLayout::field_offset(0), 8);
+    assert_eq!(EnumLayout::field_offset(1), 16);
+
+    let color = layout("enum Color { Red, Green }");
+    assert_eq!(color.payload_slots(), 0);
+    assert_eq!(color.size(), 8);
+    assert!(color.variant("Blue").is_none());
+}
+
+#[test]
+fn test_struct_layout_aligns_fields_to_their_size() {
+    let cell = struct_layout("struct Cell { alive: bool, age: int, marked: bool }");
+    let offsets: Vec<u32> = cell.fields.iter().map(|f| f.offset).collect();
+    assert_eq!(offsets, vec![0, 8, 16]);
+    assert_eq!((cell.size, cell.align), (24, 8));
+    assert_eq!(cell.field("age").map(|f| f.field_type.clone()), Some(Type::Int));
+
+    let flags = struct_layout("struct Flags { a: bool, b: bool }");
+    assert_eq!((flags.size, flags.align), (2, 1));
+
+    let pair = struct_layout("struct Pair<T> { first: T, second: T }");
+    let second = pair.field("second").unwrap();
+    assert_eq!(second.offset, 8);
+    let ty = Type::Generic("Pair".to_string(), vec![Type::Bool]);
+    assert_eq!(pair.field_type(second, &ty), Type::Bool);
+}
+
+#[test]
+fn test_string_object_holds_byte_length_bytes_and_nul() {
+    let object = layout::string_object("hé");
+    assert_eq!(object.len(), 8 + 3 + 1);
+    let length_bytes = layout::STRING_LENGTH_OFFSET as usize..layout::STRING_BYTES_OFFSET as usize;
+    assert_eq!(i64::from_ne_bytes(object[length_bytes].try_into().unwrap()), 3);
+    assert_eq!(&object[layout::STRING_BYTES_OFFSET as usize..], "hé\0".as_bytes());
+}
+
+#[test]
+fn test_array_elements_follow_the_length_in_slots() {
+    assert_eq!(layout::ARRAY_LENGTH_OFFSET, 0);
+    assert_eq!(layout::array_element_offset(0), layout::ARRAY_ELEMENTS_OFFSET);
+    assert_eq!(layout::array_element_offset(2), 8 + 2 * layout::SLOT_SIZE);
+}
@@ -1,5 +1,6 @@
 // Tests for new Cyl language features: generics, default args, tuples, nullable, dynamic, pattern matching
 
+use cylc::ast::{ExpressionKind, PatternKind, StatementKind};
 use cylc::lexer::Lexer;
 use cylc::parser::helpers::Parser;
 
@@ -44,3 +45,182 @@ fn test_struct_enum_generics() {
     parse_ok("struct Point<T> { x: T, y: T }");
     parse_ok("enum Option<T> { Some(T), None }");
 }
+
+#[test]
+fn test_mutability_markers_and_bare_assignment() {
+    let src = "let a = 1; let mut b = 2; const C = 3; fn f(mut n: int, m: int) {} b = 4;";
+    let tokens = Lexer::new(src).tokenize().expect("lexing failed");
+    let program = Parser::new(tokens).parse().expect("parsing failed");
+    let declared: Vec<_> = program
+        .statements
+        .iter()
+        .filter_map(|s| match &s.kind {
+            StatementKind::Declare(d) => Some((d.name.as_str(), d.is_mutable)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(declared, vec![("a", false), ("b", true), ("C", false)]);
+    match &program.statements[3].kind {
+        StatementKind::Function(f) => {
+            let params: Vec<_> = f.parameters.iter().map(|p| p.is_mutable).collect();
+            assert_eq!(params, vec![true, false]);
+        }
+        other => panic!("expected function, got {other:?}"),
+    }
+    match &program.statements[4].kind {
+        StatementKind::Expression(e) => {
+            assert!(matches!(e.kind, ExpressionKind::Assignment { .. }))
+        }
+        other => panic!("expected assignment, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_literal_tuple_patterns_and_guards() {
+    let src = "match p { (0, -1) => {} (x, _) if x > 0 => {} \"s\" => {} _ => {} }";
+    let tokens = Lexer::new(src).tokenize().expect("lexing failed");
+    let program = Parser::new(tokens).parse().expect("parsing failed");
+    let arms = match &program.statements[0].kind {
+        StatementKind::Match(m) => &m.arms,
+        other => panic!("expected match, got {other:?}"),
+    };
+    match &arms[0].pattern.kind {
+        PatternKind::Tuple(items) => {
+            assert!(items
+                .iter()
+                .all(|p| matches!(p.kind, PatternKind::Literal(_))))
+        }
+        other => panic!("expected tuple pattern, got {other:?}"),
+    }
+    assert!(arms[0].guard.is_none());
+    assert!(arms[1].guard.is_some());
+    assert!(matches!(arms[2].pattern.kind, PatternKind::Literal(_)));
+    assert!(matches!(arms[3].pattern.kind, PatternKind::Wildcard));
+}
+
+#[test]
+fn test_try_catch_and_throw() {
+    let src = "try { throw Oops { code: 1 }; } catch e: Oops { } catch e { } catch { }";
+    let tokens = Lexer::new(src).tokenize().expect("lexing failed");
+    let program = Parser::new(tokens).parse().expect("parsing failed");
+    let try_stmt = match &program.statements[0].kind {
+        StatementKind::Try(try_stmt) => try_stmt,
+        other => panic!("expected try, got {other:?}"),
+    };
+    assert!(matches!(
+        try_stmt.body.statements[0].kind,
+        StatementKind::Throw(_)
+    ));
+    let clauses: Vec<_> = try_stmt
+        .catch_clauses
+        .iter()
+        .map(|c| (c.variable.as_deref(), c.exception_type.is_some()))
+        .collect();
+    assert_eq!(
+        clauses,
+        vec![(Some("e"), true), (Some("e"), false), (None, false)]
+    );
+
+    let tokens = Lexer::new("try { }").tokenize().expect("lexing failed");
+    assert!(Parser::new(tokens).parse().is_err());
+}
+
+#[test]
+fn test_question_mark_propagates_postfix_expressions() {
+    let src = "let n = parse(s)?.value;";
+    let tokens = Lexer::new(src).tokenize().expect("lexing failed");
+    let program = Parser::new(tokens).parse().expect("parsing failed");
+    let StatementKind::Declare(decl) = &program.statements[0].kind else {
+        panic!("expected a declaration");
+    };
+    let ExpressionKind::MemberAccess { object, property } = &decl.value.kind else {
+        panic!("expected member access, got {:?}", decl.value.kind);
+    };
+    assert_eq!(property, "value");
+    let ExpressionKind::Propagate(inner) = &object.kind else {
+        panic!("expected `?`, got {:?}", object.kind);
+    };
+    assert!(matches!(inner.kind, ExpressionKind::Call { .. }));
+}
+
+#[test]
+fn test_labelled_loops_and_label_references() {
+    let src = "'outer: for i in 3 { while true { break 'outer; continue; } } let c = 'x';";
+    let tokens = Lexer::new(src).tokenize().expect("lexing failed");
+    let program = Parser::new(tokens).parse().expect("parsing failed");
+    let StatementKind::For(for_stmt) = &program.statements[0].kind else {
+        panic!("expected a for loop, got {:?}", program.statements[0].kind);
+    };
+    assert_eq!(for_stmt.label.as_deref(), Some("outer"));
+    let StatementKind::While(while_stmt) = &for_stmt.body.statements[0].kind else {
+        panic!("expected a while loop");
+    };
+    assert_eq!(while_stmt.label, None);
+    assert_eq!(
+        while_stmt.body.statements[0].kind,
+        StatementKind::Break(Some("outer".to_string()))
+    );
+    assert_eq!(
+        while_stmt.body.statements[1].kind,
+        StatementKind::Continue(None)
+    );
+    let StatementKind::Declare(decl) = &program.statements[1].kind else {
+        panic!("expected a declaration");
+    };
+    assert_eq!(decl.value.kind, ExpressionKind::CharLiteral('x'));
+
+    let tokens = Lexer::new("'outer: let x = 1;")
+        .tokenize()
+        .expect("lexing failed");
+    assert!(Parser::new(tokens).parse().is_err());
+}
+
+#[test]
+fn test_for_patterns_ranges_and_brace_conditions() {
+    let src = "for (i, x) in items.enumerate() { } for n in 0..=limit + 1 { } if a == b { } let m = { \"k\": 1 };";
+    let tokens = Lexer::new(src).tokenize().expect("lexing failed");
+    let program = Parser::new(tokens).parse().expect("parsing failed");
+
+    let StatementKind::For(for_stmt) = &program.statements[0].kind else {
+        panic!("expected a for loop");
+    };
+    let PatternKind::Tuple(items) = &for_stmt.pattern.kind else {
+        panic!("expected a tuple pattern, got {:?}", for_stmt.pattern.kind);
+    };
+    assert_eq!(items.len(), 2);
+    assert!(matches!(
+        for_stmt.iterable.kind,
+        ExpressionKind::Call { .. }
+    ));
+
+    let StatementKind::For(for_stmt) = &program.statements[1].kind else {
+        panic!("expected a for loop");
+    };
+    let ExpressionKind::Range { end, inclusive, .. } = &for_stmt.iterable.kind else {
+        panic!("expected a range, got {:?}", for_stmt.iterable.kind);
+    };
+    assert!(inclusive);
+    assert!(matches!(end.kind, ExpressionKind::BinaryOp { .. }));
+
+    // `b {` in a condition is not a struct literal
+    let StatementKind::If(if_stmt) = &program.statements[2].kind else {
+        panic!("expected an if statement");
+    };
+    assert!(matches!(
+        if_stmt.condition.kind,
+        ExpressionKind::BinaryOp { .. }
+    ));
+
+    let StatementKind::Declare(decl) = &program.statements[3].kind else {
+        panic!("expected a declaration");
+    };
+    let ExpressionKind::ObjectLiteral(entries) = &decl.value.kind else {
+        panic!("expected a map literal");
+    };
+    assert!(entries.contains_key("k"));
+
+    let tokens = Lexer::new("for Some(x) in items { }")
+        .tokenize()
+        .expect("lexing failed");
+    assert!(Parser::new(tokens).parse().is_err());
+}
@@ -0,0 +1,71 @@
+// Tests for parser error recovery: every syntax error in a file is reported
+
+use cylc::ast::{Program, StatementKind};
+use cylc::error::CylError;
+use cylc::lexer::Lexer;
+use cylc::parser::helpers::Parser;
+
+fn parse(src: &str) -> Result<Program, Vec<CylError>> {
+    let mut lexer = Lexer::new(src);
+    let tokens = lexer.tokenize().expect("lexing failed");
+    Parser::new(tokens).parse()
+}
+
+fn error_lines(src: &str) -> Vec<usize> {
+    parse(src)
+        .expect_err("expected parse errors")
+        .iter()
+        .map(|e| match e {
+            CylError::ParseError { line, .. } => *line,
+            other => panic!("unexpected error kind: {other:?}"),
+        })
+        .collect()
+}
+
+#[test]
+fn test_reports_every_error_in_a_block() {
+    let src = "fn main() -> void {
+    let x = ;
+    let y = 2;
+    print(y +);
+    print(y);
+}";
+    assert_eq!(error_lines(src), vec![2, 4]);
+}
+
+#[test]
+fn test_recovers_at_top_level_declarations() {
+    let src = "fn broken(a: ) -> int {
+    return 1;
+}
+struct S { a int }
+fn fine() -> void { print(1); }
+enum E { A(, B }
+";
+    assert_eq!(error_lines(src), vec![1, 4, 6]);
+}
+
+#[test]
+fn test_unexpected_top_level_tokens_are_reported() {
+    let errors = parse("fn main() -> void { print(1); }\n42;\n}\nfn after() -> void {}\n")
+        .expect_err("expected parse errors");
+    let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+    assert_eq!(messages.len(), 2, "{messages:?}");
+    assert!(messages[0].contains("Unexpected token IntLiteral(42) at top level"));
+    assert!(messages[1].contains("Unexpected token RightBrace at top level"));
+}
+
+#[test]
+fn test_valid_program_after_recovery_point_still_parses() {
+    let program = parse("fn a() -> void { print(1); }\n;;\nfn b() -> void { print(2); }")
+        .expect("parsing failed");
+    let names: Vec<_> = program
+        .statements
+        .iter()
+        .filter_map(|s| match &s.kind {
+            StatementKind::Function(f) => Some(f.name.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(names, vec!["a", "b"]);
+}
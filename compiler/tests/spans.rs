@@ -0,0 +1,125 @@
+// Tests for source spans on AST nodes and diagnostics
+
+use cylc::ast::{ExpressionKind, Program, Span, StatementKind};
+use cylc::error::CylError;
+use cylc::lexer::Lexer;
+use cylc::parser::helpers::Parser;
+use cylc::typeck::TypeChecker;
+
+fn parse(src: &str) -> Program {
+    let mut lexer = Lexer::new(src);
+    let tokens = lexer.tokenize().expect("lexing failed");
+    let mut parser = Parser::new(tokens);
+    parser.parse().expect("parsing failed")
+}
+
+#[test]
+fn test_tokens_track_lines_and_columns() {
+    let tokens = Lexer::new("let a = 1;\n  let b = 2;").tokenize().unwrap();
+    let b = tokens
+        .iter()
+        .find(|t| t.span == (17..18))
+        .expect("token for `b`");
+    assert_eq!((b.line, b.column), (2, 7));
+}
+
+#[test]
+fn test_statement_and_expression_spans() {
+    let src = "fn main() -> void {\n    let x = 1 + 2;\n    print(x);\n}\n";
+    let program = parse(src);
+
+    let func_stmt = &program.statements[0];
+    assert_eq!(func_stmt.span, Span::new(0, src.trim_end().len(), 1, 1));
+    let func = match &func_stmt.kind {
+        StatementKind::Function(f) => f,
+        other => panic!("expected function, got {other:?}"),
+    };
+
+    let declare = &func.body.statements[0];
+    assert_eq!((declare.span.line, declare.span.column), (2, 5));
+    assert_eq!(&src[declare.span.start..declare.span.end], "let x = 1 + 2;");
+    let value = match &declare.kind {
+        StatementKind::Declare(d) => &d.value,
+        other => panic!("expected declaration, got {other:?}"),
+    };
+    assert!(matches!(value.kind, ExpressionKind::BinaryOp { .. }));
+    assert_eq!(&src[value.span.start..value.span.end], "1 + 2");
+    assert_eq!((value.span.line, value.span.column), (2, 13));
+
+    let call = match &func.body.statements[1].kind {
+        StatementKind::Expression(e) => e,
+        other => panic!("expected expression, got {other:?}"),
+    };
+    assert_eq!(&src[call.span.start..call.span.end], "print(x)");
+    assert_eq!((call.span.line, call.span.column), (3, 5));
+}
+
+#[test]
+fn test_type_errors_carry_spans() {
+    let src = "fn main() -> void {\n    let ok = 1;\n    let bad: int = \"no\";\n}\n";
+    let errors = TypeChecker::new()
+        .check_program(parse(src))
+        .expect_err("expected a type error")
+        .errors;
+    assert_eq!(errors.len(), 1);
+    let span = errors[0].span().expect("type error without span");
+    assert_eq!((span.line, span.column), (3, 5));
+    assert!(matches!(errors[0], CylError::TypeError { .. }));
+    assert!(
+        errors[0].to_string().ends_with("at line 3, column 5"),
+        "{}",
+        errors[0]
+    );
+}
+
+#[test]
+fn test_type_annotation_spans() {
+    let src = "struct Pair<A, B> { first: A, second: Pair<B, A>? }\n\
+               fn f(p: (int, string), q) -> [int] { let n: int = 1; return [n]; }\n";
+    let program = parse(src);
+    let text = |span: Span| &src[span.start..span.end];
+
+    let StatementKind::Struct(pair) = &program.statements[0].kind else {
+        panic!("expected struct");
+    };
+    assert_eq!(text(pair.fields[1].type_span), "Pair<B, A>?");
+    let StatementKind::Function(f) = &program.statements[1].kind else {
+        panic!("expected function");
+    };
+    assert_eq!(text(f.parameters[0].type_span.unwrap()), "(int, string)");
+    assert_eq!(f.parameters[1].type_span, None);
+    assert_eq!(text(f.return_type_span.unwrap()), "[int]");
+    let StatementKind::Declare(n) = &f.body.statements[0].kind else {
+        panic!("expected declaration");
+    };
+    let span = n.type_span.unwrap();
+    assert_eq!((text(span), span.line, span.column), ("int", 2, 45));
+}
+
+#[test]
+fn test_errors_about_annotations_point_at_them() {
+    let src = "struct Pair<A, B> { first: A, second: B }\n\
+               enum Shape { Dot, Sized(Pair<int>) }\n\
+               fn main() -> void {\n    let p: Missing = 1;\n}\n";
+    let errors = TypeChecker::new()
+        .check_program(parse(src))
+        .expect_err("expected type errors")
+        .errors;
+    let located: Vec<_> = errors
+        .iter()
+        .map(|e| {
+            let span = e.span().expect("type error without span");
+            (&src[span.start..span.end], span.line, span.column)
+        })
+        .collect();
+    assert_eq!(located, [("Pair<int>", 2, 25), ("Missing", 4, 12)], "{errors:?}");
+}
+
+#[test]
+fn test_ast_json_includes_spans() {
+    let json = serde_json::to_value(parse("let x = 1;")).unwrap();
+    let stmt = &json["statements"][0];
+    assert_eq!(stmt["span"]["line"], 1);
+    assert_eq!(stmt["span"]["column"], 1);
+    assert_eq!(stmt["kind"]["Declare"]["value"]["span"]["column"], 9);
+}
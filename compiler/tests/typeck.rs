@@ -0,0 +1,534 @@
+// Tests for the static type checker
+
+use cylc::ast::{StatementKind, Type};
+use cylc::lexer::Lexer;
+use cylc::parser::helpers::Parser;
+use cylc::typeck::{CheckFailure, TypeChecker, TypedProgram};
+
+fn check(src: &str) -> Result<TypedProgram, CheckFailure> {
+    let mut lexer = Lexer::new(src);
+    let tokens = lexer.tokenize().expect("lexing failed");
+    let mut parser = Parser::new(tokens);
+    let program = parser.parse().expect("parsing failed");
+    TypeChecker::new().check_program(program)
+}
+
+fn check_errors(src: &str) -> Vec<String> {
+    match check(src) {
+        Ok(_) => panic!("expected type errors for: {src}"),
+        Err(failure) => failure.errors.iter().map(|e| e.to_string()).collect(),
+    }
+}
+
+#[test]
+fn test_valid_program_resolves_declaration_types() {
+    let typed = check(
+        "struct Point { x: int, y: int }
+         fn make() -> Point { return Point { x: 1, y: 2 }; }
+         fn main() -> void {
+             let p = make();
+             let total = p.x + p.y;
+             let ratio <float> = 0.5;
+         }",
+    )
+    .expect("type checking failed");
+
+    let main = typed
+        .program
+        .statements
+        .iter()
+        .find_map(|s| match &s.kind {
+            StatementKind::Function(f) if f.name == "main" => Some(f),
+            _ => None,
+        })
+        .unwrap();
+    let types: Vec<_> = main
+        .body
+        .statements
+        .iter()
+        .filter_map(|s| match &s.kind {
+            StatementKind::Declare(d) => d.var_type.clone(),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        types,
+        vec![Type::Custom("Point".to_string()), Type::Int, Type::Float]
+    );
+}
+
+#[test]
+fn test_mismatched_declaration() {
+    let errors = check_errors("fn main() -> void { let x: int = \"hello\"; }");
+    assert_eq!(errors.len(), 1);
+    assert!(
+        errors[0].contains("expected `int`, found `string`"),
+        "{errors:?}"
+    );
+}
+
+#[test]
+fn test_unknown_names_are_reported() {
+    let errors = check_errors(
+        "fn main() -> void {
+             let a: Missing = 1;
+             print(undefined_var);
+             nope();
+         }",
+    );
+    assert_eq!(errors.len(), 3, "{errors:?}");
+    assert!(errors[0].contains("Unknown type `Missing`"));
+    assert!(errors[1].contains("Undefined variable `undefined_var`"));
+    assert!(errors[2].contains("Unknown function `nope`"));
+}
+
+#[test]
+fn test_call_arity_and_defaults() {
+    check(
+        "fn greet(name: string, punct: string = \"!\") -> string { return name + punct; }
+           fn main() -> void { print(greet(\"a\")); print(greet(\"a\", \"?\")); }",
+    )
+    .expect("type checking failed");
+
+    let errors = check_errors(
+        "fn add(a: int, b: int) -> int { return a + b; }
+         fn main() -> void { let x = add(1); }",
+    );
+    assert!(
+        errors[0].contains("takes 2 argument(s) but 1 were given"),
+        "{errors:?}"
+    );
+}
+
+#[test]
+fn test_generics_and_enums() {
+    check(
+        "struct Box<T> { value: T }
+         enum Shape { Circle(float), Empty }
+         fn id<T>(x: T) -> T { return x; }
+         fn area(s: Shape) -> float {
+             match s {
+                 Shape.Circle(r) => { return r * r; }
+                 _ => { return 0.0; }
+             }
+         }
+         fn main() -> void {
+             let b = Box { value: 3 };
+             let n: int = b.value + id(4);
+             let a: float = area(Shape.Circle(1.0));
+         }",
+    )
+    .expect("type checking failed");
+
+    let errors = check_errors(
+        "fn id<T>(x: T) -> T { return x; }
+         fn main() -> void { let s: string = id(1); }",
+    );
+    assert!(
+        errors[0].contains("expected `string`, found `int`"),
+        "{errors:?}"
+    );
+}
+
+#[test]
+fn test_generic_types_need_all_their_type_arguments() {
+    let errors = check_errors(
+        "struct Pair<A, B> { first: A, second: B }
+         fn bare(p: Pair) -> void {}
+         fn short(p: Pair<int>) -> void {}
+         fn main() -> void { let o: Option = None; }",
+    );
+    assert_eq!(errors.len(), 3, "{errors:?}");
+    assert!(
+        errors[0].contains("Type `Pair` expects 2 type argument(s) but 0 were given"),
+        "{errors:?}"
+    );
+    assert!(
+        errors[1].contains("Type `Pair` expects 2 type argument(s) but 1 were given"),
+        "{errors:?}"
+    );
+    assert!(
+        errors[2].contains("Type `Option` expects 1 type argument(s) but 0 were given"),
+        "{errors:?}"
+    );
+}
+
+#[test]
+fn test_missing_return() {
+    let errors = check_errors("fn f(a: int) -> int { if a > 0 { return 1; } }");
+    assert!(
+        errors[0].contains("must return a value of type `int`"),
+        "{errors:?}"
+    );
+}
+
+#[test]
+fn test_throw_counts_as_leaving_the_function() {
+    check(
+        "fn f(a: int) -> int {
+             if a > 0 { return 1; }
+             throw \"negative\";
+         }
+         fn g(a: int) -> int {
+             try { return a; } catch e: string { throw e; }
+         }",
+    )
+    .expect("throws end their paths");
+
+    let errors = check_errors(
+        "fn nothing() -> void {}
+         fn f() -> void { throw nothing(); }",
+    );
+    assert!(
+        errors[0].contains("Cannot throw a value of type `void`"),
+        "{errors:?}"
+    );
+}
+
+#[test]
+fn test_non_exhaustive_match_lists_missing_patterns() {
+    let errors = check_errors(
+        "enum Shape { Circle(float), Square(float), Empty }
+         fn main() -> void {
+             let s = Shape.Empty;
+             match s {
+                 Shape.Circle(r) => { print(r); }
+                 Shape.Square(w) if w > 1.0 => { print(w); }
+             }
+         }",
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "Semantic error: Non-exhaustive match on a value of type `Shape`: \
+             `Shape.Square(_)`, `Shape.Empty` not covered (in function `main`) \
+             at line 4, column 20"
+        ]
+    );
+
+    let errors = check_errors(
+        "enum Flag { On(bool), Off }
+         fn main() -> void {
+             let pair = (true, false);
+             match pair {
+                 (true, _) => {}
+                 (_, true) => {}
+             }
+             match Flag.Off {
+                 Flag.On(true) => {}
+                 Off => {}
+             }
+             match 3 {
+                 0 => {}
+                 1 => {}
+             }
+         }",
+    );
+    assert_eq!(errors.len(), 3, "{errors:?}");
+    assert!(
+        errors[0].contains("`(false, false)` not covered"),
+        "{errors:?}"
+    );
+    assert!(
+        errors[1].contains("`Flag.On(false)` not covered"),
+        "{errors:?}"
+    );
+    assert!(
+        errors[2].contains("type `int`: `_` not covered"),
+        "{errors:?}"
+    );
+}
+
+#[test]
+fn test_exhaustive_matches_are_accepted() {
+    let typed = check(
+        "struct Point { x: int, y: int }
+         enum Shape { Circle(float), Empty }
+         fn main() -> void {
+             match Shape.Empty {
+                 Shape.Circle(r) => {}
+                 Empty => {}
+             }
+             let p = Point { x: 1, y: 2 };
+             match p {
+                 Point { x: 0, y } => {}
+                 Point { x, y } => {}
+             }
+             match true {
+                 true => {}
+                 false => {}
+             }
+             let anything: dynamic = 1;
+             match anything {
+                 1 => {}
+             }
+         }",
+    )
+    .expect("type checking failed");
+    assert!(typed.warnings.is_empty(), "{:?}", typed.warnings);
+}
+
+#[test]
+fn test_unreachable_match_arms_are_warnings() {
+    let typed = check(
+        "enum Shape { Circle(float), Empty }
+         fn main() -> void {
+             match 3 {
+                 n if n > 1 => {}
+                 _ => {}
+                 0 => {}
+             }
+             match Shape.Empty {
+                 Shape.Circle(_) => {}
+                 Shape.Empty => {}
+                 Shape.Circle(r) => {}
+             }
+         }",
+    )
+    .expect("type checking failed");
+    let warnings: Vec<_> = typed
+        .warnings
+        .iter()
+        .map(|w| (w.code, w.span.line, w.span.column))
+        .collect();
+    assert_eq!(warnings, vec![("W0001", 6, 18), ("W0001", 11, 18)]);
+    assert!(typed.warnings[0].message.starts_with("Unreachable pattern"));
+}
+
+#[test]
+fn test_failed_checks_keep_their_warnings() {
+    let failure = check(
+        "fn main() -> void {
+             match 3 {
+                 _ => {}
+                 0 => {}
+             }
+             let x: int = \"no\";
+         }",
+    )
+    .expect_err("expected a type error");
+    assert_eq!(failure.errors.len(), 1, "{:?}", failure.errors);
+    let warnings: Vec<_> = failure.warnings.iter().map(|w| w.code).collect();
+    assert_eq!(warnings, ["W0001"]);
+}
+
+#[test]
+fn test_prelude_option_and_result_constructors() {
+    let typed = check(
+        "fn half(n: int) -> Result<int, string> {
+             if n % 2 == 1 { return Err(\"odd\"); }
+             return Ok(n / 2);
+         }
+         fn first(n: int?) -> Option<int> {
+             let m: int? = Some(n?);
+             return m;
+         }
+         fn main() -> void {
+             let total = half(8)?;
+         }",
+    );
+    let errors = match typed {
+        Ok(_) => panic!("`?` in a void function should be rejected"),
+        Err(failure) => failure.errors.iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+    };
+    assert_eq!(errors.len(), 1, "{errors:?}");
+    assert!(
+        errors[0].contains("`?` on a `Result` requires the function to return a `Result`"),
+        "{errors:?}"
+    );
+
+    check(
+        "fn half(n: int) -> Result<int, string> {
+             if n % 2 == 1 { return Err(\"odd\"); }
+             return Ok(n / 2);
+         }
+         fn quarter(n: int) -> Result<int, string> {
+             let h: int = half(n)?;
+             return half(h);
+         }
+         fn main() -> void {
+             match quarter(8) {
+                 Ok(q) => { print(q); }
+                 Err(e) => { print(e); }
+             }
+             let missing: int? = None;
+             match missing {
+                 Some(n) => { print(n); }
+                 None => {}
+             }
+         }",
+    )
+    .expect("type checking failed");
+}
+
+#[test]
+fn test_propagating_mismatched_errors_is_rejected() {
+    let errors = check_errors(
+        "fn parse() -> Result<int, string> { return Ok(1); }
+         fn run() -> Result<int, bool> {
+             let n = parse()?;
+             return Ok(n);
+         }
+         fn count() -> int {
+             let n = Some(1)?;
+             return n;
+         }",
+    );
+    assert_eq!(errors.len(), 2, "{errors:?}");
+    assert!(
+        errors[0].contains("`?` cannot return an error of type `string`"),
+        "{errors:?}"
+    );
+    assert!(
+        errors[1].contains("`?` on an `Option` requires"),
+        "{errors:?}"
+    );
+}
+
+#[test]
+fn test_break_and_continue_need_an_enclosing_loop() {
+    check(
+        "fn main() -> void {
+             'rows: for i in 3 {
+                 while true {
+                     if i == 1 { continue 'rows; }
+                     break 'rows;
+                 }
+             }
+         }",
+    )
+    .expect("type checking failed");
+
+    let errors = check_errors(
+        "fn main() -> void {
+             break;
+             'outer: while true {
+                 for i in 2 { continue 'inner; }
+             }
+         }",
+    );
+    assert_eq!(errors.len(), 2, "{errors:?}");
+    assert!(
+        errors[0].contains("`break` outside of a loop"),
+        "{errors:?}"
+    );
+    assert!(
+        errors[1].contains("`continue 'inner` does not name an enclosing loop"),
+        "{errors:?}"
+    );
+}
+
+#[test]
+fn test_for_loops_bind_elements_of_each_iterable() {
+    check(
+        "fn main() -> void {
+             let words = [\"a\", \"b\"];
+             for (i, word) in words.enumerate() {
+                 let n: int = i;
+                 let s: string = word;
+             }
+             for c in \"abc\" {
+                 let ch: char = c;
+             }
+             for n in 1..=3 {
+                 let m: int = n;
+             }
+         }",
+    )
+    .expect("type checking failed");
+
+    let errors = check_errors(
+        "fn main() -> void {
+             for word in [\"a\"] {
+                 let n: int = word;
+             }
+             for x in 0..true { }
+             for b in false { }
+         }",
+    );
+    assert_eq!(errors.len(), 3, "{errors:?}");
+    assert!(errors[1].contains("range end"), "{errors:?}");
+    assert!(
+        errors[2].contains("Cannot iterate over a value of type `bool`"),
+        "{errors:?}"
+    );
+}
+
+#[test]
+fn test_generic_calls_record_their_instances() {
+    let typed = check(
+        "fn identity<T>(value: T) -> T { return value; }
+         fn add(a, b) -> int { return a + b; }
+         fn main() -> void {
+             let word = identity(\"hi\");
+             let sum = add(1, 2);
+         }",
+    )
+    .expect("type checking failed");
+
+    let mut names: Vec<_> = typed.instances.iter().map(|i| i.function.name.as_str()).collect();
+    names.sort();
+    assert_eq!(names, ["add(int, int)", "identity(string)"]);
+    let identity = &typed.instances.iter().find(|i| i.function.name == "identity(string)").unwrap().function;
+    assert_eq!(identity.parameters[0].param_type, Type::String);
+    assert!(typed.functions().all(|(f, _)| f.name != "identity" && f.name != "add"));
+
+    let main = typed
+        .program
+        .statements
+        .iter()
+        .find_map(|s| match &s.kind {
+            StatementKind::Function(f) if f.name == "main" => Some(f),
+            _ => None,
+        })
+        .unwrap();
+    let StatementKind::Declare(word) = &main.body.statements[0].kind else {
+        panic!("expected a declaration");
+    };
+    assert_eq!(typed.types.get(&word.value), Some(&Type::String));
+    assert_eq!(typed.types.callee(&word.value), Some("identity(string)"));
+}
+
+#[test]
+fn test_enum_constructors_are_recorded() {
+    let typed = check(
+        "enum Color { Red, Green }
+         fn main() -> void {
+             let Green = 1;
+             let c = Color.Red;
+             let n: int? = None;
+             let s = Some(Green);
+         }",
+    )
+    .expect("type checking failed");
+
+    let main = typed
+        .program
+        .statements
+        .iter()
+        .find_map(|s| match &s.kind {
+            StatementKind::Function(f) if f.name == "main" => Some(f),
+            _ => None,
+        })
+        .unwrap();
+    let constructed: Vec<_> = main
+        .body
+        .statements
+        .iter()
+        .filter_map(|s| match &s.kind {
+            StatementKind::Declare(d) => Some(typed.constructors.get(&d.value)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        constructed,
+        vec![None, Some(("Color", "Red")), Some(("Option", "None")), Some(("Option", "Some"))]
+    );
+
+    let errors = check_errors(
+        "enum Color { Red, Green }
+         let c = Color.Blue;",
+    );
+    assert_eq!(errors.len(), 1, "{errors:?}");
+    assert!(errors[0].contains("Enum `Color` has no variant `Blue`"), "{errors:?}");
+}
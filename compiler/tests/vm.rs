@@ -0,0 +1,292 @@
+// Tests for the bytecode VM, which must agree with the tree-walking interpreter
+
+use cylc::error::CylError;
+use cylc::interpreter::vm::{self, Vm};
+use cylc::interpreter::Interpreter;
+use cylc::lexer::Lexer;
+use cylc::parser::helpers::Parser;
+use cylc::typeck::{TypeChecker, TypedProgram};
+
+fn check(src: &str) -> TypedProgram {
+    let mut lexer = Lexer::new(src);
+    let tokens = lexer.tokenize().expect("lexing failed");
+    let program = Parser::new(tokens).parse().expect("parsing failed");
+    TypeChecker::new()
+        .check_program(program)
+        .expect("type checking failed")
+}
+
+fn interpret(program: &TypedProgram) -> Result<Vec<String>, CylError> {
+    let mut interpreter = Interpreter::new();
+    interpreter.run(program)?;
+    Ok(interpreter.output_buffer)
+}
+
+fn run_vm(program: &TypedProgram) -> Result<Vec<String>, CylError> {
+    let mut vm = Vm::new();
+    vm.run(program)?;
+    Ok(vm.output_buffer)
+}
+
+/// Run `src` on both engines, expecting the same output.
+fn run(src: &str) -> Vec<String> {
+    let program = check(src);
+    let expected = interpret(&program).expect("interpreter runtime error");
+    let output = run_vm(&program).expect("VM runtime error");
+    assert_eq!(output, expected, "the VM and the interpreter disagree");
+    output
+}
+
+/// Run `src` on both engines, expecting the same runtime error.
+fn runtime_error(src: &str) -> CylError {
+    let program = check(src);
+    let expected = interpret(&program).expect_err("expected an interpreter runtime error");
+    let error = run_vm(&program).expect_err("expected a VM runtime error");
+    assert_eq!(error.message(), expected.message());
+    assert_eq!(error.span(), expected.span());
+    match (&error, &expected) {
+        (CylError::RuntimeError { stack, .. }, CylError::RuntimeError { stack: expected, .. }) => {
+            assert_eq!(stack, expected, "the call stacks differ");
+        }
+        other => panic!("expected runtime errors, got {other:?}"),
+    }
+    error
+}
+
+#[test]
+fn test_calls_recursion_and_parameter_defaults() {
+    let output = run("fn fib(n: int) -> int {
+             if n < 2 { return n; }
+             return fib(n - 1) + fib(n - 2);
+         }
+         fn scale(value: int, factor: int = 10) -> int {
+             return value * factor;
+         }
+         fn main() -> void {
+             print_int(fib(15));
+             print_int(scale(4));
+             print_int(scale(4, 2));
+         }");
+    assert_eq!(output, vec!["610", "40", "8"]);
+}
+
+#[test]
+fn test_globals_scopes_and_field_assignment() {
+    let output = run("struct Point { x: int, y: int }
+         struct Line { start: Point, end: Point }
+         let mut total = 1;
+         fn bump(by: int) -> void {
+             total = total + by;
+         }
+         fn main() -> void {
+             bump(41);
+             print_int(total);
+             let x = 1;
+             {
+                 let x = 2;
+                 print_int(x);
+             }
+             print_int(x);
+             let mut line = Line { start: Point { x: 0, y: 0 }, end: Point { x: 3, y: 4 } };
+             line.end.y = line.start.x = 7;
+             print_int(line.start.x + line.end.y + line.end.x);
+         }");
+    assert_eq!(output, vec!["42", "2", "1", "17"]);
+}
+
+#[test]
+fn test_logical_operators_short_circuit() {
+    let output = run("fn loud(value: bool) -> bool {
+             print(\"evaluated\");
+             return value;
+         }
+         fn main() -> void {
+             if false && loud(true) { print(\"no\"); }
+             if true || loud(false) { print(\"yes\"); }
+             if true && loud(false) { print(\"no\"); } else { print(\"else\"); }
+         }");
+    assert_eq!(output, vec!["yes", "evaluated", "else"]);
+}
+
+#[test]
+fn test_inclusive_ranges_end_at_the_largest_int() {
+    let output = run("fn main() -> void {
+             for i in 9223372036854775806..=9223372036854775807 {
+                 print_int(i);
+             }
+             let last = 9223372036854775807..=9223372036854775807;
+             print_int(last.len());
+             for i in 5..=4 {
+                 print_int(i);
+             }
+         }");
+    assert_eq!(output, vec!["9223372036854775806", "9223372036854775807", "1"]);
+}
+
+#[test]
+fn test_loops_labels_and_iteration() {
+    let output = run("fn main() -> void {
+             let mut sum = 0;
+             'outer: for i in 0..5 {
+                 for j in 0..=i {
+                     if j == 3 { continue 'outer; }
+                     if i == 4 { break 'outer; }
+                     sum = sum + j;
+                 }
+             }
+             print_int(sum);
+             let mut n = 0;
+             while true {
+                 n = n + 1;
+                 if n % 2 == 0 { continue; }
+                 if n > 6 { break; }
+                 print_int(n);
+             }
+             for c in \"hi\" { print(c); }
+             for (key, value) in { \"b\": 2, \"a\": 1 } { print(key); print_int(value); }
+             for (i, word) in [\"x\", \"y\"].enumerate() { print_int(i); print(word); }
+         }");
+    assert_eq!(
+        output,
+        vec!["7", "1", "3", "5", "h", "i", "a", "1", "b", "2", "0", "x", "1", "y"]
+    );
+}
+
+#[test]
+fn test_match_guards_enums_and_propagation() {
+    let output = run("enum Shape { Circle(int), Square(int), Empty }
+         fn area(shape: Shape) -> int {
+             match shape {
+                 Shape.Circle(r) if r > 10 => { return 1000; }
+                 Shape.Circle(r) => { return 3 * r * r; }
+                 Shape.Square(side) => { return side * side; }
+                 Empty => { return 0; }
+             }
+             return -1;
+         }
+         fn half(n: int) -> Option<int> {
+             if n % 2 == 1 { return None; }
+             return Some(n / 2);
+         }
+         fn quarter(n: int) -> Option<int> {
+             let h = half(n)?;
+             return half(h);
+         }
+         fn main() -> void {
+             print_int(area(Shape.Circle(2)));
+             print_int(area(Shape.Circle(20)));
+             print_int(area(Shape.Square(3)));
+             print_int(area(Shape.Empty));
+             print(quarter(12));
+             print(quarter(6));
+         }");
+    assert_eq!(output, vec!["12", "1000", "9", "0", "Some(3)", "None"]);
+}
+
+#[test]
+fn test_exceptions_unwind_calls_and_loops() {
+    let output = run("fn check(n: int) -> int {
+             if n == 2 { throw \"two\"; }
+             if n == 4 { throw n; }
+             return n;
+         }
+         fn main() -> void {
+             for i in 0..6 {
+                 try {
+                     try {
+                         print_int(check(i));
+                     } catch e: int {
+                         print_int(e * 100);
+                         break;
+                     }
+                 } catch e: string {
+                     print(e);
+                     continue;
+                 }
+                 print(\"after\");
+             }
+             print(\"done\");
+         }");
+    assert_eq!(
+        output,
+        vec!["0", "after", "1", "after", "two", "3", "after", "400", "done"]
+    );
+}
+
+#[test]
+fn test_catch_clauses_check_array_element_types() {
+    let output = run("fn main() -> void {
+             try {
+                 throw [\"x\"];
+             } catch e: [int] {
+                 print(\"ints\");
+             } catch e: [string] {
+                 print(e[0]);
+             }
+         }");
+    assert_eq!(output, vec!["x"]);
+}
+
+#[test]
+fn test_runtime_errors_match_the_interpreter() {
+    let error = runtime_error(
+        "fn pick(xs: [int], i: int) -> int {
+    return xs[i];
+}
+fn main() -> void {
+    print_int(pick([1, 2, 3], 3));
+}",
+    );
+    assert_eq!(error.message(), "Index 3 is out of bounds for an array of length 3");
+    let error = runtime_error("fn main() -> void { let zero = 0; print_int(7 % zero); }");
+    assert_eq!(error.message(), "Modulo by zero");
+    let error = runtime_error(
+        "fn fail() -> int {
+             throw \"boom\";
+         }
+         fn main() -> void {
+             try {
+                 fail();
+             } catch e: int {
+                 print(e);
+             }
+         }",
+    );
+    assert_eq!(error.message(), "Uncaught exception: boom");
+}
+
+#[test]
+fn test_call_depth_is_limited_but_not_by_the_host_stack() {
+    let program = check(
+        "fn down(n: int) -> int {
+             if n == 0 { return 0; }
+             return down(n - 1);
+         }
+         fn main() -> void {
+             print_int(down(50000));
+         }",
+    );
+    let mut vm = Vm::new();
+    let error = vm.run(&program).expect_err("expected the call depth limit to be hit");
+    assert!(
+        error
+            .message()
+            .contains("Maximum call depth of 1000 exceeded in call to `down`"),
+        "{error}"
+    );
+    let mut vm = Vm::new();
+    vm.max_call_depth = 100_000;
+    vm.run(&program).expect("deep recursion runs on the VM's own stack");
+    assert_eq!(vm.output_buffer, vec!["0"]);
+}
+
+#[test]
+fn test_unsupported_programs_fail_to_compile() {
+    let program = check("fn main() -> void { let d = dynamic; print(d); }");
+    match vm::compile(&program) {
+        Err(CylError::CodeGenError { message, .. }) => {
+            assert!(message.contains("not supported by the VM"), "{message}")
+        }
+        other => panic!("expected a code generation error, got {other:?}"),
+    }
+}